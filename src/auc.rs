@@ -4,69 +4,279 @@ use std::collections::HashMap;
 pub struct AucCalculator;
 
 impl AucCalculator {
-    /// Calculate AUC using multiple methods for comparison
+    /// Calculate AUC with each of the configured methods, keyed by [`AucMethod::key`]
     pub fn calculate_all_methods(
         observations: &[Observation],
         config: &AnalysisConfig,
     ) -> Result<HashMap<String, f64>> {
-        let mut results = HashMap::new();
-        
         // Filter valid observations (remove BLQ based on config)
-        let filtered_obs = Self::filter_observations(observations, &config.lloq_handling);
-        
-        if filtered_obs.len() < 2 {
+        let filtered_obs = Self::filter_observations(observations, config);
+        Self::require_auc_points(&filtered_obs)?;
+
+        config.auc_methods
+            .iter()
+            .map(|method| Ok((method.key().to_string(), Self::calculate_auc(&filtered_obs, method)?)))
+            .collect()
+    }
+
+    /// AUC needs at least two (BLQ-handled) observations
+    pub fn require_auc_points(observations: &[Observation]) -> Result<()> {
+        if observations.len() < 2 {
             return Err(NcaError::InsufficientData(
                 "Need at least 2 data points for AUC calculation".to_string()
             ));
         }
+        Ok(())
+    }
 
-        // Linear trapezoidal method
-        results.insert(
-            "linear_trapezoidal".to_string(),
-            Self::linear_trapezoidal(&filtered_obs)?,
-        );
+    /// Calculate AUC with a single method over the given (time-sorted) observations
+    pub fn calculate_auc(observations: &[Observation], method: &AucMethod) -> Result<f64> {
+        match method {
+            AucMethod::LinearTrapezoidal => Self::linear_trapezoidal(observations),
+            AucMethod::LogTrapezoidal => Self::log_trapezoidal(observations),
+            AucMethod::LinearLogTrapezoidal => Self::linear_log_trapezoidal(observations),
+            AucMethod::LinearUpLogDown => Self::linear_up_log_down(observations),
+            AucMethod::Rectangular => Self::rectangular(observations),
+        }
+    }
 
-        // Log trapezoidal method
-        results.insert(
-            "log_trapezoidal".to_string(),
-            Self::log_trapezoidal(&filtered_obs)?,
-        );
+    /// Cumulative AUC at each observation time, as a running sum of the per-segment AUCs
+    ///
+    /// The first entry is zero and the last equals the whole-profile AUC with the same method.
+    pub fn calculate_cumulative_auc(observations: &[Observation], method: &AucMethod) -> Result<Vec<CumulativeAuc>> {
+        let mut cumulative = Vec::with_capacity(observations.len());
+        let mut auc = 0.0;
 
-        // Linear-log trapezoidal method (Phoenix WinNonlin style)
-        results.insert(
-            "linear_log_trapezoidal".to_string(),
-            Self::linear_log_trapezoidal(&filtered_obs)?,
-        );
+        for (idx, obs) in observations.iter().enumerate() {
+            if idx > 0 {
+                auc += Self::calculate_auc(&observations[idx - 1..=idx], method)?;
+            }
+            cumulative.push(CumulativeAuc {
+                time: obs.time,
+                concentration: obs.concentration,
+                auc,
+            });
+        }
+
+        Ok(cumulative)
+    }
+
+    /// Method used for the primary AUC: `primary_auc_method` when set, else linear trapezoidal
+    /// when configured, otherwise the first configured method
+    pub fn primary_method(config: &AnalysisConfig) -> AucMethod {
+        if let Some(method) = &config.primary_auc_method {
+            method.clone()
+        } else if config.auc_methods.contains(&AucMethod::LinearTrapezoidal) {
+            AucMethod::LinearTrapezoidal
+        } else {
+            config.auc_methods.first().cloned().unwrap_or(AucMethod::LinearTrapezoidal)
+        }
+    }
+
+    /// Interpolate the concentration at `time` between the bracketing observations
+    ///
+    /// Returns the observed value when a sample exists at `time`, and `None` outside the
+    /// sampled range. Log-linear interpolation falls back to linear when either bracketing
+    /// concentration is not positive.
+    pub fn interpolate_concentration(
+        observations: &[Observation],
+        time: f64,
+        method: &InterpolationMethod,
+    ) -> Option<f64> {
+        if let Some(obs) = observations.iter().find(|obs| obs.time == time) {
+            return Some(obs.concentration);
+        }
+
+        let after = observations.iter().position(|obs| obs.time > time)?;
+        if after == 0 {
+            return None;
+        }
+
+        let (t1, c1) = (observations[after - 1].time, observations[after - 1].concentration);
+        let (t2, c2) = (observations[after].time, observations[after].concentration);
+        let fraction = (time - t1) / (t2 - t1);
+
+        match method {
+            InterpolationMethod::LogLinear if c1 > 0.0 && c2 > 0.0 => {
+                Some((c1.ln() + fraction * (c2.ln() - c1.ln())).exp())
+            }
+            _ => Some(c1 + fraction * (c2 - c1)),
+        }
+    }
+
+    /// Calculate AUC over [start, end], interpolating the concentration at each endpoint
+    ///
+    /// Returns `None` when either endpoint lies outside the sampled range.
+    pub fn calculate_partial_auc(
+        observations: &[Observation],
+        start: f64,
+        end: f64,
+        method: &AucMethod,
+        interpolation: &InterpolationMethod,
+    ) -> Option<f64> {
+        if end <= start {
+            return None;
+        }
+
+        let c_start = Self::interpolate_concentration(observations, start, interpolation)?;
+        let c_end = Self::interpolate_concentration(observations, end, interpolation)?;
+        Self::integrate_interval(observations, (start, c_start), (end, c_end), method)
+    }
+
+    /// Calculate AUC over a steady-state dosing interval [dose_time, dose_time + tau]
+    ///
+    /// The starting concentration follows `anchor`; see [`AucTauAnchor`] for the convention.
+    /// The observed pre-dose concentration is the last sample at or before the dose, within one
+    /// interval of it; an older sample belongs to another dosing interval.
+    pub fn calculate_auc_tau(
+        observations: &[Observation],
+        dose_time: f64,
+        tau: f64,
+        anchor: &AucTauAnchor,
+        method: &AucMethod,
+        interpolation: &InterpolationMethod,
+    ) -> Option<f64> {
+        if tau <= 0.0 {
+            return None;
+        }
+
+        let end_time = dose_time + tau;
+        let pre_dose = observations
+            .iter()
+            .rev()
+            .find(|obs| obs.time <= dose_time)
+            .filter(|obs| obs.time >= dose_time - tau)
+            .map(|obs| obs.concentration);
+        let trough = Self::interpolate_concentration(observations, end_time, interpolation);
+
+        let c_start = match anchor {
+            AucTauAnchor::ObservedPreDose => pre_dose.or(trough)?,
+            AucTauAnchor::NextIntervalTrough => trough.or(pre_dose)?,
+        };
+        let c_end = trough.or(pre_dose)?;
+
+        Self::integrate_interval(observations, (dose_time, c_start), (end_time, c_end), method)
+    }
+
+    /// Dose times that fall strictly inside the sampled time span
+    ///
+    /// Any such dose means a single whole-profile AUC integrates across dose boundaries.
+    pub fn doses_within_sampling(observations: &[Observation], dose_times: &[f64]) -> Vec<f64> {
+        let (first, last) = match (observations.first(), observations.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => return Vec::new(),
+        };
+
+        dose_times
+            .iter()
+            .copied()
+            .filter(|&time| time > first && time < last)
+            .collect()
+    }
+
+    /// Calculate AUC separately for each dosing interval [dose_i, dose_i+1]
+    ///
+    /// Each interval starts at the dose time, with the last concentration observed at or
+    /// before the dose carried forward to it; a dose before the first sample starts at the
+    /// first sample instead. Intervals end at the concentration interpolated at the next dose,
+    /// and the final one at the last sample. A dose at or after the last sample gets no AUC.
+    pub fn calculate_dose_interval_aucs(
+        observations: &[Observation],
+        dose_times: &[f64],
+        method: &AucMethod,
+        interpolation: &InterpolationMethod,
+    ) -> Vec<DoseIntervalAuc> {
+        let (first, last) = match (observations.first(), observations.last()) {
+            (Some(first), Some(last)) => (first.clone(), last.clone()),
+            _ => return Vec::new(),
+        };
+
+        let mut times = dose_times.to_vec();
+        times.sort_by(|a, b| a.total_cmp(b));
+        times.dedup();
+
+        times
+            .iter()
+            .enumerate()
+            .map(|(idx, &dose_time)| {
+                let end = times.get(idx + 1).copied().unwrap_or(last.time);
+                let start = observations
+                    .iter()
+                    .rev()
+                    .find(|obs| obs.time <= dose_time)
+                    .map(|obs| (dose_time, obs.concentration))
+                    .unwrap_or((first.time, first.concentration));
+
+                let auc = if end > start.0 {
+                    Self::interpolate_concentration(observations, end, interpolation)
+                        .and_then(|c_end| Self::integrate_interval(observations, start, (end, c_end), method))
+                } else {
+                    None
+                };
+
+                DoseIntervalAuc {
+                    dose_number: idx + 1,
+                    dose_time,
+                    end,
+                    auc,
+                }
+            })
+            .collect()
+    }
+
+    fn integrate_interval(
+        observations: &[Observation],
+        start: (f64, f64),
+        end: (f64, f64),
+        method: &AucMethod,
+    ) -> Option<f64> {
+        let endpoint = |(time, concentration): (f64, f64)| Observation {
+            time,
+            concentration,
+            lloq: None,
+            bloq: false,
+            lod: None,
+            blod: false,
+            evid: 0,
+            dv: concentration,
+            cmt: None,
+        };
 
-        // Linear up log down method
-        results.insert(
-            "linear_up_log_down".to_string(),
-            Self::linear_up_log_down(&filtered_obs)?,
+        let mut segment = vec![endpoint(start)];
+        segment.extend(
+            observations
+                .iter()
+                .filter(|obs| obs.time > start.0 && obs.time < end.0)
+                .cloned(),
         );
+        segment.push(endpoint(end));
 
-        Ok(results)
+        Self::calculate_auc(&segment, method).ok()
     }
 
-    fn filter_observations(observations: &[Observation], lloq_handling: &LloqHandling) -> Vec<Observation> {
+    /// Apply the configured BLQ handling, and the separate BLOD handling when one is set, to a
+    /// set of observations
+    pub fn filter_observations(observations: &[Observation], config: &AnalysisConfig) -> Vec<Observation> {
         observations
             .iter()
             .filter_map(|obs| {
-                if obs.bloq {
-                    match lloq_handling {
-                        LloqHandling::Drop => None,
-                        LloqHandling::Zero => {
-                            let mut modified_obs = obs.clone();
-                            modified_obs.concentration = 0.0;
-                            Some(modified_obs)
-                        }
-                        LloqHandling::HalfLloq => {
-                            let mut modified_obs = obs.clone();
-                            modified_obs.concentration = obs.lloq.unwrap_or(0.0) / 2.0;
-                            Some(modified_obs)
-                        }
+                let (handling, limit) = match &config.blod_handling {
+                    Some(handling) if obs.blod => (handling, obs.lod.or(obs.lloq)),
+                    _ if obs.bloq || obs.blod => (&config.lloq_handling, obs.lloq),
+                    _ => return Some(obs.clone()),
+                };
+                match handling {
+                    LloqHandling::Drop => None,
+                    LloqHandling::Zero => {
+                        let mut modified_obs = obs.clone();
+                        modified_obs.concentration = 0.0;
+                        Some(modified_obs)
+                    }
+                    LloqHandling::HalfLloq => {
+                        let mut modified_obs = obs.clone();
+                        modified_obs.concentration = limit.unwrap_or(0.0) / 2.0;
+                        Some(modified_obs)
                     }
-                } else {
-                    Some(obs.clone())
                 }
             })
             .collect()
@@ -92,6 +302,25 @@ impl AucCalculator {
         Ok(auc)
     }
 
+    /// Left-endpoint rectangles; on sampled data a midpoint rule reduces to the linear
+    /// trapezoid, so the rectangle takes the concentration at the start of each interval
+    fn rectangular(observations: &[Observation]) -> Result<f64> {
+        let mut auc = 0.0;
+
+        for i in 1..observations.len() {
+            let t1 = observations[i - 1].time;
+            let t2 = observations[i].time;
+
+            if t2 <= t1 {
+                continue;
+            }
+
+            auc += (t2 - t1) * observations[i - 1].concentration;
+        }
+
+        Ok(auc)
+    }
+
     fn log_trapezoidal(observations: &[Observation]) -> Result<f64> {
         let mut auc = 0.0;
         
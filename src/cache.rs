@@ -0,0 +1,85 @@
+use crate::models::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Outcome of analyzing one subject: its results and warnings, or its failure record
+pub type SubjectAnalysis = std::result::Result<(NcaResults, Vec<String>), FailedSubjectAnalysis>;
+
+/// In-memory cache of per-subject analyses, keyed by the subject's data and the analysis
+/// configuration
+///
+/// Passed to [`crate::population::PopulationAnalyzer::analyze_population_with_cache`], it lets
+/// a rerun with a tuned configuration skip subjects whose inputs did not change. Any change to
+/// the subject or the configuration gives a new key, and keys are stored and compared in full
+/// rather than as a hash, so stale results are never returned;
+/// derivations are keyed by name only, so re-registering a different closure under the same
+/// name needs a fresh cache. Configurations are keyed by their `Debug` form, so a rebuilt
+/// (rather than cloned) configuration with several treatment roles or summary conventions may
+/// miss the cache, but never hits a wrong entry.
+#[derive(Debug, Default)]
+pub struct ResultCache {
+    entries: Mutex<HashMap<String, SubjectAnalysis>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ResultCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache key of a subject under `config`
+    ///
+    /// The subject enters through its JSON form, whose maps are sorted by key, so the same
+    /// data parsed again gives the same key.
+    pub fn key(subject: &Subject, config: &AnalysisConfig) -> String {
+        let subject = serde_json::to_value(subject)
+            .map_or_else(|_| format!("{:?}", subject), |value| value.to_string());
+        format!("{}\n{:?}", subject, config)
+    }
+
+    /// Cached analysis of `subject` under `config`, running and storing `analyze` on a miss
+    pub fn get_or_analyze<F>(&self, subject: &Subject, config: &AnalysisConfig, analyze: F) -> SubjectAnalysis
+    where
+        F: FnOnce() -> SubjectAnalysis,
+    {
+        let key = Self::key(subject, config);
+        if let Some(cached) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return cached.clone();
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let analysis = analyze();
+        self.lock().insert(key, analysis.clone());
+        analysis
+    }
+
+    /// Lookups answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Lookups that ran the analysis
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SubjectAnalysis>> {
+        // A panic while holding the lock cannot leave a half-written entry
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::models::*;
+
+pub struct CdiscExporter;
+
+/// Dimension of a parameter, from which its unit is built out of the analysis units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpUnit {
+    Concentration,
+    Time,
+    /// Concentration × time (AUC)
+    ConcentrationTime,
+    /// Concentration × time² (AUMC)
+    ConcentrationTimeSquared,
+    /// 1 / time (rate constants)
+    PerTime,
+    /// Dose / (concentration × time) (clearance)
+    Clearance,
+    /// Dose / concentration (volumes)
+    Volume,
+    Percent,
+    Unitless,
+}
+
+/// Internal parameter name, PPTESTCD, PPTEST and dimension for every exported parameter
+///
+/// Codes follow the CDISC PK Parameters codelist. AUC_inf here is extrapolated from the
+/// observed Clast, hence AUCIFO/CLO/VZO; extravascular subjects get the apparent (CL/F, Vz/F)
+/// codes from [`CdiscExporter::test_code`].
+pub const PP_TEST_CODES: &[(&str, &str, &str, PpUnit)] = &[
+    ("auc_last", "AUCLST", "AUC to Last Nonzero Conc", PpUnit::ConcentrationTime),
+    ("auc_inf", "AUCIFO", "AUC Infinity Obs", PpUnit::ConcentrationTime),
+    ("auc_percent_extrap", "AUCPEO", "AUC %Extrapolation Obs", PpUnit::Percent),
+    ("auc_tau", "AUCTAU", "AUC Over Dosing Interval", PpUnit::ConcentrationTime),
+    ("aumc_last", "AUMCLST", "AUMC to Last Nonzero Conc", PpUnit::ConcentrationTimeSquared),
+    ("aumc_inf", "AUMCIFO", "AUMC Infinity Obs", PpUnit::ConcentrationTimeSquared),
+    ("cmax", "CMAX", "Max Conc", PpUnit::Concentration),
+    ("tmax", "TMAX", "Time of CMAX", PpUnit::Time),
+    ("clast", "CLST", "Last Nonzero Conc", PpUnit::Concentration),
+    ("tlast", "TLST", "Time of Last Nonzero Conc", PpUnit::Time),
+    ("tlag", "TLAG", "Time Until First Nonzero Conc", PpUnit::Time),
+    ("lambda_z", "LAMZ", "Lambda z", PpUnit::PerTime),
+    ("half_life", "LAMZHL", "Half-Life Lambda z", PpUnit::Time),
+    ("lambda_z_r_squared", "R2", "R Squared", PpUnit::Unitless),
+    ("clearance", "CLO", "Total CL Obs", PpUnit::Clearance),
+    ("volume_terminal", "VZO", "Vz Obs", PpUnit::Volume),
+    ("volume_steady_state", "VSSO", "Vol Dist Steady State Obs", PpUnit::Volume),
+    ("mrt", "MRTIFO", "MRT Infinity Obs", PpUnit::Time),
+];
+
+/// Apparent-parameter codes used in place of CLO and VZO for extravascular doses
+const APPARENT_TEST_CODES: &[(&str, &str, &str)] = &[
+    ("clearance", "CLFO", "Total CL Obs by F"),
+    ("volume_terminal", "VZFO", "Vz Obs by F"),
+];
+
+impl CdiscExporter {
+    /// PP-domain-like records: one per subject and available parameter, in
+    /// [`PP_TEST_CODES`] order; parameters that could not be calculated are omitted
+    pub fn pp_records(results: &PopulationResults, config: &AnalysisConfig) -> Vec<PpRecord> {
+        let mut records = Vec::new();
+
+        for result in &results.individual_results {
+            let params = &result.individual_parameters;
+            for &(name, test_code, test_name, unit) in PP_TEST_CODES {
+                let Some(value) = params.get(name) else {
+                    continue;
+                };
+                let (test_code, test_name) = Self::apparent_test_code(name)
+                    .filter(|_| params.apparent_clearance)
+                    .unwrap_or((test_code, test_name));
+                records.push(PpRecord {
+                    usubjid: result.subject_id.clone(),
+                    category: result.analyte.clone(),
+                    specimen: result.matrix.clone(),
+                    test_code: test_code.to_string(),
+                    test_name: test_name.to_string(),
+                    value,
+                    unit: Self::unit(unit, config),
+                });
+            }
+        }
+
+        records
+    }
+
+    /// PPTESTCD and PPTEST of an internal parameter name
+    ///
+    /// `apparent` selects CL/F and Vz/F codes for clearance and volume of extravascular doses.
+    pub fn test_code(name: &str, apparent: bool) -> Option<(&'static str, &'static str)> {
+        Self::apparent_test_code(name).filter(|_| apparent).or_else(|| {
+            PP_TEST_CODES
+                .iter()
+                .find(|&&(param, _, _, _)| param == name)
+                .map(|&(_, code, test, _)| (code, test))
+        })
+    }
+
+    fn apparent_test_code(name: &str) -> Option<(&'static str, &'static str)> {
+        APPARENT_TEST_CODES
+            .iter()
+            .find(|&&(param, _, _)| param == name)
+            .map(|&(_, code, test)| (code, test))
+    }
+
+    /// PPSTRESU for a dimension, composed from the analysis time, concentration and dose
+    /// units without conversion (e.g. `mg/(ng/mL*h)` for clearance)
+    pub fn unit(unit: PpUnit, config: &AnalysisConfig) -> String {
+        let (time, conc, dose) = (&config.time_units, &config.concentration_units, &config.dose_units);
+        match unit {
+            PpUnit::Concentration => conc.clone(),
+            PpUnit::Time => time.clone(),
+            PpUnit::ConcentrationTime => format!("{}*{}", conc, time),
+            PpUnit::ConcentrationTimeSquared => format!("{}*{}^2", conc, time),
+            PpUnit::PerTime => config.lambda_z_unit(),
+            PpUnit::Clearance => format!("{}/({}*{})", dose, conc, time),
+            PpUnit::Volume => format!("{}/({})", dose, conc),
+            PpUnit::Percent => "%".to_string(),
+            PpUnit::Unitless => String::new(),
+        }
+    }
+}
@@ -1,18 +1,23 @@
-use crate::{models::*, Result};
+use crate::{models::*, math::safe_div, population::PopulationAnalyzer, Result};
 use std::collections::HashMap;
+use statrs::distribution::{ContinuousCDF, StudentsT};
 use statrs::statistics::Statistics;
 
 pub struct CovariateAnalyzer;
 
+/// Ratio limits of the power-model dose proportionality criterion (Smith et al., 2000)
+const DOSE_PROPORTIONALITY_LIMITS: (f64, f64) = (0.8, 1.25);
+
 impl CovariateAnalyzer {
     /// Perform comprehensive covariate analysis
     pub fn analyze_covariates(
         results: &[NcaResults],
         subjects: &[Subject],
+        config: &AnalysisConfig,
     ) -> Result<CovariateAnalysis> {
-        let correlations = Self::calculate_covariate_correlations(results, subjects)?;
-        let regression_analysis = Self::perform_regression_analysis(results, subjects)?;
-        let dose_normalized_analysis = Self::perform_dose_normalization_analysis(results, subjects)?;
+        let correlations = Self::calculate_covariate_correlations(results, subjects, config)?;
+        let regression_analysis = Self::perform_regression_analysis(results, subjects, config.min_regression_n)?;
+        let dose_normalized_analysis = Self::perform_dose_normalization_analysis(results, subjects, &config.treatment_roles)?;
 
         Ok(CovariateAnalysis {
             correlations,
@@ -24,6 +29,7 @@ impl CovariateAnalyzer {
     fn calculate_covariate_correlations(
         results: &[NcaResults],
         subjects: &[Subject],
+        config: &AnalysisConfig,
     ) -> Result<HashMap<String, CovariateCorrelation>> {
         let mut correlations = HashMap::new();
         
@@ -33,6 +39,7 @@ impl CovariateAnalyzer {
         for covariate in &covariates {
             let mut parameter_correlations = HashMap::new();
             let mut p_values = HashMap::new();
+            let mut partial_correlations = HashMap::new();
 
             for parameter in &parameters {
                 let (covariate_values, parameter_values) = Self::extract_paired_values(
@@ -40,11 +47,24 @@ impl CovariateAnalyzer {
                 );
 
                 if covariate_values.len() >= 3 {
-                    let correlation = Self::calculate_pearson_correlation(&covariate_values, &parameter_values);
-                    let p_value = Self::correlation_p_value(correlation, covariate_values.len());
-                    
-                    parameter_correlations.insert(parameter.to_string(), correlation);
-                    p_values.insert(parameter.to_string(), p_value);
+                    match Self::calculate_pearson_correlation(&covariate_values, &parameter_values) {
+                        Some(correlation) => {
+                            let p_value = Self::correlation_p_value(correlation, covariate_values.len());
+
+                            parameter_correlations.insert(parameter.to_string(), correlation);
+                            p_values.insert(parameter.to_string(), p_value);
+                            if let Some(partial) = config.partial_correlation_control.as_deref()
+                                .filter(|control| control != covariate)
+                                .and_then(|control| Self::partial_correlation(results, subjects, covariate, parameter, control))
+                            {
+                                partial_correlations.insert(parameter.to_string(), partial);
+                            }
+                        }
+                        None => log::warn!(
+                            "Correlation of {} with {} undefined - no variability in one of the variables",
+                            covariate, parameter
+                        ),
+                    }
                 }
             }
 
@@ -55,14 +75,107 @@ impl CovariateAnalyzer {
                         covariate_name: covariate.to_string(),
                         parameter_correlations,
                         p_values,
+                        adjusted_p_values: HashMap::new(),
+                        significant: HashMap::new(),
+                        partial_correlations,
                     },
                 );
             }
         }
 
+        // Adjust over the whole family of covariate-parameter tests
+        let tests: Vec<(String, String, f64)> = correlations
+            .values()
+            .flat_map(|correlation| correlation.p_values.iter().map(|(parameter, &p_value)| {
+                (correlation.covariate_name.clone(), parameter.clone(), p_value)
+            }))
+            .collect();
+        let raw: Vec<f64> = tests.iter().map(|(_, _, p_value)| *p_value).collect();
+        let adjusted = Self::adjust_p_values(&raw, &config.covariate_p_value_correction);
+        for ((covariate, parameter, _), adjusted) in tests.into_iter().zip(adjusted) {
+            if let Some(correlation) = correlations.get_mut(&covariate) {
+                correlation.significant.insert(parameter.clone(), adjusted < config.covariate_significance_level);
+                correlation.adjusted_p_values.insert(parameter, adjusted);
+            }
+        }
+
         Ok(correlations)
     }
 
+    /// P-values adjusted for multiple comparisons, in the order given
+    ///
+    /// Benjamini-Hochberg ranks the m p-values ascending and takes, for rank i, the smallest
+    /// p(j) * m / j over j >= i, so adjusted values keep the order of the raw ones. Results
+    /// are capped at 1.
+    pub fn adjust_p_values(p_values: &[f64], correction: &MultipleComparisonCorrection) -> Vec<f64> {
+        let m = p_values.len() as f64;
+        match correction {
+            MultipleComparisonCorrection::None => p_values.to_vec(),
+            MultipleComparisonCorrection::Bonferroni => p_values.iter().map(|p| (p * m).min(1.0)).collect(),
+            MultipleComparisonCorrection::BenjaminiHochberg => {
+                let mut order: Vec<usize> = (0..p_values.len()).collect();
+                order.sort_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+
+                let mut adjusted = vec![1.0; p_values.len()];
+                let mut running_min = 1.0_f64;
+                for (rank, &index) in order.iter().enumerate().rev() {
+                    running_min = running_min.min(p_values[index] * m / (rank + 1) as f64);
+                    adjusted[index] = running_min;
+                }
+                adjusted
+            }
+        }
+    }
+
+    /// Correlation of `covariate` with `parameter` controlling for the `control` covariate
+    ///
+    /// r_xy.z = (r_xy - r_xz * r_yz) / sqrt((1 - r_xz²) (1 - r_yz²)), over the subjects with all
+    /// three values; `None` for fewer than 4 such subjects or a control perfectly correlated
+    /// with either variable.
+    pub fn partial_correlation(
+        results: &[NcaResults],
+        subjects: &[Subject],
+        covariate: &str,
+        parameter: &str,
+        control: &str,
+    ) -> Option<f64> {
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        let mut z = Vec::new();
+        for result in results {
+            let Some(subject) = subjects.iter().find(|s| s.id == result.subject_id) else {
+                continue;
+            };
+            if let (Some(cov), Some(param), Some(ctrl)) = (
+                Self::covariate_value(subject, covariate),
+                result.individual_parameters.get(parameter),
+                Self::covariate_value(subject, control),
+            ) {
+                x.push(cov);
+                y.push(param);
+                z.push(ctrl);
+            }
+        }
+        if x.len() < 4 {
+            return None;
+        }
+
+        let r_xy = Self::calculate_pearson_correlation(&x, &y)?;
+        let r_xz = Self::calculate_pearson_correlation(&x, &z)?;
+        let r_yz = Self::calculate_pearson_correlation(&y, &z)?;
+        safe_div(r_xy - r_xz * r_yz, ((1.0 - r_xz * r_xz) * (1.0 - r_yz * r_yz)).sqrt())
+            .map(|r| r.clamp(-1.0, 1.0))
+    }
+
+    fn covariate_value(subject: &Subject, covariate: &str) -> Option<f64> {
+        match covariate {
+            "age" => subject.demographics.age,
+            "weight" => subject.demographics.weight,
+            "height" => subject.demographics.height,
+            _ => None,
+        }
+    }
+
     fn extract_paired_values(
         results: &[NcaResults],
         subjects: &[Subject],
@@ -72,22 +185,16 @@ impl CovariateAnalyzer {
         let mut covariate_values = Vec::new();
         let mut parameter_values = Vec::new();
 
-        for (result, subject) in results.iter().zip(subjects.iter()) {
-            let cov_value = match covariate {
-                "age" => subject.demographics.age,
-                "weight" => subject.demographics.weight,
-                "height" => subject.demographics.height,
-                _ => None,
+        // Match by subject ID: failed subjects have no result, so positions do not line up
+        for result in results {
+            let subject = match subjects.iter().find(|s| s.id == result.subject_id) {
+                Some(subject) => subject,
+                None => continue,
             };
 
-            let param_value = match parameter {
-                "auc_inf" => result.individual_parameters.auc_inf,
-                "cmax" => result.individual_parameters.cmax,
-                "clearance" => result.individual_parameters.clearance,
-                "half_life" => result.individual_parameters.half_life,
-                "volume_terminal" => result.individual_parameters.volume_terminal,
-                _ => None,
-            };
+            let cov_value = Self::covariate_value(subject, covariate);
+
+            let param_value = result.individual_parameters.get(parameter);
 
             if let (Some(cov), Some(param)) = (cov_value, param_value) {
                 covariate_values.push(cov);
@@ -98,9 +205,9 @@ impl CovariateAnalyzer {
         (covariate_values, parameter_values)
     }
 
-    fn calculate_pearson_correlation(x: &[f64], y: &[f64]) -> f64 {
+    fn calculate_pearson_correlation(x: &[f64], y: &[f64]) -> Option<f64> {
         if x.len() != y.len() || x.len() < 2 {
-            return 0.0;
+            return None;
         }
 
         let mean_x = x.mean();
@@ -113,9 +220,7 @@ impl CovariateAnalyzer {
         let sum_sq_x: f64 = x.iter().map(|xi| (xi - mean_x).powi(2)).sum();
         let sum_sq_y: f64 = y.iter().map(|yi| (yi - mean_y).powi(2)).sum();
 
-        let denominator = (sum_sq_x * sum_sq_y).sqrt();
-
-        if denominator == 0.0 { 0.0 } else { numerator / denominator }
+        safe_div(numerator, (sum_sq_x * sum_sq_y).sqrt())
     }
 
     fn correlation_p_value(r: f64, n: usize) -> f64 {
@@ -166,6 +271,7 @@ impl CovariateAnalyzer {
     fn perform_regression_analysis(
         results: &[NcaResults],
         subjects: &[Subject],
+        min_n: usize,
     ) -> Result<HashMap<String, RegressionResults>> {
         let mut regression_results = HashMap::new();
         
@@ -178,10 +284,13 @@ impl CovariateAnalyzer {
                     results, subjects, covariate, parameter
                 );
 
-                if x_values.len() >= 3 {
-                    let mut regression = Self::simple_linear_regression(&x_values, &y_values);
-                    regression.parameter = parameter.to_string();
-                    regression.covariate = covariate.to_string();
+                if x_values.len() < min_n {
+                    log::info!(
+                        "Regression of {} on {} not reported: n = {} (minimum {})",
+                        parameter, covariate, x_values.len(), min_n
+                    );
+                } else {
+                    let regression = Self::simple_linear_regression(&x_values, &y_values, parameter, covariate);
                     regression_results.insert(
                         format!("{}_{}", parameter, covariate),
                         regression,
@@ -193,17 +302,23 @@ impl CovariateAnalyzer {
         Ok(regression_results)
     }
 
-    fn simple_linear_regression(x: &[f64], y: &[f64]) -> RegressionResults {
+    fn empty_regression(parameter: &str, covariate: &str) -> RegressionResults {
+        RegressionResults {
+            parameter: parameter.to_string(),
+            covariate: covariate.to_string(),
+            n: 0,
+            slope: None,
+            intercept: None,
+            r_squared: None,
+            p_value: None,
+            confidence_interval: None,
+        }
+    }
+
+    /// Ordinary least-squares regression of `y` (the parameter) on `x` (the covariate)
+    pub fn simple_linear_regression(x: &[f64], y: &[f64], parameter: &str, covariate: &str) -> RegressionResults {
         if x.len() != y.len() || x.len() < 2 {
-            return RegressionResults {
-                parameter: "unknown".to_string(),
-                covariate: "unknown".to_string(),
-                slope: 0.0,
-                intercept: 0.0,
-                r_squared: 0.0,
-                p_value: 1.0,
-                confidence_interval: (0.0, 0.0),
-            };
+            return Self::empty_regression(parameter, covariate);
         }
 
         let n = x.len() as f64;
@@ -218,7 +333,11 @@ impl CovariateAnalyzer {
             .map(|xi| (xi - mean_x).powi(2))
             .sum();
 
-        let slope = if denominator != 0.0 { numerator / denominator } else { 0.0 };
+        // No spread in the covariate: the slope is undefined
+        let slope = match safe_div(numerator, denominator) {
+            Some(slope) => slope,
+            None => return RegressionResults { n: x.len(), ..Self::empty_regression(parameter, covariate) },
+        };
         let intercept = mean_y - slope * mean_x;
 
         // Calculate R-squared
@@ -230,33 +349,26 @@ impl CovariateAnalyzer {
             })
             .sum();
 
-        let r_squared = if ss_tot != 0.0 { 1.0 - (ss_res / ss_tot) } else { 0.0 };
-
-        // Calculate standard error and p-value for slope
-        let mse = if n > 2.0 { ss_res / (n - 2.0) } else { 0.0 };
-        let se_slope = if denominator > 0.0 && mse > 0.0 {
-            (mse / denominator).sqrt()
-        } else {
-            0.0
-        };
+        let r_squared = safe_div(ss_res, ss_tot).map(|ratio| 1.0 - ratio);
 
-        let t_stat = if se_slope > 0.0 { slope / se_slope } else { 0.0 };
-        let p_value = if n > 2.0 {
-            2.0 * (1.0 - Self::t_cdf(t_stat.abs(), n - 2.0))
-        } else {
-            1.0
-        };
+        // Standard error and p-value of the slope need at least one residual degree of freedom
+        let se_slope = (n > 2.0).then(|| (ss_res / (n - 2.0) / denominator).sqrt());
+        let p_value = se_slope.and_then(|se| match safe_div(slope, se) {
+            Some(t_stat) => Some(2.0 * (1.0 - Self::t_cdf(t_stat.abs(), n - 2.0))),
+            // Exact fit: significant unless the parameter is flat as well
+            None => r_squared.map(|_| 0.0),
+        });
 
         // 95% confidence interval for slope
         let t_critical = 1.96; // Approximate for large samples
-        let margin_error = t_critical * se_slope;
-        let confidence_interval = (slope - margin_error, slope + margin_error);
+        let confidence_interval = se_slope.map(|se| (slope - t_critical * se, slope + t_critical * se));
 
         RegressionResults {
-            parameter: "parameter".to_string(),
-            covariate: "covariate".to_string(),
-            slope,
-            intercept,
+            parameter: parameter.to_string(),
+            covariate: covariate.to_string(),
+            n: x.len(),
+            slope: Some(slope),
+            intercept: Some(intercept),
             r_squared,
             p_value,
             confidence_interval,
@@ -266,15 +378,28 @@ impl CovariateAnalyzer {
     fn perform_dose_normalization_analysis(
         results: &[NcaResults],
         subjects: &[Subject],
+        treatment_roles: &HashMap<String, TreatmentRole>,
     ) -> Result<DoseNormalizedAnalysis> {
         let mut dose_normalized_auc = HashMap::new();
         let mut dose_normalized_cmax = HashMap::new();
         let mut dose_linearity_assessment = HashMap::new();
+        let mut exclusions = Vec::new();
 
         // Group subjects by treatment/formulation for dose linearity assessment
         let treatment_groups = Self::group_by_treatment(subjects);
 
         for (treatment, treatment_subjects) in treatment_groups {
+            // Placebo arms and zero-dose subjects carry no dose to normalize by
+            let placebo = treatment_roles.get(&treatment) == Some(&TreatmentRole::Placebo);
+            let (treatment_subjects, excluded): (Vec<Subject>, Vec<Subject>) = treatment_subjects
+                .into_iter()
+                .partition(|s| !placebo && s.dosing_events.iter().map(|d| d.dose).sum::<f64>() > 0.0);
+            exclusions.extend(excluded.into_iter().map(|s| DoseNormalizationExclusion {
+                subject_id: s.id,
+                treatment: treatment.clone(),
+                reason: if placebo { "Placebo treatment" } else { "Zero total dose" }.to_string(),
+            }));
+
             let treatment_results: Vec<&NcaResults> = results
                 .iter()
                 .filter(|r| treatment_subjects.iter().any(|s| s.id == r.subject_id))
@@ -290,12 +415,12 @@ impl CovariateAnalyzer {
             );
 
             if !dn_auc_values.is_empty() {
-                let auc_stats = Self::calculate_parameter_stats(&dn_auc_values);
+                let auc_stats = PopulationAnalyzer::calculate_parameter_stats(&dn_auc_values);
                 dose_normalized_auc.insert(treatment.clone(), auc_stats);
             }
 
             if !dn_cmax_values.is_empty() {
-                let cmax_stats = Self::calculate_parameter_stats(&dn_cmax_values);
+                let cmax_stats = PopulationAnalyzer::calculate_parameter_stats(&dn_cmax_values);
                 dose_normalized_cmax.insert(treatment.clone(), cmax_stats);
             }
 
@@ -306,22 +431,31 @@ impl CovariateAnalyzer {
             }
         }
 
+        if !exclusions.is_empty() {
+            log::info!("{} subjects excluded from the dose-normalized analysis (placebo or zero dose)", exclusions.len());
+        }
+        exclusions.sort_by(|a, b| a.subject_id.cmp(&b.subject_id));
+
         Ok(DoseNormalizedAnalysis {
             dose_normalized_auc,
             dose_normalized_cmax,
             dose_linearity_assessment,
+            exclusions,
         })
     }
 
+    /// Treatment (TRT/TREATMENT value) of a subject, `Unknown` when not recorded
+    pub fn treatment(subject: &Subject) -> String {
+        subject.demographics.treatment
+            .clone()
+            .unwrap_or_else(|| "Unknown".to_string())
+    }
+
     fn group_by_treatment(subjects: &[Subject]) -> HashMap<String, Vec<Subject>> {
         let mut groups = HashMap::new();
 
         for subject in subjects {
-            let treatment = subject.demographics.treatment
-                .clone()
-                .unwrap_or_else(|| "Unknown".to_string());
-            
-            groups.entry(treatment).or_insert_with(Vec::new).push(subject.clone());
+            groups.entry(Self::treatment(subject)).or_insert_with(Vec::new).push(subject.clone());
         }
 
         groups
@@ -355,13 +489,25 @@ impl CovariateAnalyzer {
         (dn_auc_values, dn_cmax_values, doses)
     }
 
+    /// Dose linearity of AUC_inf across subjects given different doses
+    ///
+    /// The conclusion comes from the power model ln(AUC_inf) = alpha + beta * ln(dose): dose
+    /// proportionality when the 90% CI of beta lies within 1 + ln(0.8) / ln(r) to
+    /// 1 + ln(1.25) / ln(r) (r = highest / lowest dose), non-linearity when the CI lies wholly
+    /// outside, inconclusive otherwise. The regression of dose-normalized AUC on dose is kept
+    /// for reference.
     fn assess_dose_linearity(doses: &[f64], dn_auc_values: &[f64]) -> LinearityAssessment {
+        let insufficient = |conclusion: &str| LinearityAssessment {
+            slope: 0.0,
+            r_squared: 0.0,
+            beta: None,
+            beta_confidence_interval: None,
+            acceptance_bounds: None,
+            linearity_conclusion: conclusion.to_string(),
+            conclusion_basis: String::new(),
+        };
         if doses.len() != dn_auc_values.len() || doses.len() < 3 {
-            return LinearityAssessment {
-                slope: 0.0,
-                r_squared: 0.0,
-                linearity_conclusion: "Insufficient data".to_string(),
-            };
+            return insufficient("Insufficient data");
         }
 
         // Linear regression of dose-normalized AUC vs dose
@@ -377,7 +523,11 @@ impl CovariateAnalyzer {
             .map(|d| (d - mean_dose).powi(2))
             .sum();
 
-        let slope = if denominator != 0.0 { numerator / denominator } else { 0.0 };
+        // A single dose level gives no information on linearity
+        let slope = match safe_div(numerator, denominator) {
+            Some(slope) => slope,
+            None => return insufficient("Insufficient dose range"),
+        };
 
         // Calculate R-squared
         let ss_tot: f64 = dn_auc_values.iter()
@@ -391,94 +541,65 @@ impl CovariateAnalyzer {
             })
             .sum();
 
-        let r_squared = if ss_tot != 0.0 { 1.0 - (ss_res / ss_tot) } else { 0.0 };
+        let r_squared = safe_div(ss_res, ss_tot).map_or(0.0, |ratio| 1.0 - ratio);
 
-        // Assess linearity
-        let linearity_conclusion = if slope.abs() < 0.1 && r_squared < 0.3 {
-            "Linear pharmacokinetics".to_string()
-        } else if slope.abs() > 0.3 || r_squared > 0.7 {
-            "Non-linear pharmacokinetics".to_string()
+        let Some((beta, (ci_lower, ci_upper))) = Self::power_model_beta(doses, dn_auc_values) else {
+            return LinearityAssessment { slope, r_squared, ..insufficient("Insufficient data") };
+        };
+        let dose_ratio = doses.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+            / doses.iter().copied().fold(f64::INFINITY, f64::min);
+        let (lower_limit, upper_limit) = DOSE_PROPORTIONALITY_LIMITS;
+        let (bound_lower, bound_upper) = (1.0 + lower_limit.ln() / dose_ratio.ln(), 1.0 + upper_limit.ln() / dose_ratio.ln());
+
+        let interval = format!("90% CI of beta [{:.4}, {:.4}]", ci_lower, ci_upper);
+        let bounds = format!("[{:.4}, {:.4}]", bound_lower, bound_upper);
+        let (linearity_conclusion, conclusion_basis) = if ci_lower >= bound_lower && ci_upper <= bound_upper {
+            ("Linear pharmacokinetics", format!("{} within {}", interval, bounds))
+        } else if ci_upper < bound_lower || ci_lower > bound_upper {
+            ("Non-linear pharmacokinetics", format!("{} outside {}", interval, bounds))
         } else {
-            "Inconclusive".to_string()
+            ("Inconclusive", format!("{} overlaps the limits of {}", interval, bounds))
         };
 
         LinearityAssessment {
             slope,
             r_squared,
-            linearity_conclusion,
+            beta: Some(beta),
+            beta_confidence_interval: Some((ci_lower, ci_upper)),
+            acceptance_bounds: Some((bound_lower, bound_upper)),
+            linearity_conclusion: linearity_conclusion.to_string(),
+            conclusion_basis,
         }
     }
 
-    fn calculate_parameter_stats(values: &[f64]) -> ParameterStats {
-        let n = values.len();
-        
-        if n == 0 {
-            return ParameterStats {
-                n: 0,
-                mean: 0.0, 
-                arithmetic_mean: 0.0,
-                arithmetic_std: 0.0,
-                arithmetic_cv_percent: 0.0,
-                std: 0.0, 
-                cv_percent: 0.0, 
-                median: 0.0,
-                q25: 0.0,
-                q75: 0.0,
-                min: 0.0,
-                max: 0.0,
-                geometric_mean: None,
-                geometric_cv_percent: None,
-            };
+    /// Power-model exponent beta from regressing ln(AUC_inf) on ln(dose), with its 90% CI
+    /// from the t distribution on n - 2 degrees of freedom
+    fn power_model_beta(doses: &[f64], dn_auc_values: &[f64]) -> Option<(f64, (f64, f64))> {
+        let (ln_doses, ln_aucs): (Vec<f64>, Vec<f64>) = doses
+            .iter()
+            .zip(dn_auc_values)
+            .map(|(&dose, &dn_auc)| (dose, dn_auc * dose))
+            .filter(|&(dose, auc)| dose > 0.0 && auc > 0.0)
+            .map(|(dose, auc)| (dose.ln(), auc.ln()))
+            .unzip();
+        let n = ln_doses.len() as f64;
+        if n < 3.0 {
+            return None;
         }
 
-        let arithmetic_mean = values.mean();
-        let arithmetic_std = values.std_dev();
-        let arithmetic_cv_percent = if arithmetic_mean != 0.0 { (arithmetic_std / arithmetic_mean) * 100.0 } else { 0.0 };
-
-        let mut sorted_values = values.to_vec();
-        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-        let median = if n % 2 == 0 {
-            (sorted_values[n / 2 - 1] + sorted_values[n / 2]) / 2.0
-        } else {
-            sorted_values[n / 2]
-        };
-
-        let q25_idx = ((n as f64 * 0.25) as usize).min(n - 1);
-        let q75_idx = ((n as f64 * 0.75) as usize).min(n - 1);
-        let q25 = sorted_values[q25_idx];
-        let q75 = sorted_values[q75_idx];
-
-        let min = sorted_values[0];
-        let max = sorted_values[n - 1];
-
-        // Geometric statistics
-        let (geometric_mean, geometric_cv_percent) = if values.iter().all(|&v| v > 0.0) {
-            let ln_values: Vec<f64> = values.iter().map(|v| v.ln()).collect();
-            let ln_mean = ln_values.as_slice().mean();
-            let ln_std = ln_values.as_slice().std_dev();
-            let geo_mean = ln_mean.exp();
-            let geo_cv = ((ln_std.exp().powi(2) - 1.0).sqrt()) * 100.0;
-            (Some(geo_mean), Some(geo_cv))
-        } else {
-            (None, None)
-        };
+        let mean_x = ln_doses.iter().sum::<f64>() / n;
+        let mean_y = ln_aucs.iter().sum::<f64>() / n;
+        let sxx: f64 = ln_doses.iter().map(|x| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = ln_doses.iter().zip(&ln_aucs).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let beta = safe_div(sxy, sxx)?;
+        let ss_res: f64 = ln_doses
+            .iter()
+            .zip(&ln_aucs)
+            .map(|(x, y)| (y - mean_y - beta * (x - mean_x)).powi(2))
+            .sum();
+        let se_beta = (ss_res / (n - 2.0) / sxx).sqrt();
+        let t_critical = StudentsT::new(0.0, 1.0, n - 2.0).ok()?.inverse_cdf(0.95);
 
-        ParameterStats {
-            n,
-            mean: arithmetic_mean, 
-            arithmetic_mean,
-            arithmetic_std,
-            arithmetic_cv_percent,
-            std: arithmetic_std, 
-            cv_percent: arithmetic_cv_percent, 
-            median,
-            q25,
-            q75,
-            min,
-            max,
-            geometric_mean,
-            geometric_cv_percent,
-        }
+        Some((beta, (beta - t_critical * se_beta, beta + t_critical * se_beta)))
     }
 }
\ No newline at end of file
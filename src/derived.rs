@@ -0,0 +1,77 @@
+use crate::{models::*, math::safe_div};
+use std::fmt;
+use std::sync::Arc;
+
+/// Derivation of one custom parameter from a subject's estimated parameters; `None` when
+/// the inputs it needs are unavailable
+pub type DerivationFn = dyn Fn(&IndividualParameters, &Subject) -> Option<f64> + Send + Sync;
+
+/// Named custom parameter derivations, run in registration order after each subject's NCA
+///
+/// Results land in [`IndividualParameters::derived`] and are looked up by name through
+/// [`IndividualParameters::get`].
+#[derive(Clone, Default)]
+pub struct DerivationRegistry {
+    derivations: Vec<(String, Arc<DerivationFn>)>,
+}
+
+impl DerivationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry holding the built-in derivations: AUC_last / AUC_inf and Cmax / total dose
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("auc_last_inf_ratio", |params, _| {
+            params.auc_last.zip(params.auc_inf).and_then(|(auc_last, auc_inf)| safe_div(auc_last, auc_inf))
+        });
+        registry.register("cmax_per_dose", |params, subject| {
+            let total_dose: f64 = subject.dosing_events.iter().map(|dose| dose.dose).sum();
+            params.cmax.and_then(|cmax| safe_div(cmax, total_dose))
+        });
+        registry
+    }
+
+    /// Add a derivation, replacing any earlier one of the same name
+    pub fn register<F>(&mut self, name: &str, derivation: F) -> &mut Self
+    where
+        F: Fn(&IndividualParameters, &Subject) -> Option<f64> + Send + Sync + 'static,
+    {
+        self.derivations.retain(|(existing, _)| existing != name);
+        self.derivations.push((name.to_string(), Arc::new(derivation)));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.derivations.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.derivations.is_empty()
+    }
+
+    /// Evaluate every registered derivation for one subject
+    pub fn derive(&self, params: &IndividualParameters, subject: &Subject) -> Vec<DerivedParameter> {
+        self.derivations
+            .iter()
+            .map(|(name, derivation)| DerivedParameter {
+                name: name.clone(),
+                value: derivation(params, subject).filter(|value| value.is_finite()),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for DerivationRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Registries compare by derivation names, as closures cannot be compared
+impl PartialEq for DerivationRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        self.names().eq(other.names())
+    }
+}
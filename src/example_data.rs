@@ -1,25 +1,80 @@
 use crate::{models::*, Result};
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 pub struct ExampleDataGenerator;
 
+/// Simulation settings for [`ExampleDataGenerator::generate_dataset_with_options`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExampleDataOptions {
+    /// Random seed; the same seed and options reproduce the same dataset
+    pub seed: u64,
+    /// Inter-subject variability (CV, as a fraction) of CL, V and ka; `None` keeps the
+    /// built-in 30%, 40% and 50%
+    pub iiv_cv: Option<f64>,
+    /// Residual error CV (fraction) of each concentration
+    pub residual_cv: f64,
+    /// Draw subcutaneous doses alongside IV bolus, infusion and oral
+    pub include_subcutaneous: bool,
+    /// Fraction of post-dose samples dropped at random, as missed or lost samples
+    pub missing_fraction: f64,
+    /// Fraction of subjects whose profile stops early; at least the first three samples are kept
+    pub dropout_fraction: f64,
+}
+
+impl Default for ExampleDataOptions {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            iiv_cv: None,
+            residual_cv: 0.15,
+            include_subcutaneous: false,
+            missing_fraction: 0.0,
+            dropout_fraction: 0.0,
+        }
+    }
+}
+
 impl ExampleDataGenerator {
     pub fn generate_dataset<P: AsRef<Path>>(
         output_path: P,
         n_subjects: usize,
     ) -> Result<()> {
-        let mut rng = StdRng::seed_from_u64(42); // Reproducible results
+        Self::generate_dataset_with_options(output_path, n_subjects, &ExampleDataOptions::default())
+    }
+
+    /// Like [`Self::generate_dataset`], optionally drawing subcutaneous doses alongside
+    /// IV bolus, infusion and oral
+    pub fn generate_dataset_with_routes<P: AsRef<Path>>(
+        output_path: P,
+        n_subjects: usize,
+        include_subcutaneous: bool,
+    ) -> Result<()> {
+        let options = ExampleDataOptions {
+            include_subcutaneous,
+            ..ExampleDataOptions::default()
+        };
+        Self::generate_dataset_with_options(output_path, n_subjects, &options)
+    }
+
+    /// Generate an example dataset with the given seed, variability and routes
+    pub fn generate_dataset_with_options<P: AsRef<Path>>(
+        output_path: P,
+        n_subjects: usize,
+        options: &ExampleDataOptions,
+    ) -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(options.seed); // Reproducible results
         let mut file = File::create(output_path)?;
         
         // Write header
-        writeln!(file, "ID,TIME,DV,AMT,EVID,CMT,RATE,SS,II,ADDL,MDV,BLQ,LLOQ,AGE,WT,HT,SEX,RACE,TRT,STDAY,PERIOD,SEQ,FORM")?;
+        writeln!(file, "ID,TIME,DV,AMT,EVID,CMT,RATE,SS,II,ADDL,MDV,BLQ,LLOQ,AGE,WT,HT,SEX,RACE,TRT,STDAY,PERIOD,SEQ,FORM,ROUTE")?;
         
         for subject_id in 1..=n_subjects {
-            let subject_data = Self::generate_subject_data(&mut rng, subject_id)?;
+            let subject_data = Self::generate_subject_data(&mut rng, subject_id, options)?;
             Self::write_subject_data(&mut file, &subject_data)?;
         }
         
@@ -27,7 +82,7 @@ impl ExampleDataGenerator {
         Ok(())
     }
 
-    fn generate_subject_data(rng: &mut StdRng, subject_id: usize) -> Result<Subject> {
+    fn generate_subject_data(rng: &mut StdRng, subject_id: usize, options: &ExampleDataOptions) -> Result<Subject> {
         // Demographics
         let age = rng.gen_range(18.0..80.0);
         let weight = rng.gen_range(50.0..120.0);
@@ -51,15 +106,20 @@ impl ExampleDataGenerator {
             period: Some(rng.gen_range(1..=3)),
             sequence: Some(format!("SEQ{}", rng.gen_range(1..=4))),
             formulation: Some(formulation.to_string()),
+            analysis_weight: None,
+            extra: HashMap::new(),
         };
 
         // Generate dosing event
         let dose = rng.gen_range(10.0..500.0);
-        let dosing_routes = [
+        let mut dosing_routes = vec![
             (DosingRoute::IntravenousBolus, None),
             (DosingRoute::IntravenousInfusion, Some(rng.gen_range(0.5..4.0))),
             (DosingRoute::Oral, None),
         ];
+        if options.include_subcutaneous {
+            dosing_routes.push((DosingRoute::Subcutaneous, None));
+        }
         let (route, infusion_duration) = dosing_routes[rng.gen_range(0..dosing_routes.len())].clone();
 
         let dosing_event = DosingEvent {
@@ -68,16 +128,23 @@ impl ExampleDataGenerator {
             route: route.clone(),
             infusion_duration,
             evid: 1,
+            cmt: Some(1),
+            steady_state: false,
+            interval: None,
         };
 
         // Generate concentration-time profile
-        let observations = Self::generate_concentration_profile(rng, &route, dose, weight)?;
+        let mut observations = Self::generate_concentration_profile(rng, &route, dose, weight, options)?;
+        Self::apply_missing_data(rng, &mut observations, options);
 
         Ok(Subject {
             id: subject_id.to_string(),
             observations,
             dosing_events: vec![dosing_event],
             demographics,
+            covariate_records: Vec::new(),
+            analyte: None,
+            matrix: None,
         })
     }
 
@@ -86,25 +153,21 @@ impl ExampleDataGenerator {
         route: &DosingRoute,
         dose: f64,
         weight: f64,
+        options: &ExampleDataOptions,
     ) -> Result<Vec<Observation>> {
         let mut observations = Vec::new();
         
         // Typical PK parameters (population values with variability)
-        let cl = Self::log_normal_random(rng, 10.0, 0.3) * (weight / 70.0).powf(0.75); // Allometric scaling
-        let vd = Self::log_normal_random(rng, 50.0, 0.4) * (weight / 70.0);
-        let ka = match route {
-            DosingRoute::Oral => Self::log_normal_random(rng, 1.0, 0.5),
-            _ => 0.0,
-        };
-        let f = match route {
-            DosingRoute::Oral => Self::log_normal_random(rng, 0.8, 0.2).min(1.0),
-            _ => 1.0,
-        };
+        let cl = Self::log_normal_random(rng, 10.0, options.iiv_cv.unwrap_or(0.3)) * (weight / 70.0).powf(0.75); // Allometric scaling
+        let vd = Self::log_normal_random(rng, 50.0, options.iiv_cv.unwrap_or(0.4)) * (weight / 70.0);
+        let ka = if route.is_extravascular() { Self::log_normal_random(rng, 1.0, options.iiv_cv.unwrap_or(0.5)) } else { 0.0 };
+        let f = if route.is_extravascular() { Self::log_normal_random(rng, 0.8, 0.2).min(1.0) } else { 1.0 };
 
         // Time points
-        let time_points = match route {
-            DosingRoute::Oral => vec![0.0, 0.25, 0.5, 1.0, 2.0, 4.0, 6.0, 8.0, 12.0, 24.0, 36.0, 48.0],
-            _ => vec![0.0, 0.083, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 12.0, 24.0, 48.0, 72.0],
+        let time_points = if route.is_extravascular() {
+            vec![0.0, 0.25, 0.5, 1.0, 2.0, 4.0, 6.0, 8.0, 12.0, 24.0, 36.0, 48.0]
+        } else {
+            vec![0.0, 0.083, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 12.0, 24.0, 48.0, 72.0]
         };
 
         for time in time_points {
@@ -113,8 +176,7 @@ impl ExampleDataGenerator {
             );
             
             // Add residual error
-            let cv_error = 0.15; // 15% CV
-            let error_factor = Self::log_normal_random(rng, 1.0, cv_error);
+            let error_factor = Self::log_normal_random(rng, 1.0, options.residual_cv);
             let final_concentration = (concentration * error_factor).max(0.0);
             
             // LLOQ handling
@@ -126,8 +188,11 @@ impl ExampleDataGenerator {
                 concentration: if bloq { lloq / 2.0 } else { final_concentration },
                 lloq: Some(lloq),
                 bloq,
+                lod: None,
+                blod: false,
                 evid: 0,
                 dv: if bloq { lloq / 2.0 } else { final_concentration },
+                cmt: Some(Self::observation_compartment(route)),
             });
         }
         
@@ -162,8 +227,8 @@ impl ExampleDataGenerator {
                     c_end_inf * (-k * (time - t_inf)).exp()
                 }
             }
-            DosingRoute::Oral => {
-                // One-compartment oral: C = (F*Dose*ka/(Vd*(ka-k))) * (exp(-k*t) - exp(-ka*t))
+            DosingRoute::Oral | DosingRoute::Subcutaneous | DosingRoute::Intramuscular => {
+                // One-compartment first-order absorption: C = (F*Dose*ka/(Vd*(ka-k))) * (exp(-k*t) - exp(-ka*t))
                 if ka == k {
                     (f * dose / vd) * time * (-k * time).exp()
                 } else {
@@ -173,6 +238,31 @@ impl ExampleDataGenerator {
         }
     }
 
+    /// Drop random samples and truncate dropout profiles per `options`
+    ///
+    /// The pre-dose sample is always kept. No random numbers are drawn for a zero fraction,
+    /// so complete datasets are unchanged for a given seed.
+    fn apply_missing_data(rng: &mut StdRng, observations: &mut Vec<Observation>, options: &ExampleDataOptions) {
+        if options.dropout_fraction > 0.0 && rng.gen_bool(options.dropout_fraction.min(1.0)) && observations.len() > 3 {
+            let last_kept = rng.gen_range(3..observations.len());
+            observations.truncate(last_kept);
+        }
+
+        if options.missing_fraction > 0.0 {
+            let mut index = 0;
+            observations.retain(|_| {
+                index += 1;
+                index == 1 || !rng.gen_bool(options.missing_fraction.min(1.0))
+            });
+        }
+    }
+
+    /// Extravascular doses go into the depot (CMT=1) and are observed in the central compartment (CMT=2);
+    /// intravascular doses are given and observed in the central compartment (CMT=1)
+    fn observation_compartment(route: &DosingRoute) -> i32 {
+        if route.is_extravascular() { 2 } else { 1 }
+    }
+
     fn log_normal_random(rng: &mut StdRng, median: f64, cv: f64) -> f64 {
         let sigma = (1.0 + cv * cv).ln().sqrt();
         let mu = median.ln() - 0.5 * sigma * sigma;
@@ -193,16 +283,23 @@ impl ExampleDataGenerator {
     fn write_subject_data(file: &mut File, subject: &Subject) -> Result<()> {
         // Write dosing record
         for dose_event in &subject.dosing_events {
-            let (rate, cmt) = match (&dose_event.route, dose_event.infusion_duration) {
-                (DosingRoute::IntravenousBolus, _) => (-1.0, 1),
-                (DosingRoute::IntravenousInfusion, Some(duration)) => (dose_event.dose / duration, 1),
-                (DosingRoute::Oral, _) => (-2.0, 1),
-                _ => (0.0, 1),
+            let rate = match (&dose_event.route, dose_event.infusion_duration) {
+                (DosingRoute::IntravenousBolus, _) => -1.0,
+                (DosingRoute::IntravenousInfusion, Some(duration)) => dose_event.dose / duration,
+                _ => 0.0,
             };
+            let route = match dose_event.route {
+                DosingRoute::IntravenousBolus => "IV",
+                DosingRoute::IntravenousInfusion => "INF",
+                DosingRoute::Oral => "ORAL",
+                DosingRoute::Subcutaneous => "SC",
+                DosingRoute::Intramuscular => "IM",
+            };
+            let cmt = dose_event.cmt.unwrap_or(1);
 
             writeln!(
                 file,
-                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
                 subject.id,
                 dose_event.time,
                 0.0, // DV
@@ -210,8 +307,8 @@ impl ExampleDataGenerator {
                 1, // EVID
                 cmt, // CMT
                 rate, // RATE
-                0, // SS
-                0, // II
+                if dose_event.steady_state { 1 } else { 0 }, // SS
+                dose_event.interval.unwrap_or(0.0), // II
                 0, // ADDL
                 0, // MDV
                 0, // BLQ
@@ -226,6 +323,7 @@ impl ExampleDataGenerator {
                 subject.demographics.period.unwrap_or(1),
                 subject.demographics.sequence.as_ref().unwrap_or(&"SEQ1".to_string()),
                 subject.demographics.formulation.as_ref().unwrap_or(&"Tablet".to_string()),
+                route,
             )?;
         }
 
@@ -233,13 +331,13 @@ impl ExampleDataGenerator {
         for obs in &subject.observations {
             writeln!(
                 file,
-                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},", // empty ROUTE
                 subject.id,
                 obs.time,
                 obs.concentration, // DV
                 0, // AMT
                 0, // EVID
-                1, // CMT
+                obs.cmt.unwrap_or(1), // CMT
                 0, // RATE
                 0, // SS
                 0, // II
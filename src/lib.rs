@@ -14,6 +14,11 @@ pub mod example_data;
 pub mod errors;
 pub mod stratification;
 pub mod covariate;
+pub mod math;
+pub mod preprocessing;
+pub mod cdisc;
+pub mod derived;
+pub mod cache;
 
 pub use models::*;
 pub use nca::*;
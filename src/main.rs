@@ -1,14 +1,16 @@
-use clap::{Arg, Command};
-use env_logger;
+use clap::{parser::ValueSource, Arg, Command};
 use nca_analysis::{
     models::*,
-    parser::NonmemParser,
-    population::PopulationAnalyzer,
+    parser::{NonmemParser, ParseOptions},
+    derived::DerivationRegistry,
+    population::{PopulationAnalyzer, DEFAULT_PARALLEL_CHUNK_SIZE},
     output::OutputManager,
-    example_data::ExampleDataGenerator,
+    example_data::{ExampleDataGenerator, ExampleDataOptions},
+    NcaError,
     Result,
 };
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<()> {
     env_logger::init();
@@ -25,6 +27,12 @@ fn main() -> Result<()> {
                 .help("Input NONMEM dataset file")
                 .required_unless_present("generate-example"),
         )
+        .arg(
+            Arg::new("covariates")
+                .long("covariates")
+                .value_name("FILE")
+                .help("Demographics/covariates CSV with one row per ID, joined onto the dataset's subjects"),
+        )
         .arg(
             Arg::new("output")
                 .short('o')
@@ -47,6 +55,46 @@ fn main() -> Result<()> {
                 .help("Number of subjects for example dataset")
                 .default_value("20"),
         )
+        .arg(
+            Arg::new("example-sc")
+                .long("example-sc")
+                .help("Include subcutaneous subjects in the example dataset")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("example-seed")
+                .long("example-seed")
+                .value_name("SEED")
+                .help("Random seed for the example dataset")
+                .default_value("42"),
+        )
+        .arg(
+            Arg::new("example-iiv-cv")
+                .long("example-iiv-cv")
+                .value_name("PERCENT")
+                .help("Inter-subject variability CV of CL, V and ka in the example dataset (default: 30, 40 and 50)"),
+        )
+        .arg(
+            Arg::new("example-residual-cv")
+                .long("example-residual-cv")
+                .value_name("PERCENT")
+                .help("Residual error CV of example concentrations")
+                .default_value("15"),
+        )
+        .arg(
+            Arg::new("example-missing")
+                .long("example-missing")
+                .value_name("PERCENT")
+                .help("Percentage of example samples dropped at random")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("example-dropout")
+                .long("example-dropout")
+                .value_name("PERCENT")
+                .help("Percentage of example subjects whose profile ends early")
+                .default_value("0"),
+        )
         .arg(
             Arg::new("lloq-handling")
                 .long("lloq-handling")
@@ -54,6 +102,12 @@ fn main() -> Result<()> {
                 .help("LLOQ handling method: zero, drop, half-lloq")
                 .default_value("half-lloq"),
         )
+        .arg(
+            Arg::new("blod-handling")
+                .long("blod-handling")
+                .value_name("METHOD")
+                .help("Separate handling of samples below the limit of detection (BLOD column): zero, drop, half-lod"),
+        )
         .arg(
             Arg::new("lambda-z-method")
                 .long("lambda-z-method")
@@ -61,6 +115,31 @@ fn main() -> Result<()> {
                 .help("Lambda_z selection method: auto, best-fit")
                 .default_value("auto"),
         )
+        .arg(
+            Arg::new("lambda-z-start")
+                .long("lambda-z-start")
+                .value_name("TIME")
+                .help("Fit lambda_z over every quantifiable point at or after TIME (overrides --lambda-z-method)"),
+        )
+        .arg(
+            Arg::new("lambda-z-min-r2")
+                .long("lambda-z-min-r2")
+                .value_name("R2")
+                .help("Minimum R² for a lambda_z regression to be accepted")
+                .default_value("0.8"),
+        )
+        .arg(
+            Arg::new("lambda-z-exclude-tmax")
+                .long("lambda-z-exclude-tmax")
+                .help("Start automatically selected lambda_z windows after Tmax, so sparse profiles cannot anchor the slope on Cmax")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("report-unreliable-lambda-z")
+                .long("report-unreliable-lambda-z")
+                .help("Report the best lambda_z below the R² minimum, flagged as unreliable, instead of none")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("time-units")
                 .long("time-units")
@@ -75,18 +154,323 @@ fn main() -> Result<()> {
                 .help("Concentration units")
                 .default_value("ng/mL"),
         )
+        .arg(
+            Arg::new("dose-units")
+                .long("dose-units")
+                .value_name("UNITS")
+                .help("Dose (AMT) units, used for clearance and volume units")
+                .default_value("mg"),
+        )
+        .arg(
+            Arg::new("apply-dilution")
+                .long("apply-dilution")
+                .help("Multiply concentrations by the DILUTION/DF column (for datasets not already corrected)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("id-column")
+                .long("id-column")
+                .value_name("COLUMN")
+                .help("Subject identifier column (default: first of ID, SUBJID, USUBJID, SUBJECT present)"),
+        )
+        .arg(
+            Arg::new("dv-scale")
+                .long("dv-scale")
+                .value_name("SCALE")
+                .help("Scale of the DV column: linear, log (natural log, exponentiated when parsing)")
+                .default_value("linear"),
+        )
+        .arg(
+            Arg::new("no-method-comparison")
+                .long("no-method-comparison")
+                .help("Skip the per-subject AUC method comparison and compute only the primary method")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("dose-normalization")
                 .long("dose-normalization")
                 .help("Enable dose normalization")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("streaming")
+                .long("streaming")
+                .help("Write individual results as subjects complete (bounded memory); only per-subject, QC and summary files and the manifest are written, and pooled lambda_z, stratification, covariate analysis, dose normalization and data completeness are rejected")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("geometric-non-positive")
+                .long("geometric-non-positive")
+                .value_name("MODE")
+                .help("Zero/negative values in geometric statistics: exclude, undefined, offset=VALUE")
+                .default_value("exclude"),
+        )
+        .arg(
+            Arg::new("weighted-summary")
+                .long("weighted-summary")
+                .help("Weight summary statistics by the WEIGHT/ANALYSIS_WT column")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("data-completeness")
+                .long("data-completeness")
+                .help("Report per-subject sampling completeness")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("covariate-analysis")
                 .long("covariate-analysis")
                 .help("Perform covariate analysis")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("parallel-chunk-size")
+                .long("parallel-chunk-size")
+                .value_name("N")
+                .help("Subjects analyzed per parallel task, at least 1 (default: 16)"),
+        )
+        .arg(
+            Arg::new("covariate-p-adjust")
+                .long("covariate-p-adjust")
+                .value_name("METHOD")
+                .help("Multiplicity adjustment of covariate correlation p-values: bh (Benjamini-Hochberg), bonferroni, none")
+                .default_value("bh"),
+        )
+        .arg(
+            Arg::new("covariate-alpha")
+                .long("covariate-alpha")
+                .value_name("LEVEL")
+                .help("Level (FDR for bh) at which an adjusted covariate correlation p-value is significant")
+                .default_value("0.05"),
+        )
+        .arg(
+            Arg::new("partial-correlation-control")
+                .long("partial-correlation-control")
+                .value_name("COVARIATE")
+                .help("Also report covariate correlations controlled for this covariate: age, weight, height"),
+        )
+        .arg(
+            Arg::new("min-regression-n")
+                .long("min-regression-n")
+                .value_name("N")
+                .help("Minimum number of subjects for a covariate regression to be reported (at least 3)")
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("derived-parameters")
+                .long("derived-parameters")
+                .help("Report the built-in derived parameters (AUC_last/AUC_inf, Cmax/dose) in derived_parameters.csv")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("end-of-infusion-cmax")
+                .long("end-of-infusion-cmax")
+                .help("Also report the concentration interpolated at the end of infusion (CMAX_EOI) for infusion subjects")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("export-concentrations")
+                .long("export-concentrations")
+                .help("Write the concentrations each subject was analyzed with (after exclusions and BLQ handling) to concentrations.csv")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json-significant-digits")
+                .long("json-significant-digits")
+                .value_name("DIGITS")
+                .help("Round decimal numbers in complete_results.json to DIGITS significant digits (default: full precision)"),
+        )
+        .arg(
+            Arg::new("include-pre-dose")
+                .long("include-pre-dose")
+                .help("Keep samples taken before the first dose in the analyzed profile and AUC")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("min-observed-points")
+                .long("min-observed-points")
+                .value_name("N")
+                .help("Minimum quantifiable concentrations for a subject to be analyzed (observed-only parameters, at least 2)")
+                .default_value("2"),
+        )
+        .arg(
+            Arg::new("min-terminal-points")
+                .long("min-terminal-points")
+                .value_name("N")
+                .help("Minimum quantifiable concentrations for terminal-phase parameters (lambda_z, AUC_inf, CL), at least --min-observed-points")
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("analyte-cmt")
+                .long("analyte-cmt")
+                .value_name("CMT")
+                .help("Compartment (CMT) of the analyte; observations in other compartments are ignored"),
+        )
+        .arg(
+            Arg::new("auc-extrap-threshold")
+                .long("auc-extrap-threshold")
+                .value_name("PERCENT")
+                .help("AUC extrapolation percentage above which AUC_inf is flagged as unreliable")
+                .default_value("20"),
+        )
+        .arg(
+            Arg::new("exclude-unreliable-auc-inf")
+                .long("exclude-unreliable-auc-inf")
+                .help("Leave AUC_inf values flagged unreliable out of the population summary")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("pooled-lambda-z")
+                .long("pooled-lambda-z")
+                .help("Extrapolate AUC_inf with a lambda_z pooled over the dose group for subjects without their own terminal fit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("primary-auc-method")
+                .long("primary-auc-method")
+                .value_name("METHOD")
+                .help("AUC method of the reported AUC_last and its derived parameters: linear, log, linear-log, linear-up-log-down, rectangular (default: linear)"),
+        )
+        .arg(
+            Arg::new("auc-tau-anchor")
+                .long("auc-tau-anchor")
+                .value_name("ANCHOR")
+                .help("Start concentration for steady-state AUCtau: pre-dose, trough")
+                .default_value("pre-dose"),
+        )
+        .arg(
+            Arg::new("auc-extrap-slope")
+                .long("auc-extrap-slope")
+                .value_name("SLOPE")
+                .help("Slope extrapolating AUC_inf: lambda-z (terminal regression), last-two-points, pooled-group (lambda-z, or the pooled dose-group lambda_z without a fit)")
+                .default_value("lambda-z"),
+        )
+        .arg(
+            Arg::new("min-tmax-tlast-time")
+                .long("min-tmax-tlast-time")
+                .value_name("TIME")
+                .help("Report AUC_inf and its derived parameters only when Tlast - Tmax is at least TIME"),
+        )
+        .arg(
+            Arg::new("min-tmax-tlast-half-lives")
+                .long("min-tmax-tlast-half-lives")
+                .value_name("N")
+                .help("Report AUC_inf and its derived parameters only when Tlast - Tmax is at least N terminal half-lives"),
+        )
+        .arg(
+            Arg::new("population-summary")
+                .long("population-summary")
+                .value_name("STATISTICS")
+                .help("Statistics in population_summary.csv: both, arithmetic, geometric")
+                .default_value("both"),
+        )
+        .arg(
+            Arg::new("dose-time-concentration")
+                .long("dose-time-concentration")
+                .value_name("ASSUMPTION")
+                .help("Concentration at a dose time before the first sample: first-sample (none, AUC starts at the first sample), route (C0 for IV bolus, zero otherwise)")
+                .default_value("first-sample"),
+        )
+        .arg(
+            Arg::new("dosing-interval")
+                .long("dosing-interval")
+                .value_name("TAU")
+                .help("Compute AUCtau over [last dose, last dose + TAU] regardless of the steady-state flag; TAU > 0"),
+        )
+        .arg(
+            Arg::new("concentration-threshold")
+                .long("concentration-threshold")
+                .value_name("CONC")
+                .help("Report the time and AUC above CONC (e.g. an MIC) and the times the profile crosses it"),
+        )
+        .arg(
+            Arg::new("threshold-interpolation")
+                .long("threshold-interpolation")
+                .value_name("METHOD")
+                .help("Interpolation locating threshold crossings between samples: linear, log")
+                .default_value("linear"),
+        )
+        .arg(
+            Arg::new("partial-auc")
+                .long("partial-auc")
+                .value_name("START-END")
+                .help("Report a partial AUC over the given interval (e.g. 0-24)")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("max-concentration")
+                .long("max-concentration")
+                .value_name("CONC")
+                .help("Highest plausible concentration; larger values are flagged in concentration_qc.csv"),
+        )
+        .arg(
+            Arg::new("extravascular-bioavailability")
+                .long("extravascular-bioavailability")
+                .value_name("F")
+                .help("Bioavailability assumed for extravascular doses of subjects dosed by several routes (e.g. IV loading, oral maintenance)"),
+        )
+        .arg(
+            Arg::new("max-dose-ratio")
+                .long("max-dose-ratio")
+                .value_name("RATIO")
+                .help("Largest plausible ratio between a subject's largest and smallest dose; wider ranges are flagged in dose_qc.csv")
+                .default_value("100"),
+        )
+        .arg(
+            Arg::new("max-cl-discrepancy")
+                .long("max-cl-discrepancy")
+                .value_name("PERCENT")
+                .help("Largest difference between Dose/AUC_inf and Dose/AUC_inf_pred before clearance is flagged in clearance_qc.csv")
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("replicates")
+                .long("replicates")
+                .value_name("METHOD")
+                .help("Combine replicate assays at identical times: keep, mean, geometric-mean")
+                .default_value("keep"),
+        )
+        .arg(
+            Arg::new("replicate-cv-threshold")
+                .long("replicate-cv-threshold")
+                .value_name("PERCENT")
+                .help("Inter-replicate CV above which averaged replicates are flagged")
+                .default_value("20"),
+        )
+        .arg(
+            Arg::new("summary-convention")
+                .long("summary-convention")
+                .value_name("PARAM=STAT")
+                .help("Statistic emphasized for a parameter in reports: geometric, arithmetic or harmonic (e.g. tmax=arithmetic)")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("parameter-names")
+                .long("parameter-names")
+                .value_name("SCHEME")
+                .help("Naming scheme of individual_results.csv columns: default, winnonlin, cdisc")
+                .default_value("default"),
+        )
+        .arg(
+            Arg::new("column-alias")
+                .long("column-alias")
+                .value_name("COLUMN=NAME")
+                .help("Custom name of an individual_results.csv column (e.g. AUC_INF=AUC0-inf)")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("units-in-headers")
+                .long("units-in-headers")
+                .help("Label the LAMBDA_Z and half-life columns of individual_results.csv with their units, e.g. LAMBDA_Z (1/h)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("placebo-treatment")
+                .long("placebo-treatment")
+                .value_name("TREATMENT")
+                .help("Treatment arm without active drug, excluded from dose-normalized analysis (repeatable)")
+                .action(clap::ArgAction::Append),
+        )
         .arg(
             Arg::new("stratify-by")
                 .long("stratify-by")
@@ -94,6 +478,13 @@ fn main() -> Result<()> {
                 .help("Stratify results by the specified column")
                 .action(clap::ArgAction::Append),
         )
+        .arg(
+            Arg::new("comparison-test")
+                .long("comparison-test")
+                .value_name("TEST")
+                .help("Test for pairwise strata comparisons: welch (default), pooled-t, mann-whitney (adds the Hodges-Lehmann shift and CI)")
+                .default_value("welch"),
+        )
         .get_matches();
 
     let output_dir = PathBuf::from(matches.get_one::<String>("output").unwrap());
@@ -108,7 +499,8 @@ fn main() -> Result<()> {
         let example_file = output_dir.join("example_dataset.csv");
         std::fs::create_dir_all(&output_dir)?;
         
-        ExampleDataGenerator::generate_dataset(&example_file, n_subjects)?;
+        let options = create_example_options(&matches)?;
+        ExampleDataGenerator::generate_dataset_with_options(&example_file, n_subjects, &options)?;
         println!("Generated example dataset: {}", example_file.display());
         
         if !matches.contains_id("input") {
@@ -128,8 +520,8 @@ fn main() -> Result<()> {
 }
 
 fn run_analysis(
-    input_path: &PathBuf,
-    output_dir: &PathBuf,
+    input_path: &Path,
+    output_dir: &Path,
     matches: &clap::ArgMatches,
 ) -> Result<()> {
     println!("Starting NCA analysis...");
@@ -137,16 +529,70 @@ fn run_analysis(
     println!("Output directory: {}", output_dir.display());
 
     // Parse configuration
-    let config = create_analysis_config(matches, output_dir)?;
+    let mut config = create_analysis_config(matches, output_dir)?;
+    if let Some(warning) = config.ensure_primary_auc_method() {
+        println!("Warning: {}", warning);
+    }
 
     // Parse dataset
     println!("Parsing dataset...");
-    let subjects = NonmemParser::parse_dataset(input_path)?;
+    let dv_scale = match matches.get_one::<String>("dv-scale").unwrap().as_str() {
+        "linear" => DvScale::Linear,
+        "log" => DvScale::Log,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown DV scale '{}' (expected linear or log)", other
+            )))
+        }
+    };
+    let parse_options = ParseOptions {
+        apply_dilution_factor: matches.get_flag("apply-dilution"),
+        dv_scale,
+        id_column: matches.get_one::<String>("id-column").cloned(),
+    };
+    let (mut subjects, units) = NonmemParser::parse_dataset_with_options(input_path, &parse_options)?;
     println!("Loaded {} subjects", subjects.len());
 
+    if let Some(covariates_path) = matches.get_one::<String>("covariates") {
+        let warnings = NonmemParser::join_covariates_with_options(&mut subjects, covariates_path, &parse_options)?;
+        println!("Joined covariates from {}", covariates_path);
+        for warning in &warnings {
+            println!("  Warning: {}", warning);
+        }
+    }
+
+    // Units declared in the dataset apply unless given on the command line
+    if let Some(units) = units {
+        let is_default = |id: &str| matches.value_source(id) == Some(ValueSource::DefaultValue);
+        if let Some(time_units) = units.time().filter(|_| is_default("time-units")) {
+            config.time_units = time_units.to_string();
+        }
+        if let Some(concentration_units) = units.concentration().filter(|_| is_default("conc-units")) {
+            config.concentration_units = concentration_units.to_string();
+        }
+        if let Some(dose_units) = units.dose().filter(|_| is_default("dose-units")) {
+            config.dose_units = dose_units.to_string();
+        }
+        println!(
+            "Dataset units: time {}, concentration {}, dose {}",
+            config.time_units, config.concentration_units, config.dose_units
+        );
+    }
+
     // Perform population analysis
     println!("Performing NCA analysis...");
     let start_time = std::time::Instant::now();
+
+    if matches.get_flag("streaming") {
+        let streamed = PopulationAnalyzer::analyze_population_streaming(subjects, &config, output_dir)?;
+        println!("Analysis completed in {:.2} seconds", start_time.elapsed().as_secs_f64());
+        println!("Subjects analyzed: {}", streamed.subjects_analyzed);
+        if !streamed.failed_subjects.is_empty() {
+            println!("Failed subjects: {}", streamed.failed_subjects.len());
+        }
+        println!("Results streamed to: {}", output_dir.display());
+        return Ok(());
+    }
     
     let results = PopulationAnalyzer::analyze_population(subjects, &config)?;
     
@@ -158,64 +604,505 @@ fn run_analysis(
     OutputManager::save_results(&results, &config, output_dir)?;
 
     // Print summary
-    print_analysis_summary(&results);
+    print_analysis_summary(&results, &config);
 
     Ok(())
 }
 
+fn create_example_options(matches: &clap::ArgMatches) -> Result<ExampleDataOptions> {
+    let seed = matches.get_one::<String>("example-seed")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| NcaError::ParseError("Invalid example seed".to_string()))?;
+    let iiv_cv = match matches.get_one::<String>("example-iiv-cv") {
+        Some(value) => Some(
+            value.parse::<f64>()
+                .map_err(|_| NcaError::ParseError("Invalid example IIV CV".to_string()))? / 100.0,
+        ),
+        None => None,
+    };
+    let residual_cv = matches.get_one::<String>("example-residual-cv")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid example residual CV".to_string()))? / 100.0;
+    let missing_fraction = matches.get_one::<String>("example-missing")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid example missing percentage".to_string()))? / 100.0;
+    let dropout_fraction = matches.get_one::<String>("example-dropout")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid example dropout percentage".to_string()))? / 100.0;
+
+    Ok(ExampleDataOptions {
+        seed,
+        iiv_cv,
+        residual_cv,
+        include_subcutaneous: matches.get_flag("example-sc"),
+        missing_fraction,
+        dropout_fraction,
+    })
+}
+
 fn create_analysis_config(
     matches: &clap::ArgMatches,
-    output_dir: &PathBuf,
+    output_dir: &Path,
 ) -> Result<AnalysisConfig> {
     let lloq_handling = match matches.get_one::<String>("lloq-handling").unwrap().as_str() {
         "zero" => LloqHandling::Zero,
         "drop" => LloqHandling::Drop,
         "half-lloq" => LloqHandling::HalfLloq,
-        _ => LloqHandling::HalfLloq,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown LLOQ handling '{}' (expected zero, drop or half-lloq)", other
+            )))
+        }
     };
 
+    let blod_handling = match matches.get_one::<String>("blod-handling").map(String::as_str) {
+        Some("zero") => Some(LloqHandling::Zero),
+        Some("drop") => Some(LloqHandling::Drop),
+        Some("half-lod") => Some(LloqHandling::HalfLloq),
+        Some(other) => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown BLOD handling '{}' (expected zero, drop or half-lod)", other
+            )))
+        }
+        None => None,
+    };
+
+    let lambda_z_min_r_squared = matches.get_one::<String>("lambda-z-min-r2")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid minimum lambda_z R²".to_string()))?;
+
     let lambda_z_selection = match matches.get_one::<String>("lambda-z-method").unwrap().as_str() {
         "auto" => LambdaZSelection::Auto,
         "best-fit" => LambdaZSelection::BestFit { 
             min_points: 3, 
-            r_squared_threshold: 0.8 
+            r_squared_threshold: lambda_z_min_r_squared,
         },
-        _ => LambdaZSelection::Auto,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown lambda_z method '{}' (expected auto or best-fit)", other
+            )))
+        }
+    };
+    let lambda_z_selection = match matches.get_one::<String>("lambda-z-start") {
+        Some(start) => LambdaZSelection::TimeWindow {
+            start_time: start.parse::<f64>().map_err(|_| {
+                NcaError::ParseError(format!("Invalid lambda_z start time: {}", start))
+            })?,
+        },
+        None => lambda_z_selection,
+    };
+
+    let analyte_compartment = match matches.get_one::<String>("analyte-cmt") {
+        Some(cmt) => Some(cmt.parse::<i32>().map_err(|_| {
+            NcaError::ParseError(format!("Invalid analyte compartment: {}", cmt))
+        })?),
+        None => None,
+    };
+
+    let auc_tau_anchor = match matches.get_one::<String>("auc-tau-anchor").unwrap().as_str() {
+        "pre-dose" => AucTauAnchor::ObservedPreDose,
+        "trough" => AucTauAnchor::NextIntervalTrough,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown AUCtau anchor '{}' (expected pre-dose or trough)", other
+            )))
+        }
+    };
+
+    let primary_auc_method = match matches.get_one::<String>("primary-auc-method").map(String::as_str) {
+        Some("linear") => Some(AucMethod::LinearTrapezoidal),
+        Some("log") => Some(AucMethod::LogTrapezoidal),
+        Some("linear-log") => Some(AucMethod::LinearLogTrapezoidal),
+        Some("linear-up-log-down") => Some(AucMethod::LinearUpLogDown),
+        Some("rectangular") => Some(AucMethod::Rectangular),
+        Some(other) => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown primary AUC method '{}' (expected linear, log, linear-log, linear-up-log-down or rectangular)", other
+            )))
+        }
+        None => None,
+    };
+
+    let auc_extrapolation_slope = match matches.get_one::<String>("auc-extrap-slope").unwrap().as_str() {
+        "lambda-z" => AucExtrapolationSlope::TerminalRegression,
+        "last-two-points" => AucExtrapolationSlope::LastTwoPoints,
+        "pooled-group" => AucExtrapolationSlope::PooledGroup,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown AUC extrapolation slope '{}' (expected lambda-z, last-two-points or pooled-group)", other
+            )))
+        }
+    };
+
+    let parse_span = |name: &str| -> Result<Option<f64>> {
+        match matches.get_one::<String>(name) {
+            Some(value) => Ok(Some(value.parse::<f64>().ok().filter(|span| *span > 0.0).ok_or_else(|| {
+                NcaError::ParseError(format!("Invalid --{}: {}", name, value))
+            })?)),
+            None => Ok(None),
+        }
+    };
+    let min_tmax_to_tlast = match (parse_span("min-tmax-tlast-time")?, parse_span("min-tmax-tlast-half-lives")?) {
+        (Some(_), Some(_)) => {
+            return Err(NcaError::ParseError(
+                "Give either --min-tmax-tlast-time or --min-tmax-tlast-half-lives, not both".to_string()
+            ));
+        }
+        (Some(time), None) => Some(TerminalSpanRequirement::Time(time)),
+        (None, Some(half_lives)) => Some(TerminalSpanRequirement::HalfLives(half_lives)),
+        (None, None) => None,
     };
 
+    let population_summary_statistics = match matches.get_one::<String>("population-summary").unwrap().as_str() {
+        "arithmetic" => PopulationSummaryStatistics::Arithmetic,
+        "geometric" => PopulationSummaryStatistics::Geometric,
+        "both" => PopulationSummaryStatistics::Both,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown population summary statistics '{}' (expected arithmetic, geometric or both)", other
+            )))
+        }
+    };
+
+    let dose_time_concentration = match matches.get_one::<String>("dose-time-concentration").unwrap().as_str() {
+        "first-sample" => DoseTimeConcentration::FirstSample,
+        "route" => DoseTimeConcentration::RouteAware,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown dose-time concentration '{}' (expected first-sample or route)", other
+            )))
+        }
+    };
+
+    let dosing_interval = match matches.get_one::<String>("dosing-interval") {
+        Some(value) => Some(value.parse::<f64>().ok().filter(|tau| *tau > 0.0).ok_or_else(|| {
+            NcaError::ParseError(format!("Dosing interval must be positive: {}", value))
+        })?),
+        None => None,
+    };
+
+    let concentration_threshold = match matches.get_one::<String>("concentration-threshold") {
+        Some(value) => Some(value.parse::<f64>().map_err(|_| {
+            NcaError::ParseError(format!("Invalid concentration threshold: {}", value))
+        })?),
+        None => None,
+    };
+
+    let summary_conventions = match matches.get_many::<String>("summary-convention") {
+        Some(conventions) => conventions
+            .map(|convention| parse_summary_convention(convention))
+            .collect::<Result<HashMap<_, _>>>()?,
+        None => HashMap::new(),
+    };
+
+    let parameter_naming = match matches.get_one::<String>("parameter-names").unwrap().as_str() {
+        "default" => ParameterNaming::Default,
+        "winnonlin" => ParameterNaming::WinNonlin,
+        "cdisc" => ParameterNaming::Cdisc,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown parameter naming '{}' (expected default, winnonlin or cdisc)", other
+            )))
+        }
+    };
+
+    let column_aliases = match matches.get_many::<String>("column-alias") {
+        Some(aliases) => aliases
+            .map(|alias| parse_column_alias(alias))
+            .collect::<Result<HashMap<_, _>>>()?,
+        None => HashMap::new(),
+    };
+
+    let partial_auc_intervals = match matches.get_many::<String>("partial-auc") {
+        Some(intervals) => intervals
+            .map(|interval| parse_interval(interval))
+            .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+
+    let max_auc_extrap_percent = matches.get_one::<String>("auc-extrap-threshold")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid AUC extrapolation threshold".to_string()))?;
+
+    let max_concentration = match matches.get_one::<String>("max-concentration") {
+        Some(value) => Some(value.parse::<f64>().map_err(|_| {
+            NcaError::ParseError(format!("Invalid maximum concentration: {}", value))
+        })?),
+        None => None,
+    };
+
+    let max_dose_ratio = matches.get_one::<String>("max-dose-ratio")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid maximum dose ratio".to_string()))?;
+
+    let json_significant_digits = match matches.get_one::<String>("json-significant-digits") {
+        Some(value) => Some(value.parse::<u32>().ok().filter(|digits| (1..=17).contains(digits)).ok_or_else(|| {
+            NcaError::ParseError(format!("JSON significant digits must be between 1 and 17: {}", value))
+        })?),
+        None => None,
+    };
+
+    let max_clearance_discrepancy_percent = matches.get_one::<String>("max-cl-discrepancy")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid maximum clearance discrepancy".to_string()))?;
+
+    let extravascular_bioavailability = match matches.get_one::<String>("extravascular-bioavailability") {
+        Some(value) => Some(value.parse::<f64>().ok().filter(|f| *f > 0.0 && *f <= 1.0).ok_or_else(|| {
+            NcaError::ParseError(format!("Extravascular bioavailability must be in (0, 1]: {}", value))
+        })?),
+        None => None,
+    };
+
+    let covariate_p_value_correction = match matches.get_one::<String>("covariate-p-adjust").unwrap().as_str() {
+        "bh" => MultipleComparisonCorrection::BenjaminiHochberg,
+        "bonferroni" => MultipleComparisonCorrection::Bonferroni,
+        "none" => MultipleComparisonCorrection::None,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown covariate p-value adjustment '{}' (expected bh, bonferroni or none)", other
+            )))
+        }
+    };
+    let covariate_significance_level = matches.get_one::<String>("covariate-alpha")
+        .unwrap()
+        .parse::<f64>()
+        .ok()
+        .filter(|level| *level > 0.0 && *level < 1.0)
+        .ok_or_else(|| NcaError::ParseError("Covariate significance level must be between 0 and 1".to_string()))?;
+
+    let partial_correlation_control = match matches.get_one::<String>("partial-correlation-control") {
+        Some(covariate) if ["age", "weight", "height"].contains(&covariate.as_str()) => Some(covariate.clone()),
+        Some(covariate) => {
+            return Err(NcaError::ParseError(format!("Unknown partial correlation control covariate: {}", covariate)));
+        }
+        None => None,
+    };
+
+    let min_regression_n = matches.get_one::<String>("min-regression-n")
+        .unwrap()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n >= 3)
+        .ok_or_else(|| NcaError::ParseError("Minimum regression n must be an integer of at least 3".to_string()))?;
+
+    let parallel_chunk_size = match matches.get_one::<String>("parallel-chunk-size") {
+        Some(size) => size.parse::<usize>().ok().filter(|n| *n >= 1).ok_or_else(|| {
+            NcaError::ParseError(format!("Parallel chunk size must be an integer of at least 1: {}", size))
+        })?,
+        None => DEFAULT_PARALLEL_CHUNK_SIZE,
+    };
+
+    let min_observed_points = matches.get_one::<String>("min-observed-points")
+        .unwrap()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n >= 2)
+        .ok_or_else(|| NcaError::ParseError("Minimum observed points must be an integer of at least 2".to_string()))?;
+
+    let min_terminal_points = matches.get_one::<String>("min-terminal-points")
+        .unwrap()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n >= min_observed_points)
+        .ok_or_else(|| NcaError::ParseError(format!(
+            "Minimum terminal points must be an integer of at least the minimum observed points ({})",
+            min_observed_points
+        )))?;
+
+    let replicate_handling = match matches.get_one::<String>("replicates").unwrap().as_str() {
+        "mean" => ReplicateHandling::ArithmeticMean,
+        "geometric-mean" => ReplicateHandling::GeometricMean,
+        "keep" => ReplicateHandling::Keep,
+        other => {
+            return Err(NcaError::ParseError(format!(
+                "Unknown replicate handling '{}' (expected keep, mean or geometric-mean)", other
+            )))
+        }
+    };
+
+    let replicate_cv_threshold = matches.get_one::<String>("replicate-cv-threshold")
+        .unwrap()
+        .parse::<f64>()
+        .map_err(|_| NcaError::ParseError("Invalid replicate CV threshold".to_string()))?;
+
     // Get stratification columns if specified
     let stratification = if let Some(columns) = matches.get_many::<String>("stratify-by") {
         let column_names: Vec<String> = columns.cloned().collect();
+        let comparison_test = match matches.get_one::<String>("comparison-test").unwrap().as_str() {
+            "welch" => ComparisonTest::WelchTTest,
+            "pooled-t" => ComparisonTest::PooledTTest,
+            "mann-whitney" => ComparisonTest::MannWhitney,
+            other => {
+                return Err(NcaError::ParseError(format!(
+                    "Unknown comparison test '{}' (expected welch, pooled-t or mann-whitney)", other
+                )))
+            }
+        };
         Some(StratificationConfig {
             stratify_columns: column_names,
             include_interactions: false,
             minimum_n_per_stratum: 3,
             perform_statistical_tests: true,
+            comparison_test,
         })
     } else {
         None
     };
 
+    let geometric_non_positive = match matches.get_one::<String>("geometric-non-positive").unwrap().as_str() {
+        "undefined" => GeometricNonPositive::Undefined,
+        "exclude" => GeometricNonPositive::Exclude,
+        mode => match mode.strip_prefix("offset=") {
+            Some(offset) => GeometricNonPositive::Offset(offset.parse::<f64>().map_err(|_| {
+                NcaError::ParseError(format!("Invalid geometric offset: {}", offset))
+            })?),
+            None => {
+                return Err(NcaError::ParseError(format!(
+                    "Unknown geometric non-positive handling '{}' (expected exclude, undefined or offset=VALUE)", mode
+                )))
+            }
+        },
+    };
+
+    let treatment_roles = matches
+        .get_many::<String>("placebo-treatment")
+        .map(|treatments| treatments.map(|t| (t.clone(), TreatmentRole::Placebo)).collect())
+        .unwrap_or_default();
+
     Ok(AnalysisConfig {
         auc_methods: vec![
             AucMethod::LinearTrapezoidal,
             AucMethod::LogTrapezoidal,
             AucMethod::LinearLogTrapezoidal,
             AucMethod::LinearUpLogDown,
+            AucMethod::Rectangular,
         ],
+        primary_auc_method,
         lambda_z_selection,
+        lambda_z_min_r_squared,
+        lambda_z_report_below_threshold: matches.get_flag("report-unreliable-lambda-z"),
+        lambda_z_exclude_tmax: matches.get_flag("lambda-z-exclude-tmax"),
         interpolation_method: InterpolationMethod::Linear,
         output_path: output_dir.to_string_lossy().to_string(),
         lloq_handling,
+        blod_handling,
         time_units: matches.get_one::<String>("time-units").unwrap().clone(),
         concentration_units: matches.get_one::<String>("conc-units").unwrap().clone(),
+        dose_units: matches.get_one::<String>("dose-units").unwrap().clone(),
         dose_normalization: matches.get_flag("dose-normalization"),
         perform_covariate_analysis: matches.get_flag("covariate-analysis"),
+        analyte_compartment,
+        exclude_unreliable_auc_inf: matches.get_flag("exclude-unreliable-auc-inf"),
+        auc_tau_anchor,
+        auc_extrapolation_slope,
+        pooled_lambda_z: matches.get_flag("pooled-lambda-z"),
+        min_tmax_to_tlast,
+        population_summary_statistics,
+        dose_time_concentration,
+        extravascular_bioavailability,
+        dosing_interval,
+        concentration_threshold,
+        threshold_interpolation: match matches.get_one::<String>("threshold-interpolation").unwrap().as_str() {
+            "linear" => InterpolationMethod::Linear,
+            "log" => InterpolationMethod::LogLinear,
+            other => {
+                return Err(NcaError::ParseError(format!(
+                    "Unknown threshold interpolation '{}' (expected linear or log)", other
+                )))
+            }
+        },
+        partial_auc_intervals,
+        validation_thresholds: ValidationThresholds {
+            max_concentration,
+            max_dose_ratio,
+            max_clearance_discrepancy_percent,
+            max_auc_extrap_percent,
+            // One R² limit for accepting the fit and for the poor-fit warning
+            min_lambda_z_r_squared: lambda_z_min_r_squared,
+            ..ValidationThresholds::default()
+        },
+        replicate_handling,
+        replicate_cv_threshold,
+        min_regression_n,
+        covariate_p_value_correction,
+        covariate_significance_level,
+        partial_correlation_control,
+        treatment_roles,
+        report_end_of_infusion_cmax: matches.get_flag("end-of-infusion-cmax"),
+        export_concentrations: matches.get_flag("export-concentrations"),
+        json_significant_digits,
+        include_pre_dose_samples: matches.get_flag("include-pre-dose"),
+        min_observed_points,
+        min_terminal_points,
+        data_completeness: matches.get_flag("data-completeness"),
+        summary_conventions,
+        parameter_naming,
+        column_aliases,
+        units_in_headers: matches.get_flag("units-in-headers"),
+        compute_method_comparison: !matches.get_flag("no-method-comparison"),
+        parallel_chunk_size,
+        weighted_summary: matches.get_flag("weighted-summary"),
+        geometric_non_positive,
+        subject_weights: HashMap::new(),
+        derivations: if matches.get_flag("derived-parameters") {
+            DerivationRegistry::builtin()
+        } else {
+            DerivationRegistry::new()
+        },
         stratification,
     })
 }
 
-fn print_analysis_summary(results: &PopulationResults) {
+fn parse_interval(interval: &str) -> Result<(f64, f64)> {
+    let invalid = || NcaError::ParseError(format!("Invalid interval '{}', expected START-END with END after START", interval));
+    let (start, end) = interval.split_once('-').ok_or_else(invalid)?;
+    let start = start.trim().parse::<f64>().map_err(|_| invalid())?;
+    let end = end.trim().parse::<f64>().map_err(|_| invalid())?;
+    if end <= start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+fn parse_summary_convention(convention: &str) -> Result<(String, SummaryConvention)> {
+    let invalid = || NcaError::ParseError(format!(
+        "Invalid summary convention '{}', expected PARAM=geometric, PARAM=arithmetic or PARAM=harmonic", convention
+    ));
+    let (parameter, statistic) = convention.split_once('=').ok_or_else(invalid)?;
+    let statistic = match statistic.trim().to_lowercase().as_str() {
+        "geometric" => SummaryConvention::Geometric,
+        "arithmetic" => SummaryConvention::Arithmetic,
+        "harmonic" => SummaryConvention::Harmonic,
+        _ => return Err(invalid()),
+    };
+    Ok((parameter.trim().to_lowercase(), statistic))
+}
+
+fn parse_column_alias(alias: &str) -> Result<(String, String)> {
+    match alias.split_once('=') {
+        Some((column, name)) if !column.trim().is_empty() && !name.trim().is_empty() => {
+            let column = column.trim().to_uppercase();
+            if !OutputManager::is_individual_results_column(&column) {
+                return Err(NcaError::ParseError(format!(
+                    "Unknown column '{}' in column alias '{}' (expected a default individual_results.csv column name)",
+                    column, alias
+                )));
+            }
+            Ok((column, name.trim().to_string()))
+        }
+        _ => Err(NcaError::ParseError(format!("Invalid column alias '{}', expected COLUMN=NAME", alias))),
+    }
+}
+
+fn print_analysis_summary(results: &PopulationResults, config: &AnalysisConfig) {
     println!("\n=== ANALYSIS SUMMARY ===");
     println!("Subjects analyzed: {}", results.individual_results.len());
     if !results.failed_subjects.is_empty() {
@@ -224,11 +1111,11 @@ fn print_analysis_summary(results: &PopulationResults) {
     }
     
     println!("\nKey Parameters:");
-    for (param, stats) in &results.summary_statistics.parameter_stats {
-        println!("  {} (Arithmetic): {:.3} ± {:.1}%", param, stats.arithmetic_mean, stats.arithmetic_cv_percent);
-        if let (Some(geo_mean), Some(geo_cv)) = (stats.geometric_mean, stats.geometric_cv_percent) {
-            println!("  {} (Geometric): {:.3} ± {:.1}%", param, geo_mean, geo_cv);
-        }
+    let mut params: Vec<&String> = results.summary_statistics.parameter_stats.keys().collect();
+    params.sort();
+    for param in params {
+        let stats = &results.summary_statistics.parameter_stats[param];
+        println!("  {}: {}", param, OutputManager::format_emphasized_statistic(stats, &config.summary_convention(param)));
     }
     
     println!("\nAUC Method Comparison:");
@@ -239,7 +1126,7 @@ fn print_analysis_summary(results: &PopulationResults) {
     // Print stratified results summary
     if !results.stratified_results.is_empty() {
         println!("\nStratified Analysis Summary:");
-        for (stratum_key, stratum_results) in &results.stratified_results {
+        for stratum_results in results.stratified_results.values() {
             println!("  {} = {}: n = {}", 
                 stratum_results.stratum_name, 
                 stratum_results.stratum_value, 
@@ -250,13 +1137,15 @@ fn print_analysis_summary(results: &PopulationResults) {
     
     // Print covariate analysis summary
     if !results.covariate_analysis.correlations.is_empty() {
-        println!("\nSignificant Covariate Correlations (p < 0.05):");
+        println!("\nSignificant Covariate Correlations (adjusted p < {}, {:?}):",
+            config.covariate_significance_level, config.covariate_p_value_correction);
         for (covariate, correlation_data) in &results.covariate_analysis.correlations {
             for (parameter, &corr_value) in &correlation_data.parameter_correlations {
-                let p_value = correlation_data.p_values.get(parameter).copied().unwrap_or(1.0);
-                if p_value < 0.05 {
-                    println!("  {} vs {}: r = {:.3}, p = {:.3}", 
-                        covariate, parameter, corr_value, p_value);
+                if correlation_data.significant.get(parameter).copied().unwrap_or(false) {
+                    let p_value = correlation_data.p_values.get(parameter).copied().unwrap_or(1.0);
+                    let adjusted_p_value = correlation_data.adjusted_p_values.get(parameter).copied().unwrap_or(1.0);
+                    println!("  {} vs {}: r = {:.3}, p = {:.3}, adjusted p = {:.3}", 
+                        covariate, parameter, corr_value, p_value, adjusted_p_value);
                 }
             }
         }
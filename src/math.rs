@@ -0,0 +1,29 @@
+//! Numeric helpers shared by the parameter and statistics calculations
+
+/// Divide `numerator` by `denominator`, returning `None` instead of 0.0, Inf or NaN
+///
+/// A zero (or non-finite) denominator, or a quotient that is not finite, means the ratio
+/// is undefined; callers decide how to report that rather than letting it reach output.
+pub fn safe_div(numerator: f64, denominator: f64) -> Option<f64> {
+    if denominator == 0.0 || !denominator.is_finite() {
+        return None;
+    }
+
+    let quotient = numerator / denominator;
+    if quotient.is_finite() {
+        Some(quotient)
+    } else {
+        None
+    }
+}
+
+/// Round `value` to `digits` significant digits; zero and non-finite values are returned
+/// unchanged. `digits` must be at least 1
+pub fn round_significant(value: f64, digits: u32) -> f64 {
+    debug_assert!(digits >= 1, "cannot round to {} significant digits", digits);
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let precision = digits as usize - 1;
+    format!("{:.*e}", precision, value).parse().unwrap_or(value)
+}
@@ -1,3 +1,5 @@
+use crate::stratification::StrataComparison;
+use crate::derived::DerivationRegistry;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -7,6 +9,34 @@ pub struct Subject {
     pub observations: Vec<Observation>,
     pub dosing_events: Vec<DosingEvent>,
     pub demographics: Demographics,
+    /// Time-varying covariate values from EVID=2 records, in file order
+    pub covariate_records: Vec<CovariateRecord>,
+    /// Analyte measured (ANALYTE column), e.g. parent or metabolite; the parser rejects a
+    /// subject with more than one
+    pub analyte: Option<String>,
+    /// Sample matrix (MATRIX column), e.g. plasma, serum, blood, urine; one per subject as well
+    pub matrix: Option<String>,
+}
+
+impl Subject {
+    /// Value of a time-varying covariate at `time`, carried forward from the latest
+    /// EVID=2 record at or before it
+    pub fn covariate_at(&self, name: &str, time: f64) -> Option<f64> {
+        self.covariate_records
+            .iter()
+            .filter(|record| record.time <= time)
+            .filter_map(|record| record.values.get(&name.to_uppercase()).map(|&value| (record.time, value)))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Covariate values recorded at one time point (EVID=2)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CovariateRecord {
+    pub time: f64,
+    /// Numeric covariate columns keyed by upper-case column name (e.g. CRCL)
+    pub values: HashMap<String, f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,8 +45,16 @@ pub struct Observation {
     pub concentration: f64,
     pub lloq: Option<f64>,
     pub bloq: bool,
+    /// Limit of detection, below which the analyte is undetectable rather than unquantifiable
+    #[serde(default)]
+    pub lod: Option<f64>,
+    /// Below the limit of detection; a BLOD sample is also `bloq`, and is imputed by
+    /// [`AnalysisConfig::blod_handling`] when one is set
+    #[serde(default)]
+    pub blod: bool,
     pub evid: i32,
     pub dv: f64,
+    pub cmt: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,9 +64,14 @@ pub struct DosingEvent {
     pub route: DosingRoute,
     pub infusion_duration: Option<f64>,
     pub evid: i32,
+    pub cmt: Option<i32>,
+    /// Dose given at steady state (NONMEM SS=1)
+    pub steady_state: bool,
+    /// Dosing interval (NONMEM II)
+    pub interval: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DosingRoute {
     #[serde(rename = "IV")]
     IntravenousBolus,
@@ -36,9 +79,62 @@ pub enum DosingRoute {
     IntravenousInfusion,
     #[serde(rename = "ORAL")]
     Oral,
+    #[serde(rename = "SC")]
+    Subcutaneous,
+    #[serde(rename = "IM")]
+    Intramuscular,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl DosingRoute {
+    /// Oral, subcutaneous and intramuscular doses are absorbed before reaching the circulation
+    pub fn is_extravascular(&self) -> bool {
+        matches!(self, DosingRoute::Oral | DosingRoute::Subcutaneous | DosingRoute::Intramuscular)
+    }
+
+    /// Label used in the output files, as in the ROUTE column of the dataset
+    pub fn label(&self) -> &'static str {
+        match self {
+            DosingRoute::IntravenousBolus => "IV",
+            DosingRoute::IntravenousInfusion => "INFUSION",
+            DosingRoute::Oral => "ORAL",
+            DosingRoute::Subcutaneous => "SC",
+            DosingRoute::Intramuscular => "IM",
+        }
+    }
+}
+
+/// Units declared in a units row directly below the dataset header
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetUnits {
+    /// Unit per upper-case column name, without surrounding brackets
+    pub columns: HashMap<String, String>,
+}
+
+impl DatasetUnits {
+    pub fn time(&self) -> Option<&str> {
+        self.columns.get("TIME").map(String::as_str)
+    }
+
+    pub fn concentration(&self) -> Option<&str> {
+        self.columns.get("DV").map(String::as_str)
+    }
+
+    pub fn dose(&self) -> Option<&str> {
+        self.columns.get("AMT").map(String::as_str)
+    }
+}
+
+/// Scale DV is recorded on in the dataset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DvScale {
+    #[default]
+    Linear,
+    /// Natural log of the concentration, as exported by some assay pipelines; exponentiated
+    /// during parsing
+    Log,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Demographics {
     pub age: Option<f64>,
     pub weight: Option<f64>,
@@ -50,35 +146,291 @@ pub struct Demographics {
     pub period: Option<i32>,
     pub sequence: Option<String>,
     pub formulation: Option<String>,
+    /// Analysis weight (WEIGHT or ANALYSIS_WT column) used by weighted summary statistics
+    pub analysis_weight: Option<f64>,
+    /// Additional categorical covariates keyed by upper-case column name (e.g. GENOTYPE)
+    pub extra: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NcaResults {
     pub subject_id: String,
+    /// Analyte and sample matrix (ANALYTE/MATRIX columns), carried from the subject
+    pub analyte: Option<String>,
+    pub matrix: Option<String>,
+    pub result_type: NcaResultType,
+    pub dosing: DosingSummary,
     pub individual_parameters: IndividualParameters,
     pub method_comparisons: HashMap<String, IndividualParameters>,
+    /// QC summaries of averaged replicate assays
+    pub replicate_summaries: Vec<ReplicateSummary>,
+    /// Observations the parameters were calculated from, after exclusions, replicate
+    /// averaging and BLQ handling; kept only with `export_concentrations`
+    pub analyzed_observations: Vec<Observation>,
+}
+
+/// Doses behind a subject's results, so CL = dose / AUC_inf can be checked from the output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DosingSummary {
+    /// Sum of all doses, the dose used for clearance and volumes
+    pub total_dose: f64,
+    pub dose_count: usize,
+    /// Route and infusion duration of the first dose, which decides CL vs CL/F
+    pub route: Option<DosingRoute>,
+    pub infusion_duration: Option<f64>,
+    /// Doses mix intravascular and extravascular routes (e.g. an IV loading dose followed by
+    /// oral maintenance), so the total dose is not the systemic dose
+    pub mixed_routes: bool,
+    /// Systemic dose of mixed-route dosing, intravascular doses plus
+    /// [`AnalysisConfig::extravascular_bioavailability`] times extravascular ones; CL and Vz
+    /// are based on it, and are not reported for mixed routes without it
+    pub effective_dose: Option<f64>,
+}
+
+/// Whether a subject had enough quantifiable concentrations for the terminal phase
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NcaResultType {
+    Full,
+    /// Partial NCA of a sparse subject: observed-only parameters (Cmax, Tmax, Clast, Tlast,
+    /// AUC_last); lambda_z and everything derived from it are unavailable
+    ObservedOnly,
+}
+
+impl NcaResultType {
+    /// Label used in the output files
+    pub fn label(&self) -> &'static str {
+        match self {
+            NcaResultType::Full => "FULL",
+            NcaResultType::ObservedOnly => "OBSERVED_ONLY",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicateSummary {
+    pub time: f64,
+    pub n_replicates: usize,
+    /// Averaged concentration used in the analysis
+    pub concentration: f64,
+    pub sd: f64,
+    pub cv_percent: Option<f64>,
+    /// True when the inter-replicate CV exceeds `replicate_cv_threshold`
+    pub high_variability: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndividualParameters {
     pub auc_last: Option<f64>,
     pub auc_inf: Option<f64>,
+    /// AUC_last + Clast_pred / lambda_z, with Clast_pred = exp(intercept - lambda_z × tlast)
+    /// predicted by the lambda_z regression; missing without an individual fit
     pub auc_inf_pred: Option<f64>,
     pub auc_percent_extrap: Option<f64>,
+    /// Extrapolated area, AUC_inf - AUC_last (Clast / slope), to judge whether a large
+    /// percentage is of a meaningful area
+    pub auc_extrap_area: Option<f64>,
+    /// False when AUC_inf extrapolation exceeds [`ValidationThresholds::max_auc_extrap_percent`];
+    /// the value is still reported
+    pub auc_inf_reliable: Option<bool>,
+    /// False when sampling ends sooner after Tmax than [`AnalysisConfig::min_tmax_to_tlast`]
+    /// requires; AUC_inf, AUMC_inf and the parameters derived from them are then not reported
+    pub tmax_to_tlast_span_met: Option<bool>,
+    /// Slope AUC_inf was extrapolated with, and which kind it was; AUMC_inf, MRT, CL, Vss and
+    /// Vz use the same slope
+    pub auc_inf_slope: Option<f64>,
+    pub auc_inf_slope_method: Option<AucExtrapolationSlope>,
     pub aumc_last: Option<f64>,
     pub aumc_inf: Option<f64>,
+    /// AUC over the steady-state dosing interval [dose, dose + tau]
+    pub auc_tau: Option<f64>,
+    /// Peak-trough fluctuation (%) and swing (%) over the AUCtau dosing interval, reported only
+    /// when samples cover the whole interval from the dose to dose + tau
+    pub fluctuation_percent: Option<f64>,
+    pub swing_percent: Option<f64>,
+    pub partial_aucs: Vec<PartialAuc>,
+    /// AUC between consecutive doses (one entry per distinct dose time), filled when a dose falls
+    /// strictly between the first and last analyzed samples
+    pub dose_interval_aucs: Vec<DoseIntervalAuc>,
+    /// Running AUC from the first sample to each observation time
+    pub cumulative_auc: Vec<CumulativeAuc>,
+    /// Wagner-Nelson fraction absorbed at each observation time, for extravascular doses
+    /// with a terminal phase
+    pub fraction_absorbed: Vec<FractionAbsorbed>,
+    /// Observed Cmax and Tmax: the highest sampled concentration
     pub cmax: Option<f64>,
     pub tmax: Option<f64>,
+    /// Concentration interpolated at the end of an infusion (first dose time + infusion
+    /// duration), reported next to the observed Cmax with `report_end_of_infusion_cmax`
+    pub cmax_eoi: Option<f64>,
+    pub t_eoi: Option<f64>,
+    /// Concentration assumed at the dose time and integrated from (back-extrapolated C0 for
+    /// an IV bolus, zero otherwise), with `DoseTimeConcentration::RouteAware` and no sample there
+    pub c0: Option<f64>,
+    /// Absorption lag: the sample time preceding the first quantifiable concentration,
+    /// for extravascular doses only
+    pub tlag: Option<f64>,
     pub tlast: Option<f64>,
     pub clast: Option<f64>,
+    /// Average concentration over the observed interval, AUC_last / (tlast - tfirst); not the
+    /// steady-state Cavg, and `None` for a zero time span
+    pub cavg_observed: Option<f64>,
     pub half_life: Option<f64>,
+    /// AUC over the last sampled dosing interval relative to the first (Rac), for profiles
+    /// covering several doses of equal interval
+    pub accumulation_ratio: Option<f64>,
+    /// Effective half-life -tau * ln(2) / ln(1 - 1/Rac), reported alongside the terminal half-life
+    pub effective_half_life: Option<f64>,
+    /// Time to 96.9% of steady state: 5 terminal half-lives, and from the observed approach
+    /// of pre-dose troughs to their plateau (multiple-dose profiles with equal intervals)
+    pub time_to_steady_state: Option<f64>,
+    pub time_to_steady_state_observed: Option<f64>,
+    /// Time the interpolated profile spends above `concentration_threshold` (e.g. T>MIC)
+    pub time_above_threshold: Option<f64>,
+    /// AUC over the periods above `concentration_threshold` (AUC>MIC), primary AUC method
+    pub auc_above_threshold: Option<f64>,
+    /// Interpolated times the profile crosses `concentration_threshold`
+    pub threshold_crossings: Vec<ThresholdCrossing>,
     pub lambda_z: Option<f64>,
     pub lambda_z_r_squared: Option<f64>,
+    /// Intercept of the lambda_z regression, the extrapolated ln concentration at t = 0;
+    /// the fitted terminal line is exp(intercept - lambda_z * t)
+    pub lambda_z_intercept: Option<f64>,
+    /// Number of points in the lambda_z regression
+    pub lambda_z_n_points: Option<usize>,
+    /// First and last time of the lambda_z regression points
+    pub lambda_z_time_range: Option<(f64, f64)>,
+    /// False when lambda_z comes from a regression below the selection's R² threshold,
+    /// reported only with `lambda_z_report_below_threshold`; `None` for manual and time
+    /// windows, which have no R² threshold
+    pub lambda_z_reliable: Option<bool>,
     pub clearance: Option<f64>,
+    /// True for extravascular doses: `clearance` and `volume_terminal` are the apparent
+    /// CL/F and Vz/F, and Vss is not reported
+    pub apparent_clearance: bool,
+    /// Extravascular terminal phase suspected to reflect absorption rather than elimination
+    /// (see [`crate::nca::NcaAnalyzer::flip_flop_suspected`])
+    pub flip_flop_suspected: bool,
+    /// Extravascular Cmax at the first post-dose sample, so the true peak may precede sampling
+    /// (see [`crate::nca::NcaAnalyzer::tmax_at_first_sample`])
+    pub tmax_at_first_sample: bool,
+    /// Highest local maximum other than Cmax, separated from it by a trough (enterohepatic
+    /// recirculation, delayed release); see [`crate::parameters::ParameterCalculator::detect_secondary_peak`]
+    pub cmax2: Option<f64>,
+    pub tmax2: Option<f64>,
     pub volume_steady_state: Option<f64>,
     pub volume_terminal: Option<f64>,
     pub mrt: Option<f64>,
     pub bioavailability: Option<f64>,
+    /// Custom parameters from [`AnalysisConfig::derivations`], in registration order
+    pub derived: Vec<DerivedParameter>,
+}
+
+/// Reads one scalar parameter out of [`IndividualParameters`]
+pub type ParameterAccessor = fn(&IndividualParameters) -> Option<f64>;
+
+impl IndividualParameters {
+    /// Scalar parameters by field name: the names accepted by [`IndividualParameters::get`]
+    pub const PARAMETERS: &'static [(&'static str, ParameterAccessor)] = &[
+        ("auc_last", |p| p.auc_last),
+        ("auc_inf", |p| p.auc_inf),
+        ("auc_inf_pred", |p| p.auc_inf_pred),
+        ("auc_percent_extrap", |p| p.auc_percent_extrap),
+        ("auc_extrap_area", |p| p.auc_extrap_area),
+        ("aumc_last", |p| p.aumc_last),
+        ("aumc_inf", |p| p.aumc_inf),
+        ("auc_tau", |p| p.auc_tau),
+        ("fluctuation_percent", |p| p.fluctuation_percent),
+        ("swing_percent", |p| p.swing_percent),
+        ("cmax", |p| p.cmax),
+        ("cmax_eoi", |p| p.cmax_eoi),
+        ("c0", |p| p.c0),
+        ("tmax", |p| p.tmax),
+        ("tlag", |p| p.tlag),
+        ("tlast", |p| p.tlast),
+        ("clast", |p| p.clast),
+        ("cavg_observed", |p| p.cavg_observed),
+        ("half_life", |p| p.half_life),
+        ("accumulation_ratio", |p| p.accumulation_ratio),
+        ("effective_half_life", |p| p.effective_half_life),
+        ("time_to_steady_state", |p| p.time_to_steady_state),
+        ("time_to_steady_state_observed", |p| p.time_to_steady_state_observed),
+        ("time_above_threshold", |p| p.time_above_threshold),
+        ("auc_above_threshold", |p| p.auc_above_threshold),
+        ("lambda_z", |p| p.lambda_z),
+        ("lambda_z_r_squared", |p| p.lambda_z_r_squared),
+        ("lambda_z_intercept", |p| p.lambda_z_intercept),
+        ("clearance", |p| p.clearance),
+        ("volume_steady_state", |p| p.volume_steady_state),
+        ("volume_terminal", |p| p.volume_terminal),
+        ("mrt", |p| p.mrt),
+        ("bioavailability", |p| p.bioavailability),
+    ];
+
+    /// Names accepted by [`IndividualParameters::get`], in [`Self::PARAMETERS`] order
+    pub fn parameter_names() -> impl Iterator<Item = &'static str> {
+        Self::PARAMETERS.iter().map(|(name, _)| *name)
+    }
+
+    /// Look up a scalar parameter by its field name (e.g. "auc_inf", "cmax", "clearance")
+    ///
+    /// Names of custom derived parameters are looked up in `derived`. Returns `None` for
+    /// unknown names as well as for parameters that were not estimated.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        match Self::PARAMETERS.iter().find(|(parameter, _)| *parameter == name) {
+            Some((_, value)) => value(self),
+            None => self.derived.iter().find(|derived| derived.name == name).and_then(|derived| derived.value),
+        }
+    }
+}
+
+/// Value of a custom derivation registered in [`DerivationRegistry`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedParameter {
+    pub name: String,
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialAuc {
+    pub start: f64,
+    pub end: f64,
+    pub auc: Option<f64>,
+    /// Partial AUC as a percentage of `reference` (AUC_inf, or AUC_last when AUC_inf is unavailable)
+    pub percent_of_total: Option<f64>,
+    pub reference: Option<String>,
+}
+
+/// Time the interpolated profile crosses the concentration threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdCrossing {
+    pub time: f64,
+    /// True for an upward crossing (concentration rising above the threshold)
+    pub rising: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CumulativeAuc {
+    pub time: f64,
+    pub concentration: f64,
+    /// AUC from the first sample up to `time`
+    pub auc: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FractionAbsorbed {
+    pub time: f64,
+    /// Amount absorbed up to `time` relative to the total absorbed, (C + lambda_z * AUC_0-t) / (lambda_z * AUC_0-inf)
+    pub fraction: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseIntervalAuc {
+    /// 1-based dose number in time order
+    pub dose_number: usize,
+    pub dose_time: f64,
+    /// Time of the next dose, or the last sample for the final dose
+    pub end: f64,
+    pub auc: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,9 +438,116 @@ pub struct PopulationResults {
     pub individual_results: Vec<NcaResults>,
     pub failed_subjects: Vec<FailedSubjectAnalysis>,
     pub summary_statistics: SummaryStatistics,
+    /// Per-subject dose-normalized parameters, filled when `dose_normalization` is enabled
+    pub dose_normalized_parameters: Vec<DoseNormalizedParameters>,
+    /// Summary of dose-proportional parameters divided by each subject's total dose,
+    /// present when `dose_normalization` is enabled
+    pub dose_normalized_summary: Option<SummaryStatistics>,
+    /// Subjects left out of `dose_normalized_parameters` and its summary
+    pub dose_normalization_exclusions: Vec<DoseNormalizationExclusion>,
     pub method_comparison: MethodComparison,
     pub stratified_results: HashMap<String, StratifiedResults>,
+    /// Pairwise strata comparisons, when `perform_statistical_tests` is enabled
+    pub strata_comparisons: Vec<StrataComparison>,
+    /// Observed concentrations outside the plausibility bounds (reported, not altered)
+    pub concentration_flags: Vec<ConcentrationFlag>,
+    /// Subjects whose dosing suggests a data or unit error (reported, not altered)
+    pub dose_flags: Vec<DoseFlag>,
+    /// Two-way clearance cross-check of every intravascular subject with CL and lambda_z
+    pub clearance_checks: Vec<ClearanceCheck>,
+    /// Per-subject sampling completeness, when `data_completeness` is enabled
+    pub data_completeness: Vec<DataCompleteness>,
     pub covariate_analysis: CovariateAnalysis,
+    pub data_quality: DataQualitySummary,
+}
+
+/// Dataset-level quality indicators, reported at the top of analysis_report.txt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataQualitySummary {
+    /// Subject profiles analyzed or failed
+    pub subjects_total: usize,
+    pub percent_analyzed: f64,
+    /// Percent of analyzed subjects whose AUC_inf is reliable (extrapolated within
+    /// [`ValidationThresholds::max_auc_extrap_percent`])
+    pub percent_reliable_auc_inf: f64,
+    /// Median R² of the terminal regressions; `None` when no lambda_z was estimated
+    pub median_lambda_z_r_squared: Option<f64>,
+    /// Percent of analyte observations flagged BLQ (BLOD included)
+    pub percent_blq: f64,
+    /// Composite 0-100 score: the mean of the analyzed and reliable-AUC_inf percentages, the
+    /// median terminal R² × 100 (0 without one) and the percentage of quantifiable observations
+    pub score: f64,
+}
+
+/// Sampling completeness of one subject's analyte profile, for data review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataCompleteness {
+    pub subject_id: String,
+    /// Largest number of distinct sampling times of any subject in the dataset
+    pub planned_timepoints: usize,
+    /// Distinct sampling times recorded for this subject
+    pub observed_timepoints: usize,
+    pub quantifiable: usize,
+    pub blq: usize,
+    pub first_quantifiable_time: Option<f64>,
+    pub last_quantifiable_time: Option<f64>,
+    /// Whether the analysis produced a lambda_z for this subject
+    pub terminal_phase_estimable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseNormalizedParameters {
+    pub subject_id: String,
+    /// Total dose the parameters other than AUCtau were divided by (the systemic dose for
+    /// mixed routes)
+    pub dose: f64,
+    /// Dose starting the dosing interval, the divisor of AUCtau
+    pub interval_dose: Option<f64>,
+    /// Parameter name (e.g. "auc_inf") to value per unit dose
+    pub values: HashMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcentrationFlag {
+    pub subject_id: String,
+    pub time: f64,
+    pub concentration: f64,
+    pub reason: String,
+}
+
+/// Subject whose dose amounts look implausible: a non-positive total or doses spanning too
+/// wide a range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseFlag {
+    pub subject_id: String,
+    pub min_dose: f64,
+    pub max_dose: f64,
+    pub total_dose: f64,
+    pub reason: String,
+}
+
+/// Clearance of an intravascular subject as Dose / AUC_inf against Dose / AUC_inf_pred, the
+/// clearance implied by the Clast the terminal regression predicts; a large difference points
+/// to a Clast off the fitted line or an extrapolation slope that disagrees with lambda_z
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearanceCheck {
+    pub subject_id: String,
+    /// Reported clearance, Dose / AUC_inf
+    pub clearance: f64,
+    /// Dose / AUC_inf_pred
+    pub expected_clearance: f64,
+    /// 100 * (clearance - expected) / expected
+    pub percent_difference: f64,
+    /// Difference beyond `max_clearance_discrepancy_percent`
+    pub flagged: bool,
+}
+
+/// Outcome of a streamed population run; the individual results are already on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingResults {
+    pub subjects_analyzed: usize,
+    pub failed_subjects: Vec<FailedSubjectAnalysis>,
+    pub summary_statistics: SummaryStatistics,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -110,6 +569,26 @@ pub struct StratifiedResults {
     pub method_comparison: MethodComparison,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PooledSummary {
+    pub stratum_name: String,
+    pub n_strata: usize,
+    pub n_subjects: usize,
+    pub parameter_stats: HashMap<String, PooledParameterStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PooledParameterStats {
+    pub n: usize,
+    pub mean: f64,
+    pub std: f64,
+    pub cv_percent: Option<f64>,
+    pub min: f64,
+    pub max: f64,
+    pub geometric_mean: Option<f64>,
+    pub geometric_cv_percent: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CovariateAnalysis {
     pub correlations: HashMap<String, CovariateCorrelation>,
@@ -122,17 +601,30 @@ pub struct CovariateCorrelation {
     pub covariate_name: String,
     pub parameter_correlations: HashMap<String, f64>,
     pub p_values: HashMap<String, f64>,
+    /// P-values adjusted with [`AnalysisConfig::covariate_p_value_correction`] across every
+    /// covariate-parameter correlation tested
+    pub adjusted_p_values: HashMap<String, f64>,
+    /// Adjusted p-value below [`AnalysisConfig::covariate_significance_level`]
+    pub significant: HashMap<String, bool>,
+    /// Correlation with each parameter controlling for
+    /// [`AnalysisConfig::partial_correlation_control`], over subjects with all three values
+    pub partial_correlations: HashMap<String, f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegressionResults {
     pub parameter: String,
     pub covariate: String,
-    pub slope: f64,
-    pub intercept: f64,
-    pub r_squared: f64,
-    pub p_value: f64,
-    pub confidence_interval: (f64, f64),
+    /// Number of (covariate, parameter) pairs in the fit
+    pub n: usize,
+    /// None when the covariate has no spread and the slope is undefined
+    pub slope: Option<f64>,
+    pub intercept: Option<f64>,
+    /// None when the parameter has no spread
+    pub r_squared: Option<f64>,
+    /// None with fewer than 3 pairs or an undefined slope
+    pub p_value: Option<f64>,
+    pub confidence_interval: Option<(f64, f64)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,13 +632,59 @@ pub struct DoseNormalizedAnalysis {
     pub dose_normalized_auc: HashMap<String, ParameterStats>,
     pub dose_normalized_cmax: HashMap<String, ParameterStats>,
     pub dose_linearity_assessment: HashMap<String, LinearityAssessment>,
+    /// Subjects left out of the dose-normalized analysis (placebo arms, zero dose)
+    pub exclusions: Vec<DoseNormalizationExclusion>,
+}
+
+/// One parameter of one subject in a CDISC PP-domain-like dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PpRecord {
+    /// USUBJID
+    pub usubjid: String,
+    /// PPCAT, the analyte
+    pub category: Option<String>,
+    /// PPSPEC, the sample matrix
+    pub specimen: Option<String>,
+    /// PPTESTCD and PPTEST
+    pub test_code: String,
+    pub test_name: String,
+    /// PPSTRESN and PPSTRESU
+    pub value: f64,
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseNormalizationExclusion {
+    pub subject_id: String,
+    pub treatment: String,
+    pub reason: String,
+}
+
+/// Role of a treatment arm in dose-normalized and dose-linearity analysis
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TreatmentRole {
+    /// Active drug; subjects are dose-normalized unless their total dose is zero
+    Active,
+    /// Placebo or other no-drug reference arm, excluded from dose normalization
+    Placebo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LinearityAssessment {
+    /// Slope and R² of dose-normalized AUC_inf regressed on dose
     pub slope: f64,
     pub r_squared: f64,
+    /// Power-model exponent beta of ln(AUC_inf) = alpha + beta * ln(dose), 1 under dose proportionality
+    pub beta: Option<f64>,
+    /// 90% confidence interval of beta
+    pub beta_confidence_interval: Option<(f64, f64)>,
+    /// Interval beta's CI must lie within for dose proportionality over the studied dose range,
+    /// 1 + ln(0.8) / ln(r) to 1 + ln(1.25) / ln(r) with r the highest over the lowest dose
+    pub acceptance_bounds: Option<(f64, f64)>,
+    /// Conclusion drawn from the CI against the bounds
     pub linearity_conclusion: String,
+    /// The comparison behind the conclusion, e.g. "90% CI of beta [0.93, 1.05] within [0.84, 1.16]"
+    pub conclusion_basis: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -155,11 +693,29 @@ pub struct StratificationConfig {
     pub include_interactions: bool,
     pub minimum_n_per_stratum: usize,
     pub perform_statistical_tests: bool,
+    /// Two-sample test behind the pairwise strata comparisons
+    #[serde(default)]
+    pub comparison_test: ComparisonTest,
+}
+
+/// Two-sample test used to compare strata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ComparisonTest {
+    /// Welch's t-test, not assuming equal variances
+    #[default]
+    WelchTTest,
+    /// Student's t-test with pooled variance
+    PooledTTest,
+    /// Mann-Whitney U test, with the Hodges-Lehmann shift estimate and CI
+    MannWhitney,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummaryStatistics {
     pub parameter_stats: HashMap<String, ParameterStats>,
+    /// Subjects whose unreliable AUC_inf was left out of the AUC_inf summary
+    /// (`exclude_unreliable_auc_inf`); the AUC_inf `n` counts those included
+    pub auc_inf_excluded: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,10 +723,11 @@ pub struct ParameterStats {
     pub n: usize,
     pub mean: f64,
     pub arithmetic_mean: f64,
-    pub arithmetic_std: f64,
-    pub arithmetic_cv_percent: f64,
-    pub std: f64,
-    pub cv_percent: f64,
+    /// Sample SD; `None` for a single value
+    pub arithmetic_std: Option<f64>,
+    pub arithmetic_cv_percent: Option<f64>,
+    pub std: Option<f64>,
+    pub cv_percent: Option<f64>,
     pub median: f64,
     pub q25: f64,
     pub q75: f64,
@@ -178,11 +735,36 @@ pub struct ParameterStats {
     pub max: f64,
     pub geometric_mean: Option<f64>,
     pub geometric_cv_percent: Option<f64>,
+    /// Values behind the geometric statistics; smaller than `n` when non-positive values
+    /// were excluded (see [`GeometricNonPositive`])
+    pub geometric_n: usize,
+    /// n / sum(1/x), for positive values only
+    pub harmonic_mean: Option<f64>,
+    /// Jackknife pseudo-SD of the harmonic mean (Lam et al., 1985), for n >= 2
+    pub harmonic_pseudo_sd: Option<f64>,
+    pub distribution: DistributionSummary,
+}
+
+/// Shape of a parameter's distribution across subjects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DistributionSummary {
+    /// Adjusted Fisher-Pearson skewness (G1), undefined for n < 3 or zero spread
+    pub skewness: Option<f64>,
+    /// Sample excess kurtosis (G2), undefined for n < 4 or zero spread
+    pub excess_kurtosis: Option<f64>,
+    /// Histogram bin boundaries, one more than `bin_counts`
+    pub bin_edges: Vec<f64>,
+    /// Number of values falling in each bin; the last bin includes its upper edge
+    pub bin_counts: Vec<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MethodComparison {
     pub auc_methods: HashMap<String, f64>,
+    /// Method of the reported AUC_last (see [`crate::auc::AucCalculator::primary_method`])
+    pub primary_method: String,
+    /// Each method's mean AUC_last as a percent difference from the primary method's mean
+    pub percent_difference_from_primary: HashMap<String, f64>,
     pub correlation_matrix: HashMap<String, HashMap<String, f64>>,
     pub bias_analysis: HashMap<String, BiasAnalysis>,
 }
@@ -197,15 +779,408 @@ pub struct BiasAnalysis {
 #[derive(Debug, Clone, PartialEq)]
 pub struct AnalysisConfig {
     pub auc_methods: Vec<AucMethod>,
+    /// Method of the reported AUC_last and everything derived from it; `None` uses linear
+    /// trapezoidal when configured, otherwise the first of `auc_methods`. Should be one of
+    /// `auc_methods` (see [`AnalysisConfig::ensure_primary_auc_method`])
+    pub primary_auc_method: Option<AucMethod>,
     pub lambda_z_selection: LambdaZSelection,
+    /// R² an automatically selected lambda_z regression must reach; `BestFit` uses its own
+    /// threshold. The poor-fit warning uses
+    /// [`ValidationThresholds::min_lambda_z_r_squared`], which should match
+    pub lambda_z_min_r_squared: f64,
+    /// Report the best lambda_z found even below the R² threshold, flagged with
+    /// `lambda_z_reliable = false`, instead of leaving the terminal parameters missing
+    pub lambda_z_report_below_threshold: bool,
+    /// Keep Tmax and every earlier point out of automatically selected (`Auto`, `BestFit`)
+    /// lambda_z windows, so a sparse profile cannot anchor the terminal slope on Cmax
+    pub lambda_z_exclude_tmax: bool,
     pub interpolation_method: InterpolationMethod,
     pub output_path: String,
     pub lloq_handling: LloqHandling,
+    /// Handling of samples below the limit of detection, separate from the other BLQ samples
+    /// (e.g. BLOD as zero, BLOQ as half the LLOQ); `None` handles them like any BLQ sample.
+    /// `HalfLloq` imputes half the LOD for BLOD samples, or half the LLOQ without a LOD
+    pub blod_handling: Option<LloqHandling>,
     pub time_units: String,
     pub concentration_units: String,
+    /// Dose (AMT) units, used in the clearance and volume units of the PP export
+    pub dose_units: String,
     pub stratification: Option<StratificationConfig>,
     pub perform_covariate_analysis: bool,
     pub dose_normalization: bool,
+    /// Compartment (CMT) holding the analyte of interest; observations in other
+    /// compartments are ignored. `None` analyzes observations from every compartment.
+    pub analyte_compartment: Option<i32>,
+    /// Leave AUC_inf values flagged unreliable out of the population AUC_inf summary
+    pub exclude_unreliable_auc_inf: bool,
+    pub auc_tau_anchor: AucTauAnchor,
+    /// Slope extrapolating AUC_inf, and so everything derived from AUC_inf (% extrapolated,
+    /// CL, MRT, volumes); half-life and AUMC_inf use the regression lambda_z, or the pooled
+    /// slope for a subject salvaged by `pooled_lambda_z`
+    pub auc_extrapolation_slope: AucExtrapolationSlope,
+    /// Extrapolate AUC_inf with a lambda_z pooled over each dose group (same analyte and total
+    /// dose) for subjects whose own terminal fit failed, recomputing the parameters derived
+    /// from it; half-life and Vz stay missing. Also enabled by `AucExtrapolationSlope::PooledGroup`
+    pub pooled_lambda_z: bool,
+    /// Shortest Tlast - Tmax for which AUC_inf and its derived parameters are reported, a
+    /// check independent of R² and of the extrapolated percentage
+    pub min_tmax_to_tlast: Option<TerminalSpanRequirement>,
+    /// Arithmetic and/or geometric statistics in population_summary.csv
+    pub population_summary_statistics: PopulationSummaryStatistics,
+    /// Start of the AUC and AUMC integration when no sample is taken at the dose time
+    pub dose_time_concentration: DoseTimeConcentration,
+    /// Bioavailability assumed for the extravascular doses of subjects whose doses mix
+    /// intravascular and extravascular routes
+    pub extravascular_bioavailability: Option<f64>,
+    /// Dosing interval tau; when set, AUCtau is the partial AUC over [last dose, last dose + tau]
+    /// whether or not the dose is flagged steady state. Must be positive
+    /// (see [`AnalysisConfig::validate`])
+    pub dosing_interval: Option<f64>,
+    /// Time intervals (start, end) over which partial AUCs are reported
+    pub partial_auc_intervals: Vec<(f64, f64)>,
+    /// Concentration (e.g. an MIC or a toxicity ceiling) above which the time above threshold
+    /// is reported
+    pub concentration_threshold: Option<f64>,
+    /// Interpolation between the samples bracketing a threshold crossing, used for the
+    /// crossing times, time above threshold and AUC above threshold
+    pub threshold_interpolation: InterpolationMethod,
+    pub validation_thresholds: ValidationThresholds,
+    pub replicate_handling: ReplicateHandling,
+    /// Inter-replicate CV (%) above which averaged replicates are flagged
+    pub replicate_cv_threshold: f64,
+    /// Fewest subjects with both values for a covariate regression to be reported; the CLI
+    /// rejects values below 3
+    pub min_regression_n: usize,
+    /// Multiplicity adjustment of the covariate correlation p-values
+    pub covariate_p_value_correction: MultipleComparisonCorrection,
+    /// FDR (Benjamini-Hochberg) or family-wise error rate (Bonferroni) at which an adjusted
+    /// covariate correlation p-value is significant
+    pub covariate_significance_level: f64,
+    /// Covariate (age, weight or height) the covariate correlations are also reported
+    /// controlled for, as partial correlations
+    pub partial_correlation_control: Option<String>,
+    /// Role of each treatment (TRT/TREATMENT value) in the covariate dose-normalized analysis;
+    /// unlisted treatments are active
+    pub treatment_roles: HashMap<String, TreatmentRole>,
+    /// Report the concentration interpolated at the end of infusion (`cmax_eoi`) for infusion
+    /// subjects, alongside the observed Cmax
+    pub report_end_of_infusion_cmax: bool,
+    /// Keep each subject's analyzed observations (`NcaResults::analyzed_observations`) for
+    /// the concentrations.csv export
+    pub export_concentrations: bool,
+    /// Significant digits of the decimal numbers in complete_results.json, 1 to 17; `None`
+    /// keeps full f64 precision
+    pub json_significant_digits: Option<u32>,
+    /// Keep samples taken before the first dose in the analyzed profile; by default they are
+    /// excluded, so AUC integration starts at the dose
+    pub include_pre_dose_samples: bool,
+    /// Fewest quantifiable concentrations for a subject to be analyzed at all, at least 2;
+    /// subjects with fewer than `min_terminal_points` get observed-only parameters
+    /// ([`NcaResultType::ObservedOnly`])
+    pub min_observed_points: usize,
+    /// Fewest quantifiable concentrations for the terminal phase (lambda_z, AUC_inf, CL, ...),
+    /// at least `min_observed_points`. Both are checked by [`AnalysisConfig::validate`]
+    pub min_terminal_points: usize,
+    /// Report per-subject sampling completeness (data_completeness.csv)
+    pub data_completeness: bool,
+    /// Per-parameter overrides of the summary statistic emphasized in reports
+    /// (see [`SummaryConvention::default_for`])
+    pub summary_conventions: HashMap<String, SummaryConvention>,
+    /// Naming scheme of the individual_results.csv parameter columns, and of the parameters
+    /// in the summary files
+    pub parameter_naming: ParameterNaming,
+    /// Custom individual_results.csv column names, keyed by the default column name (e.g.
+    /// `AUC_INF` -> `AUC0-inf`), also naming the parameters in the summary files; these take
+    /// precedence over `parameter_naming`
+    pub column_aliases: HashMap<String, String>,
+    /// Append units to the LAMBDA_Z (`1/time`) and half-life (`time`) column headers of
+    /// individual_results.csv, e.g. `LAMBDA_Z (1/h)`
+    pub units_in_headers: bool,
+    /// Re-run each subject with every configured AUC method for `method_comparisons`;
+    /// when false only the primary method is computed
+    pub compute_method_comparison: bool,
+    /// Subjects analyzed per rayon task; larger chunks cut scheduling overhead when there are
+    /// many subjects with little work each. At least 1 (see [`AnalysisConfig::validate`])
+    pub parallel_chunk_size: usize,
+    /// Weight subjects in the population summary statistics (mean, SD, geometric mean)
+    pub weighted_summary: bool,
+    /// Treatment of zero and negative values in the geometric mean and CV
+    pub geometric_non_positive: GeometricNonPositive,
+    /// Per-subject analysis weights keyed by subject ID, taking precedence over the
+    /// WEIGHT/ANALYSIS_WT column; subjects with neither get weight 1
+    pub subject_weights: HashMap<String, f64>,
+    /// Custom parameter derivations evaluated for each subject (derived_parameters.csv)
+    pub derivations: DerivationRegistry,
+}
+
+impl AnalysisConfig {
+    /// Statistic the report leads with for `parameter`: the configured override, or the default convention
+    pub fn summary_convention(&self, parameter: &str) -> SummaryConvention {
+        self.summary_conventions
+            .get(parameter)
+            .cloned()
+            .unwrap_or_else(|| SummaryConvention::default_for(parameter))
+    }
+
+    /// Add the primary AUC method to `auc_methods` when it is missing, so the primary AUC is
+    /// always among the computed methods and their comparison
+    ///
+    /// Covers an explicit `primary_auc_method` left out of `auc_methods`, and an empty
+    /// `auc_methods` falling back on linear trapezoidal. Returns a warning describing the
+    /// change, `None` when the configuration was already consistent.
+    pub fn ensure_primary_auc_method(&mut self) -> Option<String> {
+        let primary = crate::auc::AucCalculator::primary_method(self);
+        if self.auc_methods.contains(&primary) {
+            return None;
+        }
+        let warning = match self.primary_auc_method {
+            Some(_) => format!("Primary AUC method {} is not among the configured AUC methods; added", primary.key()),
+            None => format!("No AUC methods configured; added the default primary method {}", primary.key()),
+        };
+        self.auc_methods.insert(0, primary);
+        Some(warning)
+    }
+
+    /// Reject settings no analysis can run with, once before any subject is analyzed
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.parallel_chunk_size < 1 {
+            return Err(crate::errors::NcaError::ParseError(
+                "Parallel chunk size must be at least 1".to_string(),
+            ));
+        }
+        if let Some(tau) = self.dosing_interval.filter(|tau| *tau <= 0.0 || tau.is_nan()) {
+            return Err(crate::errors::NcaError::ParseError(format!(
+                "Dosing interval must be positive, got {}", tau
+            )));
+        }
+        if let Some(digits) = self.json_significant_digits.filter(|digits| !(1..=17).contains(digits)) {
+            return Err(crate::errors::NcaError::ParseError(format!(
+                "JSON significant digits must be between 1 and 17, got {}", digits
+            )));
+        }
+        if self.min_observed_points < 2 {
+            return Err(crate::errors::NcaError::ParseError(format!(
+                "Minimum observed points must be at least 2, got {}", self.min_observed_points
+            )));
+        }
+        if self.min_terminal_points < self.min_observed_points {
+            return Err(crate::errors::NcaError::ParseError(format!(
+                "Minimum terminal points ({}) must be at least the minimum observed points ({})",
+                self.min_terminal_points, self.min_observed_points
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether AUC_inf is extrapolated with the pooled dose-group lambda_z for subjects without
+    /// a terminal fit: `pooled_lambda_z`, or the `PooledGroup` extrapolation slope
+    pub fn uses_pooled_lambda_z(&self) -> bool {
+        self.pooled_lambda_z || self.auc_extrapolation_slope == AucExtrapolationSlope::PooledGroup
+    }
+
+    /// Unit of lambda_z, the reciprocal of the time unit (e.g. `1/h`)
+    pub fn lambda_z_unit(&self) -> String {
+        format!("1/{}", self.time_units)
+    }
+
+    /// Unit of a rate constant or half-life parameter, `None` for any other parameter
+    pub fn terminal_parameter_unit(&self, parameter: &str) -> Option<String> {
+        match parameter {
+            "lambda_z" => Some(self.lambda_z_unit()),
+            "half_life" | "effective_half_life" => Some(self.time_units.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Parameter nomenclature of output column headers; internal parameter keys never change
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterNaming {
+    /// This tool's own column names (AUC_INF, LAMBDA_Z, ...)
+    Default,
+    /// Phoenix WinNonlin NCA names (AUCINF_obs, Lambda_z, HL_Lambda_z, ...)
+    WinNonlin,
+    /// CDISC PPTESTCD codes (AUCIFO, LAMZ, LAMZHL, ...)
+    Cdisc,
+}
+
+/// Which central tendency a report emphasizes for a parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum SummaryConvention {
+    /// Geometric mean and geometric CV, for log-normally distributed exposure parameters
+    Geometric,
+    /// Arithmetic mean and CV
+    Arithmetic,
+    /// Harmonic mean and jackknife pseudo-SD, for rate-derived parameters such as half-life
+    Harmonic,
+}
+
+impl SummaryConvention {
+    /// Regulatory convention: geometric for exposure, clearance and volume, harmonic for
+    /// half-life (the reciprocal of the mean elimination rate), arithmetic for other
+    /// time-type parameters (Tmax, MRT)
+    pub fn default_for(parameter: &str) -> Self {
+        match parameter {
+            "half_life" => SummaryConvention::Harmonic,
+            "tmax" | "tlast" | "mrt" | "lambda_z" => SummaryConvention::Arithmetic,
+            _ => SummaryConvention::Geometric,
+        }
+    }
+}
+
+/// How geometric statistics treat values at or below zero, whose logarithm is undefined
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum GeometricNonPositive {
+    /// Leave non-positive values out of the geometric mean and CV (reported `geometric_n`)
+    #[default]
+    Exclude,
+    /// Geometric mean of value + offset, less the offset; values still non-positive after
+    /// the offset are excluded
+    Offset(f64),
+    /// No geometric statistics for a parameter with any non-positive value
+    Undefined,
+}
+
+impl GeometricNonPositive {
+    /// Offset added to each value before taking logarithms
+    pub fn offset(&self) -> f64 {
+        match self {
+            GeometricNonPositive::Offset(offset) => *offset,
+            _ => 0.0,
+        }
+    }
+}
+
+/// How replicate assays of one sample (several DV rows at the same time) are combined
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicateHandling {
+    /// Keep every row as a separate observation
+    Keep,
+    ArithmeticMean,
+    GeometricMean,
+}
+
+/// Acceptance limits applied by quality-control validation of individual results
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationThresholds {
+    /// AUC extrapolation (%) above which AUC_inf is flagged unreliable (`auc_inf_reliable`),
+    /// with a warning, but still reported
+    pub max_auc_extrap_percent: f64,
+    /// Minimum acceptable R² of the lambda_z regression; keep equal to
+    /// [`AnalysisConfig::lambda_z_min_r_squared`] (as the CLI does) so that a fit accepted as
+    /// reliable is not also warned about
+    pub min_lambda_z_r_squared: f64,
+    /// Fewest points in the lambda_z regression before the fit is flagged
+    pub min_lambda_z_points: usize,
+    /// Plausible half-life range, in the analysis time units
+    pub min_half_life: f64,
+    pub max_half_life: f64,
+    /// Lowest plausible concentration; negative values are always flagged, whatever the floor
+    pub min_concentration: f64,
+    /// Highest plausible concentration, if any
+    pub max_concentration: Option<f64>,
+    /// Extravascular half-life to Tmax ratio above which flip-flop kinetics are suspected
+    pub flip_flop_half_life_tmax_ratio: f64,
+    /// Extravascular half-life, relative to the median intravascular half-life of the
+    /// population, above which flip-flop kinetics are suspected
+    pub flip_flop_iv_half_life_ratio: f64,
+    /// Rise from the intervening trough, as a fraction of Cmax, for a later or earlier local
+    /// maximum to count as a secondary peak
+    pub secondary_peak_min_rise: f64,
+    /// Warn when an extravascular Tmax is at the first post-dose sample
+    pub warn_tmax_at_first_sample: bool,
+    /// Largest plausible ratio between a subject's largest and smallest positive dose; wider
+    /// ranges usually mean mixed units (e.g. mg and µg)
+    pub max_dose_ratio: f64,
+    /// Largest difference (%) between Dose / AUC_inf and Dose / AUC_inf_pred before a clearance cross-check is flagged
+    pub max_clearance_discrepancy_percent: f64,
+}
+
+impl Default for ValidationThresholds {
+    fn default() -> Self {
+        Self {
+            max_auc_extrap_percent: 20.0,
+            min_lambda_z_r_squared: crate::parameters::DEFAULT_LAMBDA_Z_MIN_R_SQUARED,
+            min_lambda_z_points: 3,
+            min_half_life: 0.1,
+            max_half_life: 1000.0,
+            min_concentration: 0.0,
+            max_concentration: None,
+            flip_flop_half_life_tmax_ratio: 10.0,
+            flip_flop_iv_half_life_ratio: 2.0,
+            secondary_peak_min_rise: 0.1,
+            warn_tmax_at_first_sample: true,
+            max_dose_ratio: 100.0,
+            max_clearance_discrepancy_percent: 10.0,
+        }
+    }
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            auc_methods: vec![
+                AucMethod::LinearTrapezoidal,
+                AucMethod::LogTrapezoidal,
+                AucMethod::LinearLogTrapezoidal,
+                AucMethod::LinearUpLogDown,
+                AucMethod::Rectangular,
+            ],
+            primary_auc_method: None,
+            lambda_z_selection: LambdaZSelection::Auto,
+            lambda_z_min_r_squared: crate::parameters::DEFAULT_LAMBDA_Z_MIN_R_SQUARED,
+            lambda_z_report_below_threshold: false,
+            lambda_z_exclude_tmax: false,
+            interpolation_method: InterpolationMethod::Linear,
+            output_path: "./nca_results".to_string(),
+            lloq_handling: LloqHandling::HalfLloq,
+            blod_handling: None,
+            time_units: "h".to_string(),
+            concentration_units: "ng/mL".to_string(),
+            dose_units: "mg".to_string(),
+            stratification: None,
+            perform_covariate_analysis: false,
+            dose_normalization: false,
+            analyte_compartment: None,
+            exclude_unreliable_auc_inf: false,
+            auc_tau_anchor: AucTauAnchor::ObservedPreDose,
+            auc_extrapolation_slope: AucExtrapolationSlope::TerminalRegression,
+            pooled_lambda_z: false,
+            min_tmax_to_tlast: None,
+            population_summary_statistics: PopulationSummaryStatistics::Both,
+            dose_time_concentration: DoseTimeConcentration::FirstSample,
+            extravascular_bioavailability: None,
+            dosing_interval: None,
+            partial_auc_intervals: Vec::new(),
+            concentration_threshold: None,
+            threshold_interpolation: InterpolationMethod::Linear,
+            validation_thresholds: ValidationThresholds::default(),
+            replicate_handling: ReplicateHandling::Keep,
+            replicate_cv_threshold: 20.0,
+            min_regression_n: 3,
+            covariate_p_value_correction: MultipleComparisonCorrection::BenjaminiHochberg,
+            covariate_significance_level: 0.05,
+            partial_correlation_control: None,
+            treatment_roles: HashMap::new(),
+            report_end_of_infusion_cmax: false,
+            export_concentrations: false,
+            json_significant_digits: None,
+            include_pre_dose_samples: false,
+            min_observed_points: 2,
+            min_terminal_points: 3,
+            data_completeness: false,
+            summary_conventions: HashMap::new(),
+            parameter_naming: ParameterNaming::Default,
+            column_aliases: HashMap::new(),
+            units_in_headers: false,
+            compute_method_comparison: true,
+            parallel_chunk_size: crate::population::DEFAULT_PARALLEL_CHUNK_SIZE,
+            weighted_summary: false,
+            geometric_non_positive: GeometricNonPositive::Exclude,
+            subject_weights: HashMap::new(),
+            derivations: DerivationRegistry::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -214,6 +1189,103 @@ pub enum AucMethod {
     LogTrapezoidal,
     LinearLogTrapezoidal,
     LinearUpLogDown,
+    /// Left-endpoint rectangular rule, each concentration held until the next sample; a
+    /// deliberately simple baseline for method comparison, never the primary method by default
+    Rectangular,
+}
+
+impl AucMethod {
+    /// Key used for this method in AUC result maps
+    pub fn key(&self) -> &'static str {
+        match self {
+            AucMethod::LinearTrapezoidal => "linear_trapezoidal",
+            AucMethod::LogTrapezoidal => "log_trapezoidal",
+            AucMethod::LinearLogTrapezoidal => "linear_log_trapezoidal",
+            AucMethod::LinearUpLogDown => "linear_up_log_down",
+            AucMethod::Rectangular => "rectangular",
+        }
+    }
+}
+
+/// Concentration used at the start of a steady-state dosing interval for AUCtau
+///
+/// At steady state the pre-dose trough and the end-of-interval trough estimate the same
+/// concentration, but packages differ in which one anchors the start of the interval.
+/// The end of the interval is always the concentration observed (or interpolated) at
+/// dose + tau; when one trough is missing the other is used for both endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AucTauAnchor {
+    /// Start at the last concentration observed at or before the dose time
+    ObservedPreDose,
+    /// Start at the trough observed at dose + tau, carried back to the dose time
+    NextIntervalTrough,
+}
+
+/// Statistics in the parameter table of population_summary.csv; the median is always included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PopulationSummaryStatistics {
+    /// MEAN and CV%
+    Arithmetic,
+    /// GEO_MEAN and GEO_CV%
+    Geometric,
+    Both,
+}
+
+/// Adjustment of p-values for testing many hypotheses at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MultipleComparisonCorrection {
+    /// Benjamini-Hochberg step-up, controlling the false discovery rate
+    BenjaminiHochberg,
+    /// Each p-value times the number of tests, controlling the family-wise error rate
+    Bonferroni,
+    /// Raw p-values
+    None,
+}
+
+/// Concentration assumed at the dose time when the first sample comes after the dose
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DoseTimeConcentration {
+    /// Nothing is assumed: AUC starts at the first sample
+    FirstSample,
+    /// Add a point at the dose time, unless a sample exists at or before it: C0 back-extrapolated
+    /// from the first two quantifiable concentrations for an IV bolus, zero for infusions and
+    /// extravascular doses
+    RouteAware,
+}
+
+/// Shortest sampling after Tmax for AUC_inf to be reported, see
+/// [`AnalysisConfig::min_tmax_to_tlast`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TerminalSpanRequirement {
+    /// Tlast - Tmax of at least this time
+    Time(f64),
+    /// Tlast - Tmax of at least this many terminal half-lives, from the extrapolation slope
+    HalfLives(f64),
+}
+
+impl TerminalSpanRequirement {
+    /// Required Tlast - Tmax for a terminal extrapolation `slope`; `None` when it depends on a
+    /// half-life and the slope is not positive
+    pub fn required_span(&self, slope: f64) -> Option<f64> {
+        match self {
+            TerminalSpanRequirement::Time(span) => Some(*span),
+            TerminalSpanRequirement::HalfLives(n) => (slope > 0.0).then(|| n * std::f64::consts::LN_2 / slope),
+        }
+    }
+}
+
+/// Terminal slope used to extrapolate AUC_last to infinity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AucExtrapolationSlope {
+    /// Lambda_z from the terminal-phase regression
+    TerminalRegression,
+    /// Log-linear slope between the last two quantifiable concentrations
+    LastTwoPoints,
+    /// Lambda_z pooled over the subject's dose group, applied by
+    /// [`crate::population::PopulationAnalyzer::apply_pooled_lambda_z`] to subjects without a
+    /// terminal fit of their own. As a configuration it enables `pooled_lambda_z`, and subjects
+    /// with their own fit extrapolate (and are labelled) `TerminalRegression`
+    PooledGroup,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -221,6 +1293,9 @@ pub enum LambdaZSelection {
     Auto,
     Manual(Vec<usize>),
     BestFit { min_points: usize, r_squared_threshold: f64 },
+    /// Every quantifiable point at or after `start_time`, for protocol-specified terminal
+    /// windows (e.g. from 24 h onward); like `Manual`, the fit is not held to an R² minimum
+    TimeWindow { start_time: f64 },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,4 +1,4 @@
-use crate::{models::*, parameters::ParameterCalculator, auc::AucCalculator, Result};
+use crate::{models::*, parameters::ParameterCalculator, auc::AucCalculator, math::safe_div, preprocessing::Preprocessor, Result};
 use std::collections::HashMap;
 
 pub struct NcaAnalyzer;
@@ -9,8 +9,57 @@ impl NcaAnalyzer {
         subject: &Subject,
         config: &AnalysisConfig,
     ) -> Result<(NcaResults, Vec<String>)> {
-        let observations = &subject.observations;
+        Self::analyze_subject_with_slope(subject, config, None)
+    }
+
+    /// [`Self::analyze_subject`] extrapolating AUC_inf with the dose-group `pooled_lambda_z`
+    /// when the subject has no terminal fit of its own
+    ///
+    /// AUC_inf and everything derived from it (AUMC_inf, MRT, CL, Vss, partial AUC
+    /// percentages, derived parameters) then use the pooled slope, labelled `PooledGroup`;
+    /// half-life and Vz stay missing. A subject with its own fit is analyzed as usual.
+    pub fn analyze_subject_with_pooled_lambda_z(
+        subject: &Subject,
+        config: &AnalysisConfig,
+        pooled_lambda_z: f64,
+    ) -> Result<(NcaResults, Vec<String>)> {
+        Self::analyze_subject_with_slope(subject, config, Some(pooled_lambda_z))
+    }
+
+    fn analyze_subject_with_slope(
+        subject: &Subject,
+        config: &AnalysisConfig,
+        pooled_lambda_z: Option<f64>,
+    ) -> Result<(NcaResults, Vec<String>)> {
+        let mut observations: Vec<Observation> = subject.observations
+            .iter()
+            .filter(|obs| Self::is_analyte_observation(obs, config))
+            .cloned()
+            .collect();
         let mut warnings = Vec::new();
+
+        // NaN times or concentrations (corrupt input) would poison sorting and every parameter
+        let n_observations = observations.len();
+        observations.retain(|obs| !obs.time.is_nan() && !obs.concentration.is_nan());
+        let n_nan = n_observations - observations.len();
+        if n_nan > 0 {
+            log::warn!("Subject {}: {} observation(s) with a NaN time or concentration dropped", subject.id, n_nan);
+            warnings.push(format!("{} observation(s) with a NaN time or concentration dropped", n_nan));
+        }
+
+        // Samples before the first dose are baselines, not part of the profile integrated from the dose
+        let first_dose_time = subject.dosing_events.iter().map(|dose| dose.time).reduce(f64::min);
+        if let Some(first_dose_time) = first_dose_time.filter(|_| !config.include_pre_dose_samples) {
+            let n_observations = observations.len();
+            observations.retain(|obs| obs.time >= first_dose_time);
+            let n_pre_dose = n_observations - observations.len();
+            if n_pre_dose > 0 {
+                warnings.push(format!(
+                    "{} pre-dose sample(s) before t = {} excluded from the analysis",
+                    n_pre_dose, first_dose_time
+                ));
+            }
+        }
         
         if observations.is_empty() {
             return Err(crate::errors::NcaError::InsufficientData(
@@ -19,56 +68,272 @@ impl NcaAnalyzer {
         }
 
         // Sort observations by time
-        let mut sorted_obs = observations.clone();
-        sorted_obs.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        let mut sorted_obs = observations;
+        sorted_obs.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        // Average replicate assays at identical times
+        let (sorted_obs, replicate_summaries) = Preprocessor::aggregate_replicates(
+            &sorted_obs, &config.replicate_handling, config.replicate_cv_threshold,
+        );
+        for summary in replicate_summaries.iter().filter(|s| s.high_variability) {
+            warnings.push(format!(
+                "High inter-replicate variability at t = {} (CV = {:.1}%, n = {})",
+                summary.time, summary.cv_percent.unwrap_or(0.0), summary.n_replicates
+            ));
+        }
 
         // Check minimum quantifiable concentrations requirement
         let quantifiable_count = sorted_obs.iter()
             .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
             .count();
         
-        if quantifiable_count < 3 {
+        if quantifiable_count < config.min_observed_points {
             return Err(crate::errors::NcaError::InsufficientData(
-                format!("Subject {} has only {} quantifiable concentrations (minimum 3 required)", 
-                    subject.id, quantifiable_count)
+                format!("Subject {} has only {} quantifiable concentrations (minimum {} required)", 
+                    subject.id, quantifiable_count, config.min_observed_points)
             ));
         }
 
+        // Sparse subjects get the observed parameters only
+        let result_type = if quantifiable_count < config.min_terminal_points {
+            warnings.push(format!(
+                "Only {} quantifiable concentrations (minimum {} for the terminal phase) - observed-only parameters reported",
+                quantifiable_count, config.min_terminal_points
+            ));
+            NcaResultType::ObservedOnly
+        } else {
+            NcaResultType::Full
+        };
+
         // Calculate primary parameters
-        let individual_params = Self::calculate_individual_parameters(&sorted_obs, subject, config)?;
+        let dose_times: Vec<f64> = subject.dosing_events.iter().map(|dose| dose.time).collect();
+        let mut individual_params = Self::calculate_individual_parameters(
+            &sorted_obs, subject, &dose_times, config, result_type, pooled_lambda_z,
+        )?;
+        individual_params.derived = config.derivations.derive(&individual_params, subject);
         
         // Calculate using all AUC methods for comparison
         let mut method_comparisons = HashMap::new();
+        let comparison_methods: &[AucMethod] = if config.compute_method_comparison { &config.auc_methods } else { &[] };
         
-        for auc_method in &config.auc_methods {
+        for auc_method in comparison_methods {
             let method_name = format!("{:?}", auc_method);
             let method_config = AnalysisConfig {
                 auc_methods: vec![auc_method.clone()],
+                primary_auc_method: Some(auc_method.clone()),
                 ..config.clone()
             };
             
-            if let Ok(params) = Self::calculate_individual_parameters(&sorted_obs, subject, &method_config) {
+            if let Ok(params) = Self::calculate_individual_parameters(
+                &sorted_obs, subject, &dose_times, &method_config, result_type, pooled_lambda_z,
+            ) {
                 method_comparisons.insert(method_name, params);
             }
         }
 
         let results = NcaResults {
             subject_id: subject.id.clone(),
+            analyte: subject.analyte.clone(),
+            matrix: subject.matrix.clone(),
+            result_type,
+            dosing: Self::summarize_dosing(subject, config),
             individual_parameters: individual_params,
             method_comparisons,
+            replicate_summaries,
+            analyzed_observations: if config.export_concentrations {
+                AucCalculator::filter_observations(&sorted_obs, config)
+            } else {
+                Vec::new()
+            },
         };
 
-        // Generate warnings for missing parameters
-        let param_warnings = Self::check_parameter_completeness(&results);
-        warnings.extend(param_warnings);
+        // Warned from the per-dose AUCs so the warning and dose_interval_aucs always agree
+        let dose_intervals = &results.individual_parameters.dose_interval_aucs;
+        if !dose_intervals.is_empty() {
+            warnings.push(format!(
+                "Dose(s) within the sampled profile - whole-profile AUC spans the doses at t = {:?}; see per-dose AUCs",
+                dose_intervals.iter().map(|interval| interval.dose_time).collect::<Vec<_>>()
+            ));
+        }
+
+        if let (Some(false), Some(requirement)) = (results.individual_parameters.tmax_to_tlast_span_met, &config.min_tmax_to_tlast) {
+            let params = &results.individual_parameters;
+            let required = match requirement {
+                TerminalSpanRequirement::Time(span) => format!("{}", span),
+                TerminalSpanRequirement::HalfLives(n) => format!("{} half-lives", n),
+            };
+            warnings.push(format!(
+                "Sampling ends {} after Tmax (Tmax = {}, Tlast = {}) for subject {}, short of the required {}; AUC_inf and parameters derived from it not reported",
+                params.tlast.zip(params.tmax).map_or(0.0, |(tlast, tmax)| tlast - tmax),
+                params.tmax.map_or("NA".to_string(), |v| v.to_string()),
+                params.tlast.map_or("NA".to_string(), |v| v.to_string()),
+                subject.id,
+                required
+            ));
+        }
+
+        if let Some((dose_time, tau)) = Self::dosing_interval(subject, config) {
+            let analyzed = AucCalculator::filter_observations(&sorted_obs, config);
+            if !ParameterCalculator::interval_fully_observed(&analyzed, dose_time, dose_time + tau) {
+                warnings.push(format!(
+                    "Dosing interval t = {}-{} not fully sampled for subject {} (samples t = {}-{}); fluctuation and swing not reported",
+                    dose_time,
+                    dose_time + tau,
+                    subject.id,
+                    analyzed.first().map_or("NA".to_string(), |obs| obs.time.to_string()),
+                    analyzed.last().map_or("NA".to_string(), |obs| obs.time.to_string()),
+                ));
+            }
+        }
+
+        if results.dosing.total_dose <= 0.0 {
+            let dosing = if subject.dosing_events.is_empty() {
+                "no dosing record".to_string()
+            } else {
+                format!("total dose {}", results.dosing.total_dose)
+            };
+            warnings.push(format!(
+                "No dose for subject {} ({}): exposure parameters (Cmax, AUC) reported, dose-dependent CL, Vz and Vss not",
+                subject.id, dosing
+            ));
+        }
+
+        if results.dosing.mixed_routes {
+            let mut routes: Vec<&str> = Vec::new();
+            for route in subject.dosing_events.iter().map(|dose| dose.route.label()) {
+                if !routes.contains(&route) {
+                    routes.push(route);
+                }
+            }
+            let basis = match (results.dosing.effective_dose, config.extravascular_bioavailability) {
+                (Some(effective_dose), Some(bioavailability)) => format!(
+                    "CL and Vz use the effective dose {} assuming F = {} for extravascular doses; interpret with care",
+                    effective_dose, bioavailability
+                ),
+                _ => "the total dose is not the systemic dose, so CL, Vz and Vss are not reported".to_string(),
+            };
+            warnings.push(format!("Mixed dosing routes ({}) for subject {}: {}", routes.join(", "), subject.id, basis));
+        }
+
+        if let (Some(cmax2), Some(tmax2)) = (results.individual_parameters.cmax2, results.individual_parameters.tmax2) {
+            warnings.push(format!(
+                "Secondary peak at t = {} (C = {}) - possible enterohepatic recirculation or delayed release; Cmax/Tmax and lambda_z may be misleading",
+                tmax2, cmax2
+            ));
+        }
+
+        if let Some(warning) = Self::post_tmax_points_warning(&sorted_obs, &results, config) {
+            warnings.push(warning);
+        }
+
+        if let Some(warning) = Self::interval_start_point_warning(&sorted_obs, subject, &results, config) {
+            warnings.push(warning);
+        }
+
+        // A rising tail explains a missing lambda_z; a fit over an earlier window is reported as such
+        if result_type == NcaResultType::Full && ParameterCalculator::is_terminal_phase_declining(&sorted_obs, 3) == Some(false) {
+            let params = &results.individual_parameters;
+            warnings.push(match (params.lambda_z, params.lambda_z_time_range) {
+                (Some(_), Some((start, end))) => format!(
+                    "Last quantifiable concentrations are increasing; lambda_z reported from the declining window t = {} to {}",
+                    start, end
+                ),
+                _ => "Non-declining terminal phase - last quantifiable concentrations are increasing; lambda_z not reported"
+                    .to_string(),
+            });
+        }
+
+        // Generate warnings for missing parameters; expected for observed-only results
+        if result_type == NcaResultType::Full {
+            let param_warnings = Self::check_parameter_completeness(&results, &config.validation_thresholds);
+            warnings.extend(param_warnings);
+        }
 
         Ok((results, warnings))
     }
 
+    /// Run NCA on a bare concentration-time profile after a single dose at t = 0
+    ///
+    /// Builds the subject and its observations internally, so one-off calculations need no
+    /// dataset. All concentrations are treated as quantifiable.
+    ///
+    /// ```
+    /// use nca_analysis::{models::{AnalysisConfig, DosingRoute}, nca::NcaAnalyzer};
+    ///
+    /// let times = [0.5, 1.0, 2.0, 4.0, 8.0, 12.0];
+    /// let concentrations = [10.0, 8.0, 6.0, 3.5, 1.2, 0.4];
+    /// let (results, _warnings) = NcaAnalyzer::from_profile(
+    ///     &times, &concentrations, 100.0, DosingRoute::IntravenousBolus, &AnalysisConfig::default(),
+    /// ).unwrap();
+    ///
+    /// let params = &results.individual_parameters;
+    /// assert!((params.auc_last.unwrap() - 33.6).abs() < 1e-9);
+    /// assert!((params.half_life.unwrap() - 2.56).abs() < 0.01);
+    /// ```
+    pub fn from_profile(
+        times: &[f64],
+        concentrations: &[f64],
+        dose: f64,
+        route: DosingRoute,
+        config: &AnalysisConfig,
+    ) -> Result<(NcaResults, Vec<String>)> {
+        if times.len() != concentrations.len() {
+            return Err(crate::errors::NcaError::ParseError(format!(
+                "Profile has {} times but {} concentrations", times.len(), concentrations.len()
+            )));
+        }
+
+        let subject = Subject {
+            id: "profile".to_string(),
+            observations: times
+                .iter()
+                .zip(concentrations)
+                .map(|(&time, &concentration)| Observation {
+                    time,
+                    concentration,
+                    lloq: None,
+                    bloq: false,
+                    lod: None,
+                    blod: false,
+                    evid: 0,
+                    dv: concentration,
+                    cmt: None,
+                })
+                .collect(),
+            dosing_events: vec![DosingEvent {
+                time: 0.0,
+                dose,
+                route,
+                infusion_duration: None,
+                evid: 1,
+                cmt: None,
+                steady_state: false,
+                interval: None,
+            }],
+            demographics: Demographics::default(),
+            covariate_records: Vec::new(),
+            analyte: None,
+            matrix: None,
+        };
+
+        Self::analyze_subject(&subject, config)
+    }
+
+    /// Whether an observation belongs to the configured analyte compartment
+    pub fn is_analyte_observation(observation: &Observation, config: &AnalysisConfig) -> bool {
+        match (config.analyte_compartment, observation.cmt) {
+            (Some(analyte_cmt), Some(cmt)) => cmt == analyte_cmt,
+            _ => true,
+        }
+    }
+
     fn calculate_individual_parameters(
         observations: &[Observation],
         subject: &Subject,
+        dose_times: &[f64],
         config: &AnalysisConfig,
+        result_type: NcaResultType,
+        pooled_lambda_z: Option<f64>,
     ) -> Result<IndividualParameters> {
         // Basic parameters
         let (cmax, tmax) = ParameterCalculator::calculate_cmax_tmax(observations)?;
@@ -77,26 +342,165 @@ impl NcaAnalyzer {
                 "No quantifiable concentrations found".to_string()
             ))?;
 
-        // AUC calculations
-        let auc_methods = AucCalculator::calculate_all_methods(observations, config)?;
-        let auc_last = auc_methods.get("linear_trapezoidal").copied()
-            .or_else(|| auc_methods.values().next().copied())
-            .unwrap_or(0.0);
-
-        // Terminal elimination parameters
-        let (lambda_z, lambda_z_r_squared, _) = ParameterCalculator::calculate_lambda_z(
-            observations,
-            &config.lambda_z_selection,
-        ).unwrap_or((0.0, 0.0, Vec::new()));
-
-        // Calculate AUC to infinity
-        let (auc_inf, auc_inf_pred) = if lambda_z > 0.0 {
-            let auc_inf = AucCalculator::calculate_auc_inf(auc_last, clast, lambda_z)?;
-            (Some(auc_inf), Some(auc_inf))
+        // Concentration assumed at a dose time preceding the first sample, integrated from with
+        // the observations for AUC and AUMC (Cmax, lambda_z and Tlag stay observed)
+        let dose_time_point = Self::dose_time_point(observations, subject, config);
+        let with_dose_time_point = |observations: Vec<Observation>| match &dose_time_point {
+            Some(point) => [vec![point.clone()], observations].concat(),
+            None => observations,
+        };
+
+        // AUC with the primary method only; other methods are compared in `analyze_subject`
+        let auc_observations = with_dose_time_point(AucCalculator::filter_observations(observations, config));
+        AucCalculator::require_auc_points(&auc_observations)?;
+        let primary_method = AucCalculator::primary_method(config);
+        let auc_last = AucCalculator::calculate_auc(&auc_observations, &primary_method)?;
+        let cavg_observed = auc_observations
+            .first()
+            .and_then(|first| ParameterCalculator::calculate_cavg_observed(auc_last, first.time, tlast));
+
+        // Time, AUC and crossings above a concentration threshold such as an MIC
+        let threshold = config.concentration_threshold;
+        let interpolation = &config.threshold_interpolation;
+        let time_above_threshold = threshold
+            .map(|threshold| ParameterCalculator::time_above_threshold(&auc_observations, threshold, interpolation));
+        let auc_above_threshold = threshold.and_then(|threshold| {
+            ParameterCalculator::auc_above_threshold(&auc_observations, threshold, &primary_method, interpolation)
+        });
+        let threshold_crossings = threshold.map_or_else(Vec::new, |threshold| {
+            ParameterCalculator::threshold_crossings(&auc_observations, threshold, interpolation)
+        });
+
+        // Interpolated end-of-infusion concentration, the peak sparse sampling may miss
+        let end_of_infusion = subject.dosing_events
+            .first()
+            .filter(|dose| config.report_end_of_infusion_cmax && dose.route == DosingRoute::IntravenousInfusion)
+            .and_then(|dose| dose.infusion_duration.map(|duration| dose.time + duration))
+            .and_then(|t_eoi| {
+                AucCalculator::interpolate_concentration(&auc_observations, t_eoi, &config.interpolation_method)
+                    .map(|c_eoi| (c_eoi, t_eoi))
+            });
+
+        // Intervals starting at a (non steady-state) first dose before the first sample start
+        // from the route-aware dose-time concentration, even when AUC_last does not
+        let interval_observations = match (&dose_time_point, Self::interval_start_point(observations, subject)) {
+            (None, Some(point)) => [vec![point], auc_observations.clone()].concat(),
+            _ => auc_observations.clone(),
+        };
+
+        // AUCtau over [last dose, last dose + tau] for an explicit dosing interval, otherwise
+        // the steady-state AUC over the dosing interval of the last SS dose
+        let tau_interval = Self::dosing_interval(subject, config);
+        let auc_tau = tau_interval.and_then(|(dose_time, tau)| match config.dosing_interval {
+            Some(_) => AucCalculator::calculate_partial_auc(
+                &interval_observations,
+                dose_time,
+                dose_time + tau,
+                &primary_method,
+                &config.interpolation_method,
+            ),
+            None => AucCalculator::calculate_auc_tau(
+                &auc_observations,
+                dose_time,
+                tau,
+                &config.auc_tau_anchor,
+                &primary_method,
+                &config.interpolation_method,
+            ),
+        });
+
+        // Fluctuation and swing only over a dosing interval sampled from dose to dose + tau
+        let tau_extremes = tau_interval.and_then(|(dose_time, tau)| ParameterCalculator::interval_extremes(
+            &AucCalculator::filter_observations(observations, config),
+            dose_time,
+            dose_time + tau,
+        ));
+        let (fluctuation_percent, swing_percent) = match (tau_extremes, tau_interval, auc_tau) {
+            (Some((cmax_tau, cmin_tau)), Some((_, tau)), Some(auc_tau)) => (
+                safe_div(auc_tau, tau).and_then(|cavg| ParameterCalculator::calculate_fluctuation(cmax_tau, cmin_tau, cavg)),
+                ParameterCalculator::calculate_swing(cmax_tau, cmin_tau),
+            ),
+            _ => (None, None),
+        };
+
+        // Per-dose AUCs when a dose falls strictly inside the analyzed profile
+        let dose_interval_aucs = if AucCalculator::doses_within_sampling(&auc_observations, dose_times).is_empty() {
+            Vec::new()
         } else {
-            (None, None)
+            AucCalculator::calculate_dose_interval_aucs(
+                &auc_observations, dose_times, &primary_method, &config.interpolation_method,
+            )
+        };
+
+        let cumulative_auc = AucCalculator::calculate_cumulative_auc(&auc_observations, &primary_method)?;
+
+        // Terminal elimination parameters, not attempted for observed-only results
+        let terminal_phase = result_type == NcaResultType::Full;
+        // Automatic selection only searches the points after Tmax when it is excluded
+        let window_start = Self::lambda_z_window_start(observations, tmax, config);
+        let (lambda_z, lambda_z_r_squared, lambda_z_indices) = if terminal_phase {
+            ParameterCalculator::calculate_lambda_z_with_threshold(
+                &observations[window_start..],
+                &config.lambda_z_selection,
+                config.lambda_z_min_r_squared,
+                config.lambda_z_report_below_threshold,
+            )
+            .map(|(lambda_z, r_squared, indices)| {
+                (lambda_z, r_squared, indices.into_iter().map(|i| i + window_start).collect())
+            })
+            .unwrap_or((0.0, 0.0, Vec::new()))
+        } else {
+            (0.0, 0.0, Vec::new())
+        };
+        let lambda_z_times: Vec<f64> = lambda_z_indices
+            .iter()
+            .filter_map(|&i| observations.get(i).map(|obs| obs.time))
+            .collect();
+        let lambda_z_time_range = lambda_z_times
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .zip(lambda_z_times.iter().copied().reduce(f64::max))
+            .filter(|_| lambda_z > 0.0);
+        let lambda_z_intercept = lambda_z_time_range
+            .and_then(|_| ParameterCalculator::lambda_z_intercept(observations, &lambda_z_indices));
+
+        // Calculate AUC to infinity with the configured extrapolation slope, or with the pooled
+        // dose-group slope standing in for a missing terminal fit
+        let pooled_slope = pooled_lambda_z.filter(|&slope| slope > 0.0 && lambda_z <= 0.0);
+        let (auc_inf_slope, auc_inf_slope_method) = match pooled_slope {
+            Some(slope) => (Some(slope), AucExtrapolationSlope::PooledGroup),
+            None => match config.auc_extrapolation_slope {
+                AucExtrapolationSlope::TerminalRegression | AucExtrapolationSlope::PooledGroup => (
+                    Some(lambda_z).filter(|&slope| slope > 0.0 && terminal_phase),
+                    AucExtrapolationSlope::TerminalRegression,
+                ),
+                AucExtrapolationSlope::LastTwoPoints => (
+                    ParameterCalculator::calculate_last_two_points_slope(observations).filter(|_| terminal_phase),
+                    AucExtrapolationSlope::LastTwoPoints,
+                ),
+            },
         };
 
+        // Sampling must extend far enough past Tmax for the extrapolation to be trusted
+        let tmax_to_tlast_span_met = config.min_tmax_to_tlast
+            .as_ref()
+            .zip(auc_inf_slope)
+            .and_then(|(requirement, slope)| requirement.required_span(slope))
+            .map(|required| tlast - tmax >= required);
+        let auc_inf_slope = auc_inf_slope.filter(|_| tmax_to_tlast_span_met != Some(false));
+        let auc_inf = auc_inf_slope
+            .map(|slope| AucCalculator::calculate_auc_inf(auc_last, clast, slope))
+            .transpose()?;
+        // AUC_inf_pred extrapolates from the Clast predicted by the lambda_z regression instead
+        let auc_inf_pred = auc_inf
+            .and(lambda_z_intercept)
+            .map(|intercept| {
+                let clast_pred = (intercept - lambda_z * tlast).exp();
+                AucCalculator::calculate_auc_inf(auc_last, clast_pred, lambda_z)
+            })
+            .transpose()?;
+
         // AUC extrapolation percentage
         let auc_percent_extrap = if let Some(auc_inf_val) = auc_inf {
             Some(ParameterCalculator::calculate_auc_percent_extrap(auc_last, auc_inf_val)?)
@@ -104,13 +508,19 @@ impl NcaAnalyzer {
             None
         };
 
-        // AUMC calculations
-        let aumc_last = AucCalculator::calculate_aumc(observations)?;
-        let aumc_inf = if lambda_z > 0.0 {
-            Some(AucCalculator::calculate_aumc_inf(aumc_last, tlast, clast, lambda_z)?)
-        } else {
-            None
-        };
+        let auc_inf_reliable = auc_percent_extrap
+            .map(|extrap| extrap <= config.validation_thresholds.max_auc_extrap_percent);
+
+        let partial_aucs = Self::calculate_partial_aucs(
+            &interval_observations, config, &primary_method, auc_last, auc_inf,
+        );
+
+        // AUMC calculations; AUMC_inf is extrapolated with the same slope as AUC_inf, so MRT,
+        // Vss and Vz all follow AUC_INF_SLOPE_METHOD
+        let aumc_last = AucCalculator::calculate_aumc(&with_dose_time_point(observations.to_vec()))?;
+        let aumc_inf = auc_inf_slope
+            .map(|slope| AucCalculator::calculate_aumc_inf(aumc_last, tlast, clast, slope))
+            .transpose()?;
 
         // Half-life
         let half_life = if lambda_z > 0.0 {
@@ -119,80 +529,292 @@ impl NcaAnalyzer {
             None
         };
 
-        // MRT
+        // Accumulation over the sampled dosing intervals and the effective half-life it implies
+        let accumulation_ratio = ParameterCalculator::calculate_accumulation_ratio(&dose_interval_aucs);
+        let effective_half_life = accumulation_ratio.zip(dose_interval_aucs.first()).and_then(|(ratio, first)| {
+            ParameterCalculator::calculate_effective_half_life(first.end - first.dose_time, ratio)
+        });
+
+        // Time to steady state: 5 terminal half-lives, and from the troughs' approach to plateau
+        let time_to_steady_state = half_life.map(ParameterCalculator::calculate_time_to_steady_state);
+        let time_to_steady_state_observed = ParameterCalculator::time_to_steady_state_from_troughs(
+            &ParameterCalculator::pre_dose_troughs(observations, dose_times),
+        );
+
+        // MRT; an undefined ratio (AUC_inf = 0) is reported as missing, not as an error
         let mrt = if let (Some(aumc_inf_val), Some(auc_inf_val)) = (aumc_inf, auc_inf) {
-            Some(ParameterCalculator::calculate_mrt(aumc_inf_val, auc_inf_val)?)
+            ParameterCalculator::calculate_mrt(aumc_inf_val, auc_inf_val).ok()
         } else {
             None
         };
 
-        // Clearance and volume calculations
-        let total_dose = Self::calculate_total_dose(subject);
-        let (clearance, volume_steady_state, volume_terminal) = 
-            Self::calculate_clearance_and_volumes(total_dose, auc_inf, lambda_z, mrt)?;
+        // Clearance and volume calculations; extravascular doses give apparent CL/F and Vz/F,
+        // and Vss is not defined since MRT then includes the absorption time. Mixed-route
+        // dosing uses the effective systemic dose, and gives no CL without an assumed F. Without
+        // a positive dose only the exposure parameters are reported
+        let extravascular = subject.dosing_events.first().is_some_and(|dose| dose.route.is_extravascular());
+        let mixed_routes = Self::has_mixed_routes(subject);
+        let clearance_dose = if mixed_routes {
+            config.extravascular_bioavailability.map(|bioavailability| Self::effective_dose(subject, bioavailability))
+        } else {
+            Some(Self::calculate_total_dose(subject))
+        }
+        .filter(|&dose| dose > 0.0);
+        let (clearance, volume_steady_state, volume_terminal) = match clearance_dose {
+            Some(dose) => Self::calculate_clearance_and_volumes(dose, auc_inf, auc_inf_slope.unwrap_or(0.0), mrt),
+            None => (None, None, None),
+        };
+        let volume_steady_state = volume_steady_state.filter(|_| !extravascular && !mixed_routes);
+        let tlag = if extravascular { ParameterCalculator::calculate_tlag(observations) } else { None };
+        let flip_flop_suspected = extravascular
+            && Self::flip_flop_suspected(half_life, tmax, &config.validation_thresholds);
+        let tmax_at_first_sample = extravascular
+            && subject.dosing_events.first().is_some_and(|dose| Self::tmax_at_first_sample(observations, tmax, dose.time));
+        let secondary_peak = ParameterCalculator::detect_secondary_peak(
+            observations, config.validation_thresholds.secondary_peak_min_rise,
+        );
+        let fraction_absorbed = if extravascular {
+            ParameterCalculator::wagner_nelson(&cumulative_auc, lambda_z)
+        } else {
+            Vec::new()
+        };
 
         Ok(IndividualParameters {
             auc_last: Some(auc_last),
             auc_inf,
             auc_inf_pred,
             auc_percent_extrap,
+            auc_extrap_area: auc_inf.map(|auc_inf| auc_inf - auc_last),
+            auc_inf_reliable,
+            tmax_to_tlast_span_met,
+            auc_inf_slope,
+            auc_inf_slope_method: auc_inf_slope.map(|_| auc_inf_slope_method),
             aumc_last: Some(aumc_last),
             aumc_inf,
+            auc_tau,
+            fluctuation_percent,
+            swing_percent,
+            partial_aucs,
+            dose_interval_aucs,
+            cumulative_auc,
+            fraction_absorbed,
             cmax: Some(cmax),
             tmax: Some(tmax),
+            cmax_eoi: end_of_infusion.map(|(c_eoi, _)| c_eoi),
+            t_eoi: end_of_infusion.map(|(_, t_eoi)| t_eoi),
+            c0: dose_time_point.as_ref().map(|point| point.concentration),
+            tlag,
             tlast: Some(tlast),
             clast: Some(clast),
+            cavg_observed,
             half_life,
+            accumulation_ratio,
+            effective_half_life,
+            time_to_steady_state,
+            time_to_steady_state_observed,
+            time_above_threshold,
+            auc_above_threshold,
+            threshold_crossings,
             lambda_z: if lambda_z > 0.0 { Some(lambda_z) } else { None },
             lambda_z_r_squared: if lambda_z_r_squared > 0.0 { Some(lambda_z_r_squared) } else { None },
+            lambda_z_intercept,
+            lambda_z_n_points: lambda_z_time_range.map(|_| lambda_z_times.len()),
+            lambda_z_time_range,
+            lambda_z_reliable: ParameterCalculator::lambda_z_r_squared_threshold(
+                &config.lambda_z_selection, config.lambda_z_min_r_squared,
+            )
+            .filter(|_| lambda_z > 0.0)
+            .map(|threshold| lambda_z_r_squared >= threshold),
             clearance,
+            apparent_clearance: extravascular && !mixed_routes,
+            flip_flop_suspected,
+            tmax_at_first_sample,
+            cmax2: secondary_peak.map(|(cmax2, _)| cmax2),
+            tmax2: secondary_peak.map(|(_, tmax2)| tmax2),
             volume_steady_state,
             volume_terminal,
             mrt,
             bioavailability: None, // Would need reference data
+            derived: Vec::new(),
         })
     }
 
+    /// Partial AUCs over the configured intervals, each expressed as a percentage of AUC_inf
+    /// (or of AUC_last, labeled as such, when AUC_inf is unavailable)
+    fn calculate_partial_aucs(
+        observations: &[Observation],
+        config: &AnalysisConfig,
+        method: &AucMethod,
+        auc_last: f64,
+        auc_inf: Option<f64>,
+    ) -> Vec<PartialAuc> {
+        let (total, reference) = match auc_inf {
+            Some(auc_inf_val) if auc_inf_val > 0.0 => (Some(auc_inf_val), Some("AUC_inf")),
+            _ if auc_last > 0.0 => (Some(auc_last), Some("AUC_last")),
+            _ => (None, None),
+        };
+
+        config.partial_auc_intervals
+            .iter()
+            .map(|&(start, end)| {
+                let auc = AucCalculator::calculate_partial_auc(
+                    observations, start, end, method, &config.interpolation_method,
+                );
+                let percent_of_total = match (auc, total) {
+                    (Some(partial), Some(total_val)) => safe_div(partial, total_val).map(|f| f * 100.0),
+                    _ => None,
+                };
+
+                PartialAuc {
+                    start,
+                    end,
+                    auc,
+                    percent_of_total,
+                    reference: percent_of_total.and(reference).map(str::to_string),
+                }
+            })
+            .collect()
+    }
+
+    /// Observation assumed at the first dose time under `DoseTimeConcentration::RouteAware`,
+    /// when no sample is taken at or before it
+    fn dose_time_point(observations: &[Observation], subject: &Subject, config: &AnalysisConfig) -> Option<Observation> {
+        if config.dose_time_concentration != DoseTimeConcentration::RouteAware {
+            return None;
+        }
+        Self::route_aware_dose_time_point(observations, subject)
+    }
+
+    /// Warning for AUCtau or partial AUCs integrated from the assumed dose-time point of
+    /// [`Self::interval_start_point`]
+    fn interval_start_point_warning(
+        observations: &[Observation],
+        subject: &Subject,
+        results: &NcaResults,
+        config: &AnalysisConfig,
+    ) -> Option<String> {
+        if config.dose_time_concentration != DoseTimeConcentration::FirstSample {
+            return None;
+        }
+        let point = Self::interval_start_point(observations, subject)?;
+        let first_sample = observations.first()?.time;
+        let params = &results.individual_parameters;
+        let auc_tau_from_point = config.dosing_interval.is_some()
+            && params.auc_tau.is_some()
+            && Self::dosing_interval(subject, config).is_some_and(|(dose_time, _)| dose_time < first_sample);
+        let partial_from_point = params.partial_aucs.iter().any(|partial| partial.auc.is_some() && partial.start < first_sample);
+        (auc_tau_from_point || partial_from_point).then(|| format!(
+            "AUCtau / partial AUCs starting before the first sample (t = {}) integrate from an assumed C = {} at the dose time",
+            first_sample, point.concentration
+        ))
+    }
+
+    /// Dose-time point AUCtau and partial AUCs start from under `DoseTimeConcentration::FirstSample`:
+    /// the route-aware point, unless the first dose is at steady state (its pre-dose level is a
+    /// trough, not the route's zero or C0)
+    fn interval_start_point(observations: &[Observation], subject: &Subject) -> Option<Observation> {
+        if subject.dosing_events.first()?.steady_state {
+            return None;
+        }
+        Self::route_aware_dose_time_point(observations, subject)
+    }
+
+    fn route_aware_dose_time_point(observations: &[Observation], subject: &Subject) -> Option<Observation> {
+        let dose = subject.dosing_events.first()?;
+        if observations.first().is_none_or(|first| first.time <= dose.time) {
+            return None;
+        }
+
+        let concentration = match dose.route {
+            DosingRoute::IntravenousBolus => ParameterCalculator::back_extrapolate_c0(observations, dose.time)?,
+            _ => 0.0,
+        };
+        Some(Observation {
+            time: dose.time,
+            concentration,
+            lloq: None,
+            bloq: false,
+            lod: None,
+            blod: false,
+            evid: 0,
+            dv: concentration,
+            cmt: None,
+        })
+    }
+
+    /// Dose time and length of the dosing interval AUCtau, fluctuation and swing cover: the
+    /// configured interval after the last dose, else the interval of the last steady-state dose
+    pub fn dosing_interval(subject: &Subject, config: &AnalysisConfig) -> Option<(f64, f64)> {
+        match config.dosing_interval {
+            Some(tau) => subject.dosing_events
+                .iter()
+                .map(|dose| dose.time)
+                .reduce(f64::max)
+                .map(|dose_time| (dose_time, tau)),
+            None => subject.dosing_events
+                .iter()
+                .rev()
+                .find(|dose| dose.steady_state && dose.interval.is_some())
+                .map(|dose| (dose.time, dose.interval.unwrap_or(0.0))),
+        }
+        .filter(|&(_, tau)| tau > 0.0)
+    }
+
     fn calculate_total_dose(subject: &Subject) -> f64 {
         subject.dosing_events.iter().map(|dose| dose.dose).sum()
     }
 
+    fn summarize_dosing(subject: &Subject, config: &AnalysisConfig) -> DosingSummary {
+        let first_dose = subject.dosing_events.first();
+        let mixed_routes = Self::has_mixed_routes(subject);
+        DosingSummary {
+            total_dose: Self::calculate_total_dose(subject),
+            dose_count: subject.dosing_events.len(),
+            route: first_dose.map(|dose| dose.route.clone()),
+            infusion_duration: first_dose.and_then(|dose| dose.infusion_duration),
+            mixed_routes,
+            effective_dose: config.extravascular_bioavailability
+                .filter(|_| mixed_routes)
+                .map(|bioavailability| Self::effective_dose(subject, bioavailability)),
+        }
+    }
+
+    /// Whether a subject's doses mix intravascular (IV bolus, infusion) and extravascular routes
+    pub fn has_mixed_routes(subject: &Subject) -> bool {
+        let extravascular = |dose: &DosingEvent| dose.route.is_extravascular();
+        subject.dosing_events.iter().any(extravascular) && !subject.dosing_events.iter().all(extravascular)
+    }
+
+    /// Systemic dose: intravascular doses in full, extravascular doses times `bioavailability`
+    pub fn effective_dose(subject: &Subject, bioavailability: f64) -> f64 {
+        subject.dosing_events
+            .iter()
+            .map(|dose| if dose.route.is_extravascular() { bioavailability * dose.dose } else { dose.dose })
+            .sum()
+    }
+
     fn calculate_clearance_and_volumes(
         total_dose: f64,
         auc_inf: Option<f64>,
         lambda_z: f64,
         mrt: Option<f64>,
-    ) -> Result<(Option<f64>, Option<f64>, Option<f64>)> {
-        let clearance = if let Some(auc_inf_val) = auc_inf {
-            if auc_inf_val > 0.0 {
-                Some(ParameterCalculator::calculate_clearance_iv(total_dose, auc_inf_val)?)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+    ) -> (Option<f64>, Option<f64>, Option<f64>) {
+        let clearance = auc_inf
+            .and_then(|auc_inf_val| ParameterCalculator::calculate_clearance_iv(total_dose, auc_inf_val).ok());
 
         let volume_steady_state = if let (Some(cl), Some(mrt_val)) = (clearance, mrt) {
-            Some(ParameterCalculator::calculate_vss(cl, mrt_val)?)
+            ParameterCalculator::calculate_vss(cl, mrt_val).ok()
         } else {
             None
         };
 
-        let volume_terminal = if let Some(cl) = clearance {
-            if lambda_z > 0.0 {
-                Some(ParameterCalculator::calculate_vz(cl, lambda_z)?)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+        let volume_terminal = clearance
+            .and_then(|cl| ParameterCalculator::calculate_vz(cl, lambda_z).ok());
 
-        Ok((clearance, volume_steady_state, volume_terminal))
+        (clearance, volume_steady_state, volume_terminal)
     }
 
-    fn check_parameter_completeness(results: &NcaResults) -> Vec<String> {
+    fn check_parameter_completeness(results: &NcaResults, thresholds: &ValidationThresholds) -> Vec<String> {
         let mut warnings = Vec::new();
         let params = &results.individual_parameters;
         
@@ -208,57 +830,104 @@ impl NcaAnalyzer {
             warnings.push("Half-life could not be calculated - lambda_z unavailable".to_string());
         }
         
-        if params.clearance.is_none() {
-            warnings.push("Clearance could not be calculated - AUC_inf unavailable".to_string());
+        if params.clearance.is_none() && results.dosing.total_dose <= 0.0 {
+            warnings.push("Clearance and volumes not calculated - no positive dose recorded".to_string());
+        } else if params.clearance.is_none() && results.dosing.mixed_routes && results.dosing.effective_dose.is_none() {
+            warnings.push("Clearance not calculated - mixed intravascular and extravascular dosing needs an assumed extravascular bioavailability".to_string());
+        } else if params.clearance.is_none() {
+            warnings.push("Clearance could not be calculated - AUC_inf unavailable or zero".to_string());
+        }
+
+        let vss_expected = !params.apparent_clearance && !results.dosing.mixed_routes;
+        if params.clearance.is_some() && (params.volume_terminal.is_none() || (vss_expected && params.volume_steady_state.is_none())) {
+            warnings.push("Volume of distribution could not be calculated - clearance, MRT or lambda_z not positive".to_string());
         }
         
         if params.mrt.is_none() {
-            warnings.push("MRT could not be calculated - AUMC_inf or AUC_inf unavailable".to_string());
+            warnings.push("MRT could not be calculated - AUMC_inf or AUC_inf unavailable or zero".to_string());
         }
         
-        if let Some(extrap) = params.auc_percent_extrap {
-            if extrap > 20.0 {
-                warnings.push(format!("High AUC extrapolation ({}%) - results may be unreliable", extrap));
-            }
+        if let (Some(extrap), Some(false)) = (params.auc_percent_extrap, params.auc_inf_reliable) {
+            warnings.push(format!("High AUC extrapolation ({}%) - AUC_inf flagged as unreliable", extrap));
         }
         
         if let Some(r_sq) = params.lambda_z_r_squared {
-            if r_sq < 0.8 {
+            if r_sq < thresholds.min_lambda_z_r_squared {
                 warnings.push(format!("Poor terminal phase fit (R² = {:.3}) - lambda_z may be unreliable", r_sq));
             }
         }
+
+        if params.lambda_z_reliable == Some(false) {
+            warnings.push(format!(
+                "Lambda_z reported below the R² acceptance threshold (R² = {:.3}) - flagged as unreliable",
+                params.lambda_z_r_squared.unwrap_or(0.0)
+            ));
+        }
         
         warnings
     }
 
+    /// Start of the observations automatic lambda_z selection may use: just after Tmax with
+    /// `lambda_z_exclude_tmax`, otherwise the whole profile
+    fn lambda_z_window_start(observations: &[Observation], tmax: f64, config: &AnalysisConfig) -> usize {
+        let automatic = ParameterCalculator::lambda_z_min_points(&config.lambda_z_selection).is_some();
+        if config.lambda_z_exclude_tmax && automatic {
+            ParameterCalculator::lambda_z_window_start(observations, tmax)
+        } else {
+            0
+        }
+    }
+
+    /// Warning for a full result left without lambda_z because too few quantifiable points
+    /// follow Tmax once it is excluded from the terminal window
+    fn post_tmax_points_warning(observations: &[Observation], results: &NcaResults, config: &AnalysisConfig) -> Option<String> {
+        let params = &results.individual_parameters;
+        let min_points = ParameterCalculator::lambda_z_min_points(&config.lambda_z_selection)?;
+        let tmax = params.tmax?;
+        if !config.lambda_z_exclude_tmax || results.result_type != NcaResultType::Full || params.lambda_z.is_some() {
+            return None;
+        }
+        let window_start = Self::lambda_z_window_start(observations, tmax, config);
+        let post_tmax_points = observations[window_start..]
+            .iter()
+            .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
+            .count();
+        (post_tmax_points < min_points).then(|| format!(
+            "Only {} quantifiable point(s) after Tmax (t = {}) for subject {}, {} needed with Tmax excluded from the terminal phase; lambda_z not estimated",
+            post_tmax_points, tmax, results.subject_id, min_points
+        ))
+    }
+
     /// Validate analysis results for quality control
-    pub fn validate_results(results: &NcaResults) -> Vec<String> {
+    pub fn validate_results(results: &NcaResults, thresholds: &ValidationThresholds) -> Vec<String> {
         let mut warnings = Vec::new();
         let params = &results.individual_parameters;
 
-        // Check AUC extrapolation
-        if let Some(extrap) = params.auc_percent_extrap {
-            if extrap > 20.0 {
+        // Check R-squared and size of the lambda_z regression
+        let fit_points = match (params.lambda_z_n_points, params.lambda_z_time_range) {
+            (Some(n), Some((start, end))) => format!("{} points, t = {}-{}", n, start, end),
+            _ => "no points".to_string(),
+        };
+        if let Some(r_sq) = params.lambda_z_r_squared {
+            if r_sq < thresholds.min_lambda_z_r_squared {
                 warnings.push(format!(
-                    "High AUC extrapolation ({}%) for subject {}", 
-                    extrap, results.subject_id
+                    "Poor terminal phase fit (R² = {:.3}, {}) for subject {}", 
+                    r_sq, fit_points, results.subject_id
                 ));
             }
         }
-
-        // Check R-squared for lambda_z
-        if let Some(r_sq) = params.lambda_z_r_squared {
-            if r_sq < 0.8 {
+        if let Some(n) = params.lambda_z_n_points {
+            if n < thresholds.min_lambda_z_points {
                 warnings.push(format!(
-                    "Poor terminal phase fit (R² = {:.3}) for subject {}", 
-                    r_sq, results.subject_id
+                    "Terminal phase fit uses fewer than {} points ({}) for subject {}",
+                    thresholds.min_lambda_z_points, fit_points, results.subject_id
                 ));
             }
         }
 
         // Check for reasonable half-life values
         if let Some(t_half) = params.half_life {
-            if t_half < 0.1 || t_half > 1000.0 {
+            if !(thresholds.min_half_life..=thresholds.max_half_life).contains(&t_half) {
                 warnings.push(format!(
                     "Unusual half-life ({:.3} h) for subject {}", 
                     t_half, results.subject_id
@@ -266,6 +935,160 @@ impl NcaAnalyzer {
             }
         }
 
+        // Cmax at the last quantifiable sample: absorption may be incomplete and the
+        // terminal phase is not characterized
+        if let (Some(tmax), Some(tlast)) = (params.tmax, params.tlast) {
+            if tmax >= tlast {
+                warnings.push(format!(
+                    "Cmax at the last quantifiable time (Tmax = Tlast = {}) for subject {}; profile may be truncated, lambda_z and AUC_inf unreliable",
+                    tlast, results.subject_id
+                ));
+            }
+        }
+
+        if thresholds.warn_tmax_at_first_sample && params.tmax_at_first_sample {
+            warnings.push(format!(
+                "Tmax at the first post-dose sample (t = {}) for extravascular subject {}; the peak may precede sampling and Cmax be underestimated - consider earlier sampling or back-extrapolating C0",
+                params.tmax.map_or("NA".to_string(), |v| v.to_string()),
+                results.subject_id
+            ));
+        }
+
+        if params.flip_flop_suspected {
+            warnings.push(format!(
+                "Possible flip-flop kinetics for subject {}: terminal half-life {} vs Tmax {}; lambda_z may reflect absorption, CL/F and Vz/F unreliable",
+                results.subject_id,
+                params.half_life.map_or("NA".to_string(), |v| format!("{:.3}", v)),
+                params.tmax.map_or("NA".to_string(), |v| format!("{:.3}", v)),
+            ));
+        }
+
         warnings
     }
-}
\ No newline at end of file
+
+    /// Heuristic flip-flop check for an extravascular profile: a terminal half-life
+    /// implausibly long relative to Tmax
+    ///
+    /// When absorption is slower than elimination, the terminal slope is the absorption rate.
+    /// A single profile cannot tell the two cases apart, so this only flags extreme half-life
+    /// to Tmax ratios; [`crate::population::PopulationAnalyzer::flag_flip_flop_against_iv`]
+    /// adds a comparison against an intravascular arm when one is present.
+    pub fn flip_flop_suspected(half_life: Option<f64>, tmax: f64, thresholds: &ValidationThresholds) -> bool {
+        half_life
+            .and_then(|t_half| safe_div(t_half, tmax))
+            .is_some_and(|ratio| ratio > thresholds.flip_flop_half_life_tmax_ratio)
+    }
+
+    /// Whether Tmax is at (or before) the first sample taken after the dose at `dose_time`
+    ///
+    /// For an extravascular dose this means absorption may have peaked before the first
+    /// sample, so the observed Cmax can underestimate the true peak.
+    pub fn tmax_at_first_sample(observations: &[Observation], tmax: f64, dose_time: f64) -> bool {
+        observations
+            .iter()
+            .map(|obs| obs.time)
+            .filter(|&time| time > dose_time)
+            .reduce(f64::min)
+            .is_some_and(|first_sample| tmax <= first_sample)
+    }
+
+    /// Cross-check the clearance of an intravascular subject against the terminal regression
+    ///
+    /// `None` for extravascular or mixed-route subjects and whenever CL or AUC_inf_pred is
+    /// missing. See [`ClearanceCheck`].
+    pub fn cross_check_clearance(result: &NcaResults, thresholds: &ValidationThresholds) -> Option<ClearanceCheck> {
+        let params = &result.individual_parameters;
+        if params.apparent_clearance || result.dosing.mixed_routes {
+            return None;
+        }
+        let clearance = params.clearance?;
+        let expected_clearance = safe_div(result.dosing.total_dose, params.auc_inf_pred?)?;
+        let percent_difference = safe_div(100.0 * (clearance - expected_clearance), expected_clearance)?;
+
+        Some(ClearanceCheck {
+            subject_id: result.subject_id.clone(),
+            clearance,
+            expected_clearance,
+            percent_difference,
+            flagged: percent_difference.abs() > thresholds.max_clearance_discrepancy_percent,
+        })
+    }
+
+    /// Flag a subject whose doses are implausible: no dosing record, a zero or negative total
+    /// dose, negative amounts, or positive amounts spanning more than `max_dose_ratio`
+    ///
+    /// Doses are only reported; clearance still uses the summed total dose, and is not
+    /// reported without a positive one.
+    pub fn check_dosing(subject: &Subject, thresholds: &ValidationThresholds) -> Vec<DoseFlag> {
+        if subject.dosing_events.is_empty() {
+            return vec![DoseFlag {
+                subject_id: subject.id.clone(),
+                min_dose: 0.0,
+                max_dose: 0.0,
+                total_dose: 0.0,
+                reason: "No dosing record".to_string(),
+            }];
+        }
+
+        let doses: Vec<f64> = subject.dosing_events.iter().map(|dose| dose.dose).collect();
+        let min_dose = doses.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_dose = doses.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let total_dose = Self::calculate_total_dose(subject);
+        let min_positive = doses.iter().copied().filter(|&dose| dose > 0.0).reduce(f64::min);
+
+        let mut reasons = Vec::new();
+        if total_dose <= 0.0 {
+            reasons.push(format!("Non-positive total dose ({})", total_dose));
+        }
+        if min_dose < 0.0 {
+            reasons.push("Negative dose amount".to_string());
+        }
+        if let Some(ratio) = min_positive.and_then(|min| safe_div(max_dose, min)) {
+            if ratio > thresholds.max_dose_ratio {
+                reasons.push(format!(
+                    "Doses span a {:.0}-fold range (above {}); possible unit error", ratio, thresholds.max_dose_ratio
+                ));
+            }
+        }
+
+        reasons
+            .into_iter()
+            .map(|reason| DoseFlag {
+                subject_id: subject.id.clone(),
+                min_dose,
+                max_dose,
+                total_dose,
+                reason,
+            })
+            .collect()
+    }
+
+    /// Flag observed concentrations outside the plausibility bounds of `thresholds`
+    ///
+    /// Values are only reported; the analysis still uses them as recorded.
+    pub fn check_concentration_bounds(subject: &Subject, thresholds: &ValidationThresholds) -> Vec<ConcentrationFlag> {
+        let floor = thresholds.min_concentration.max(0.0);
+
+        subject.observations
+            .iter()
+            .filter_map(|obs| {
+                let reason = if obs.concentration < 0.0 {
+                    "Negative concentration".to_string()
+                } else if obs.concentration < floor {
+                    format!("Below plausible minimum ({})", floor)
+                } else if let Some(ceiling) = thresholds.max_concentration.filter(|&c| obs.concentration > c) {
+                    format!("Above plausible maximum ({})", ceiling)
+                } else {
+                    return None;
+                };
+
+                Some(ConcentrationFlag {
+                    subject_id: subject.id.clone(),
+                    time: obs.time,
+                    concentration: obs.concentration,
+                    reason,
+                })
+            })
+            .collect()
+    }
+}
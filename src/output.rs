@@ -1,133 +1,897 @@
-use crate::{models::*, Result};
+use crate::{models::*, math::round_significant, Result};
+use crate::stratification::StrataComparison;
+use crate::cdisc::CdiscExporter;
 use serde_json;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
 
 pub struct OutputManager;
 
+/// Directory one run writes into, recording the name of every file created in it
+struct OutputDir {
+    path: PathBuf,
+    written: RefCell<Vec<String>>,
+}
+
+impl OutputDir {
+    fn create(path: &Path) -> Result<Self> {
+        fs::create_dir_all(path)?;
+        Ok(Self { path: path.to_path_buf(), written: RefCell::new(Vec::new()) })
+    }
+
+    /// Create (or truncate) `file_name` in the directory and record it
+    fn file(&self, file_name: &str) -> Result<File> {
+        let file = File::create(self.path.join(file_name))?;
+        self.written.borrow_mut().push(file_name.to_string());
+        Ok(file)
+    }
+}
+
+/// Output names of the individual_results.csv columns and of the parameters in the summary
+/// files, under the configured naming scheme and custom aliases
+struct ParameterLabels<'a> {
+    config: &'a AnalysisConfig,
+    /// Route naming CLEARANCE and VZ (see [`OutputManager::individual_results_header`])
+    extravascular: Option<bool>,
+}
+
+impl<'a> ParameterLabels<'a> {
+    fn new(config: &'a AnalysisConfig, extravascular: Option<bool>) -> Self {
+        Self { config, extravascular }
+    }
+
+    /// Name of a default individual_results.csv column: its alias, else its scheme name
+    fn column(&self, column: &str) -> String {
+        if let Some(alias) = self.config.column_aliases.get(column) {
+            return alias.clone();
+        }
+        let (names, route_names) = match self.config.parameter_naming {
+            ParameterNaming::Default => return column.to_string(),
+            ParameterNaming::WinNonlin => (WINNONLIN_COLUMN_NAMES, WINNONLIN_ROUTE_COLUMN_NAMES),
+            ParameterNaming::Cdisc => (CDISC_COLUMN_NAMES, CDISC_ROUTE_COLUMN_NAMES),
+        };
+        let route_name = route_names
+            .iter()
+            .find(|&&(default, _, _)| default == column)
+            .map(|&(_, intravascular, extravascular)| match self.extravascular {
+                Some(false) => intravascular,
+                Some(true) => extravascular,
+                None => column,
+            });
+        route_name
+            .or_else(|| names.iter().find(|&&(default, _)| default == column).map(|&(_, name)| name))
+            .unwrap_or(column)
+            .to_string()
+    }
+
+    /// Name of a summarized parameter (e.g. `auc_inf`): the name of its individual_results.csv
+    /// column when a naming scheme or alias renames it, else the parameter name itself
+    fn parameter(&self, parameter: &str) -> String {
+        let column = match parameter {
+            "volume_terminal" => "VZ".to_string(),
+            "volume_steady_state" => "VSS".to_string(),
+            "lambda_z_r_squared" => "LAMBDA_Z_R2".to_string(),
+            "auc_percent_extrap" => "AUC_EXTRAP_PERCENT".to_string(),
+            other => other.to_uppercase(),
+        };
+        let renamed = self.config.parameter_naming != ParameterNaming::Default
+            || self.config.column_aliases.contains_key(&column);
+        if renamed && OutputManager::is_individual_results_column(&column) {
+            self.column(&column)
+        } else {
+            parameter.to_string()
+        }
+    }
+}
+
+/// Parameters whose availability is counted in the Data Yield section of analysis_report.txt
+const YIELD_PARAMETERS: &[&str] = &[
+    "auc_last", "auc_inf", "cmax", "lambda_z", "half_life", "clearance", "volume_terminal", "mrt",
+];
+
+/// WinNonlin names of individual_results.csv columns; unlisted columns keep their default name
+/// (CLEARANCE and VZ are named by route, see [`WINNONLIN_ROUTE_COLUMN_NAMES`])
+const WINNONLIN_COLUMN_NAMES: &[(&str, &str)] = &[
+    ("AUC_LAST", "AUClast"), ("AUC_INF", "AUCINF_obs"), ("AUC_INF_PRED", "AUCINF_pred"),
+    ("AUC_EXTRAP_PERCENT", "AUC_%Extrap_obs"), ("AUMC_LAST", "AUMClast"), ("AUMC_INF", "AUMCINF_obs"),
+    ("CMAX", "Cmax"), ("TMAX", "Tmax"), ("TLAST", "Tlast"), ("CLAST", "Clast"),
+    ("HALF_LIFE", "HL_Lambda_z"), ("LAMBDA_Z", "Lambda_z"), ("LAMBDA_Z_R2", "Rsq"),
+    ("VSS", "Vss_obs"), ("MRT", "MRTINF_obs"), ("TLAG", "Tlag"),
+    ("LAMBDA_Z_N_POINTS", "No_points_lambda_z"), ("LAMBDA_Z_TSTART", "Lambda_z_lower"),
+    ("LAMBDA_Z_TEND", "Lambda_z_upper"), ("TOTAL_DOSE", "Dose"), ("CAVG_OBSERVED", "Cavg"),
+    ("FLUCTUATION_PERCENT", "Fluctuation%"), ("SWING_PERCENT", "Swing"),
+];
+
+/// CDISC PPTESTCD names of individual_results.csv columns, matching the codes of pp.csv;
+/// unlisted columns keep their default name (CLEARANCE and VZ are named by route, see
+/// [`CDISC_ROUTE_COLUMN_NAMES`])
+const CDISC_COLUMN_NAMES: &[(&str, &str)] = &[
+    ("SUBJECT_ID", "USUBJID"), ("AUC_LAST", "AUCLST"), ("AUC_INF", "AUCIFO"), ("AUC_INF_PRED", "AUCIFP"),
+    ("AUC_EXTRAP_PERCENT", "AUCPEO"), ("AUMC_LAST", "AUMCLST"), ("AUMC_INF", "AUMCIFO"), ("AUC_TAU", "AUCTAU"),
+    ("CMAX", "CMAX"), ("TMAX", "TMAX"), ("TLAST", "TLST"), ("CLAST", "CLST"), ("HALF_LIFE", "LAMZHL"),
+    ("LAMBDA_Z", "LAMZ"), ("LAMBDA_Z_R2", "R2"), ("VSS", "VSSO"),
+    ("MRT", "MRTIFO"), ("TLAG", "TLAG"), ("LAMBDA_Z_N_POINTS", "LAMZNPT"), ("LAMBDA_Z_TSTART", "LAMZLL"),
+    ("LAMBDA_Z_TEND", "LAMZUL"), ("CAVG_OBSERVED", "CAVG"), ("FLUCTUATION_PERCENT", "FLUCP"),
+];
+
+/// WinNonlin names of the route-dependent columns: intravascular, then extravascular (CL/F, Vz/F)
+const WINNONLIN_ROUTE_COLUMN_NAMES: &[(&str, &str, &str)] = &[("CLEARANCE", "Cl_obs", "Cl_F_obs"), ("VZ", "Vz_obs", "Vz_F_obs")];
+
+/// CDISC names of the route-dependent columns: intravascular, then extravascular (CL/F, Vz/F)
+const CDISC_ROUTE_COLUMN_NAMES: &[(&str, &str, &str)] = &[("CLEARANCE", "CLO", "CLFO"), ("VZ", "VZO", "VZFO")];
+
+const INDIVIDUAL_RESULTS_HEADER: [&str; 55] = [
+    "SUBJECT_ID", "ANALYTE", "MATRIX", "AUC_LAST", "AUC_INF", "AUC_INF_PRED", "AUC_EXTRAP_PERCENT", "AUC_INF_RELIABLE",
+    "AUMC_LAST", "AUMC_INF", "AUC_TAU", "CMAX", "TMAX", "TLAST", "CLAST", "HALF_LIFE", "LAMBDA_Z",
+    "LAMBDA_Z_R2", "CLEARANCE", "VSS", "VZ", "MRT", "TLAG", "CL_TYPE", "FLIP_FLOP",
+    "LAMBDA_Z_N_POINTS", "LAMBDA_Z_TSTART", "LAMBDA_Z_TEND", "AUC_INF_SLOPE", "AUC_INF_SLOPE_METHOD",
+    "RESULT_TYPE", "TOTAL_DOSE", "DOSE_COUNT", "ROUTE", "INFUSION_DURATION", "LAMBDA_Z_RELIABLE",
+    "CAVG_OBSERVED", "CMAX2", "TMAX2", "AUC_EXTRAP_AREA",
+    "ACCUMULATION_RATIO", "EFFECTIVE_HALF_LIFE", "CMAX_EOI", "T_EOI", "TMAX_AT_FIRST_SAMPLE",
+    "TIME_ABOVE_THRESHOLD", "AUC_ABOVE_THRESHOLD", "C0", "LAMBDA_Z_INTERCEPT",
+    "MIXED_ROUTES", "EFFECTIVE_DOSE", "FLUCTUATION_PERCENT", "SWING_PERCENT",
+    "TIME_TO_SS", "TIME_TO_SS_OBSERVED",
+];
+
+const CONCENTRATIONS_HEADER: [&str; 9] = ["SUBJECT_ID", "ANALYTE", "MATRIX", "TIME", "CONCENTRATION", "BLQ", "LLOQ", "BLOD", "LOD"];
+
+const THRESHOLD_CROSSINGS_HEADER: [&str; 5] = ["SUBJECT_ID", "ANALYTE", "MATRIX", "TIME", "DIRECTION"];
+
+/// Incremental writer behind
+/// [`PopulationAnalyzer::analyze_population_streaming`](crate::population::PopulationAnalyzer::analyze_population_streaming)
+///
+/// Each result is appended to individual_results.csv, and its rows to concentrations.csv
+/// and threshold_crossings.csv, as it is written, so only the current batch of results is
+/// held in memory. The two per-row files are only created once they have a row, as in
+/// [`OutputManager::save_results`].
+pub struct StreamingOutput {
+    output_dir: OutputDir,
+    individual_results: csv::Writer<File>,
+    concentrations: Option<csv::Writer<File>>,
+    threshold_crossings: Option<csv::Writer<File>>,
+}
+
+impl StreamingOutput {
+    /// Create `output_dir` and start individual_results.csv with its header, named as
+    /// configured
+    pub fn create(output_dir: &Path, config: &AnalysisConfig) -> Result<Self> {
+        let output_dir = OutputDir::create(output_dir)?;
+        let mut individual_results = OutputManager::csv_writer(&output_dir, "individual_results.csv")?;
+        individual_results.write_record(OutputManager::individual_results_header(config, None))?;
+        Ok(Self {
+            output_dir,
+            individual_results,
+            concentrations: None,
+            threshold_crossings: None,
+        })
+    }
+
+    pub fn write_result(&mut self, result: &NcaResults) -> Result<()> {
+        self.individual_results.write_record(OutputManager::individual_result_record(result))?;
+        Self::append_rows(
+            &mut self.concentrations, &self.output_dir, "concentrations.csv", &CONCENTRATIONS_HEADER,
+            OutputManager::analyzed_concentration_records(result),
+        )?;
+        Self::append_rows(
+            &mut self.threshold_crossings, &self.output_dir, "threshold_crossings.csv", &THRESHOLD_CROSSINGS_HEADER,
+            OutputManager::threshold_crossing_records(result),
+        )?;
+        Ok(())
+    }
+
+    /// Flush the streamed files and write the QC files, summary_statistics.csv,
+    /// failed_subjects.log and manifest.json
+    pub fn finish(
+        mut self,
+        config: &AnalysisConfig,
+        summary: &SummaryStatistics,
+        failed_subjects: &[FailedSubjectAnalysis],
+        concentration_flags: &[ConcentrationFlag],
+        dose_flags: &[DoseFlag],
+        clearance_checks: &[ClearanceCheck],
+    ) -> Result<()> {
+        self.individual_results.flush()?;
+        for writer in self.concentrations.iter_mut().chain(self.threshold_crossings.iter_mut()) {
+            writer.flush()?;
+        }
+        OutputManager::save_concentration_qc(concentration_flags, &self.output_dir)?;
+        OutputManager::save_dose_qc(dose_flags, &self.output_dir)?;
+        OutputManager::save_clearance_qc(clearance_checks, &self.output_dir)?;
+        OutputManager::save_failed_subjects_log(failed_subjects, &self.output_dir)?;
+        OutputManager::save_summary_statistics(summary, &ParameterLabels::new(config, None), &self.output_dir, "summary_statistics.csv")?;
+        OutputManager::save_manifest(config, &self.output_dir)?;
+        log::info!("Streamed results saved to: {}", self.output_dir.path.display());
+        Ok(())
+    }
+
+    /// Write `records` to the file behind `writer`, creating it with `header` on the first row
+    fn append_rows(
+        writer: &mut Option<csv::Writer<File>>,
+        output_dir: &OutputDir,
+        file_name: &str,
+        header: &[&str],
+        records: Vec<Vec<String>>,
+    ) -> Result<()> {
+        for record in records {
+            if writer.is_none() {
+                let mut created = OutputManager::csv_writer(output_dir, file_name)?;
+                created.write_record(header)?;
+                *writer = Some(created);
+            }
+            if let Some(writer) = writer.as_mut() {
+                writer.write_record(record)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl OutputManager {
     pub fn save_results<P: AsRef<Path>>(
         results: &PopulationResults,
         config: &AnalysisConfig,
         output_path: P,
     ) -> Result<()> {
-        let output_dir = output_path.as_ref();
-        fs::create_dir_all(output_dir)?;
+        let output_dir = &OutputDir::create(output_path.as_ref())?;
+        let labels = &ParameterLabels::new(config, Self::uniform_extravascular(&results.individual_results, config));
 
         // Save individual results
-        Self::save_individual_results(&results.individual_results, output_dir)?;
+        Self::save_individual_results(&results.individual_results, labels, output_dir)?;
         
+        // Save partial AUCs
+        Self::save_partial_aucs(&results.individual_results, output_dir)?;
+        Self::save_dose_interval_aucs(&results.individual_results, output_dir)?;
+        Self::save_cumulative_aucs(&results.individual_results, output_dir)?;
+        Self::save_fraction_absorbed(&results.individual_results, output_dir)?;
+        Self::save_derived_parameters(&results.individual_results, output_dir)?;
+        Self::save_analyzed_concentrations(&results.individual_results, output_dir)?;
+        Self::save_threshold_crossings(&results.individual_results, output_dir)?;
+        Self::save_replicate_qc(&results.individual_results, output_dir)?;
+
+        // Save concentration QC
+        Self::save_concentration_qc(&results.concentration_flags, output_dir)?;
+        Self::save_dose_qc(&results.dose_flags, output_dir)?;
+        Self::save_clearance_qc(&results.clearance_checks, output_dir)?;
+        Self::save_data_completeness(&results.data_completeness, output_dir)?;
+
         // Save failed subjects log
         Self::save_failed_subjects_log(&results.failed_subjects, output_dir)?;
         
         // Save summary statistics
-        Self::save_summary_statistics(&results.summary_statistics, output_dir)?;
+        Self::save_summary_statistics(&results.summary_statistics, labels, output_dir, "summary_statistics.csv")?;
+        Self::save_distributions(&results.summary_statistics, labels, output_dir)?;
+        if let Some(dn_summary) = &results.dose_normalized_summary {
+            Self::save_summary_statistics(dn_summary, labels, output_dir, "summary_statistics_dn.csv")?;
+            Self::save_dose_normalized_parameters(&results.dose_normalized_parameters, output_dir)?;
+        }
         
         // Save method comparison
         Self::save_method_comparison(&results.method_comparison, output_dir)?;
         
         // Save stratified results
-        Self::save_stratified_results(&results.stratified_results, output_dir)?;
+        Self::save_stratified_results(&results.stratified_results, labels, output_dir)?;
+
+        // Save strata comparisons
+        Self::save_strata_comparisons(&results.strata_comparisons, labels, output_dir)?;
         
         // Save covariate analysis
         Self::save_covariate_analysis(&results.covariate_analysis, output_dir)?;
+        Self::save_dose_normalization_exclusions(results, output_dir)?;
         
+        // Save CDISC PP-style parameters
+        Self::save_pp_dataset(results, config, output_dir)?;
+
         // Save complete results as JSON
-        Self::save_json_results(results, output_dir)?;
+        Self::save_json_results(results, config, output_dir)?;
         
         // Save CSV summary
-        Self::save_csv_summary(results, output_dir)?;
+        Self::save_csv_summary(results, config, labels, output_dir)?;
         
         // Generate analysis report
         Self::generate_analysis_report(results, config, output_dir)?;
 
-        log::info!("Results saved to: {}", output_dir.display());
+        // List everything written above
+        Self::save_manifest(config, output_dir)?;
+
+        log::info!("Results saved to: {}", output_dir.path.display());
         Ok(())
     }
 
+    /// CSV writer for `file_name` in `output_dir`; fields containing delimiters, quotes or
+    /// line breaks (e.g. a formulation "Tablet, 50mg") are quoted by the csv crate
+    fn csv_writer(output_dir: &OutputDir, file_name: &str) -> Result<csv::Writer<File>> {
+        Ok(csv::Writer::from_writer(output_dir.file(file_name)?))
+    }
+
     fn save_individual_results(
         results: &[NcaResults],
-        output_dir: &Path,
+        labels: &ParameterLabels,
+        output_dir: &OutputDir,
     ) -> Result<()> {
-        let file_path = output_dir.join("individual_results.csv");
-        let mut file = File::create(file_path)?;
-        
-        // Write header
-        writeln!(file, "SUBJECT_ID,AUC_LAST,AUC_INF,AUC_INF_PRED,AUC_EXTRAP_PERCENT,AUMC_LAST,AUMC_INF,CMAX,TMAX,TLAST,CLAST,HALF_LIFE,LAMBDA_Z,LAMBDA_Z_R2,CLEARANCE,VSS,VZ,MRT")?;
-        
-        // Write data
+        let mut writer = Self::csv_writer(output_dir, "individual_results.csv")?;
+        writer.write_record(Self::individual_results_header(labels.config, labels.extravascular))?;
         for result in results {
-            let p = &result.individual_parameters;
-            writeln!(
-                file,
-                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                result.subject_id,
-                p.auc_last.map_or("NA".to_string(), |v| v.to_string()),
-                p.auc_inf.map_or("NA".to_string(), |v| v.to_string()),
-                p.auc_inf_pred.map_or("NA".to_string(), |v| v.to_string()),
-                p.auc_percent_extrap.map_or("NA".to_string(), |v| v.to_string()),
-                p.aumc_last.map_or("NA".to_string(), |v| v.to_string()),
-                p.aumc_inf.map_or("NA".to_string(), |v| v.to_string()),
-                p.cmax.map_or("NA".to_string(), |v| v.to_string()),
-                p.tmax.map_or("NA".to_string(), |v| v.to_string()),
-                p.tlast.map_or("NA".to_string(), |v| v.to_string()),
-                p.clast.map_or("NA".to_string(), |v| v.to_string()),
-                p.half_life.map_or("NA".to_string(), |v| v.to_string()),
-                p.lambda_z.map_or("NA".to_string(), |v| v.to_string()),
-                p.lambda_z_r_squared.map_or("NA".to_string(), |v| v.to_string()),
-                p.clearance.map_or("NA".to_string(), |v| v.to_string()),
-                p.volume_steady_state.map_or("NA".to_string(), |v| v.to_string()),
-                p.volume_terminal.map_or("NA".to_string(), |v| v.to_string()),
-                p.mrt.map_or("NA".to_string(), |v| v.to_string()),
-            )?;
+            writer.write_record(Self::individual_result_record(result))?;
         }
         
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// individual_results.csv column names under the configured naming scheme, with the
+    /// custom aliases applied on top, and the lambda_z and half-life units with
+    /// `units_in_headers`
+    ///
+    /// The scheme names CLEARANCE and VZ by route: `extravascular` is whether every subject
+    /// was dosed extravascularly (CL/F and Vz/F), and `None` (routes differ, or are not known
+    /// yet when streaming) keeps their default names, CL_TYPE telling CL from CL/F per row.
+    pub fn individual_results_header(config: &AnalysisConfig, extravascular: Option<bool>) -> Vec<String> {
+        let labels = ParameterLabels::new(config, extravascular);
+        INDIVIDUAL_RESULTS_HEADER
+            .iter()
+            .map(|&column| {
+                let name = labels.column(column);
+                match config.terminal_parameter_unit(&column.to_lowercase()).filter(|_| config.units_in_headers) {
+                    Some(unit) => format!("{} ({})", name, unit),
+                    None => name,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `column` is a default individual_results.csv column name
+    pub fn is_individual_results_column(column: &str) -> bool {
+        INDIVIDUAL_RESULTS_HEADER.contains(&column)
+    }
+
+    /// Whether all `results` are extravascular (CL/F), or all intravascular; `None` when the
+    /// routes differ, logged when the naming scheme then cannot name CLEARANCE and VZ by route
+    fn uniform_extravascular(results: &[NcaResults], config: &AnalysisConfig) -> Option<bool> {
+        let extravascular = |result: &NcaResults| result.individual_parameters.apparent_clearance;
+        let uniform = match results.first() {
+            Some(first) if results.iter().all(|result| extravascular(result) == extravascular(first)) => Some(extravascular(first)),
+            Some(_) => None,
+            None => Some(false),
+        };
+        if uniform.is_none() && config.parameter_naming != ParameterNaming::Default {
+            log::warn!("Subjects were dosed by different routes; CLEARANCE and VZ keep their default names, CL_TYPE tells CL from CL/F");
+        }
+        uniform
+    }
+
+    /// Analyte or matrix tag of a result, NA when the dataset has none
+    fn format_tag(tag: &Option<String>) -> String {
+        tag.clone().unwrap_or_else(|| "NA".to_string())
+    }
+
+    /// One row of individual_results.csv, in [`INDIVIDUAL_RESULTS_HEADER`] order
+    fn individual_result_record(result: &NcaResults) -> Vec<String> {
+        let p = &result.individual_parameters;
+        vec![
+            result.subject_id.clone(),
+            Self::format_tag(&result.analyte),
+            Self::format_tag(&result.matrix),
+            p.auc_last.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_inf.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_inf_pred.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_percent_extrap.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_inf_reliable.map_or("NA".to_string(), |v| v.to_string()),
+            p.aumc_last.map_or("NA".to_string(), |v| v.to_string()),
+            p.aumc_inf.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_tau.map_or("NA".to_string(), |v| v.to_string()),
+            p.cmax.map_or("NA".to_string(), |v| v.to_string()),
+            p.tmax.map_or("NA".to_string(), |v| v.to_string()),
+            p.tlast.map_or("NA".to_string(), |v| v.to_string()),
+            p.clast.map_or("NA".to_string(), |v| v.to_string()),
+            p.half_life.map_or("NA".to_string(), |v| v.to_string()),
+            p.lambda_z.map_or("NA".to_string(), |v| v.to_string()),
+            p.lambda_z_r_squared.map_or("NA".to_string(), |v| v.to_string()),
+            p.clearance.map_or("NA".to_string(), |v| v.to_string()),
+            p.volume_steady_state.map_or("NA".to_string(), |v| v.to_string()),
+            p.volume_terminal.map_or("NA".to_string(), |v| v.to_string()),
+            p.mrt.map_or("NA".to_string(), |v| v.to_string()),
+            p.tlag.map_or("NA".to_string(), |v| v.to_string()),
+            if p.apparent_clearance { "CL/F" } else { "CL" }.to_string(),
+            p.flip_flop_suspected.to_string(),
+            p.lambda_z_n_points.map_or("NA".to_string(), |v| v.to_string()),
+            p.lambda_z_time_range.map_or("NA".to_string(), |(start, _)| start.to_string()),
+            p.lambda_z_time_range.map_or("NA".to_string(), |(_, end)| end.to_string()),
+            p.auc_inf_slope.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_inf_slope_method.as_ref().map_or("NA".to_string(), |m| format!("{:?}", m)),
+            result.result_type.label().to_string(),
+            result.dosing.total_dose.to_string(),
+            result.dosing.dose_count.to_string(),
+            result.dosing.route.as_ref().map_or("NA".to_string(), |route| route.label().to_string()),
+            result.dosing.infusion_duration.map_or("NA".to_string(), |v| v.to_string()),
+            p.lambda_z_reliable.map_or("NA".to_string(), |v| v.to_string()),
+            p.cavg_observed.map_or("NA".to_string(), |v| v.to_string()),
+            p.cmax2.map_or("NA".to_string(), |v| v.to_string()),
+            p.tmax2.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_extrap_area.map_or("NA".to_string(), |v| v.to_string()),
+            p.accumulation_ratio.map_or("NA".to_string(), |v| v.to_string()),
+            p.effective_half_life.map_or("NA".to_string(), |v| v.to_string()),
+            p.cmax_eoi.map_or("NA".to_string(), |v| v.to_string()),
+            p.t_eoi.map_or("NA".to_string(), |v| v.to_string()),
+            p.tmax_at_first_sample.to_string(),
+            p.time_above_threshold.map_or("NA".to_string(), |v| v.to_string()),
+            p.auc_above_threshold.map_or("NA".to_string(), |v| v.to_string()),
+            p.c0.map_or("NA".to_string(), |v| v.to_string()),
+            p.lambda_z_intercept.map_or("NA".to_string(), |v| v.to_string()),
+            result.dosing.mixed_routes.to_string(),
+            result.dosing.effective_dose.map_or("NA".to_string(), |v| v.to_string()),
+            p.fluctuation_percent.map_or("NA".to_string(), |v| v.to_string()),
+            p.swing_percent.map_or("NA".to_string(), |v| v.to_string()),
+            p.time_to_steady_state.map_or("NA".to_string(), |v| v.to_string()),
+            p.time_to_steady_state_observed.map_or("NA".to_string(), |v| v.to_string()),
+        ]
+    }
+
+    fn save_partial_aucs(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.individual_parameters.partial_aucs.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "partial_auc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "START", "END", "AUC", "PERCENT_OF_TOTAL", "REFERENCE"])?;
+
+        for result in results {
+            for partial in &result.individual_parameters.partial_aucs {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    partial.start.to_string(),
+                    partial.end.to_string(),
+                    partial.auc.map_or("NA".to_string(), |v| v.to_string()),
+                    partial.percent_of_total.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                    partial.reference.clone().unwrap_or_else(|| "NA".to_string()),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_derived_parameters(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.individual_parameters.derived.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "derived_parameters.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "PARAMETER", "VALUE"])?;
+
+        for result in results {
+            for derived in &result.individual_parameters.derived {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    derived.name.clone(),
+                    derived.value.map_or("NA".to_string(), |v| v.to_string()),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_analyzed_concentrations(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.analyzed_observations.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "concentrations.csv")?;
+
+        writer.write_record(CONCENTRATIONS_HEADER)?;
+
+        for result in results {
+            for record in Self::analyzed_concentration_records(result) {
+                writer.write_record(record)?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// concentrations.csv rows of one result
+    fn analyzed_concentration_records(result: &NcaResults) -> Vec<Vec<String>> {
+        result
+            .analyzed_observations
+            .iter()
+            .map(|obs| vec![
+                result.subject_id.clone(),
+                Self::format_tag(&result.analyte),
+                Self::format_tag(&result.matrix),
+                obs.time.to_string(),
+                obs.concentration.to_string(),
+                obs.bloq.to_string(),
+                obs.lloq.map_or("NA".to_string(), |v| v.to_string()),
+                obs.blod.to_string(),
+                obs.lod.map_or("NA".to_string(), |v| v.to_string()),
+            ])
+            .collect()
+    }
+
+    fn save_threshold_crossings(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.individual_parameters.threshold_crossings.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "threshold_crossings.csv")?;
+
+        writer.write_record(THRESHOLD_CROSSINGS_HEADER)?;
+
+        for result in results {
+            for record in Self::threshold_crossing_records(result) {
+                writer.write_record(record)?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// threshold_crossings.csv rows of one result
+    fn threshold_crossing_records(result: &NcaResults) -> Vec<Vec<String>> {
+        result
+            .individual_parameters
+            .threshold_crossings
+            .iter()
+            .map(|crossing| vec![
+                result.subject_id.clone(),
+                Self::format_tag(&result.analyte),
+                Self::format_tag(&result.matrix),
+                crossing.time.to_string(),
+                if crossing.rising { "UP" } else { "DOWN" }.to_string(),
+            ])
+            .collect()
+    }
+
+    fn save_dose_normalized_parameters(
+        normalized: &[DoseNormalizedParameters],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        let mut writer = Self::csv_writer(output_dir, "individual_results_dn.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "DOSE", "DOSE_TAU", "AUC_LAST_DN", "AUC_INF_DN", "AUC_TAU_DN", "CMAX_DN"])?;
+
+        for subject in normalized {
+            let value = |name: &str| subject.values.get(name).map_or("NA".to_string(), |v| v.to_string());
+            writer.write_record([
+                subject.subject_id.clone(),
+                subject.dose.to_string(),
+                subject.interval_dose.map_or("NA".to_string(), |dose| dose.to_string()),
+                value("auc_last"),
+                value("auc_inf"),
+                value("auc_tau"),
+                value("cmax"),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_concentration_qc(
+        flags: &[ConcentrationFlag],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if flags.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "concentration_qc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "TIME", "CONCENTRATION", "REASON"])?;
+
+        for flag in flags {
+            writer.write_record([
+                flag.subject_id.clone(),
+                flag.time.to_string(),
+                flag.concentration.to_string(),
+                flag.reason.clone(),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_dose_qc(
+        flags: &[DoseFlag],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if flags.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "dose_qc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "MIN_DOSE", "MAX_DOSE", "TOTAL_DOSE", "REASON"])?;
+
+        for flag in flags {
+            writer.write_record([
+                flag.subject_id.clone(),
+                flag.min_dose.to_string(),
+                flag.max_dose.to_string(),
+                flag.total_dose.to_string(),
+                flag.reason.clone(),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_clearance_qc(
+        checks: &[ClearanceCheck],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if checks.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "clearance_qc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "CLEARANCE", "EXPECTED_CLEARANCE", "PERCENT_DIFFERENCE", "FLAGGED"])?;
+
+        for check in checks {
+            writer.write_record([
+                check.subject_id.clone(),
+                check.clearance.to_string(),
+                check.expected_clearance.to_string(),
+                check.percent_difference.to_string(),
+                check.flagged.to_string(),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_data_completeness(
+        completeness: &[DataCompleteness],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if completeness.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "data_completeness.csv")?;
+
+        writer.write_record([
+            "SUBJECT_ID", "PLANNED_TIMEPOINTS", "OBSERVED_TIMEPOINTS", "N_QUANTIFIABLE", "N_BLQ",
+            "FIRST_QUANTIFIABLE_TIME", "LAST_QUANTIFIABLE_TIME", "TERMINAL_PHASE_ESTIMABLE",
+        ])?;
+
+        for entry in completeness {
+            writer.write_record([
+                entry.subject_id.clone(),
+                entry.planned_timepoints.to_string(),
+                entry.observed_timepoints.to_string(),
+                entry.quantifiable.to_string(),
+                entry.blq.to_string(),
+                entry.first_quantifiable_time.map_or("NA".to_string(), |t| t.to_string()),
+                entry.last_quantifiable_time.map_or("NA".to_string(), |t| t.to_string()),
+                entry.terminal_phase_estimable.to_string(),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_replicate_qc(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.replicate_summaries.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "assay_replicates.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "TIME", "N_REPLICATES", "CONCENTRATION", "SD", "CV_PERCENT", "HIGH_VARIABILITY"])?;
+
+        for result in results {
+            for summary in &result.replicate_summaries {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    summary.time.to_string(),
+                    summary.n_replicates.to_string(),
+                    summary.concentration.to_string(),
+                    summary.sd.to_string(),
+                    summary.cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                    summary.high_variability.to_string(),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_dose_interval_aucs(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.individual_parameters.dose_interval_aucs.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "dose_interval_auc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "DOSE_NUMBER", "DOSE_TIME", "END", "AUC"])?;
+
+        for result in results {
+            for interval in &result.individual_parameters.dose_interval_aucs {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    interval.dose_number.to_string(),
+                    interval.dose_time.to_string(),
+                    interval.end.to_string(),
+                    interval.auc.map_or("NA".to_string(), |v| v.to_string()),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_cumulative_aucs(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        let mut writer = Self::csv_writer(output_dir, "cumulative_auc.csv")?;
+
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "TIME", "CONCENTRATION", "CUMULATIVE_AUC"])?;
+
+        for result in results {
+            for point in &result.individual_parameters.cumulative_auc {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    point.time.to_string(),
+                    point.concentration.to_string(),
+                    format!("{:.6}", point.auc),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_fraction_absorbed(
+        results: &[NcaResults],
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if results.iter().all(|r| r.individual_parameters.fraction_absorbed.is_empty()) {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "fraction_absorbed.csv")?;
+        writer.write_record(["SUBJECT_ID", "ANALYTE", "MATRIX", "TIME", "FRACTION_ABSORBED"])?;
+
+        for result in results {
+            for point in &result.individual_parameters.fraction_absorbed {
+                writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
+                    point.time.to_string(),
+                    format!("{:.6}", point.fraction),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
         Ok(())
     }
 
     fn save_summary_statistics(
         summary: &SummaryStatistics,
-        output_dir: &Path,
+        labels: &ParameterLabels,
+        output_dir: &OutputDir,
+        file_name: &str,
     ) -> Result<()> {
-        let file_path = output_dir.join("summary_statistics.csv");
-        let mut file = File::create(file_path)?;
+        let mut writer = Self::csv_writer(output_dir, file_name)?;
         
-        writeln!(file, "PARAMETER,N,MEAN,STD,CV_PERCENT,MEDIAN,Q25,Q75,MIN,MAX,GEO_MEAN,GEO_CV_PERCENT")?;
+        writer.write_record([
+            "PARAMETER", "N", "MEAN", "STD", "CV_PERCENT", "MEDIAN", "Q25", "Q75", "MIN", "MAX",
+            "GEO_MEAN", "GEO_CV_PERCENT", "HARMONIC_MEAN", "HARMONIC_PSEUDO_SD", "GEO_N",
+        ])?;
         
         for (param, stats) in &summary.parameter_stats {
-            writeln!(
-                file,
-                "{},{},{:.6},{:.6},{:.2},{:.6},{:.6},{:.6},{:.6},{:.6},{},{}",
-                param,
-                stats.n,
-                stats.arithmetic_mean,
-                stats.arithmetic_std,
-                stats.arithmetic_cv_percent,
-                stats.median,
-                stats.q25,
-                stats.q75,
-                stats.min,
-                stats.max,
+            writer.write_record([
+                labels.parameter(param),
+                stats.n.to_string(),
+                format!("{:.6}", stats.arithmetic_mean),
+                stats.arithmetic_std.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                stats.arithmetic_cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                Self::format_order_statistic(stats.median),
+                Self::format_order_statistic(stats.q25),
+                Self::format_order_statistic(stats.q75),
+                format!("{:.6}", stats.min),
+                format!("{:.6}", stats.max),
                 stats.geometric_mean.map_or("NA".to_string(), |v| format!("{:.6}", v)),
                 stats.geometric_cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
-            )?;
+                stats.harmonic_mean.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                stats.harmonic_pseudo_sd.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                stats.geometric_n.to_string(),
+            ])?;
         }
         
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// NA for quantiles that were not computed (NaN, as in streamed summaries)
+    fn format_order_statistic(value: f64) -> String {
+        if value.is_finite() { format!("{:.6}", value) } else { "NA".to_string() }
+    }
+
+    fn save_distributions(summary: &SummaryStatistics, labels: &ParameterLabels, output_dir: &OutputDir) -> Result<()> {
+        let mut writer = Self::csv_writer(output_dir, "distributions.csv")?;
+
+        writer.write_record(["PARAMETER", "N", "SKEWNESS", "EXCESS_KURTOSIS", "BIN_EDGES", "BIN_COUNTS"])?;
+
+        let mut params: Vec<&String> = summary.parameter_stats.keys().collect();
+        params.sort();
+        for param in params {
+            let stats = &summary.parameter_stats[param];
+            let distribution = &stats.distribution;
+            let edges: Vec<String> = distribution.bin_edges.iter().map(|e| format!("{:.6}", e)).collect();
+            let counts: Vec<String> = distribution.bin_counts.iter().map(|c| c.to_string()).collect();
+            writer.write_record([
+                labels.parameter(param),
+                stats.n.to_string(),
+                distribution.skewness.map_or("NA".to_string(), |v| format!("{:.4}", v)),
+                distribution.excess_kurtosis.map_or("NA".to_string(), |v| format!("{:.4}", v)),
+                edges.join(";"),
+                counts.join(";"),
+            ])?;
+        }
+
+        writer.flush()?;
         Ok(())
     }
 
     fn save_failed_subjects_log(
         failed_subjects: &[FailedSubjectAnalysis],
-        output_dir: &Path,
+        output_dir: &OutputDir,
     ) -> Result<()> {
         if failed_subjects.is_empty() {
             return Ok(());
         }
 
-        let file_path = output_dir.join("failed_subjects.log");
-        let mut file = File::create(file_path)?;
+        let mut file = output_dir.file("failed_subjects.log")?;
         
         writeln!(file, "FAILED SUBJECT ANALYSIS LOG")?;
         writeln!(file, "==========================")?;
@@ -149,88 +913,140 @@ impl OutputManager {
 
     fn save_method_comparison(
         comparison: &MethodComparison,
-        output_dir: &Path,
+        output_dir: &OutputDir,
     ) -> Result<()> {
         // Save method means
-        let file_path = output_dir.join("method_comparison.csv");
-        let mut file = File::create(file_path)?;
+        let mut writer = Self::csv_writer(output_dir, "method_comparison.csv")?;
         
-        writeln!(file, "METHOD,MEAN_AUC")?;
+        writer.write_record(["METHOD", "MEAN_AUC", "PERCENT_DIFF_FROM_PRIMARY", "PRIMARY"])?;
         for (method, mean_auc) in &comparison.auc_methods {
-            writeln!(file, "{},{:.6}", method, mean_auc)?;
+            writer.write_record([
+                method.clone(),
+                format!("{:.6}", mean_auc),
+                comparison.percent_difference_from_primary
+                    .get(method)
+                    .map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                (*method == comparison.primary_method).to_string(),
+            ])?;
         }
+        writer.flush()?;
         
         // Save correlation matrix
-        let corr_path = output_dir.join("method_correlations.csv");
-        let mut corr_file = File::create(corr_path)?;
+        let mut corr_writer = Self::csv_writer(output_dir, "method_correlations.csv")?;
         
         let methods: Vec<&String> = comparison.correlation_matrix.keys().collect();
-        write!(corr_file, "METHOD")?;
-        for method in &methods {
-            write!(corr_file, ",{}", method)?;
-        }
-        writeln!(corr_file)?;
+        let mut header = vec!["METHOD".to_string()];
+        header.extend(methods.iter().map(|method| method.to_string()));
+        corr_writer.write_record(&header)?;
         
         for method1 in &methods {
-            write!(corr_file, "{}", method1)?;
+            let mut row = vec![method1.to_string()];
             for method2 in &methods {
                 let corr = comparison.correlation_matrix
                     .get(*method1)
                     .and_then(|m| m.get(*method2))
                     .unwrap_or(&0.0);
-                write!(corr_file, ",{:.4}", corr)?;
+                row.push(format!("{:.4}", corr));
             }
-            writeln!(corr_file)?;
+            corr_writer.write_record(&row)?;
         }
         
+        corr_writer.flush()?;
+        Ok(())
+    }
+
+    fn save_strata_comparisons(
+        strata_comparisons: &[StrataComparison],
+        labels: &ParameterLabels,
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        if strata_comparisons.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "strata_comparisons.csv")?;
+
+        writer.write_record([
+            "VARIABLE", "PARAMETER", "STRATUM1", "STRATUM2", "N1", "N2", "MEAN1", "MEAN2", "TEST",
+            "STATISTIC", "P_VALUE", "SIGNIFICANT", "EFFECT_SIZE", "HL_ESTIMATE", "HL_CI_LOWER", "HL_CI_UPPER",
+        ])?;
+
+        for comparison in strata_comparisons {
+            for pairwise in &comparison.pairwise_comparisons {
+                writer.write_record([
+                    comparison.stratification_variable.clone(),
+                    labels.parameter(&comparison.parameter),
+                    pairwise.stratum1_name.clone(),
+                    pairwise.stratum2_name.clone(),
+                    pairwise.n1.to_string(),
+                    pairwise.n2.to_string(),
+                    format!("{:.6}", pairwise.mean1),
+                    format!("{:.6}", pairwise.mean2),
+                    pairwise.test_type.clone(),
+                    format!("{:.6}", pairwise.test_statistic),
+                    format!("{:.6}", pairwise.p_value),
+                    pairwise.significant.to_string(),
+                    format!("{:.6}", pairwise.effect_size),
+                    pairwise.hodges_lehmann_estimate.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                    pairwise.hodges_lehmann_ci.map_or("NA".to_string(), |(lo, _)| format!("{:.6}", lo)),
+                    pairwise.hodges_lehmann_ci.map_or("NA".to_string(), |(_, hi)| format!("{:.6}", hi)),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
         Ok(())
     }
 
     fn save_stratified_results(
         stratified_results: &HashMap<String, StratifiedResults>,
-        output_dir: &Path,
+        labels: &ParameterLabels,
+        output_dir: &OutputDir,
     ) -> Result<()> {
         if stratified_results.is_empty() {
             return Ok(());
         }
 
-        let file_path = output_dir.join("stratified_analysis.csv");
-        let mut file = File::create(file_path)?;
+        let mut writer = Self::csv_writer(output_dir, "stratified_analysis.csv")?;
         
-        writeln!(file, "STRATUM,STRATUM_VALUE,N,PARAMETER,MEAN,STD,CV_PERCENT,MEDIAN,GEO_MEAN,GEO_CV_PERCENT")?;
+        writer.write_record([
+            "STRATUM", "STRATUM_VALUE", "N", "PARAMETER", "MEAN", "STD", "CV_PERCENT", "MEDIAN",
+            "GEO_MEAN", "GEO_CV_PERCENT",
+        ])?;
         
-        for (stratum_key, stratum_results) in stratified_results {
+        for stratum_results in stratified_results.values() {
             for (param, stats) in &stratum_results.summary_statistics.parameter_stats {
-                writeln!(
-                    file,
-                    "{},{},{},{},{:.6},{:.6},{:.2},{:.6},{},{}",
-                    stratum_results.stratum_name,
-                    stratum_results.stratum_value,
-                    stratum_results.n_subjects,
-                    param,
-                    stats.mean,
-                    stats.std,
-                    stats.cv_percent,
-                    stats.median,
+                writer.write_record([
+                    stratum_results.stratum_name.clone(),
+                    stratum_results.stratum_value.clone(),
+                    stratum_results.n_subjects.to_string(),
+                    labels.parameter(param),
+                    format!("{:.6}", stats.mean),
+                    stats.std.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                    stats.cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                    format!("{:.6}", stats.median),
                     stats.geometric_mean.map_or("NA".to_string(), |v| format!("{:.6}", v)),
                     stats.geometric_cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
-                )?;
+                ])?;
             }
         }
+        writer.flush()?;
         
         // Save detailed stratified results
         for (stratum_key, stratum_results) in stratified_results {
-            let stratum_file_path = output_dir.join(format!("stratum_{}.csv", stratum_key));
-            let mut stratum_file = File::create(stratum_file_path)?;
+            let mut stratum_writer = Self::csv_writer(output_dir, &format!("stratum_{}.csv", stratum_key))?;
             
-            writeln!(stratum_file, "SUBJECT_ID,AUC_LAST,AUC_INF,CMAX,TMAX,HALF_LIFE,CLEARANCE,VSS,VZ,MRT")?;
+            stratum_writer.write_record(
+                ["SUBJECT_ID", "ANALYTE", "MATRIX", "AUC_LAST", "AUC_INF", "CMAX", "TMAX", "HALF_LIFE", "CLEARANCE", "VSS", "VZ", "MRT"]
+                    .map(|column| labels.column(column)),
+            )?;
             
             for result in &stratum_results.individual_results {
                 let p = &result.individual_parameters;
-                writeln!(
-                    stratum_file,
-                    "{},{},{},{},{},{},{},{},{},{}",
-                    result.subject_id,
+                stratum_writer.write_record([
+                    result.subject_id.clone(),
+                    Self::format_tag(&result.analyte),
+                    Self::format_tag(&result.matrix),
                     p.auc_last.map_or("NA".to_string(), |v| v.to_string()),
                     p.auc_inf.map_or("NA".to_string(), |v| v.to_string()),
                     p.cmax.map_or("NA".to_string(), |v| v.to_string()),
@@ -240,8 +1056,9 @@ impl OutputManager {
                     p.volume_steady_state.map_or("NA".to_string(), |v| v.to_string()),
                     p.volume_terminal.map_or("NA".to_string(), |v| v.to_string()),
                     p.mrt.map_or("NA".to_string(), |v| v.to_string()),
-                )?;
+                ])?;
             }
+            stratum_writer.flush()?;
         }
         
         Ok(())
@@ -249,136 +1066,341 @@ impl OutputManager {
 
     fn save_covariate_analysis(
         covariate_analysis: &CovariateAnalysis,
-        output_dir: &Path,
+        output_dir: &OutputDir,
     ) -> Result<()> {
         // Save correlations
-        let corr_path = output_dir.join("covariate_correlations.csv");
-        let mut corr_file = File::create(corr_path)?;
+        let mut corr_writer = Self::csv_writer(output_dir, "covariate_correlations.csv")?;
         
-        writeln!(corr_file, "COVARIATE,PARAMETER,CORRELATION,P_VALUE,SIGNIFICANCE")?;
+        corr_writer.write_record([
+            "COVARIATE", "PARAMETER", "CORRELATION", "P_VALUE", "P_VALUE_ADJUSTED", "SIGNIFICANCE",
+            "PARTIAL_CORRELATION",
+        ])?;
         
         for (covariate, correlation_data) in &covariate_analysis.correlations {
             for (parameter, &corr_value) in &correlation_data.parameter_correlations {
                 let p_value = correlation_data.p_values.get(parameter).copied().unwrap_or(1.0);
-                let significant = if p_value < 0.05 { "Yes" } else { "No" };
+                let adjusted_p_value = correlation_data.adjusted_p_values.get(parameter).copied().unwrap_or(1.0);
+                let significant = if correlation_data.significant.get(parameter).copied().unwrap_or(false) { "Yes" } else { "No" };
                 
-                writeln!(
-                    corr_file,
-                    "{},{},{:.4},{:.4},{}",
-                    covariate, parameter, corr_value, p_value, significant
-                )?;
+                corr_writer.write_record([
+                    covariate.clone(),
+                    parameter.clone(),
+                    format!("{:.4}", corr_value),
+                    format!("{:.4}", p_value),
+                    format!("{:.4}", adjusted_p_value),
+                    significant.to_string(),
+                    correlation_data.partial_correlations.get(parameter).map_or("NA".to_string(), |v| format!("{:.4}", v)),
+                ])?;
             }
         }
+        corr_writer.flush()?;
         
         // Save regression analysis
-        let reg_path = output_dir.join("regression_analysis.csv");
-        let mut reg_file = File::create(reg_path)?;
+        let mut reg_writer = Self::csv_writer(output_dir, "regression_analysis.csv")?;
         
-        writeln!(reg_file, "PARAMETER,COVARIATE,SLOPE,INTERCEPT,R_SQUARED,P_VALUE,CI_LOWER,CI_UPPER")?;
+        reg_writer.write_record([
+            "PARAMETER", "COVARIATE", "N", "SLOPE", "INTERCEPT", "R_SQUARED", "P_VALUE", "CI_LOWER", "CI_UPPER",
+        ])?;
         
-        for (key, regression) in &covariate_analysis.regression_analysis {
-            writeln!(
-                reg_file,
-                "{},{},{:.6},{:.6},{:.4},{:.4},{:.6},{:.6}",
-                regression.parameter,
-                regression.covariate,
-                regression.slope,
-                regression.intercept,
-                regression.r_squared,
-                regression.p_value,
-                regression.confidence_interval.0,
-                regression.confidence_interval.1,
-            )?;
+        for regression in covariate_analysis.regression_analysis.values() {
+            reg_writer.write_record([
+                regression.parameter.clone(),
+                regression.covariate.clone(),
+                regression.n.to_string(),
+                regression.slope.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                regression.intercept.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                regression.r_squared.map_or("NA".to_string(), |v| format!("{:.4}", v)),
+                regression.p_value.map_or("NA".to_string(), |v| format!("{:.4}", v)),
+                regression.confidence_interval.map_or("NA".to_string(), |ci| format!("{:.6}", ci.0)),
+                regression.confidence_interval.map_or("NA".to_string(), |ci| format!("{:.6}", ci.1)),
+            ])?;
         }
+        reg_writer.flush()?;
         
         // Save dose normalization analysis
         if let Some(dose_analysis) = &covariate_analysis.dose_normalized_analysis {
-            let dose_path = output_dir.join("dose_normalized_analysis.csv");
-            let mut dose_file = File::create(dose_path)?;
+            let mut dose_writer = Self::csv_writer(output_dir, "dose_normalized_analysis.csv")?;
             
-            writeln!(dose_file, "TREATMENT,PARAMETER,N,MEAN,STD,CV_PERCENT,LINEARITY_ASSESSMENT")?;
+            dose_writer.write_record([
+                "TREATMENT", "PARAMETER", "N", "MEAN", "STD", "CV_PERCENT", "LINEARITY_ASSESSMENT",
+                "BETA", "BETA_CI90_LOWER", "BETA_CI90_UPPER", "BOUND_LOWER", "BOUND_UPPER", "CONCLUSION_BASIS",
+            ])?;
             
             for (treatment, stats) in &dose_analysis.dose_normalized_auc {
-                let linearity = dose_analysis.dose_linearity_assessment
-                    .get(treatment)
-                    .map(|l| l.linearity_conclusion.clone())
-                    .unwrap_or_else(|| "Unknown".to_string());
+                let linearity = dose_analysis.dose_linearity_assessment.get(treatment);
+                let format_bound = |bound: Option<f64>| bound.map_or("NA".to_string(), |v| format!("{:.4}", v));
                 
-                writeln!(
-                    dose_file,
-                    "{},AUC_DN,{},{:.6},{:.6},{:.2},{}",
-                    treatment, stats.n, stats.mean, stats.std, stats.cv_percent, linearity
-                )?;
+                dose_writer.write_record([
+                    treatment.clone(),
+                    "AUC_DN".to_string(),
+                    stats.n.to_string(),
+                    format!("{:.6}", stats.mean),
+                    stats.std.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                    stats.cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                    linearity.map_or("Unknown".to_string(), |l| l.linearity_conclusion.clone()),
+                    format_bound(linearity.and_then(|l| l.beta)),
+                    format_bound(linearity.and_then(|l| l.beta_confidence_interval).map(|ci| ci.0)),
+                    format_bound(linearity.and_then(|l| l.beta_confidence_interval).map(|ci| ci.1)),
+                    format_bound(linearity.and_then(|l| l.acceptance_bounds).map(|bounds| bounds.0)),
+                    format_bound(linearity.and_then(|l| l.acceptance_bounds).map(|bounds| bounds.1)),
+                    linearity.map_or("NA".to_string(), |l| l.conclusion_basis.clone()),
+                ])?;
             }
             
             for (treatment, stats) in &dose_analysis.dose_normalized_cmax {
-                writeln!(
-                    dose_file,
-                    "{},CMAX_DN,{},{:.6},{:.6},{:.2},NA",
-                    treatment, stats.n, stats.mean, stats.std, stats.cv_percent
-                )?;
+                dose_writer.write_record([
+                    treatment.clone(),
+                    "CMAX_DN".to_string(),
+                    stats.n.to_string(),
+                    format!("{:.6}", stats.mean),
+                    stats.std.map_or("NA".to_string(), |v| format!("{:.6}", v)),
+                    stats.cv_percent.map_or("NA".to_string(), |v| format!("{:.2}", v)),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                    "NA".to_string(),
+                ])?;
             }
+            dose_writer.flush()?;
         }
         
         Ok(())
     }
 
+    /// Subjects left out of dose normalization and of the covariate dose-normalized analysis,
+    /// each exclusion listed once
+    fn save_dose_normalization_exclusions(results: &PopulationResults, output_dir: &OutputDir) -> Result<()> {
+        let covariate_exclusions = results.covariate_analysis
+            .dose_normalized_analysis
+            .as_ref()
+            .map_or(&[][..], |analysis| &analysis.exclusions);
+        let mut exclusions: Vec<&DoseNormalizationExclusion> = results.dose_normalization_exclusions
+            .iter()
+            .chain(covariate_exclusions)
+            .collect();
+        if exclusions.is_empty() {
+            return Ok(());
+        }
+        exclusions.sort_by(|a, b| (&a.subject_id, &a.reason).cmp(&(&b.subject_id, &b.reason)));
+        exclusions.dedup_by(|a, b| a.subject_id == b.subject_id && a.reason == b.reason);
+
+        let mut writer = Self::csv_writer(output_dir, "dose_normalization_exclusions.csv")?;
+        writer.write_record(["SUBJECT_ID", "TREATMENT", "REASON"])?;
+        for exclusion in exclusions {
+            writer.write_record([&exclusion.subject_id, &exclusion.treatment, &exclusion.reason])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn save_pp_dataset(
+        results: &PopulationResults,
+        config: &AnalysisConfig,
+        output_dir: &OutputDir,
+    ) -> Result<()> {
+        let records = CdiscExporter::pp_records(results, config);
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut writer = Self::csv_writer(output_dir, "pp.csv")?;
+        writer.write_record(["USUBJID", "PPCAT", "PPSPEC", "PPTESTCD", "PPTEST", "PPSTRESN", "PPSTRESU"])?;
+        for record in &records {
+            writer.write_record([
+                record.usubjid.clone(),
+                record.category.clone().unwrap_or_default(),
+                record.specimen.clone().unwrap_or_default(),
+                record.test_code.clone(),
+                record.test_name.clone(),
+                record.value.to_string(),
+                record.unit.clone(),
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
     fn save_json_results(
         results: &PopulationResults,
-        output_dir: &Path,
+        config: &AnalysisConfig,
+        output_dir: &OutputDir,
     ) -> Result<()> {
-        let file_path = output_dir.join("complete_results.json");
-        let json_string = serde_json::to_string_pretty(results)?;
-        fs::write(file_path, json_string)?;
+        let mut json = serde_json::to_value(results)?;
+        if let Some(digits) = config.json_significant_digits {
+            Self::round_json_numbers(&mut json, digits);
+        }
+        let json_string = serde_json::to_string_pretty(&json)?;
+        output_dir.file("complete_results.json")?.write_all(json_string.as_bytes())?;
         Ok(())
     }
 
+    /// Round every decimal number in a JSON tree to `digits` significant digits; integers
+    /// (counts, flags) are left as they are
+    pub fn round_json_numbers(value: &mut serde_json::Value, digits: u32) {
+        match value {
+            serde_json::Value::Number(number) if number.is_f64() => {
+                if let Some(rounded) = number.as_f64().and_then(|x| serde_json::Number::from_f64(round_significant(x, digits))) {
+                    *number = rounded;
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| Self::round_json_numbers(item, digits)),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| Self::round_json_numbers(field, digits)),
+            _ => {}
+        }
+    }
+
     fn save_csv_summary(
         results: &PopulationResults,
-        output_dir: &Path,
+        config: &AnalysisConfig,
+        labels: &ParameterLabels,
+        output_dir: &OutputDir,
     ) -> Result<()> {
-        let file_path = output_dir.join("population_summary.csv");
-        let mut file = File::create(file_path)?;
+        // Two sections of different widths, separated by a blank line
+        let mut file = output_dir.file("population_summary.csv")?;
         
-        writeln!(file, "ANALYSIS_SUMMARY")?;
-        writeln!(file, "Total Subjects,{}", results.individual_results.len())?;
-        writeln!(file, "Successful Analyses,{}", results.individual_results.len())?;
+        let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(&mut file);
+        writer.write_record(["ANALYSIS_SUMMARY"])?;
+        writer.write_record(["Total Subjects".to_string(), results.individual_results.len().to_string()])?;
+        writer.write_record(["Successful Analyses".to_string(), results.individual_results.len().to_string()])?;
+        if config.exclude_unreliable_auc_inf {
+            let included = results.summary_statistics.parameter_stats.get("auc_inf").map_or(0, |s| s.n);
+            writer.write_record(["AUC_inf Included".to_string(), included.to_string()])?;
+            writer.write_record(["AUC_inf Excluded (Unreliable)".to_string(), results.summary_statistics.auc_inf_excluded.to_string()])?;
+        }
+        writer.flush()?;
+        drop(writer);
         writeln!(file)?;
         
-        writeln!(file, "PARAMETER,N,MEAN,MEDIAN,CV%,GEO_MEAN,GEO_CV%")?;
+        let (arithmetic, geometric) = match config.population_summary_statistics {
+            PopulationSummaryStatistics::Arithmetic => (true, false),
+            PopulationSummaryStatistics::Geometric => (false, true),
+            PopulationSummaryStatistics::Both => (true, true),
+        };
+        let mut writer = csv::Writer::from_writer(&mut file);
+        let columns = [
+            ("PARAMETER", true), ("N", true), ("MEAN", arithmetic), ("MEDIAN", true),
+            ("CV%", arithmetic), ("GEO_MEAN", geometric), ("GEO_CV%", geometric),
+        ];
+        writer.write_record(columns.iter().filter(|(_, shown)| *shown).map(|(name, _)| *name))?;
         for (param, stats) in &results.summary_statistics.parameter_stats {
-            writeln!(
-                file,
-                "{},{},{:.3},{:.3},{:.1},{},{}",
-                param,
-                stats.n,
-                stats.arithmetic_mean,
-                stats.median,
-                stats.arithmetic_cv_percent,
+            let values = [
+                labels.parameter(param),
+                stats.n.to_string(),
+                format!("{:.3}", stats.arithmetic_mean),
+                format!("{:.3}", stats.median),
+                stats.arithmetic_cv_percent.map_or("NA".to_string(), |v| format!("{:.1}", v)),
                 stats.geometric_mean.map_or("NA".to_string(), |v| format!("{:.3}", v)),
                 stats.geometric_cv_percent.map_or("NA".to_string(), |v| format!("{:.1}", v)),
-            )?;
+            ];
+            writer.write_record(values.iter().zip(&columns).filter(|(_, (_, shown))| *shown).map(|(value, _)| value))?;
         }
         
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// manifest.json: every file written by this run, in writing order, with a one-line
+    /// description and the configuration that produced it
+    fn save_manifest(config: &AnalysisConfig, output_dir: &OutputDir) -> Result<()> {
+        let written = output_dir.written.borrow().clone();
+        let mut files: Vec<serde_json::Value> = Vec::new();
+        for (idx, file_name) in written.iter().enumerate() {
+            if written[..idx].contains(file_name) {
+                continue;
+            }
+            files.push(serde_json::json!({
+                "file": file_name,
+                "description": Self::file_description(file_name),
+            }));
+        }
+
+        let manifest = serde_json::json!({
+            "generated_by": format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            "config": format!("{:?}", config),
+            "files": files,
+        });
+        let json_string = serde_json::to_string_pretty(&manifest)?;
+        output_dir.file("manifest.json")?.write_all(json_string.as_bytes())?;
         Ok(())
     }
 
+    /// One-line description of an output file, for the manifest
+    fn file_description(file_name: &str) -> &'static str {
+        match file_name {
+            "individual_results.csv" => "Individual NCA parameters, one row per subject",
+            "partial_auc.csv" => "Partial AUCs over the configured intervals",
+            "dose_interval_auc.csv" => "AUC between consecutive doses within the sampled profile",
+            "cumulative_auc.csv" => "Cumulative AUC at each observation time",
+            "fraction_absorbed.csv" => "Wagner-Nelson fraction absorbed for extravascular subjects",
+            "derived_parameters.csv" => "Custom derived parameters per subject",
+            "concentrations.csv" => "Concentrations each subject was analyzed with, after exclusions and BLQ handling",
+            "threshold_crossings.csv" => "Times the profile crosses the concentration threshold",
+            "assay_replicates.csv" => "QC summaries of averaged replicate assays",
+            "concentration_qc.csv" => "Negative or implausible concentrations (flagged, not altered)",
+            "dose_qc.csv" => "Subjects with implausible dosing (flagged, not altered)",
+            "clearance_qc.csv" => "Clearance as Dose / AUC_inf against Dose / AUC_inf_pred from the predicted Clast",
+            "data_completeness.csv" => "Per-subject sampling completeness",
+            "failed_subjects.log" => "Subjects that could not be analyzed and why",
+            "summary_statistics.csv" => "Population summary statistics per parameter",
+            "summary_statistics_dn.csv" => "Summary statistics of dose-normalized parameters",
+            "individual_results_dn.csv" => "Dose-normalized parameters per subject",
+            "distributions.csv" => "Skewness, kurtosis and histogram of each summarized parameter",
+            "method_comparison.csv" => "Mean AUC_last per AUC method and difference from the primary method",
+            "method_correlations.csv" => "Correlations between AUC methods",
+            "stratified_analysis.csv" => "Summary of the stratified analysis",
+            "strata_comparisons.csv" => "Pairwise statistical comparisons between strata",
+            "covariate_correlations.csv" => "Covariate-parameter correlations",
+            "regression_analysis.csv" => "Covariate regression results",
+            "dose_normalized_analysis.csv" => "Dose linearity assessment",
+            "dose_normalization_exclusions.csv" => "Subjects left out of dose normalization and the dose-normalized analysis",
+            "pp.csv" => "Parameters in a CDISC PP-domain-like long format",
+            "complete_results.json" => "Complete results in JSON format",
+            "population_summary.csv" => "High-level population summary",
+            "analysis_report.txt" => "Human-readable analysis report",
+            name if name.starts_with("stratum_") => "Individual results of one stratum",
+            _ => "",
+        }
+    }
+
     fn generate_analysis_report(
         results: &PopulationResults,
         config: &AnalysisConfig,
-        output_dir: &Path,
+        output_dir: &OutputDir,
     ) -> Result<()> {
-        let file_path = output_dir.join("analysis_report.txt");
-        let mut file = File::create(file_path)?;
+        let mut file = output_dir.file("analysis_report.txt")?;
         
         writeln!(file, "PHARMACOKINETICS NON-COMPARTMENTAL ANALYSIS REPORT")?;
         writeln!(file, "==================================================")?;
         writeln!(file)?;
+
+        let quality = &results.data_quality;
+        writeln!(file, "Data Quality: {:.0}/100", quality.score)?;
+        writeln!(
+            file,
+            "- Subjects analyzed: {:.1}% ({} of {})",
+            quality.percent_analyzed, results.individual_results.len(), quality.subjects_total
+        )?;
+        writeln!(file, "- Reliable AUC_inf: {:.1}% of analyzed subjects", quality.percent_reliable_auc_inf)?;
+        writeln!(
+            file,
+            "- Median terminal R²: {}",
+            quality.median_lambda_z_r_squared.map_or("NA".to_string(), |r2| format!("{:.3}", r2))
+        )?;
+        writeln!(file, "- BLQ observations: {:.1}%", quality.percent_blq)?;
+        writeln!(file)?;
         
         writeln!(file, "Analysis Configuration:")?;
         writeln!(file, "- Time units: {}", config.time_units)?;
+        writeln!(file, "- Lambda_z units: {}", config.lambda_z_unit())?;
         writeln!(file, "- Concentration units: {}", config.concentration_units)?;
         writeln!(file, "- LLOQ handling: {:?}", config.lloq_handling)?;
+        if let Some(blod_handling) = &config.blod_handling {
+            writeln!(file, "- BLOD handling: {:?}", blod_handling)?;
+        }
         writeln!(file, "- Lambda_z selection: {:?}", config.lambda_z_selection)?;
         writeln!(file)?;
         
@@ -387,22 +1409,96 @@ impl OutputManager {
         if !results.failed_subjects.is_empty() {
             writeln!(file, "- Failed subjects: {}", results.failed_subjects.len())?;
         }
-        writeln!(file)?;
-        
-        writeln!(file, "Key Parameters (Geometric Mean ± Geometric CV%):")?;
-        for (param, stats) in &results.summary_statistics.parameter_stats {
-            if let (Some(geo_mean), Some(geo_cv)) = (stats.geometric_mean, stats.geometric_cv_percent) {
-                writeln!(file, "- {} (Arithmetic): {:.3} ± {:.1}%", param, stats.arithmetic_mean, stats.arithmetic_cv_percent)?;
-                writeln!(file, "- {} (Geometric): {:.3} ± {:.1}%", param, geo_mean, geo_cv)?;
+        for (label, tags) in [
+            ("Analytes", results.individual_results.iter().filter_map(|r| r.analyte.as_deref()).collect::<std::collections::BTreeSet<_>>()),
+            ("Matrices", results.individual_results.iter().filter_map(|r| r.matrix.as_deref()).collect::<std::collections::BTreeSet<_>>()),
+        ] {
+            if !tags.is_empty() {
+                writeln!(file, "- {}: {}", label, tags.into_iter().collect::<Vec<_>>().join(", "))?;
             }
         }
+        if config.exclude_unreliable_auc_inf {
+            let included = results.summary_statistics.parameter_stats.get("auc_inf").map_or(0, |s| s.n);
+            writeln!(
+                file,
+                "- AUC_inf summary: {} included, {} excluded as unreliable (>{}% extrapolated)",
+                included, results.summary_statistics.auc_inf_excluded, config.validation_thresholds.max_auc_extrap_percent
+            )?;
+        }
+        writeln!(file)?;
+
+        writeln!(file, "Key Parameters (geometric or arithmetic mean ± CV% by reporting convention):")?;
+        let mut params: Vec<&String> = results.summary_statistics.parameter_stats.keys().collect();
+        params.sort();
+        for param in params {
+            let stats = &results.summary_statistics.parameter_stats[param];
+            let label = match config.terminal_parameter_unit(param) {
+                Some(unit) => format!("{} ({})", param, unit),
+                None => param.clone(),
+            };
+            writeln!(file, "- {}: {}", label, Self::format_emphasized_statistic(stats, &config.summary_convention(param)))?;
+        }
         
         writeln!(file)?;
         writeln!(file, "Method Comparison:")?;
         for (method, mean_auc) in &results.method_comparison.auc_methods {
             writeln!(file, "- {}: {:.3}", method, mean_auc)?;
         }
+
+        writeln!(file)?;
+        writeln!(file, "Data Yield:")?;
+        writeln!(
+            file,
+            "- Subjects: {} analyzed, {} failed",
+            results.individual_results.len(), results.failed_subjects.len()
+        )?;
+        let failure_categories = Self::failure_categories(&results.failed_subjects);
+        if !failure_categories.is_empty() {
+            writeln!(file, "- Failure categories:")?;
+            for (category, count) in failure_categories {
+                writeln!(file, "  - {}: {}", category, count)?;
+            }
+        }
+        writeln!(file, "- Analyzed subjects missing each key parameter:")?;
+        for &param in YIELD_PARAMETERS {
+            let missing = results.individual_results
+                .iter()
+                .filter(|result| result.individual_parameters.get(param).is_none())
+                .count();
+            writeln!(file, "  - {} missing for {} of {}", param, missing, results.individual_results.len())?;
+        }
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Failed subjects counted by the kind of error, the failure reason up to its first colon
+    /// (e.g. "Insufficient data points for calculation"), most frequent first
+    pub fn failure_categories(failed_subjects: &[FailedSubjectAnalysis]) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for failed in failed_subjects {
+            let category = failed.failure_reason.split(':').next().unwrap_or_default().trim();
+            *counts.entry(category.to_string()).or_default() += 1;
+        }
+        let mut categories: Vec<(String, usize)> = counts.into_iter().collect();
+        categories.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        categories
+    }
+
+    /// Mean ± CV% in the given convention; geometric falls back to arithmetic when the
+    /// geometric mean is undefined (non-positive values)
+    pub fn format_emphasized_statistic(stats: &ParameterStats, convention: &SummaryConvention) -> String {
+        match (convention, stats.geometric_mean, stats.geometric_cv_percent, stats.harmonic_mean) {
+            (SummaryConvention::Geometric, Some(geo_mean), Some(geo_cv), _) => {
+                format!("{:.3} ± {:.1}% (geometric)", geo_mean, geo_cv)
+            }
+            (SummaryConvention::Harmonic, _, _, Some(harmonic_mean)) => {
+                let pseudo_sd = stats.harmonic_pseudo_sd.map_or("NA".to_string(), |v| format!("{:.3}", v));
+                format!("{:.3} ± {} (harmonic, pseudo-SD)", harmonic_mean, pseudo_sd)
+            }
+            _ => {
+                let arithmetic_cv = stats.arithmetic_cv_percent.map_or("NA".to_string(), |v| format!("{:.1}", v));
+                format!("{:.3} ± {}% (arithmetic)", stats.arithmetic_mean, arithmetic_cv)
+            }
+        }
+    }
+}
@@ -1,25 +1,82 @@
-use crate::{models::*, errors::NcaError, Result};
-use nalgebra::{DMatrix, DVector};
-use statrs::statistics::Statistics;
+use crate::{models::*, auc::AucCalculator, errors::NcaError, math::safe_div, Result};
 
 pub struct ParameterCalculator;
 
+/// Smallest terminal rate constant accepted as a genuine elimination phase
+const MIN_LAMBDA_Z: f64 = 1e-6;
+
+/// R² an automatically selected lambda_z regression must reach by default
+pub const DEFAULT_LAMBDA_Z_MIN_R_SQUARED: f64 = 0.8;
+
+/// Most candidate windows a `BestFit` lambda_z search evaluates; denser profiles are searched
+/// on a coarser grid of window starts and ends
+pub const MAX_BEST_FIT_WINDOWS: usize = 5_000;
+
+/// How far, as a fraction of the interval length, a sample may sit from a dosing-interval
+/// bound and still stand for the concentration at it (e.g. a trough drawn just before the
+/// next dose)
+pub const INTERVAL_BOUND_TOLERANCE: f64 = 0.05;
+
 impl ParameterCalculator {
     /// Calculate terminal elimination rate constant (lambda_z)
     pub fn calculate_lambda_z(
         observations: &[Observation],
         selection: &LambdaZSelection,
+    ) -> Result<(f64, f64, Vec<usize>)> {
+        Self::calculate_lambda_z_with_threshold(observations, selection, DEFAULT_LAMBDA_Z_MIN_R_SQUARED, false)
+    }
+
+    /// Calculate lambda_z with an explicit R² acceptance threshold for automatic selection
+    ///
+    /// `BestFit` keeps its own threshold. When no regression reaches the threshold the
+    /// selection fails, unless `report_below_threshold` is set, in which case the best fit
+    /// found is returned anyway for the caller to flag.
+    pub fn calculate_lambda_z_with_threshold(
+        observations: &[Observation],
+        selection: &LambdaZSelection,
+        min_r_squared: f64,
+        report_below_threshold: bool,
     ) -> Result<(f64, f64, Vec<usize>)> {
         match selection {
-            LambdaZSelection::Auto => Self::auto_lambda_z_selection(observations),
+            LambdaZSelection::Auto => Self::auto_lambda_z_selection(observations, min_r_squared, report_below_threshold),
             LambdaZSelection::Manual(indices) => Self::manual_lambda_z_selection(observations, indices),
-            LambdaZSelection::BestFit { min_points, r_squared_threshold } => {
-                Self::best_fit_lambda_z_selection(observations, *min_points, *r_squared_threshold)
-            }
+            LambdaZSelection::BestFit { min_points, r_squared_threshold } => Self::best_fit_lambda_z_selection(
+                observations, *min_points, *r_squared_threshold, report_below_threshold,
+            ),
+            LambdaZSelection::TimeWindow { start_time } => Self::time_window_lambda_z_selection(observations, *start_time),
         }
     }
 
-    fn auto_lambda_z_selection(observations: &[Observation]) -> Result<(f64, f64, Vec<usize>)> {
+    /// Index of the first observation after Tmax in a time-sorted profile, where an
+    /// automatically selected lambda_z window starts when it excludes Tmax
+    pub fn lambda_z_window_start(observations: &[Observation], tmax: f64) -> usize {
+        observations.iter().position(|obs| obs.time > tmax).unwrap_or(observations.len())
+    }
+
+    /// Fewest regression points an automatic `selection` needs; `None` for user-chosen windows
+    pub fn lambda_z_min_points(selection: &LambdaZSelection) -> Option<usize> {
+        match selection {
+            LambdaZSelection::Auto => Some(3),
+            LambdaZSelection::BestFit { min_points, .. } => Some(*min_points),
+            LambdaZSelection::Manual(_) | LambdaZSelection::TimeWindow { .. } => None,
+        }
+    }
+
+    /// R² a regression must reach under `selection` to be considered reliable; `None` for
+    /// manual and time windows, which are not held to an R² minimum
+    pub fn lambda_z_r_squared_threshold(selection: &LambdaZSelection, min_r_squared: f64) -> Option<f64> {
+        match selection {
+            LambdaZSelection::Auto => Some(min_r_squared),
+            LambdaZSelection::BestFit { r_squared_threshold, .. } => Some(*r_squared_threshold),
+            LambdaZSelection::Manual(_) | LambdaZSelection::TimeWindow { .. } => None,
+        }
+    }
+
+    fn auto_lambda_z_selection(
+        observations: &[Observation],
+        min_r_squared: f64,
+        report_below_threshold: bool,
+    ) -> Result<(f64, f64, Vec<usize>)> {
         let n = observations.len();
         if n < 3 {
             return Err(NcaError::InsufficientData(
@@ -35,8 +92,8 @@ impl ParameterCalculator {
         for start_idx in 0..=(n.saturating_sub(3)) {
             let indices: Vec<usize> = (start_idx..n).collect();
             
-            if let Ok((lambda_z, r_squared)) = Self::fit_lambda_z(observations, &indices) {
-                if r_squared > best_r_squared && r_squared >= 0.8 {
+            if let Ok((lambda_z, r_squared, _)) = Self::fit_lambda_z(observations, &indices) {
+                if lambda_z > MIN_LAMBDA_Z && r_squared > best_r_squared {
                     best_r_squared = r_squared;
                     best_lambda_z = lambda_z;
                     best_indices = indices;
@@ -44,7 +101,7 @@ impl ParameterCalculator {
             }
         }
 
-        if best_indices.is_empty() {
+        if best_indices.is_empty() || (best_r_squared < min_r_squared && !report_below_threshold) {
             return Err(NcaError::CalculationError(
                 "Could not find suitable points for lambda_z calculation".to_string()
             ));
@@ -57,14 +114,68 @@ impl ParameterCalculator {
         observations: &[Observation],
         indices: &[usize],
     ) -> Result<(f64, f64, Vec<usize>)> {
-        let (lambda_z, r_squared) = Self::fit_lambda_z(observations, indices)?;
+        let (lambda_z, r_squared, _) = Self::fit_lambda_z(observations, indices)?;
+        if lambda_z <= MIN_LAMBDA_Z {
+            return Err(NcaError::CalculationError(format!(
+                "Non-declining terminal phase (lambda_z = {:.6})", lambda_z
+            )));
+        }
         Ok((lambda_z, r_squared, indices.to_vec()))
     }
 
+    fn time_window_lambda_z_selection(
+        observations: &[Observation],
+        start_time: f64,
+    ) -> Result<(f64, f64, Vec<usize>)> {
+        let indices: Vec<usize> = observations
+            .iter()
+            .enumerate()
+            .filter(|(_, obs)| obs.time >= start_time && obs.concentration > 0.0 && !obs.bloq)
+            .map(|(idx, _)| idx)
+            .collect();
+        if indices.len() < 2 {
+            return Err(NcaError::InsufficientData(format!(
+                "Need at least 2 quantifiable points at or after t = {} for lambda_z", start_time
+            )));
+        }
+        Self::manual_lambda_z_selection(observations, &indices)
+    }
+
+    /// Check whether the last `n_points` quantifiable concentrations are declining
+    ///
+    /// A flat or rising tail (secondary peak, contamination, data error) fits to a
+    /// negative or near-zero lambda_z, which must not be reported as elimination. `None` when
+    /// the tail cannot be judged: `n_points` below 2, fewer quantifiable concentrations than
+    /// `n_points`, or no fit.
+    pub fn is_terminal_phase_declining(observations: &[Observation], n_points: usize) -> Option<bool> {
+        let quantifiable: Vec<usize> = observations
+            .iter()
+            .enumerate()
+            .filter(|(_, obs)| obs.concentration > 0.0 && !obs.bloq)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if n_points < 2 || quantifiable.len() < n_points {
+            return None;
+        }
+
+        let tail = &quantifiable[quantifiable.len() - n_points..];
+        Self::fit_lambda_z(observations, tail)
+            .ok()
+            .map(|(lambda_z, _, _)| lambda_z > MIN_LAMBDA_Z)
+    }
+
+    /// Best-R² regression over every window of at least `min_points` consecutive points
+    ///
+    /// Windows are fitted in constant time from running sums, and the chosen one is refitted
+    /// with [`Self::fit_lambda_z`]. Beyond [`MAX_BEST_FIT_WINDOWS`] windows, starts and ends are
+    /// taken every `stride` points counted back from the last point, so the terminal-most
+    /// windows are always tried and the work per subject stays bounded.
     fn best_fit_lambda_z_selection(
         observations: &[Observation],
         min_points: usize,
         r_squared_threshold: f64,
+        report_below_threshold: bool,
     ) -> Result<(f64, f64, Vec<usize>)> {
         let n = observations.len();
         if n < min_points {
@@ -73,35 +184,92 @@ impl ParameterCalculator {
             ));
         }
 
+        let last_start = n - min_points;
+        let windows = (last_start + 1) * (last_start + 2) / 2;
+        let stride = if windows > MAX_BEST_FIT_WINDOWS {
+            (windows as f64 / MAX_BEST_FIT_WINDOWS as f64).sqrt().ceil() as usize
+        } else {
+            1
+        };
+        let grid = |first: usize, last: usize| -> Vec<usize> {
+            let mut points: Vec<usize> = (first..=last).rev().step_by(stride).collect();
+            points.reverse();
+            points
+        };
+
+        let sums = RegressionSums::new(observations);
         let mut best_r_squared = 0.0;
-        let mut best_lambda_z = 0.0;
-        let mut best_indices = Vec::new();
+        let mut best_window = None;
 
         // Try different combinations of points
-        for start_idx in 0..=(n.saturating_sub(min_points)) {
-            for end_idx in (start_idx + min_points - 1)..n {
-                let indices: Vec<usize> = (start_idx..=end_idx).collect();
-                
-                if let Ok((lambda_z, r_squared)) = Self::fit_lambda_z(observations, &indices) {
-                    if r_squared > best_r_squared && r_squared >= r_squared_threshold {
+        for start_idx in grid(0, last_start) {
+            for end_idx in grid(start_idx + min_points.saturating_sub(1), n - 1) {
+                if let Some((lambda_z, r_squared)) = sums.fit(start_idx, end_idx) {
+                    if lambda_z > MIN_LAMBDA_Z && r_squared > best_r_squared {
                         best_r_squared = r_squared;
-                        best_lambda_z = lambda_z;
-                        best_indices = indices;
+                        best_window = Some((start_idx, end_idx));
                     }
                 }
             }
         }
 
-        if best_indices.is_empty() {
-            return Err(NcaError::CalculationError(
+        let best = best_window.and_then(|(start_idx, end_idx)| {
+            let indices: Vec<usize> = (start_idx..=end_idx).collect();
+            let (lambda_z, r_squared, _) = Self::fit_lambda_z(observations, &indices).ok()?;
+            Some((lambda_z, r_squared, indices))
+        });
+        match best {
+            Some((lambda_z, r_squared, indices)) if r_squared >= r_squared_threshold || report_below_threshold => {
+                Ok((lambda_z, r_squared, indices))
+            }
+            _ => Err(NcaError::CalculationError(
                 format!("Could not find suitable points with R² >= {}", r_squared_threshold)
-            ));
+            )),
         }
+    }
 
-        Ok((best_lambda_z, best_r_squared, best_indices))
+    /// Common terminal slope of several profiles, each given by its terminal (time,
+    /// concentration) points, fitted with one intercept per profile
+    ///
+    /// The slope is the pooled within-profile regression of ln(C) on time, so profiles at
+    /// different levels do not bias it. Needs at least two profiles of two or more positive
+    /// points and a declining result.
+    pub fn pooled_lambda_z(profiles: &[Vec<(f64, f64)>]) -> Option<f64> {
+        let mut s_tt = 0.0;
+        let mut s_ty = 0.0;
+        let mut contributing = 0;
+        for profile in profiles {
+            let points: Vec<(f64, f64)> = profile
+                .iter()
+                .filter(|&&(_, concentration)| concentration > 0.0)
+                .map(|&(time, concentration)| (time, concentration.ln()))
+                .collect();
+            if points.len() < 2 {
+                continue;
+            }
+            let n = points.len() as f64;
+            let mean_t = points.iter().map(|&(t, _)| t).sum::<f64>() / n;
+            let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+            s_tt += points.iter().map(|&(t, _)| (t - mean_t).powi(2)).sum::<f64>();
+            s_ty += points.iter().map(|&(t, y)| (t - mean_t) * (y - mean_y)).sum::<f64>();
+            contributing += 1;
+        }
+        if contributing < 2 {
+            return None;
+        }
+        safe_div(-s_ty, s_tt).filter(|&lambda_z| lambda_z > MIN_LAMBDA_Z)
+    }
+
+    /// Intercept (ln C at t = 0) of the lambda_z regression over `indices`
+    ///
+    /// With the indices returned by [`Self::calculate_lambda_z`], exp(intercept - lambda_z * t)
+    /// is the fitted terminal line, e.g. the predicted Clast at t = tlast.
+    pub fn lambda_z_intercept(observations: &[Observation], indices: &[usize]) -> Option<f64> {
+        Self::fit_lambda_z(observations, indices).ok().map(|(_, _, intercept)| intercept)
     }
 
-    fn fit_lambda_z(observations: &[Observation], indices: &[usize]) -> Result<(f64, f64)> {
+    /// Regression of ln(C) on time over `indices`: lambda_z, R² and the intercept
+    fn fit_lambda_z(observations: &[Observation], indices: &[usize]) -> Result<(f64, f64, f64)> {
         let mut times = Vec::new();
         let mut ln_concentrations = Vec::new();
 
@@ -132,7 +300,10 @@ impl ParameterCalculator {
             .sum::<f64>();
         let sum_t2 = times.iter().map(|t| t * t).sum::<f64>();
 
-        let slope = (n * sum_t_ln_c - sum_t * sum_ln_c) / (n * sum_t2 - sum_t * sum_t);
+        let slope = safe_div(n * sum_t_ln_c - sum_t * sum_ln_c, n * sum_t2 - sum_t * sum_t)
+            .ok_or_else(|| NcaError::MathError(
+                "Lambda_z regression requires distinct sampling times".to_string()
+            ))?;
         let lambda_z = -slope; // Negative because we're fitting declining concentrations
 
         // Calculate R-squared
@@ -149,21 +320,109 @@ impl ParameterCalculator {
             })
             .sum::<f64>();
 
-        let r_squared = if ss_tot > 0.0 { 1.0 - (ss_res / ss_tot) } else { 0.0 };
+        let r_squared = safe_div(ss_res, ss_tot).map_or(0.0, |ratio| 1.0 - ratio);
 
-        Ok((lambda_z, r_squared))
+        Ok((lambda_z, r_squared, intercept))
     }
 
     /// Calculate Cmax and Tmax
     pub fn calculate_cmax_tmax(observations: &[Observation]) -> Result<(f64, f64)> {
         let max_obs = observations
             .iter()
-            .max_by(|a, b| a.concentration.partial_cmp(&b.concentration).unwrap())
+            .filter(|obs| !obs.concentration.is_nan())
+            .max_by(|a, b| a.concentration.total_cmp(&b.concentration))
             .ok_or_else(|| NcaError::InsufficientData("No observations available".to_string()))?;
 
         Ok((max_obs.concentration, max_obs.time))
     }
 
+    /// Wagner-Nelson cumulative fraction absorbed (one-compartment disposition)
+    ///
+    /// F(t) = (C(t) + lambda_z * AUC_0-t) / (lambda_z * AUC_0-inf), with AUC_0-inf extrapolated
+    /// from the last point of `cumulative_auc` so that F approaches 1 by the end of the profile.
+    /// Empty without a positive lambda_z or a positive denominator.
+    pub fn wagner_nelson(cumulative_auc: &[CumulativeAuc], lambda_z: f64) -> Vec<FractionAbsorbed> {
+        let Some(last) = cumulative_auc.last().filter(|_| lambda_z > 0.0) else {
+            return Vec::new();
+        };
+        let total_absorbed = last.concentration + lambda_z * last.auc;
+
+        cumulative_auc
+            .iter()
+            .map_while(|point| {
+                let fraction = safe_div(point.concentration + lambda_z * point.auc, total_absorbed)?;
+                Some(FractionAbsorbed { time: point.time, fraction })
+            })
+            .collect()
+    }
+
+    /// Secondary peak (Cmax2, Tmax2) of a time-sorted profile
+    ///
+    /// A candidate is a quantifiable local maximum, higher than the sample before it and
+    /// followed by a lower one, other than Cmax. It counts when it rises above the lowest
+    /// concentration between it and Cmax by at least `min_rise` × Cmax; the highest such
+    /// candidate is returned.
+    pub fn detect_secondary_peak(observations: &[Observation], min_rise: f64) -> Option<(f64, f64)> {
+        let quantifiable: Vec<&Observation> = observations
+            .iter()
+            .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
+            .collect();
+        let cmax_idx = quantifiable
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.concentration.total_cmp(&b.concentration))
+            .map(|(idx, _)| idx)?;
+        let cmax = quantifiable[cmax_idx].concentration;
+
+        (1..quantifiable.len().saturating_sub(1))
+            .filter(|&idx| idx != cmax_idx)
+            .filter(|&idx| {
+                let c = quantifiable[idx].concentration;
+                c > quantifiable[idx - 1].concentration && c > quantifiable[idx + 1].concentration
+            })
+            .filter(|&idx| {
+                let between = if idx > cmax_idx { cmax_idx + 1..idx } else { idx + 1..cmax_idx };
+                let trough = quantifiable[between]
+                    .iter()
+                    .map(|obs| obs.concentration)
+                    .reduce(f64::min);
+                trough.is_some_and(|trough| quantifiable[idx].concentration - trough >= min_rise * cmax)
+            })
+            .map(|idx| (quantifiable[idx].concentration, quantifiable[idx].time))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Absorption lag time for extravascular dosing
+    ///
+    /// The time of the last sample before the first quantifiable concentration; `None` when
+    /// the first sample is already quantifiable or nothing is quantifiable.
+    pub fn calculate_tlag(observations: &[Observation]) -> Option<f64> {
+        let first_quantifiable = observations
+            .iter()
+            .position(|obs| obs.concentration > 0.0 && !obs.bloq)?;
+        first_quantifiable
+            .checked_sub(1)
+            .map(|idx| observations[idx].time)
+    }
+
+    /// Concentration at `dose_time` back-extrapolated log-linearly from the first two
+    /// quantifiable concentrations after it
+    ///
+    /// When those two are not declining, the first one is used, as no decline can be projected
+    /// back; `None` with no quantifiable concentration after the dose.
+    pub fn back_extrapolate_c0(observations: &[Observation], dose_time: f64) -> Option<f64> {
+        let mut quantifiable = observations
+            .iter()
+            .filter(|obs| obs.time > dose_time && obs.concentration > 0.0 && !obs.bloq);
+        let first = quantifiable.next()?;
+        let declining = |second: &&Observation| second.concentration < first.concentration && second.time > first.time;
+        let Some(second) = quantifiable.next().filter(declining) else {
+            return Some(first.concentration);
+        };
+        let slope = (first.concentration.ln() - second.concentration.ln()) / (second.time - first.time);
+        Some(first.concentration * (slope * (first.time - dose_time)).exp())
+    }
+
     /// Calculate half-life from lambda_z
     pub fn calculate_half_life(lambda_z: f64) -> Result<f64> {
         if lambda_z <= 0.0 {
@@ -172,7 +431,7 @@ impl ParameterCalculator {
             ));
         }
         
-        Ok(0.693147 / lambda_z) // ln(2) / lambda_z
+        Self::ratio(std::f64::consts::LN_2, lambda_z, "half-life")
     }
 
     /// Calculate clearance for IV dosing
@@ -183,7 +442,7 @@ impl ParameterCalculator {
             ));
         }
         
-        Ok(dose / auc_inf)
+        Self::ratio(dose, auc_inf, "clearance")
     }
 
     /// Calculate apparent clearance for oral dosing
@@ -191,7 +450,7 @@ impl ParameterCalculator {
         let cl_f = Self::calculate_clearance_iv(dose, auc_inf)?;
         
         match bioavailability {
-            Some(f) if f > 0.0 => Ok(cl_f / f),
+            Some(f) if f > 0.0 => Self::ratio(cl_f, f, "clearance"),
             _ => Ok(cl_f), // Return CL/F if bioavailability unknown
         }
     }
@@ -215,7 +474,7 @@ impl ParameterCalculator {
             ));
         }
         
-        Ok(clearance / lambda_z)
+        Self::ratio(clearance, lambda_z, "Vz")
     }
 
     /// Calculate mean residence time
@@ -226,7 +485,7 @@ impl ParameterCalculator {
             ));
         }
         
-        Ok(aumc_inf / auc_inf)
+        Self::ratio(aumc_inf, auc_inf, "MRT")
     }
 
     /// Find time of last quantifiable concentration
@@ -238,6 +497,247 @@ impl ParameterCalculator {
             .map(|obs| (obs.time, obs.concentration))
     }
 
+    /// Log-linear slope between the last two quantifiable concentrations, as an alternative
+    /// AUC_inf extrapolation slope
+    ///
+    /// `None` when there are fewer than two quantifiable concentrations or they do not decline.
+    pub fn calculate_last_two_points_slope(observations: &[Observation]) -> Option<f64> {
+        let mut quantifiable = observations
+            .iter()
+            .rev()
+            .filter(|obs| obs.concentration > 0.0 && !obs.bloq);
+        let last = quantifiable.next()?;
+        let previous = quantifiable.next()?;
+        let slope = safe_div((previous.concentration / last.concentration).ln(), last.time - previous.time)?;
+        Some(slope).filter(|&s| s > MIN_LAMBDA_Z)
+    }
+
+    /// Observed accumulation ratio Rac: AUC over the last dosing interval relative to the first
+    ///
+    /// The last interval counts only when it is as long as the first (the final interval ends
+    /// at the last sample, so the profile must cover a full interval after the last dose).
+    /// Rac approximates the steady-state ratio once the last interval is at steady state.
+    pub fn calculate_accumulation_ratio(dose_interval_aucs: &[DoseIntervalAuc]) -> Option<f64> {
+        let first = dose_interval_aucs.first()?;
+        let tau = first.end - first.dose_time;
+        let last = dose_interval_aucs
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|interval| ((interval.end - interval.dose_time) - tau).abs() <= 1e-6 * tau)?;
+        safe_div(last.auc?, first.auc?).filter(|_| tau > 0.0)
+    }
+
+    /// Effective (accumulation) half-life, -tau * ln(2) / ln(1 - 1/Rac)
+    ///
+    /// Predicts the time to steady state better than the terminal half-life for drugs whose
+    /// accumulation is driven by an earlier phase; `None` unless Rac > 1.
+    pub fn calculate_effective_half_life(tau: f64, accumulation_ratio: f64) -> Option<f64> {
+        if accumulation_ratio <= 1.0 || tau <= 0.0 {
+            return None;
+        }
+        Some(-tau * std::f64::consts::LN_2 / (1.0 - 1.0 / accumulation_ratio).ln()).filter(|t| t.is_finite())
+    }
+
+    /// Theoretical time to steady state, 5 half-lives (96.9% of the plateau)
+    pub fn calculate_time_to_steady_state(half_life: f64) -> f64 {
+        5.0 * half_life
+    }
+
+    /// Pre-dose troughs: for each dose, the last sample after the previous dose and at or
+    /// before this one, as (time, concentration)
+    pub fn pre_dose_troughs(observations: &[Observation], dose_times: &[f64]) -> Vec<(f64, f64)> {
+        dose_times
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &dose_time)| {
+                let previous_dose = if i == 0 { f64::NEG_INFINITY } else { dose_times[i - 1] };
+                observations
+                    .iter()
+                    .rev()
+                    .find(|obs| obs.time > previous_dose && obs.time <= dose_time)
+                    .map(|obs| (obs.time, obs.concentration))
+            })
+            .collect()
+    }
+
+    /// Time from the first trough to 96.9% of the plateau, from the observed approach of
+    /// troughs taken a constant interval apart
+    ///
+    /// Troughs rising as Css * (1 - exp(-k * t)) have successive differences shrinking by
+    /// exp(-k * tau); k comes from the geometric mean of those ratios, and 5 * ln(2) / k
+    /// matches [`Self::calculate_time_to_steady_state`] for k = lambda_z. `None` for fewer than
+    /// three troughs, unequal spacing, or troughs not approaching a plateau.
+    pub fn time_to_steady_state_from_troughs(troughs: &[(f64, f64)]) -> Option<f64> {
+        if troughs.len() < 3 {
+            return None;
+        }
+        let tau = troughs[1].0 - troughs[0].0;
+        let equally_spaced = troughs
+            .windows(2)
+            .all(|pair| ((pair[1].0 - pair[0].0) - tau).abs() <= 1e-6 * tau.abs());
+        if tau <= 0.0 || !equally_spaced {
+            return None;
+        }
+
+        let differences: Vec<f64> = troughs.windows(2).map(|pair| pair[1].1 - pair[0].1).collect();
+        let log_ratios: Vec<f64> = differences
+            .windows(2)
+            .filter_map(|pair| safe_div(pair[1], pair[0]))
+            .filter(|ratio| *ratio > 0.0 && *ratio < 1.0)
+            .map(f64::ln)
+            .collect();
+        if log_ratios.is_empty() {
+            return None;
+        }
+
+        let rate = -(log_ratios.iter().sum::<f64>() / log_ratios.len() as f64) / tau;
+        Some(5.0 * std::f64::consts::LN_2 / rate).filter(|t| t.is_finite())
+    }
+
+    /// Total time the interpolated profile spends above `threshold` (e.g. an MIC)
+    ///
+    /// Each sampling interval contributes the part where the concentration interpolated
+    /// between its samples exceeds the threshold, so any number of up and down crossings is
+    /// handled; time outside the sampled range is not counted.
+    pub fn time_above_threshold(observations: &[Observation], threshold: f64, interpolation: &InterpolationMethod) -> f64 {
+        Self::periods_above_threshold(observations, threshold, interpolation)
+            .iter()
+            .map(|(start, end)| end - start)
+            .sum()
+    }
+
+    /// AUC over the periods the profile is above `threshold` (AUC>MIC), with `method` between
+    /// samples and the concentration at each crossing interpolated to the threshold
+    ///
+    /// This is the whole area under the curve while above the threshold; the area between the
+    /// curve and the threshold is this minus threshold × time above threshold.
+    pub fn auc_above_threshold(
+        observations: &[Observation],
+        threshold: f64,
+        method: &AucMethod,
+        interpolation: &InterpolationMethod,
+    ) -> Option<f64> {
+        Self::periods_above_threshold(observations, threshold, interpolation)
+            .iter()
+            .map(|&(start, end)| AucCalculator::calculate_partial_auc(observations, start, end, method, interpolation))
+            .sum()
+    }
+
+    /// Times where the interpolated profile crosses `threshold`, in time order
+    pub fn threshold_crossings(
+        observations: &[Observation],
+        threshold: f64,
+        interpolation: &InterpolationMethod,
+    ) -> Vec<ThresholdCrossing> {
+        observations
+            .windows(2)
+            .filter(|pair| pair[1].time > pair[0].time)
+            .filter(|pair| (pair[0].concentration > threshold) != (pair[1].concentration > threshold))
+            .map(|pair| ThresholdCrossing {
+                time: Self::threshold_crossing(
+                    (pair[0].time, pair[0].concentration),
+                    (pair[1].time, pair[1].concentration),
+                    threshold,
+                    interpolation,
+                ),
+                rising: pair[1].concentration > threshold,
+            })
+            .collect()
+    }
+
+    /// Maximal (start, end) periods within the sampled range where the interpolated profile
+    /// is above `threshold`
+    fn periods_above_threshold(
+        observations: &[Observation],
+        threshold: f64,
+        interpolation: &InterpolationMethod,
+    ) -> Vec<(f64, f64)> {
+        let mut periods: Vec<(f64, f64)> = Vec::new();
+        for pair in observations.windows(2).filter(|pair| pair[1].time > pair[0].time) {
+            let (t1, c1) = (pair[0].time, pair[0].concentration);
+            let (t2, c2) = (pair[1].time, pair[1].concentration);
+            let (start, end) = match (c1 > threshold, c2 > threshold) {
+                (true, true) => (t1, t2),
+                (false, false) => continue,
+                (true, false) => (t1, Self::threshold_crossing((t1, c1), (t2, c2), threshold, interpolation)),
+                (false, true) => (Self::threshold_crossing((t1, c1), (t2, c2), threshold, interpolation), t2),
+            };
+            match periods.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => periods.push((start, end)),
+            }
+        }
+        periods
+    }
+
+    /// Time at which the concentration interpolated between two samples on opposite sides of
+    /// `threshold` reaches it; log-linear falls back to linear for non-positive values, as in
+    /// [`crate::auc::AucCalculator::interpolate_concentration`]
+    fn threshold_crossing(
+        (t1, c1): (f64, f64),
+        (t2, c2): (f64, f64),
+        threshold: f64,
+        interpolation: &InterpolationMethod,
+    ) -> f64 {
+        let fraction = match interpolation {
+            InterpolationMethod::LogLinear if c1 > 0.0 && c2 > 0.0 && threshold > 0.0 => {
+                (threshold.ln() - c1.ln()) / (c2.ln() - c1.ln())
+            }
+            _ => (threshold - c1) / (c2 - c1),
+        };
+        t1 + fraction * (t2 - t1)
+    }
+
+    /// Whether samples cover [start, end]: one at each bound, within
+    /// [`INTERVAL_BOUND_TOLERANCE`] of the interval length
+    pub fn interval_fully_observed(observations: &[Observation], start: f64, end: f64) -> bool {
+        Self::sample_at(observations, start, end).is_some() && Self::sample_at(observations, end, start).is_some()
+    }
+
+    /// Observed maximum and minimum over a fully sampled interval [start, end]
+    ///
+    /// Taken over the samples inside the interval and the samples nearest each bound (see
+    /// [`Self::interval_fully_observed`]), so nothing sampled in another interval counts;
+    /// `None` unless the interval is fully observed.
+    pub fn interval_extremes(observations: &[Observation], start: f64, end: f64) -> Option<(f64, f64)> {
+        let at_start = Self::sample_at(observations, start, end)?;
+        let at_end = Self::sample_at(observations, end, start)?;
+        let concentrations = observations
+            .iter()
+            .filter(|obs| obs.time > start && obs.time < end)
+            .chain([at_start, at_end])
+            .map(|obs| obs.concentration);
+        let cmax = concentrations.clone().fold(f64::NEG_INFINITY, f64::max);
+        let cmin = concentrations.fold(f64::INFINITY, f64::min);
+        Some((cmax, cmin))
+    }
+
+    /// Sample nearest the interval bound `bound` (the other bound being `opposite`), if one
+    /// lies within [`INTERVAL_BOUND_TOLERANCE`] of the interval length
+    fn sample_at(observations: &[Observation], bound: f64, opposite: f64) -> Option<&Observation> {
+        let tolerance = INTERVAL_BOUND_TOLERANCE * (opposite - bound).abs();
+        observations
+            .iter()
+            .filter(|obs| (obs.time - bound).abs() <= tolerance)
+            .min_by(|a, b| (a.time - bound).abs().total_cmp(&(b.time - bound).abs()))
+    }
+
+    /// Peak-trough fluctuation over a dosing interval, 100 * (Cmax - Cmin) / Cavg
+    pub fn calculate_fluctuation(cmax: f64, cmin: f64, cavg: f64) -> Option<f64> {
+        safe_div(100.0 * (cmax - cmin), cavg).filter(|_| cavg > 0.0)
+    }
+
+    /// Swing over a dosing interval, 100 * (Cmax - Cmin) / Cmin; `None` for a zero trough
+    pub fn calculate_swing(cmax: f64, cmin: f64) -> Option<f64> {
+        safe_div(100.0 * (cmax - cmin), cmin).filter(|_| cmin > 0.0)
+    }
+
+    /// Average concentration over the observed interval [tfirst, tlast]
+    pub fn calculate_cavg_observed(auc_last: f64, tfirst: f64, tlast: f64) -> Option<f64> {
+        safe_div(auc_last, tlast - tfirst).filter(|_| tlast > tfirst)
+    }
+
     /// Calculate percentage of AUC extrapolated to infinity
     pub fn calculate_auc_percent_extrap(auc_last: f64, auc_inf: f64) -> Result<f64> {
         if auc_inf <= 0.0 {
@@ -246,6 +746,70 @@ impl ParameterCalculator {
             ));
         }
         
-        Ok(((auc_inf - auc_last) / auc_inf) * 100.0)
+        Self::ratio(auc_inf - auc_last, auc_inf, "AUC extrapolation").map(|fraction| fraction * 100.0)
+    }
+
+    /// Divide through [`safe_div`], reporting an undefined ratio as a `MathError`
+    fn ratio(numerator: f64, denominator: f64, parameter: &str) -> Result<f64> {
+        safe_div(numerator, denominator).ok_or_else(|| NcaError::MathError(format!(
+            "Division by zero or non-finite result calculating {}", parameter
+        )))
+    }
+}
+
+/// Running sums of t and ln(C) over the positive concentrations of a profile, giving the
+/// log-linear regression of any run of consecutive points in constant time
+///
+/// Times are taken relative to the first observation to limit cancellation in the sums of
+/// squares.
+struct RegressionSums {
+    /// Per point prefix of (count, Σt, Σy, Σt², Σty, Σy²), y = ln(C)
+    prefix: Vec<(usize, f64, f64, f64, f64, f64)>,
+}
+
+impl RegressionSums {
+    fn new(observations: &[Observation]) -> Self {
+        let origin = observations.first().map_or(0.0, |obs| obs.time);
+        let mut prefix = Vec::with_capacity(observations.len() + 1);
+        let mut running = (0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        prefix.push(running);
+        for obs in observations {
+            if obs.concentration > 0.0 {
+                let (t, y) = (obs.time - origin, obs.concentration.ln());
+                running = (running.0 + 1, running.1 + t, running.2 + y, running.3 + t * t, running.4 + t * y, running.5 + y * y);
+            }
+            prefix.push(running);
+        }
+        Self { prefix }
+    }
+
+    /// lambda_z and R² of the regression over points `start..=end`, as in
+    /// [`ParameterCalculator::fit_lambda_z`]
+    fn fit(&self, start: usize, end: usize) -> Option<(f64, f64)> {
+        let (to, from) = (self.prefix[end + 1], self.prefix[start]);
+        let count = to.0 - from.0;
+        if count < 2 {
+            return None;
+        }
+        let n = count as f64;
+        let (sum_t, sum_y) = (to.1 - from.1, to.2 - from.2);
+        let (sum_t2, sum_ty, sum_y2) = (to.3 - from.3, to.4 - from.4, to.5 - from.5);
+
+        // Centered sums; below rounding level they are taken as exactly zero
+        let tolerance = 64.0 * f64::EPSILON;
+        let s_tt = sum_t2 - sum_t * sum_t / n;
+        if s_tt <= tolerance * sum_t2 {
+            return None;
+        }
+        let s_ty = sum_ty - sum_t * sum_y / n;
+        let s_yy = sum_y2 - sum_y * sum_y / n;
+
+        let slope = safe_div(s_ty, s_tt)?;
+        let r_squared = if s_yy <= tolerance * sum_y2 {
+            0.0
+        } else {
+            safe_div((s_yy - slope * s_ty).max(0.0), s_yy).map_or(0.0, |ratio| 1.0 - ratio)
+        };
+        Some((-slope, r_squared))
     }
-}
\ No newline at end of file
+}
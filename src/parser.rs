@@ -6,71 +6,283 @@ use std::path::Path;
 
 pub struct NonmemParser;
 
+/// Columns with a dedicated meaning; any other column is carried as an extra covariate
+const KNOWN_COLUMNS: &[&str] = &[
+    "ID", "TIME", "DV", "AMT", "EVID", "CMT", "RATE", "SS", "II", "ADDL",
+    "MDV", "BLQ", "LLOQ", "AGE", "WT", "HT", "SEX", "RACE", "TRT", "TREAT",
+    "TREATMENT", "STDAY", "PERIOD", "SEQ", "SEQUENCE", "FORM", "FORMULATION", "ROUTE",
+    "WEIGHT", "ANALYSIS_WT", "ANALYTE", "MATRIX", "DILUTION", "DF", "LOD", "BLOD"
+];
+
+/// Record-structure columns, never read as covariate values
+const EVENT_COLUMNS: &[&str] = &[
+    "ID", "TIME", "DV", "AMT", "EVID", "CMT", "RATE", "SS", "II", "ADDL", "MDV", "BLQ", "LLOQ",
+    "DILUTION", "DF", "LOD", "BLOD"
+];
+
+/// Subject identifier columns searched, in order, when no ID column is configured
+pub const DEFAULT_ID_COLUMNS: &[&str] = &["ID", "SUBJID", "USUBJID", "SUBJECT"];
+
+/// Settings for [`NonmemParser::parse_dataset_with_options`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Multiply each DV by its DILUTION (or DF) value; off by default so datasets with
+    /// concentrations already corrected for dilution are not scaled twice
+    pub apply_dilution_factor: bool,
+    /// Scale of the DV column; log-scale values are converted back to linear concentrations
+    /// before the dilution factor is applied
+    pub dv_scale: DvScale,
+    /// Column holding the subject identifier; when unset the first of [`DEFAULT_ID_COLUMNS`]
+    /// present in the header is used
+    pub id_column: Option<String>,
+}
+
 impl NonmemParser {
     pub fn parse_dataset<P: AsRef<Path>>(file_path: P) -> Result<Vec<Subject>> {
+        Ok(Self::parse_dataset_with_units(file_path)?.0)
+    }
+
+    /// Parse a dataset together with its optional units row
+    ///
+    /// A first row whose TIME value is present but not numeric (e.g. `h`, as in SDTM-derived
+    /// exports) is read as the units of each column instead of as data.
+    pub fn parse_dataset_with_units<P: AsRef<Path>>(file_path: P) -> Result<(Vec<Subject>, Option<DatasetUnits>)> {
+        Self::parse_dataset_with_options(file_path, &ParseOptions::default())
+    }
+
+    /// Parse a dataset and its optional units row with the given [`ParseOptions`]
+    pub fn parse_dataset_with_options<P: AsRef<Path>>(
+        file_path: P,
+        options: &ParseOptions,
+    ) -> Result<(Vec<Subject>, Option<DatasetUnits>)> {
         let file = File::open(file_path)?;
         let mut reader = ReaderBuilder::new()
             .has_headers(true)
             .from_reader(file);
 
+        let headers: Vec<String> = reader.headers()?
+            .iter()
+            .map(|h| h.trim().to_uppercase())
+            .collect();
+        let id_column = Self::id_column(&headers, options, "dataset")?;
+
         let mut subjects_map: HashMap<String, Subject> = HashMap::new();
+        // Dose records without a ROUTE value, by subject, whose route is inferred once all rows are read
+        let mut unrouted_doses: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut units = None;
 
-        for result in reader.records() {
+        for (index, result) in reader.records().enumerate() {
             let record = result?;
-            let row = Self::parse_record(&record)?;
+            let mut row = Self::parse_record(&record, &headers)?;
+            if index == 0 && Self::is_units_row(&row) {
+                let columns = row
+                    .into_iter()
+                    .map(|(column, unit)| (column, unit.trim_matches(|c| matches!(c, '(' | ')' | '[' | ']')).to_string()))
+                    .filter(|(_, unit)| !unit.is_empty())
+                    .collect();
+                units = Some(DatasetUnits { columns });
+                continue;
+            }
             
-            let subject_id = row.get("ID")
-                .ok_or_else(|| NcaError::ParseError("Missing ID column".to_string()))?
-                .to_string();
+            // Taken out of the row so a non-standard ID column is not kept as a covariate
+            let subject_id = row.remove(&id_column).unwrap_or_default();
 
             let subject = subjects_map.entry(subject_id.clone()).or_insert_with(|| Subject {
                 id: subject_id.clone(),
                 observations: Vec::new(),
                 dosing_events: Vec::new(),
                 demographics: Demographics::default(),
+                covariate_records: Vec::new(),
+                analyte: None,
+                matrix: None,
             });
 
-            Self::process_row(&row, subject)?;
+            let doses_before = subject.dosing_events.len();
+            Self::process_row(&row, subject, options)?;
+            if subject.dosing_events.len() > doses_before && row.get("ROUTE").is_none_or(|route| route.is_empty()) {
+                unrouted_doses.entry(subject_id).or_default().push(doses_before);
+            }
+        }
+
+        let mut assumed_intravascular: Vec<String> = unrouted_doses
+            .into_iter()
+            .filter(|(id, doses)| {
+                subjects_map.get_mut(id).is_some_and(|subject| !Self::infer_route_from_compartments(subject, doses))
+            })
+            .map(|(id, _)| id)
+            .collect();
+        if !assumed_intravascular.is_empty() {
+            assumed_intravascular.sort();
+            log::warn!(
+                "No ROUTE, and no CMT telling dose from observation compartments, for subjects {}: doses treated as intravascular from RATE",
+                assumed_intravascular.join(", ")
+            );
         }
 
-        Ok(subjects_map.into_values().collect())
+        Ok((subjects_map.into_values().collect(), units))
     }
 
-    fn parse_record(record: &csv::StringRecord) -> Result<HashMap<String, String>> {
-        let headers = vec![
-            "ID", "TIME", "DV", "AMT", "EVID", "CMT", "RATE", "SS", "II", "ADDL",
-            "MDV", "BLQ", "LLOQ", "AGE", "WT", "HT", "SEX", "RACE", "TRT", "TREAT", 
-            "TREATMENT", "STDAY", "PERIOD", "SEQ", "SEQUENCE", "FORM", "FORMULATION"
-        ];
+    /// Infer the route of dose records without a ROUTE value from their compartment
+    ///
+    /// A dose into a compartment no observation is sampled from (e.g. a CMT=1 depot with
+    /// plasma samples in CMT=2) is extravascular; a dose with a positive RATE stays an
+    /// infusion. Returns whether every such dose had compartments to tell by, i.e. whether no
+    /// dose was left intravascular by default.
+    fn infer_route_from_compartments(subject: &mut Subject, unrouted: &[usize]) -> bool {
+        let observed: Vec<i32> = subject.observations.iter().filter_map(|obs| obs.cmt).collect();
+        let mut resolved = true;
+        for &index in unrouted {
+            let dose = &mut subject.dosing_events[index];
+            match dose.cmt {
+                _ if dose.infusion_duration.is_some() => {}
+                Some(cmt) if !observed.is_empty() && !observed.contains(&cmt) => {
+                    dose.route = DosingRoute::Oral;
+                }
+                Some(_) if !observed.is_empty() => {}
+                _ => resolved = false,
+            }
+        }
+        resolved
+    }
 
-        let mut row = HashMap::new();
-        for (i, value) in record.iter().enumerate() {
-            if i < headers.len() {
-                row.insert(headers[i].to_string(), value.to_string());
+    /// Join a separate demographics/covariates file (one row per ID) onto parsed subjects
+    ///
+    /// Columns are read as in the dataset: AGE, WT, SEX, TRT, ... fill [`Demographics`] and
+    /// other columns become extra covariates, taking precedence over values from the dataset.
+    /// Returns a warning for every subject without a covariates row and every covariates ID
+    /// without a subject.
+    pub fn join_covariates<P: AsRef<Path>>(subjects: &mut [Subject], file_path: P) -> Result<Vec<String>> {
+        Self::join_covariates_with_options(subjects, file_path, &ParseOptions::default())
+    }
+
+    /// [`Self::join_covariates`] with the covariates file's ID column chosen as in
+    /// [`ParseOptions::id_column`]
+    pub fn join_covariates_with_options<P: AsRef<Path>>(
+        subjects: &mut [Subject],
+        file_path: P,
+        options: &ParseOptions,
+    ) -> Result<Vec<String>> {
+        let file = File::open(file_path)?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(file);
+
+        let headers: Vec<String> = reader.headers()?
+            .iter()
+            .map(|h| h.trim().to_uppercase())
+            .collect();
+        let id_column = Self::id_column(&headers, options, "covariates file")?;
+
+        let mut rows: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for result in reader.records() {
+            let mut row = Self::parse_record(&result?, &headers)?;
+            let subject_id = row.remove(&id_column).unwrap_or_default();
+            rows.insert(subject_id, row);
+        }
+
+        let mut warnings = Vec::new();
+        for subject in subjects.iter_mut() {
+            match rows.remove(&subject.id) {
+                Some(row) => Self::update_demographics(&row, &mut subject.demographics)?,
+                None => warnings.push(format!("Subject {} has no row in the covariates file", subject.id)),
             }
         }
+        let mut unmatched: Vec<String> = rows.into_keys().collect();
+        unmatched.sort();
+        warnings.extend(unmatched.iter().map(|id| format!("Covariates for ID {} match no subject in the dataset", id)));
+
+        for warning in &warnings {
+            log::warn!("{}", warning);
+        }
+        Ok(warnings)
+    }
+
+    /// Subject identifier column of a file: the configured one, else the first default present
+    fn id_column(headers: &[String], options: &ParseOptions, file: &str) -> Result<String> {
+        match &options.id_column {
+            Some(column) => {
+                let column = column.trim().to_uppercase();
+                if headers.contains(&column) {
+                    Ok(column)
+                } else {
+                    Err(NcaError::ParseError(format!("ID column {} not found in {}", column, file)))
+                }
+            }
+            None => DEFAULT_ID_COLUMNS
+                .iter()
+                .find(|column| headers.iter().any(|header| header == *column))
+                .map(|column| column.to_string())
+                .ok_or_else(|| NcaError::ParseError(format!(
+                    "Missing ID column in {} (looked for {})",
+                    file,
+                    DEFAULT_ID_COLUMNS.join(", ")
+                ))),
+        }
+    }
+
+    /// DILUTION or DF value of a row, 1 when both are blank
+    fn dilution_factor(row: &HashMap<String, String>) -> Result<f64> {
+        let Some(value) = ["DILUTION", "DF"]
+            .iter()
+            .find_map(|column| row.get(*column).filter(|value| !value.is_empty()))
+        else {
+            return Ok(1.0);
+        };
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|factor| *factor > 0.0)
+            .ok_or_else(|| NcaError::ParseError(format!("Invalid dilution factor: {}", value)))
+    }
+
+    fn is_units_row(row: &HashMap<String, String>) -> bool {
+        row.get("TIME").is_some_and(|time| !time.is_empty() && time.parse::<f64>().is_err())
+    }
+
+    fn parse_record(record: &csv::StringRecord, headers: &[String]) -> Result<HashMap<String, String>> {
+        let mut row = HashMap::new();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            row.insert(header.clone(), value.trim().to_string());
+        }
 
         Ok(row)
     }
 
-    fn process_row(row: &HashMap<String, String>, subject: &mut Subject) -> Result<()> {
+    fn process_row(row: &HashMap<String, String>, subject: &mut Subject, options: &ParseOptions) -> Result<()> {
         let time = Self::parse_float(row, "TIME")?;
         let evid = Self::parse_int(row, "EVID").unwrap_or(0);
+        let cmt = Self::parse_int(row, "CMT").ok();
 
         match evid {
             0 => {
                 // Observation record
-                let concentration = Self::parse_float(row, "DV")?;
+                let mut concentration = Self::parse_float(row, "DV")?;
+                if options.dv_scale == DvScale::Log {
+                    concentration = concentration.exp();
+                    if !concentration.is_finite() {
+                        return Err(NcaError::ParseError(format!(
+                            "Log-scale DV too large to convert: {}", row.get("DV").map_or("", String::as_str)
+                        )));
+                    }
+                }
+                if options.apply_dilution_factor {
+                    concentration *= Self::dilution_factor(row)?;
+                }
                 let lloq = Self::parse_float_optional(row, "LLOQ");
-                let bloq = Self::parse_bool(row, "BLQ").unwrap_or(false);
+                let lod = Self::parse_float_optional(row, "LOD");
+                let blod = Self::parse_bool(row, "BLOD").unwrap_or(false);
+                let bloq = Self::parse_bool(row, "BLQ").unwrap_or(false) || blod;
                 
                 subject.observations.push(Observation {
                     time,
                     concentration,
                     lloq,
                     bloq,
+                    lod,
+                    blod,
                     evid,
                     dv: concentration,
+                    cmt,
                 });
             }
             1 => {
@@ -78,7 +290,13 @@ impl NonmemParser {
                 let dose = Self::parse_float(row, "AMT")?;
                 let rate = Self::parse_float_optional(row, "RATE");
                 
-                let (route, infusion_duration) = Self::determine_dosing_route(rate, dose);
+                let route = match row.get("ROUTE").filter(|value| !value.is_empty()) {
+                    Some(value) => Some(Self::parse_route(value)?),
+                    None => None,
+                };
+                let (route, infusion_duration) = Self::determine_dosing_route(route, rate, dose);
+                let steady_state = Self::parse_int(row, "SS").map(|ss| ss > 0).unwrap_or(false);
+                let interval = Self::parse_float_optional(row, "II").filter(|ii| *ii > 0.0);
                 
                 subject.dosing_events.push(DosingEvent {
                     time,
@@ -86,28 +304,80 @@ impl NonmemParser {
                     route,
                     infusion_duration,
                     evid,
+                    cmt,
+                    steady_state,
+                    interval,
                 });
             }
+            2 => {
+                // Other-type record carrying time-varying covariates
+                let values: HashMap<String, f64> = row
+                    .iter()
+                    .filter(|(column, _)| !EVENT_COLUMNS.contains(&column.as_str()))
+                    .filter_map(|(column, value)| value.parse::<f64>().ok().map(|v| (column.clone(), v)))
+                    .collect();
+
+                if values.is_empty() {
+                    log::debug!("Subject {}: EVID=2 record at t = {} has no numeric covariates", subject.id, time);
+                } else {
+                    subject.covariate_records.push(CovariateRecord { time, values });
+                }
+            }
             _ => {
                 // Other event types (reset, additional dose, etc.)
+                log::debug!("Subject {}: ignoring EVID={} record at t = {}", subject.id, evid, time);
             }
         }
 
         // Update demographics if available
         Self::update_demographics(row, &mut subject.demographics)?;
+        // One analyte and matrix per subject: a second value would otherwise be mixed into the
+        // same profile and overwrite the first tag
+        for (column, tag) in [("ANALYTE", &mut subject.analyte), ("MATRIX", &mut subject.matrix)] {
+            if let Some(value) = row.get(column).filter(|value| !value.is_empty()) {
+                match tag {
+                    Some(existing) if existing != value => {
+                        return Err(NcaError::ParseError(format!(
+                            "Subject {} has more than one {} ({} and {}); analyze each {} separately",
+                            subject.id, column, existing, value, column.to_lowercase()
+                        )));
+                    }
+                    _ => *tag = Some(value.clone()),
+                }
+            }
+        }
 
         Ok(())
     }
 
-    fn determine_dosing_route(rate: Option<f64>, dose: f64) -> (DosingRoute, Option<f64>) {
-        match rate {
-            Some(r) if r > 0.0 => {
-                let duration = dose / r;
-                (DosingRoute::IntravenousInfusion, Some(duration))
+    /// Route and infusion duration of a dose record
+    ///
+    /// An explicit ROUTE column takes precedence; RATE then only supplies the duration of an
+    /// infusion. Without ROUTE the route is first inferred from RATE, which only describes
+    /// intravascular input, and then from the dose compartment once the subject's
+    /// observations are known (see [`Self::infer_route_from_compartments`]): RATE > 0 is an infusion lasting AMT / RATE, and the NONMEM
+    /// estimation flags mean "rate is modeled" (RATE=-1, treated as a bolus) and "duration is
+    /// modeled" (RATE=-2, an infusion of unknown duration). Neither flag implies oral dosing.
+    fn determine_dosing_route(route: Option<DosingRoute>, rate: Option<f64>, dose: f64) -> (DosingRoute, Option<f64>) {
+        match (route, rate) {
+            (Some(DosingRoute::IntravenousInfusion), Some(r)) if r > 0.0 => {
+                (DosingRoute::IntravenousInfusion, Some(dose / r))
             }
-            Some(-1.0) => (DosingRoute::IntravenousBolus, None),
-            Some(-2.0) => (DosingRoute::Oral, None),
-            _ => (DosingRoute::IntravenousBolus, None),
+            (Some(route), _) => (route, None),
+            (None, Some(r)) if r > 0.0 => (DosingRoute::IntravenousInfusion, Some(dose / r)),
+            (None, Some(-2.0)) => (DosingRoute::IntravenousInfusion, None),
+            (None, _) => (DosingRoute::IntravenousBolus, None),
+        }
+    }
+
+    fn parse_route(value: &str) -> Result<DosingRoute> {
+        match value.trim().to_uppercase().as_str() {
+            "IV" | "IVB" | "BOLUS" => Ok(DosingRoute::IntravenousBolus),
+            "INF" | "IVI" | "INFUSION" => Ok(DosingRoute::IntravenousInfusion),
+            "ORAL" | "PO" | "EV" | "EXTRAVASCULAR" => Ok(DosingRoute::Oral),
+            "SC" | "SUBCUTANEOUS" => Ok(DosingRoute::Subcutaneous),
+            "IM" | "INTRAMUSCULAR" => Ok(DosingRoute::Intramuscular),
+            _ => Err(NcaError::ParseError(format!("Unknown dose route: {}", value))),
         }
     }
 
@@ -182,6 +452,20 @@ impl NonmemParser {
             }
         }
 
+        for weight_col in &["ANALYSIS_WT", "WEIGHT"] {
+            if let Some(weight) = row.get(*weight_col).and_then(|w| w.parse::<f64>().ok()) {
+                demographics.analysis_weight = Some(weight);
+                break;
+            }
+        }
+
+        // Study-specific columns (genotype, center, ...) are kept verbatim
+        for (column, value) in row {
+            if !value.is_empty() && !KNOWN_COLUMNS.contains(&column.as_str()) {
+                demographics.extra.insert(column.clone(), value.clone());
+            }
+        }
+
         Ok(())
     }
 
@@ -211,20 +495,3 @@ impl NonmemParser {
         }
     }
 }
-
-impl Default for Demographics {
-    fn default() -> Self {
-        Self {
-            age: None,
-            weight: None,
-            height: None,
-            sex: None,
-            race: None,
-            treatment: None,
-            study_day: None,
-            period: None,
-            sequence: None,
-            formulation: None,
-        }
-    }
-}
\ No newline at end of file
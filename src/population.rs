@@ -1,64 +1,75 @@
-use crate::{models::*, nca::NcaAnalyzer, Result};
-use crate::stratification::StratificationAnalyzer;
+use crate::{models::*, auc::AucCalculator, errors::NcaError, math::safe_div, nca::NcaAnalyzer, parameters::ParameterCalculator, Result};
+use crate::stratification::{MannWhitneyTest, PooledTTest, StatisticalTest, StratificationAnalyzer, WelchTTest};
 use crate::covariate::CovariateAnalyzer;
+use crate::output::StreamingOutput;
+use crate::cache::{ResultCache, SubjectAnalysis};
 use rayon::prelude::*;
 use statrs::statistics::Statistics;
+use std::any::Any;
 use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 
 pub struct PopulationAnalyzer;
 
+/// Parameters summarized across the population and compared between strata
+const SUMMARY_PARAMETERS: &[&str] = &[
+    "auc_last", "auc_inf", "auc_tau", "cmax", "tmax",
+    "half_life", "clearance", "volume_terminal", "mrt",
+];
+
+/// Subjects analyzed in parallel per batch by [`PopulationAnalyzer::analyze_population_streaming`]
+pub const STREAMING_BATCH_SIZE: usize = 256;
+
+/// Default subjects per rayon task in population analyses; small enough to keep every core
+/// busy for a few hundred subjects, large enough to amortize scheduling over tiny subjects
+pub const DEFAULT_PARALLEL_CHUNK_SIZE: usize = 16;
+
+/// Summary parameters that scale with dose under linear pharmacokinetics
+const DOSE_PROPORTIONAL_PARAMETERS: &[&str] = &["auc_last", "auc_inf", "auc_tau", "cmax"];
+
 impl PopulationAnalyzer {
     /// Perform population NCA analysis with parallel processing
     pub fn analyze_population(
         subjects: Vec<Subject>,
         config: &AnalysisConfig,
     ) -> Result<PopulationResults> {
+        Self::analyze_population_cached(subjects, config, None)
+    }
+
+    /// Population analysis reusing the per-subject analyses in `cache`
+    ///
+    /// Subjects whose data and configuration match an earlier run are taken from the cache
+    /// instead of being reanalyzed; population-level steps (summaries, strata, covariates)
+    /// always run.
+    pub fn analyze_population_with_cache(
+        subjects: Vec<Subject>,
+        config: &AnalysisConfig,
+        cache: &ResultCache,
+    ) -> Result<PopulationResults> {
+        Self::analyze_population_cached(subjects, config, Some(cache))
+    }
+
+    fn analyze_population_cached(
+        subjects: Vec<Subject>,
+        config: &AnalysisConfig,
+        cache: Option<&ResultCache>,
+    ) -> Result<PopulationResults> {
+        config.validate()?;
         log::info!("Starting population analysis for {} subjects", subjects.len());
+        Self::warn_primary_method_not_configured(config);
 
         // Parallel processing of individual subjects
-        let mut individual_results = Vec::new();
+        let mut analyses = Vec::new();
         let mut failed_subjects = Vec::new();
         
-        let analysis_results: Vec<_> = subjects
-            .par_iter()
-            .map(|subject| {
-                match NcaAnalyzer::analyze_subject(subject, config) {
-                    Ok((result, warnings)) => {
-                        let validation_warnings = NcaAnalyzer::validate_results(&result);
-                        let all_warnings = [warnings, validation_warnings].concat();
-                        
-                        if !all_warnings.is_empty() {
-                            log::warn!("Warnings for subject {}: {:?}", subject.id, all_warnings);
-                        }
-                        Ok((result, all_warnings))
-                    }
-                    Err(e) => {
-                        log::error!("Failed to analyze subject {}: {}", subject.id, e);
-                        
-                        // Count quantifiable concentrations for failed subject
-                        let quantifiable_count = subject.observations.iter()
-                            .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
-                            .count();
-                        
-                        let failed_analysis = FailedSubjectAnalysis {
-                            subject_id: subject.id.clone(),
-                            failure_reason: e.to_string(),
-                            quantifiable_concentrations: quantifiable_count,
-                            total_observations: subject.observations.len(),
-                            failed_parameters: vec!["All parameters".to_string()],
-                        };
-                        
-                        Err(failed_analysis)
-                    }
-                }
-            })
-            .collect();
+        let analysis_results = Self::analyze_subjects(&subjects, config, cache);
         
         // Separate successful and failed analyses
         for result in analysis_results {
             match result {
-                Ok((nca_result, _warnings)) => {
-                    individual_results.push(nca_result);
+                Ok(analysis) => {
+                    analyses.push(analysis);
                 }
                 Err(failed_analysis) => {
                     failed_subjects.push(failed_analysis);
@@ -66,23 +77,97 @@ impl PopulationAnalyzer {
             }
         }
 
-        log::info!("Successfully analyzed {} subjects", individual_results.len());
+        log::info!("Successfully analyzed {} subjects", analyses.len());
         if !failed_subjects.is_empty() {
             log::warn!("Failed to analyze {} subjects", failed_subjects.len());
         }
 
+        // Group-extrapolated AUC_inf for subjects without a terminal fit, before the warnings
+        // are logged so they describe the final results
+        if config.uses_pooled_lambda_z() {
+            Self::apply_pooled_lambda_z(&mut analyses, &subjects, config);
+        }
+        let mut individual_results: Vec<NcaResults> = analyses
+            .into_iter()
+            .map(|(result, warnings)| {
+                Self::log_subject_warnings(&result.subject_id, &warnings);
+                result
+            })
+            .collect();
+
+        // Extravascular half-lives against the intravascular arm, if there is one
+        Self::flag_flip_flop_against_iv(&mut individual_results, &config.validation_thresholds);
+
+        // Concentration plausibility checks on the data as recorded
+        let concentration_flags: Vec<ConcentrationFlag> = subjects
+            .iter()
+            .flat_map(|subject| NcaAnalyzer::check_concentration_bounds(subject, &config.validation_thresholds))
+            .collect();
+        if !concentration_flags.is_empty() {
+            log::warn!("{} concentrations outside plausibility bounds", concentration_flags.len());
+        }
+        let dose_flags: Vec<DoseFlag> = subjects
+            .iter()
+            .flat_map(|subject| NcaAnalyzer::check_dosing(subject, &config.validation_thresholds))
+            .collect();
+        if !dose_flags.is_empty() {
+            log::warn!("{} implausible dosing record(s); see dose_qc.csv", dose_flags.len());
+        }
+
+        // Clearance recomputed from the terminal regression
+        let clearance_checks: Vec<ClearanceCheck> = individual_results
+            .iter()
+            .filter_map(|result| NcaAnalyzer::cross_check_clearance(result, &config.validation_thresholds))
+            .collect();
+        let discrepant = clearance_checks.iter().filter(|check| check.flagged).count();
+        if discrepant > 0 {
+            log::warn!("{} subject(s) whose Dose / AUC_inf disagrees with Dose / AUC_inf_pred; see clearance_qc.csv", discrepant);
+        }
+
+        // Sampling completeness for data review
+        let data_completeness = if config.data_completeness {
+            Self::assess_data_completeness(&subjects, &individual_results, config)
+        } else {
+            Vec::new()
+        };
+
+        let data_quality = Self::assess_data_quality(&subjects, &individual_results, failed_subjects.len(), config);
+
         // Calculate summary statistics
-        let summary_statistics = Self::calculate_summary_statistics(&individual_results)?;
+        let weights = config.weighted_summary.then(|| Self::subject_weights(&subjects, config));
+        let summary_statistics = Self::calculate_summary_statistics(&individual_results, weights.as_ref(), config)?;
+
+        // Dose-normalized parameters and their summary statistics
+        let (dose_normalized_parameters, dose_normalized_summary, dose_normalization_exclusions) = if config.dose_normalization {
+            let (normalized, exclusions) = Self::dose_normalize(&individual_results, &subjects, config);
+            let summary = Self::calculate_dose_normalized_summary(&normalized, &config.geometric_non_positive);
+            (normalized, Some(summary), exclusions)
+        } else {
+            (Vec::new(), None, Vec::new())
+        };
 
         // Method comparison across all subjects
-        let method_comparison = Self::perform_method_comparison(&individual_results)?;
+        let method_comparison = Self::perform_method_comparison(&individual_results, config)?;
 
         // Stratified analysis
         let stratified_results = StratificationAnalyzer::analyze_stratified(&subjects, config)?;
 
+        // Statistical comparisons between strata
+        let strata_comparisons = match &config.stratification {
+            Some(strat_config) if strat_config.perform_statistical_tests => {
+                let test: &dyn StatisticalTest = match strat_config.comparison_test {
+                    ComparisonTest::WelchTTest => &WelchTTest,
+                    ComparisonTest::PooledTTest => &PooledTTest,
+                    ComparisonTest::MannWhitney => &MannWhitneyTest,
+                };
+                StratificationAnalyzer::compare_strata_by_variable(&stratified_results, SUMMARY_PARAMETERS, test)?
+            }
+            _ => Vec::new(),
+        };
+
         // Covariate analysis
         let covariate_analysis = if config.perform_covariate_analysis {
-            CovariateAnalyzer::analyze_covariates(&individual_results, &subjects)?
+            CovariateAnalyzer::analyze_covariates(&individual_results, &subjects, config)?
         } else {
             CovariateAnalysis {
                 correlations: HashMap::new(),
@@ -95,43 +180,656 @@ impl PopulationAnalyzer {
             individual_results,
             failed_subjects,
             summary_statistics,
+            dose_normalized_parameters,
+            dose_normalized_summary,
+            dose_normalization_exclusions,
             method_comparison,
             stratified_results,
+            strata_comparisons,
+            concentration_flags,
+            dose_flags,
+            clearance_checks,
+            data_completeness,
             covariate_analysis,
+            data_quality,
         })
     }
 
-    fn calculate_summary_statistics(results: &[NcaResults]) -> Result<SummaryStatistics> {
-        let mut parameter_stats = HashMap::new();
+    /// Extrapolate AUC_inf with a lambda_z pooled over each dose group for the subjects whose
+    /// own terminal fit failed
+    ///
+    /// A dose group is the results of one analyte at the same total dose. Its slope is
+    /// [`ParameterCalculator::pooled_lambda_z`] over the lambda_z windows of the fitted
+    /// subjects, at least two. Salvaged subjects are reanalyzed with
+    /// [`NcaAnalyzer::analyze_subject_with_pooled_lambda_z`], replacing their results and
+    /// warnings, so AUC_inf and the parameters derived from it are labelled `PooledGroup`;
+    /// half-life and Vz stay missing. Returns the IDs of the salvaged subjects.
+    pub fn apply_pooled_lambda_z(
+        analyses: &mut [(NcaResults, Vec<String>)],
+        subjects: &[Subject],
+        config: &AnalysisConfig,
+    ) -> Vec<String> {
+        let find_subject = |result: &NcaResults| {
+            subjects
+                .iter()
+                .find(|subject| subject.id == result.subject_id && subject.analyte == result.analyte)
+        };
+        let mut groups: HashMap<(Option<String>, u64), Vec<usize>> = HashMap::new();
+        for (idx, (result, _)) in analyses.iter().enumerate() {
+            groups.entry((result.analyte.clone(), result.dosing.total_dose.to_bits())).or_default().push(idx);
+        }
 
-        // Define parameters to analyze
-        let parameters: Vec<(&str, fn(&IndividualParameters) -> Option<f64>)> = vec![
-            ("auc_last", |p| p.auc_last),
-            ("auc_inf", |p| p.auc_inf),
-            ("cmax", |p| p.cmax),
-            ("tmax", |p| p.tmax),
-            ("half_life", |p| p.half_life),
-            ("clearance", |p| p.clearance),
-            ("volume_terminal", |p| p.volume_terminal),
-            ("mrt", |p| p.mrt),
-        ];
-
-        for (param_name, extractor) in parameters {
-            let values: Vec<f64> = results
+        let mut salvaged = Vec::new();
+        for members in groups.values() {
+            let profiles: Vec<Vec<(f64, f64)>> = members
                 .iter()
-                .filter_map(|r| extractor(&r.individual_parameters))
+                .filter_map(|&idx| {
+                    let result = &analyses[idx].0;
+                    let (start, end) = result.individual_parameters.lambda_z_time_range?;
+                    let subject = find_subject(result)?;
+                    Some(subject
+                        .observations
+                        .iter()
+                        .filter(|obs| NcaAnalyzer::is_analyte_observation(obs, config) && !obs.bloq)
+                        .filter(|obs| obs.time >= start && obs.time <= end)
+                        .map(|obs| (obs.time, obs.concentration))
+                        .collect())
+                })
                 .collect();
+            let Some(pooled) = ParameterCalculator::pooled_lambda_z(&profiles) else {
+                continue;
+            };
+
+            for &idx in members {
+                let result = &analyses[idx].0;
+                let params = &result.individual_parameters;
+                if params.lambda_z.is_some() || params.auc_inf.is_some() {
+                    continue;
+                }
+                let Some(subject) = find_subject(result) else {
+                    continue;
+                };
+                let Ok((result, mut warnings)) = NcaAnalyzer::analyze_subject_with_pooled_lambda_z(subject, config, pooled)
+                else {
+                    continue;
+                };
+                if result.individual_parameters.auc_inf_slope_method != Some(AucExtrapolationSlope::PooledGroup) {
+                    continue;
+                }
+                warnings.extend(NcaAnalyzer::validate_results(&result, &config.validation_thresholds));
+                warnings.push(format!("AUC_inf extrapolated with the pooled dose-group lambda_z {:.4}", pooled));
+                salvaged.push(result.subject_id.clone());
+                analyses[idx] = (result, warnings);
+            }
+        }
+        salvaged.sort();
+        salvaged
+    }
+
+    /// Flag extravascular subjects whose terminal half-life exceeds the median intravascular
+    /// half-life by more than `flip_flop_iv_half_life_ratio`
+    ///
+    /// Elimination does not depend on the route, so a much longer extravascular terminal
+    /// phase points to absorption-limited (flip-flop) kinetics. Without intravascular subjects
+    /// nothing is changed. Returns the IDs of the newly flagged subjects.
+    pub fn flag_flip_flop_against_iv(results: &mut [NcaResults], thresholds: &ValidationThresholds) -> Vec<String> {
+        let mut iv_half_lives: Vec<f64> = results
+            .iter()
+            .filter(|r| !r.individual_parameters.apparent_clearance)
+            .filter_map(|r| r.individual_parameters.half_life)
+            .collect();
+        if iv_half_lives.is_empty() {
+            return Vec::new();
+        }
+        iv_half_lives.sort_by(|a, b| a.total_cmp(b));
+        let reference = Self::quantile(&iv_half_lives, 0.5);
+
+        let mut flagged = Vec::new();
+        for result in results.iter_mut() {
+            let params = &mut result.individual_parameters;
+            if !params.apparent_clearance || params.flip_flop_suspected {
+                continue;
+            }
+            let ratio = params.half_life.and_then(|t_half| safe_div(t_half, reference));
+            if ratio.is_some_and(|r| r > thresholds.flip_flop_iv_half_life_ratio) {
+                params.flip_flop_suspected = true;
+                log::warn!(
+                    "Possible flip-flop kinetics for subject {}: terminal half-life {:.3} vs median IV half-life {:.3}",
+                    result.subject_id, params.half_life.unwrap_or_default(), reference
+                );
+                flagged.push(result.subject_id.clone());
+            }
+        }
+        flagged
+    }
+
+    /// Analyze subjects in parallel, `config.parallel_chunk_size` subjects per rayon task,
+    /// keeping the input order; with a cache, unchanged subjects are taken from it
+    fn analyze_subjects(
+        subjects: &[Subject],
+        config: &AnalysisConfig,
+        cache: Option<&ResultCache>,
+    ) -> Vec<SubjectAnalysis> {
+        subjects
+            .par_chunks(config.parallel_chunk_size)
+            .flat_map_iter(|chunk| {
+                chunk.iter().map(|subject| match cache {
+                    Some(cache) => cache.get_or_analyze(subject, config, || Self::analyze_one(subject, config)),
+                    None => Self::analyze_one(subject, config),
+                })
+            })
+            .collect()
+    }
+
+    /// Analyze and validate one subject; a failure is logged and returned as its
+    /// [`FailedSubjectAnalysis`] record
+    ///
+    /// A panic while analyzing the subject (e.g. in a registered derivation) is caught and
+    /// recorded as that subject's failure, so the remaining subjects still complete.
+    fn analyze_one(subject: &Subject, config: &AnalysisConfig) -> SubjectAnalysis {
+        let analysis = panic::catch_unwind(AssertUnwindSafe(|| {
+            NcaAnalyzer::analyze_subject(subject, config).map(|(result, warnings)| {
+                let validation_warnings = NcaAnalyzer::validate_results(&result, &config.validation_thresholds);
+                let all_warnings = [warnings, validation_warnings].concat();
+                (result, all_warnings)
+            })
+        }));
+        let outcome = match analysis {
+            Ok(outcome) => outcome.map_err(|e| e.to_string()),
+            Err(payload) => Err(format!("Analysis panicked: {}", Self::panic_message(payload.as_ref()))),
+        };
+
+        match outcome {
+            Ok(analysis) => Ok(analysis),
+            Err(reason) => {
+                log::error!("Failed to analyze subject {}: {}", subject.id, reason);
+                
+                // Count quantifiable concentrations for failed subject
+                let quantifiable_count = subject.observations.iter()
+                    .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
+                    .count();
+                
+                let failed_analysis = FailedSubjectAnalysis {
+                    subject_id: subject.id.clone(),
+                    failure_reason: reason,
+                    quantifiable_concentrations: quantifiable_count,
+                    total_observations: subject.observations.len(),
+                    failed_parameters: vec!["All parameters".to_string()],
+                };
+                
+                Err(failed_analysis)
+            }
+        }
+    }
+
+    /// Log the warnings of one analyzed subject
+    fn log_subject_warnings(subject_id: &str, warnings: &[String]) {
+        if !warnings.is_empty() {
+            log::warn!("Warnings for subject {}: {:?}", subject_id, warnings);
+        }
+    }
+
+    /// Message of a caught panic: the `panic!` text when it is a string
+    fn panic_message(payload: &(dyn Any + Send)) -> String {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    }
+
+    /// Population analysis that writes each individual result as soon as it is available
+    ///
+    /// Subjects are consumed from `subjects` in batches of [`STREAMING_BATCH_SIZE`], analyzed
+    /// in parallel and appended in input order to individual_results.csv (with their rows of
+    /// concentrations.csv and threshold_crossings.csv), so memory is bounded by the batch
+    /// rather than the population. Summary statistics come from single-pass [`OnlineStats`]
+    /// accumulators, which reproduce the batch mean, SD, CV, min, max and geometric statistics
+    /// (honouring `weighted_summary`); the median, quartiles, distribution and harmonic
+    /// pseudo-SD come from the summary values kept alongside, one number per subject and
+    /// parameter, so the summary matches the batch one. The per-subject QC files,
+    /// summary_statistics.csv, failed_subjects.log and manifest.json are written at the end.
+    ///
+    /// Steps that need every result at once are not available: options enabling them
+    /// (`pooled_lambda_z`, stratification, covariate analysis, dose normalization, data
+    /// completeness) are rejected, and the flip-flop comparison against an intravascular arm
+    /// ([`Self::flag_flip_flop_against_iv`]) is skipped with a warning when both routes are
+    /// present. Partial AUCs, the JSON, report and other population-level files are not
+    /// written.
+    pub fn analyze_population_streaming<I, P>(
+        subjects: I,
+        config: &AnalysisConfig,
+        output_path: P,
+    ) -> Result<StreamingResults>
+    where
+        I: IntoIterator<Item = Subject>,
+        P: AsRef<Path>,
+    {
+        config.validate()?;
+        Self::check_streaming_options(config)?;
+        Self::warn_primary_method_not_configured(config);
+
+        let mut output = StreamingOutput::create(output_path.as_ref(), config)?;
+        let mut accumulators: HashMap<&str, OnlineStats> = HashMap::new();
+        let mut summary_values: HashMap<&str, Vec<f64>> = HashMap::new();
+        let mut failed_subjects = Vec::new();
+        let mut concentration_flags = Vec::new();
+        let mut dose_flags = Vec::new();
+        let mut clearance_checks = Vec::new();
+        let mut routes_seen = (false, false);
+        let mut subjects_analyzed = 0;
+        let mut auc_inf_excluded = 0;
+
+        let mut subjects = subjects.into_iter();
+        let mut batch = Vec::with_capacity(STREAMING_BATCH_SIZE);
+        loop {
+            batch.clear();
+            batch.extend(subjects.by_ref().take(STREAMING_BATCH_SIZE));
+            if batch.is_empty() {
+                break;
+            }
+
+            let outcomes = Self::analyze_subjects(&batch, config, None);
+
+            for (subject, outcome) in batch.iter().zip(outcomes) {
+                concentration_flags.extend(NcaAnalyzer::check_concentration_bounds(subject, &config.validation_thresholds));
+                dose_flags.extend(NcaAnalyzer::check_dosing(subject, &config.validation_thresholds));
+                match outcome {
+                    Ok((result, warnings)) => {
+                        Self::log_subject_warnings(&result.subject_id, &warnings);
+                        if result.individual_parameters.apparent_clearance {
+                            routes_seen.1 = true;
+                        } else {
+                            routes_seen.0 = true;
+                        }
+                        clearance_checks.extend(NcaAnalyzer::cross_check_clearance(&result, &config.validation_thresholds));
+                        let weight = if config.weighted_summary { Self::subject_weight(subject, config) } else { 1.0 };
+                        if Self::auc_inf_excluded(&result, config) {
+                            auc_inf_excluded += 1;
+                        }
+                        for &param_name in SUMMARY_PARAMETERS {
+                            if let Some(value) = Self::summary_value(&result, param_name, config) {
+                                accumulators
+                                    .entry(param_name)
+                                    .or_insert_with(|| OnlineStats::with_geometric(config.geometric_non_positive.clone()))
+                                    .push(value, weight);
+                                summary_values.entry(param_name).or_default().push(value);
+                            }
+                        }
+                        output.write_result(&result)?;
+                        subjects_analyzed += 1;
+                    }
+                    Err(failed_analysis) => failed_subjects.push(failed_analysis),
+                }
+            }
+        }
+
+        if routes_seen == (true, true) {
+            log::warn!("Streaming output does not compare extravascular half-lives with the intravascular arm for flip-flop kinetics");
+        }
+
+        let summary_statistics = SummaryStatistics {
+            parameter_stats: accumulators
+                .into_iter()
+                .map(|(name, acc)| {
+                    let mut stats = acc.to_parameter_stats();
+                    Self::fill_order_statistics(&mut stats, summary_values.remove(name).unwrap_or_default());
+                    (name.to_string(), stats)
+                })
+                .collect(),
+            auc_inf_excluded,
+        };
+        output.finish(config, &summary_statistics, &failed_subjects, &concentration_flags, &dose_flags, &clearance_checks)?;
+
+        log::info!("Streamed {} subjects", subjects_analyzed);
+        Ok(StreamingResults {
+            subjects_analyzed,
+            failed_subjects,
+            summary_statistics,
+        })
+    }
+
+    /// Warn when the primary AUC method is missing from `auc_methods` (see
+    /// [`AnalysisConfig::ensure_primary_auc_method`])
+    fn warn_primary_method_not_configured(config: &AnalysisConfig) {
+        let primary_method = AucCalculator::primary_method(config);
+        if !config.auc_methods.contains(&primary_method) {
+            log::warn!(
+                "Primary AUC method {} is not among the configured AUC methods and is missing from the method comparison",
+                primary_method.key()
+            );
+        }
+    }
+
+    /// Reject the options [`Self::analyze_population_streaming`] cannot honour
+    fn check_streaming_options(config: &AnalysisConfig) -> Result<()> {
+        let unsupported: Vec<&str> = [
+            (config.uses_pooled_lambda_z(), "pooled lambda_z"),
+            (config.stratification.is_some(), "stratification"),
+            (config.perform_covariate_analysis, "covariate analysis"),
+            (config.dose_normalization, "dose normalization"),
+            (config.data_completeness, "data completeness"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(NcaError::ParseError(format!(
+                "Streaming output does not support {}; run without streaming", unsupported.join(", ")
+            )))
+        }
+    }
+
+    /// Median, quartiles, distribution and harmonic pseudo-SD of streamed summary values,
+    /// computed as in the batch statistics
+    fn fill_order_statistics(stats: &mut ParameterStats, mut values: Vec<f64>) {
+        values.retain(|v| !v.is_nan());
+        if values.is_empty() {
+            return;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        stats.median = Self::quantile(&values, 0.5);
+        stats.q25 = Self::quantile(&values, 0.25);
+        stats.q75 = Self::quantile(&values, 0.75);
+        stats.harmonic_pseudo_sd = Self::calculate_harmonic_mean(&values).1;
+        // The batch distribution summary is unweighted
+        let mean = (&values).mean();
+        let std = if values.len() > 1 { (&values).std_dev() } else { 0.0 };
+        stats.distribution = Self::calculate_distribution(&values, mean, if std.is_finite() { std } else { 0.0 });
+    }
+
+    /// Overall data-quality indicators and their composite score for the analysis report
+    pub fn assess_data_quality(
+        subjects: &[Subject],
+        results: &[NcaResults],
+        failed: usize,
+        config: &AnalysisConfig,
+    ) -> DataQualitySummary {
+        let percent = |part: usize, whole: usize| safe_div(100.0 * part as f64, whole as f64).unwrap_or(0.0);
+
+        let subjects_total = results.len() + failed;
+        let percent_analyzed = percent(results.len(), subjects_total);
+        let reliable = results
+            .iter()
+            .filter(|r| r.individual_parameters.auc_inf_reliable == Some(true))
+            .count();
+        let percent_reliable_auc_inf = percent(reliable, results.len());
+
+        let mut r_squared: Vec<f64> = results
+            .iter()
+            .filter_map(|r| r.individual_parameters.lambda_z_r_squared)
+            .collect();
+        r_squared.sort_by(|a, b| a.total_cmp(b));
+        let median_lambda_z_r_squared = (!r_squared.is_empty()).then(|| Self::quantile(&r_squared, 0.5));
+
+        let (observations, blq) = subjects
+            .iter()
+            .flat_map(|subject| subject.observations.iter())
+            .filter(|obs| NcaAnalyzer::is_analyte_observation(obs, config))
+            .fold((0, 0), |(total, blq), obs| (total + 1, blq + usize::from(obs.bloq || obs.blod)));
+        let percent_blq = percent(blq, observations);
+
+        let score = (percent_analyzed
+            + percent_reliable_auc_inf
+            + 100.0 * median_lambda_z_r_squared.unwrap_or(0.0)
+            + (100.0 - percent_blq))
+            / 4.0;
+
+        DataQualitySummary {
+            subjects_total,
+            percent_analyzed,
+            percent_reliable_auc_inf,
+            median_lambda_z_r_squared,
+            percent_blq,
+            score,
+        }
+    }
+
+    /// Count planned, observed, quantifiable and BLQ samples for every subject, successful or not
+    ///
+    /// Only analyte observations (see [`NcaAnalyzer::is_analyte_observation`]) are counted.
+    /// The planned number of timepoints is taken as the largest number of distinct sampling
+    /// times of any subject, since the dataset carries no protocol schedule.
+    pub fn assess_data_completeness(
+        subjects: &[Subject],
+        results: &[NcaResults],
+        config: &AnalysisConfig,
+    ) -> Vec<DataCompleteness> {
+        let estimable: HashMap<&str, bool> = results
+            .iter()
+            .map(|r| (r.subject_id.as_str(), r.individual_parameters.lambda_z.is_some()))
+            .collect();
+
+        let mut completeness: Vec<DataCompleteness> = subjects
+            .iter()
+            .map(|subject| {
+                let observations: Vec<&Observation> = subject
+                    .observations
+                    .iter()
+                    .filter(|obs| NcaAnalyzer::is_analyte_observation(obs, config))
+                    .collect();
+
+                let mut times: Vec<f64> = observations.iter().map(|obs| obs.time).collect();
+                times.sort_by(|a, b| a.total_cmp(b));
+                times.dedup();
+
+                let quantifiable_times: Vec<f64> = observations
+                    .iter()
+                    .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
+                    .map(|obs| obs.time)
+                    .collect();
+
+                DataCompleteness {
+                    subject_id: subject.id.clone(),
+                    planned_timepoints: 0,
+                    observed_timepoints: times.len(),
+                    quantifiable: quantifiable_times.len(),
+                    blq: observations.iter().filter(|obs| obs.bloq).count(),
+                    first_quantifiable_time: quantifiable_times.iter().copied().reduce(f64::min),
+                    last_quantifiable_time: quantifiable_times.iter().copied().reduce(f64::max),
+                    terminal_phase_estimable: estimable.get(subject.id.as_str()).copied().unwrap_or(false),
+                }
+            })
+            .collect();
+
+        let planned = completeness.iter().map(|c| c.observed_timepoints).max().unwrap_or(0);
+        for entry in &mut completeness {
+            entry.planned_timepoints = planned;
+        }
+        completeness.sort_by(|a, b| a.subject_id.cmp(&b.subject_id));
+        completeness
+    }
+
+    /// Analysis weight of each subject: `config.subject_weights`, else the WEIGHT/ANALYSIS_WT
+    /// column, else 1
+    pub fn subject_weights(subjects: &[Subject], config: &AnalysisConfig) -> HashMap<String, f64> {
+        subjects
+            .iter()
+            .map(|s| (s.id.clone(), Self::subject_weight(s, config)))
+            .collect()
+    }
+
+    fn subject_weight(subject: &Subject, config: &AnalysisConfig) -> f64 {
+        config.subject_weights.get(&subject.id).copied()
+            .or(subject.demographics.analysis_weight)
+            .unwrap_or(1.0)
+    }
+
+    fn calculate_summary_statistics(
+        results: &[NcaResults],
+        weights: Option<&HashMap<String, f64>>,
+        config: &AnalysisConfig,
+    ) -> Result<SummaryStatistics> {
+        let mut parameter_stats = HashMap::new();
+
+        for &param_name in SUMMARY_PARAMETERS {
+            let (values, subject_weights): (Vec<f64>, Vec<f64>) = results
+                .iter()
+                .filter_map(|r| {
+                    let value = Self::summary_value(r, param_name, config)?;
+                    let weight = weights.and_then(|w| w.get(&r.subject_id).copied()).unwrap_or(1.0);
+                    Some((value, weight))
+                })
+                .unzip();
 
             if !values.is_empty() {
-                let stats = Self::calculate_parameter_stats(&values);
+                let stats = if weights.is_some() {
+                    Self::calculate_weighted_parameter_stats_with_geometric(&values, &subject_weights, &config.geometric_non_positive)
+                } else {
+                    Self::calculate_parameter_stats_with_geometric(&values, &config.geometric_non_positive)
+                };
                 parameter_stats.insert(param_name.to_string(), stats);
             }
         }
 
-        Ok(SummaryStatistics { parameter_stats })
+        let auc_inf_excluded = results.iter().filter(|r| Self::auc_inf_excluded(r, config)).count();
+        if auc_inf_excluded > 0 {
+            log::info!("{} unreliable AUC_inf values excluded from the summary", auc_inf_excluded);
+        }
+
+        Ok(SummaryStatistics { parameter_stats, auc_inf_excluded })
+    }
+
+    /// True when `exclude_unreliable_auc_inf` leaves this subject's AUC_inf out of the summary
+    fn auc_inf_excluded(result: &NcaResults, config: &AnalysisConfig) -> bool {
+        config.exclude_unreliable_auc_inf
+            && result.individual_parameters.auc_inf.is_some()
+            && result.individual_parameters.auc_inf_reliable == Some(false)
+    }
+
+    /// A subject's value of a summary parameter, unless the summary excludes it
+    fn summary_value(result: &NcaResults, param_name: &str, config: &AnalysisConfig) -> Option<f64> {
+        if param_name == "auc_inf" && Self::auc_inf_excluded(result, config) {
+            return None;
+        }
+        result.individual_parameters.get(param_name)
+    }
+
+    /// Divide each subject's dose-proportional parameters (AUCs, Cmax) by its dose
+    ///
+    /// AUCtau is divided by the dose starting its dosing interval
+    /// ([`NcaAnalyzer::dosing_interval`]), the other parameters by the total dose. Mixed-route
+    /// subjects use the systemic dose ([`DosingSummary::effective_dose`]). Subjects of a
+    /// placebo arm (`treatment_roles`), without a positive dose, or with mixed routes but no
+    /// `extravascular_bioavailability` are left out, and returned as exclusions.
+    pub fn dose_normalize(
+        results: &[NcaResults],
+        subjects: &[Subject],
+        config: &AnalysisConfig,
+    ) -> (Vec<DoseNormalizedParameters>, Vec<DoseNormalizationExclusion>) {
+        let mut exclusions = Vec::new();
+        let normalized = results
+            .iter()
+            .filter_map(|r| {
+                let subject = subjects.iter().find(|s| s.id == r.subject_id && s.analyte == r.analyte)?;
+                let treatment = CovariateAnalyzer::treatment(subject);
+                let mixed_routes = r.dosing.mixed_routes;
+                let dose = Self::systemic_dose(&subject.dosing_events, mixed_routes, config);
+                let reason = if config.treatment_roles.get(&treatment) == Some(&TreatmentRole::Placebo) {
+                    Some("Placebo treatment")
+                } else if dose.is_none() && mixed_routes && config.extravascular_bioavailability.is_none() {
+                    Some("Mixed routes without extravascular bioavailability")
+                } else if dose.is_none() {
+                    Some("Zero total dose")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    exclusions.push(DoseNormalizationExclusion {
+                        subject_id: r.subject_id.clone(),
+                        treatment,
+                        reason: reason.to_string(),
+                    });
+                    return None;
+                }
+                let dose = dose?;
+                let interval_dose = NcaAnalyzer::dosing_interval(subject, config).and_then(|(dose_time, _)| {
+                    let starting: Vec<DosingEvent> = subject.dosing_events
+                        .iter()
+                        .filter(|event| event.time == dose_time)
+                        .cloned()
+                        .collect();
+                    Self::systemic_dose(&starting, mixed_routes, config)
+                });
+                let values = DOSE_PROPORTIONAL_PARAMETERS
+                    .iter()
+                    .filter_map(|&name| {
+                        let divisor = if name == "auc_tau" { interval_dose? } else { dose };
+                        let value = safe_div(r.individual_parameters.get(name)?, divisor)?;
+                        Some((name.to_string(), value))
+                    })
+                    .collect();
+
+                Some(DoseNormalizedParameters {
+                    subject_id: r.subject_id.clone(),
+                    dose,
+                    interval_dose,
+                    values,
+                })
+            })
+            .collect();
+
+        if !exclusions.is_empty() {
+            log::info!("{} subjects excluded from dose normalization (placebo, zero dose or unknown systemic dose)", exclusions.len());
+        }
+        (normalized, exclusions)
+    }
+
+    /// Sum of `doses`, extravascular ones times `extravascular_bioavailability` for mixed
+    /// routes; `None` when it is not positive or the bioavailability is needed but missing
+    fn systemic_dose(doses: &[DosingEvent], mixed_routes: bool, config: &AnalysisConfig) -> Option<f64> {
+        doses
+            .iter()
+            .map(|dose| match (mixed_routes && dose.route.is_extravascular(), config.extravascular_bioavailability) {
+                (false, _) => Some(dose.dose),
+                (true, bioavailability) => bioavailability.map(|f| f * dose.dose),
+            })
+            .sum::<Option<f64>>()
+            .filter(|&dose| dose > 0.0)
     }
 
-    fn calculate_parameter_stats(values: &[f64]) -> ParameterStats {
+    /// Summary statistics of the dose-normalized parameters from [`Self::dose_normalize`]
+    pub fn calculate_dose_normalized_summary(
+        normalized: &[DoseNormalizedParameters],
+        geometric: &GeometricNonPositive,
+    ) -> SummaryStatistics {
+        let mut parameter_stats = HashMap::new();
+        for &param_name in DOSE_PROPORTIONAL_PARAMETERS {
+            let values: Vec<f64> = normalized
+                .iter()
+                .filter_map(|n| n.values.get(param_name).copied())
+                .collect();
+
+            if !values.is_empty() {
+                parameter_stats.insert(param_name.to_string(), Self::calculate_parameter_stats_with_geometric(&values, geometric));
+            }
+        }
+
+        SummaryStatistics { parameter_stats, auc_inf_excluded: 0 }
+    }
+
+    /// Calculate descriptive statistics for a single parameter
+    ///
+    /// Median, Q25 and Q75 are type-7 quantiles (see [`Self::quantile`]), and min/max are the
+    /// extremes of the same sorted array. SD is the sample (n - 1) standard deviation. A single
+    /// value is its own mean, median, min, max and geometric mean, with SD, CV and geometric CV
+    /// `None` rather than a misleading zero. NaN values are left out (and logged). Geometric
+    /// statistics exclude non-positive values ([`GeometricNonPositive::Exclude`]).
+    pub fn calculate_parameter_stats(values: &[f64]) -> ParameterStats {
+        Self::calculate_parameter_stats_with_geometric(values, &GeometricNonPositive::default())
+    }
+
+    /// [`Self::calculate_parameter_stats`] with an explicit treatment of non-positive values
+    /// in the geometric statistics
+    pub fn calculate_parameter_stats_with_geometric(values: &[f64], geometric: &GeometricNonPositive) -> ParameterStats {
+        let n_nan = values.iter().filter(|v| v.is_nan()).count();
+        if n_nan > 0 {
+            log::warn!("{} NaN value(s) left out of the summary statistics", n_nan);
+        }
+        let values: Vec<f64> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+        let values = values.as_slice();
         let n = values.len();
         
         if n == 0 {
@@ -139,10 +837,10 @@ impl PopulationAnalyzer {
                 n: 0,
                 mean: 0.0,
                 arithmetic_mean: 0.0,
-                arithmetic_std: 0.0,
-                arithmetic_cv_percent: 0.0,
-                std: 0.0,
-                cv_percent: 0.0,
+                arithmetic_std: None,
+                arithmetic_cv_percent: None,
+                std: None,
+                cv_percent: None,
                 median: 0.0,
                 q25: 0.0,
                 q75: 0.0,
@@ -150,41 +848,42 @@ impl PopulationAnalyzer {
                 max: 0.0,
                 geometric_mean: None,
                 geometric_cv_percent: None,
+                geometric_n: 0,
+                harmonic_mean: None,
+                harmonic_pseudo_sd: None,
+                distribution: DistributionSummary::default(),
             };
         }
 
         let mean = values.mean();
-        let std = values.std_dev();
-        let cv_percent = if mean != 0.0 { (std / mean) * 100.0 } else { 0.0 };
+        let std = if n > 1 { Some(values.std_dev()).filter(|sd| sd.is_finite()) } else { None };
+        let cv_percent = std.and_then(|sd| safe_div(sd, mean)).map(|cv| cv * 100.0);
 
+        // Order statistics all come from this single sorted copy
         let mut sorted_values = values.to_vec();
-        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        sorted_values.sort_by(|a, b| a.total_cmp(b));
 
-        let median = if n % 2 == 0 {
-            (sorted_values[n / 2 - 1] + sorted_values[n / 2]) / 2.0
-        } else {
-            sorted_values[n / 2]
-        };
-
-        let q25_idx = ((n as f64 * 0.25) as usize).min(n - 1);
-        let q75_idx = ((n as f64 * 0.75) as usize).min(n - 1);
-        let q25 = sorted_values[q25_idx];
-        let q75 = sorted_values[q75_idx];
+        let median = Self::quantile(&sorted_values, 0.5);
+        let q25 = Self::quantile(&sorted_values, 0.25);
+        let q75 = Self::quantile(&sorted_values, 0.75);
 
         let min = sorted_values[0];
         let max = sorted_values[n - 1];
 
-        // Geometric statistics (for positive values only)
-        let (geometric_mean, geometric_cv_percent) = if values.iter().all(|&v| v > 0.0) {
-            let ln_values: Vec<f64> = values.iter().map(|v| v.ln()).collect();
-            let ln_mean = (&ln_values).mean();
-            let ln_std = (&ln_values).std_dev();
-            let geo_mean = ln_mean.exp();
-            let geo_cv = ((ln_std.exp().powi(2) - 1.0).sqrt()) * 100.0;
-            (Some(geo_mean), Some(geo_cv))
-        } else {
-            (None, None)
+        // Geometric statistics on the (offset) positive values
+        let geometric_inputs = Self::geometric_inputs(values, geometric);
+        let geometric_n = geometric_inputs.as_ref().map_or(0, Vec::len);
+        let (geometric_mean, geometric_cv_percent) = match &geometric_inputs {
+            Some(inputs) => {
+                let ln_values: Vec<f64> = inputs.iter().map(|(_, v)| v.ln()).collect();
+                let ln_mean = (&ln_values).mean();
+                let geo_mean = ln_mean.exp() - geometric.offset();
+                let geo_cv = (geometric_n > 1).then(|| ((ln_values.std_dev().powi(2).exp() - 1.0).sqrt()) * 100.0);
+                (Some(geo_mean), geo_cv)
+            }
+            None => (None, None),
         };
+        let (harmonic_mean, harmonic_pseudo_sd) = Self::calculate_harmonic_mean(values);
 
         ParameterStats {
             n,
@@ -201,10 +900,206 @@ impl PopulationAnalyzer {
             max,
             geometric_mean,
             geometric_cv_percent,
+            geometric_n,
+            harmonic_mean,
+            harmonic_pseudo_sd,
+            distribution: Self::calculate_distribution(&sorted_values, mean, std.unwrap_or(0.0)),
+        }
+    }
+
+    /// Values entering the geometric statistics, offset and paired with their index in
+    /// `values`; `None` when there are none, or when `geometric` is
+    /// [`GeometricNonPositive::Undefined`] and any value is non-positive
+    fn geometric_inputs(values: &[f64], geometric: &GeometricNonPositive) -> Option<Vec<(usize, f64)>> {
+        if *geometric == GeometricNonPositive::Undefined && values.iter().any(|&v| v <= 0.0) {
+            return None;
+        }
+        let offset = geometric.offset();
+        let inputs: Vec<(usize, f64)> = values
+            .iter()
+            .map(|v| v + offset)
+            .enumerate()
+            .filter(|(_, v)| *v > 0.0)
+            .collect();
+        Some(inputs).filter(|inputs| !inputs.is_empty())
+    }
+
+    /// Harmonic mean n / sum(1/x) and its jackknife pseudo-SD
+    ///
+    /// The pseudo-SD is sqrt((n - 1) * sum (H_i - H_bar)^2), where H_i is the harmonic mean
+    /// with value i left out and H_bar the mean of the H_i (Lam et al., 1985). Both are `None`
+    /// unless every value is positive; the pseudo-SD also needs n >= 2.
+    pub fn calculate_harmonic_mean(values: &[f64]) -> (Option<f64>, Option<f64>) {
+        if values.is_empty() || values.iter().any(|&v| v <= 0.0) {
+            return (None, None);
+        }
+
+        let n = values.len() as f64;
+        let reciprocal_sum: f64 = values.iter().map(|v| 1.0 / v).sum();
+        let harmonic_mean = safe_div(n, reciprocal_sum);
+        if values.len() < 2 {
+            return (harmonic_mean, None);
+        }
+
+        let leave_one_out: Vec<f64> = values
+            .iter()
+            .filter_map(|v| safe_div(n - 1.0, reciprocal_sum - 1.0 / v))
+            .collect();
+        if leave_one_out.len() != values.len() {
+            return (harmonic_mean, None);
+        }
+        let jackknife_mean = leave_one_out.iter().sum::<f64>() / n;
+        let squared_deviations: f64 = leave_one_out.iter().map(|h| (h - jackknife_mean).powi(2)).sum();
+        (harmonic_mean, Some(((n - 1.0) * squared_deviations).sqrt()))
+    }
+
+    /// Descriptive statistics with a weight per value
+    ///
+    /// Mean, SD, CV and the geometric mean/CV are weighted; SD uses reliability weights,
+    /// sqrt(sum w(x - m)^2 / (V1 - V2 / V1)) with V1 = sum w and V2 = sum w^2, so equal weights
+    /// give exactly the unweighted sample SD. Order statistics and the distribution summary stay
+    /// unweighted. Weights that are mismatched in length, negative, non-finite or all zero fall
+    /// back to the unweighted statistics.
+    pub fn calculate_weighted_parameter_stats(values: &[f64], weights: &[f64]) -> ParameterStats {
+        Self::calculate_weighted_parameter_stats_with_geometric(values, weights, &GeometricNonPositive::default())
+    }
+
+    /// [`Self::calculate_weighted_parameter_stats`] with an explicit treatment of non-positive
+    /// values in the geometric statistics
+    pub fn calculate_weighted_parameter_stats_with_geometric(
+        values: &[f64],
+        weights: &[f64],
+        geometric: &GeometricNonPositive,
+    ) -> ParameterStats {
+        let mut stats = Self::calculate_parameter_stats_with_geometric(values, geometric);
+
+        let v1: f64 = weights.iter().sum();
+        if weights.len() != values.len()
+            || weights.iter().any(|w| !w.is_finite() || *w < 0.0)
+            || v1 <= 0.0
+        {
+            log::warn!("Invalid analysis weights; reporting unweighted statistics");
+            return stats;
+        }
+
+        let weighted_moments = |xs: &[f64], ws: &[f64]| -> Option<(f64, Option<f64>)> {
+            let v1: f64 = ws.iter().sum();
+            let v2: f64 = ws.iter().map(|w| w * w).sum();
+            let mean = safe_div(xs.iter().zip(ws).map(|(x, w)| w * x).sum::<f64>(), v1)?;
+            let ss = xs.iter().zip(ws).map(|(x, w)| w * (x - mean).powi(2)).sum::<f64>();
+            let std = safe_div(ss, v1 - v2 / v1).map(f64::sqrt).filter(|_| xs.len() > 1);
+            Some((mean, std))
+        };
+
+        let Some((mean, std)) = weighted_moments(values, weights) else {
+            return stats;
+        };
+        let cv_percent = std.and_then(|sd| safe_div(sd, mean)).map(|cv| cv * 100.0);
+        stats.mean = mean;
+        stats.arithmetic_mean = mean;
+        stats.std = std;
+        stats.arithmetic_std = std;
+        stats.cv_percent = cv_percent;
+        stats.arithmetic_cv_percent = cv_percent;
+
+        if let Some(inputs) = Self::geometric_inputs(values, geometric) {
+            let ln_values: Vec<f64> = inputs.iter().map(|(_, v)| v.ln()).collect();
+            let ln_weights: Vec<f64> = inputs.iter().map(|&(idx, _)| weights[idx]).collect();
+            if let Some((ln_mean, ln_std)) = weighted_moments(&ln_values, &ln_weights) {
+                stats.geometric_mean = Some(ln_mean.exp() - geometric.offset());
+                stats.geometric_cv_percent = ln_std.map(|sd| ((sd.powi(2).exp() - 1.0).sqrt()) * 100.0);
+            }
+        }
+
+        stats
+    }
+
+    /// Skewness, excess kurtosis and a histogram of an ascending-sorted slice
+    ///
+    /// Skewness and kurtosis are the bias-adjusted sample estimators G1 and G2 (as reported by
+    /// SAS and Excel), so a normal sample gives values near zero. The histogram uses Sturges'
+    /// rule, ceil(log2(n)) + 1 equal-width bins spanning min to max; a constant sample gets a
+    /// single bin.
+    pub fn calculate_distribution(sorted_values: &[f64], mean: f64, std: f64) -> DistributionSummary {
+        let n = sorted_values.len();
+        if n == 0 {
+            return DistributionSummary::default();
+        }
+
+        let nf = n as f64;
+        let standardized_moment = |power: i32| -> Option<f64> {
+            let sum = sorted_values
+                .iter()
+                .map(|v| safe_div(v - mean, std).map(|z| z.powi(power)))
+                .sum::<Option<f64>>()?;
+            sum.is_finite().then_some(sum)
+        };
+
+        let skewness = if n >= 3 {
+            standardized_moment(3).and_then(|m3| safe_div(nf * m3, (nf - 1.0) * (nf - 2.0)))
+        } else {
+            None
+        };
+
+        let excess_kurtosis = if n >= 4 {
+            standardized_moment(4).and_then(|m4| {
+                let scale = safe_div(nf * (nf + 1.0), (nf - 1.0) * (nf - 2.0) * (nf - 3.0))?;
+                let correction = safe_div(3.0 * (nf - 1.0).powi(2), (nf - 2.0) * (nf - 3.0))?;
+                Some(scale * m4 - correction)
+            })
+        } else {
+            None
+        };
+
+        let min = sorted_values[0];
+        let max = sorted_values[n - 1];
+        let n_bins = if max > min { (nf.log2().ceil() as usize) + 1 } else { 1 };
+        let width = (max - min) / n_bins as f64;
+
+        let bin_edges: Vec<f64> = (0..=n_bins)
+            .map(|i| if i == n_bins { max } else { min + width * i as f64 })
+            .collect();
+        let mut bin_counts = vec![0; n_bins];
+        for &value in sorted_values {
+            let bin = safe_div(value - min, width).map_or(0, |b| b.floor() as usize);
+            bin_counts[bin.min(n_bins - 1)] += 1;
+        }
+
+        DistributionSummary {
+            skewness,
+            excess_kurtosis,
+            bin_edges,
+            bin_counts,
         }
     }
 
-    fn perform_method_comparison(results: &[NcaResults]) -> Result<MethodComparison> {
+    /// Sample quantile of an ascending-sorted slice using linear interpolation between order
+    /// statistics (Hyndman-Fan type 7)
+    ///
+    /// With h = (n - 1) * p, the quantile is x[floor(h)] + (h - floor(h)) * (x[floor(h) + 1] - x[floor(h)])
+    /// on zero-based indices. For p = 0.5 this is the usual median, including the average of
+    /// the two middle values when n is even, so median and quartiles share one definition.
+    ///
+    /// This is R's default `quantile(x, p, type = 7)` and Excel's PERCENTILE.INC/QUARTILE.INC.
+    /// The rule is deterministic: when h is a whole number the order statistic x[h] is returned
+    /// as is, otherwise the two neighbours are interpolated, and tied values need no tie-break
+    /// since only their positions in the sorted slice matter. For n = 3 the quartiles are
+    /// midway between x[0] and x[1] and between x[1] and x[2]; for n = 5 they are exactly x[1]
+    /// and x[3]. `p` is clamped to [0, 1], a single value is every quantile, and an empty
+    /// slice gives 0.
+    pub fn quantile(sorted_values: &[f64], p: f64) -> f64 {
+        let n = sorted_values.len();
+        if n == 0 {
+            return 0.0;
+        }
+
+        let h = (n - 1) as f64 * p.clamp(0.0, 1.0);
+        let lower = h.floor() as usize;
+        let upper = (lower + 1).min(n - 1);
+        sorted_values[lower] + (h - lower as f64) * (sorted_values[upper] - sorted_values[lower])
+    }
+
+    fn perform_method_comparison(results: &[NcaResults], config: &AnalysisConfig) -> Result<MethodComparison> {
         let mut auc_methods = HashMap::new();
         let correlation_matrix = HashMap::new();
         let bias_analysis = HashMap::new();
@@ -215,7 +1110,7 @@ impl PopulationAnalyzer {
         for result in results {
             for (method, params) in &result.method_comparisons {
                 if let Some(auc) = params.auc_last {
-                    method_values.entry(method.clone()).or_insert_with(Vec::new).push(auc);
+                    method_values.entry(method.clone()).or_default().push(auc);
                 }
             }
         }
@@ -227,19 +1122,34 @@ impl PopulationAnalyzer {
             }
         }
 
+        // Percent difference of each method's mean from the primary method's mean
+        let primary_method = format!("{:?}", AucCalculator::primary_method(config));
+        let percent_difference_from_primary = match auc_methods.get(&primary_method) {
+            Some(&primary_mean) => auc_methods
+                .iter()
+                .filter_map(|(method, &mean)| {
+                    let difference = safe_div(mean - primary_mean, primary_mean)?;
+                    Some((method.clone(), difference * 100.0))
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+
         // For now, return simplified method comparison
         // Full correlation and bias analysis would require additional implementation
         Ok(MethodComparison {
             auc_methods,
+            primary_method,
+            percent_difference_from_primary,
             correlation_matrix,
             bias_analysis,
         })
     }
 
     #[allow(dead_code)]
-    fn calculate_correlation(values1: &[f64], values2: &[f64]) -> f64 {
+    fn calculate_correlation(values1: &[f64], values2: &[f64]) -> Option<f64> {
         if values1.len() != values2.len() || values1.len() < 2 {
-            return 0.0;
+            return None;
         }
 
         let mean1 = values1.mean();
@@ -254,13 +1164,7 @@ impl PopulationAnalyzer {
         let sum_sq1: f64 = values1.iter().map(|x| (x - mean1).powi(2)).sum();
         let sum_sq2: f64 = values2.iter().map(|y| (y - mean2).powi(2)).sum();
 
-        let denominator = (sum_sq1 * sum_sq2).sqrt();
-
-        if denominator == 0.0 {
-            0.0
-        } else {
-            numerator / denominator
-        }
+        safe_div(numerator, (sum_sq1 * sum_sq2).sqrt())
     }
 
     #[allow(dead_code)]
@@ -282,14 +1186,7 @@ impl PopulationAnalyzer {
         let percent_differences: Vec<f64> = values1
             .iter()
             .zip(values2.iter())
-            .map(|(x, y)| {
-                let mean_val = (x + y) / 2.0;
-                if mean_val != 0.0 {
-                    ((x - y) / mean_val) * 100.0
-                } else {
-                    0.0
-                }
-            })
+            .filter_map(|(x, y)| safe_div(x - y, (x + y) / 2.0).map(|diff| diff * 100.0))
             .collect();
 
         let mean_diff = (&differences).mean();
@@ -306,4 +1203,134 @@ impl PopulationAnalyzer {
             limits_of_agreement: (lower_limit, upper_limit),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Weighted mean and sum of squared deviations, updated one value at a time
+///
+/// West's weighted form of Welford's algorithm; with all weights 1 it is the classic
+/// Welford recurrence.
+#[derive(Debug, Clone, Default)]
+struct WelfordAccumulator {
+    sum_weights: f64,
+    sum_squared_weights: f64,
+    mean: f64,
+    sum_squared_deviations: f64,
+}
+
+impl WelfordAccumulator {
+    fn push(&mut self, value: f64, weight: f64) {
+        self.sum_weights += weight;
+        self.sum_squared_weights += weight * weight;
+        let previous_mean = self.mean;
+        self.mean += safe_div(weight, self.sum_weights).unwrap_or(0.0) * (value - previous_mean);
+        self.sum_squared_deviations += weight * (value - previous_mean) * (value - self.mean);
+    }
+
+    /// Reliability-weighted SD, matching [`PopulationAnalyzer::calculate_weighted_parameter_stats`]
+    /// (the sample SD for equal weights); `None` for fewer than two values
+    fn std_dev(&self) -> Option<f64> {
+        let denominator = self.sum_weights - safe_div(self.sum_squared_weights, self.sum_weights).unwrap_or(0.0);
+        safe_div(self.sum_squared_deviations, denominator).map(f64::sqrt)
+    }
+}
+
+/// Single-pass accumulator for the moment statistics of [`ParameterStats`]
+///
+/// Tracks the count, min and max, the (weighted) mean and variance of the values, and the
+/// same on ln(value) of the values entering the geometric statistics (see
+/// [`GeometricNonPositive`]); the harmonic mean is unweighted, as in the batch statistics. Quantiles and the jackknife
+/// harmonic pseudo-SD need the whole sample and are left NaN and `None`.
+#[derive(Debug, Clone)]
+pub struct OnlineStats {
+    n: usize,
+    min: f64,
+    max: f64,
+    moments: WelfordAccumulator,
+    log_moments: WelfordAccumulator,
+    geometric: GeometricNonPositive,
+    geometric_n: usize,
+    reciprocal_sum: f64,
+    all_positive: bool,
+}
+
+impl Default for OnlineStats {
+    fn default() -> Self {
+        Self {
+            n: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            moments: WelfordAccumulator::default(),
+            log_moments: WelfordAccumulator::default(),
+            geometric: GeometricNonPositive::default(),
+            geometric_n: 0,
+            reciprocal_sum: 0.0,
+            all_positive: true,
+        }
+    }
+}
+
+impl OnlineStats {
+    /// Accumulator treating non-positive values in the geometric statistics as `geometric` says
+    pub fn with_geometric(geometric: GeometricNonPositive) -> Self {
+        Self { geometric, ..Self::default() }
+    }
+
+    pub fn push(&mut self, value: f64, weight: f64) {
+        self.n += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.moments.push(value, weight);
+        let shifted = value + self.geometric.offset();
+        if shifted > 0.0 {
+            self.log_moments.push(shifted.ln(), weight);
+            self.geometric_n += 1;
+        }
+        if value > 0.0 {
+            self.reciprocal_sum += 1.0 / value;
+        } else {
+            self.all_positive = false;
+        }
+    }
+
+    pub fn to_parameter_stats(&self) -> ParameterStats {
+        if self.n == 0 {
+            return PopulationAnalyzer::calculate_parameter_stats(&[]);
+        }
+
+        let mean = self.moments.mean;
+        let std = self.moments.std_dev();
+        let cv_percent = std.and_then(|sd| safe_div(sd, mean)).map(|cv| cv * 100.0);
+        let geometric_defined = self.geometric_n > 0
+            && (self.all_positive || self.geometric != GeometricNonPositive::Undefined);
+        let (geometric_mean, geometric_cv_percent) = if geometric_defined {
+            let ln_std = self.log_moments.std_dev();
+            (
+                Some(self.log_moments.mean.exp() - self.geometric.offset()),
+                ln_std.map(|sd| ((sd.powi(2).exp() - 1.0).sqrt()) * 100.0),
+            )
+        } else {
+            (None, None)
+        };
+
+        ParameterStats {
+            n: self.n,
+            mean,
+            arithmetic_mean: mean,
+            arithmetic_std: std,
+            arithmetic_cv_percent: cv_percent,
+            std,
+            cv_percent,
+            median: f64::NAN,
+            q25: f64::NAN,
+            q75: f64::NAN,
+            min: self.min,
+            max: self.max,
+            geometric_mean,
+            geometric_cv_percent,
+            geometric_n: if geometric_defined { self.geometric_n } else { 0 },
+            harmonic_mean: safe_div(self.n as f64, self.reciprocal_sum).filter(|_| self.all_positive),
+            harmonic_pseudo_sd: None,
+            distribution: DistributionSummary::default(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{models::*, math::safe_div};
+use statrs::statistics::Statistics;
+
+pub struct Preprocessor;
+
+impl Preprocessor {
+    /// Collapse replicate assays (same time and compartment) into a single observation
+    ///
+    /// Observations must be sorted by time. Only quantifiable replicates are averaged; a group
+    /// without any keeps its first record. Every group with at least two quantifiable replicates
+    /// gets a QC summary, flagged when the inter-replicate CV exceeds `cv_threshold` (%).
+    pub fn aggregate_replicates(
+        observations: &[Observation],
+        handling: &ReplicateHandling,
+        cv_threshold: f64,
+    ) -> (Vec<Observation>, Vec<ReplicateSummary>) {
+        if *handling == ReplicateHandling::Keep {
+            return (observations.to_vec(), Vec::new());
+        }
+
+        let mut aggregated = Vec::new();
+        let mut summaries = Vec::new();
+
+        for group in Self::replicate_groups(observations) {
+            let quantifiable: Vec<&Observation> = group
+                .iter()
+                .copied()
+                .filter(|obs| obs.concentration > 0.0 && !obs.bloq)
+                .collect();
+
+            if group.len() == 1 || quantifiable.is_empty() {
+                aggregated.push(group[0].clone());
+                continue;
+            }
+
+            let values: Vec<f64> = quantifiable.iter().map(|obs| obs.concentration).collect();
+            let arithmetic_mean = (&values).mean();
+            let concentration = match handling {
+                ReplicateHandling::GeometricMean => values.iter().map(|v| v.ln()).collect::<Vec<f64>>().mean().exp(),
+                _ => arithmetic_mean,
+            };
+
+            let mut observation = quantifiable[0].clone();
+            observation.concentration = concentration;
+            observation.dv = concentration;
+            aggregated.push(observation);
+
+            if values.len() >= 2 {
+                let sd = (&values).std_dev();
+                let cv_percent = safe_div(sd, arithmetic_mean).map(|cv| cv * 100.0);
+                summaries.push(ReplicateSummary {
+                    time: group[0].time,
+                    n_replicates: values.len(),
+                    concentration,
+                    sd,
+                    cv_percent,
+                    high_variability: cv_percent.is_some_and(|cv| cv > cv_threshold),
+                });
+            }
+        }
+
+        (aggregated, summaries)
+    }
+
+    /// Group time-sorted observations sharing a time point and compartment, in time order
+    fn replicate_groups(observations: &[Observation]) -> Vec<Vec<&Observation>> {
+        let mut groups: Vec<Vec<&Observation>> = Vec::new();
+        let mut run_start = 0;
+
+        for obs in observations {
+            if groups.get(run_start).is_some_and(|group| group[0].time != obs.time) {
+                run_start = groups.len();
+            }
+
+            match groups[run_start..].iter_mut().find(|group| group[0].cmt == obs.cmt) {
+                Some(group) => group.push(obs),
+                None => groups.push(vec![obs]),
+            }
+        }
+
+        groups
+    }
+}
@@ -1,6 +1,5 @@
-use crate::{models::*, population::PopulationAnalyzer, Result};
+use crate::{models::*, math::safe_div, population::PopulationAnalyzer, Result};
 use std::collections::HashMap;
-use rayon::prelude::*;
 use statrs::statistics::Statistics;
 use serde::{Serialize, Deserialize};
 
@@ -79,7 +78,7 @@ impl StratificationAnalyzer {
             "AGE_GROUP" => Self::categorize_age(subject.demographics.age),
             "WEIGHT_GROUP" => Self::categorize_weight(subject.demographics.weight),
             "DOSE_GROUP" => Self::categorize_dose(subject),
-            _ => None,
+            other => subject.demographics.extra.get(other).cloned(),
         }
     }
 
@@ -119,8 +118,13 @@ impl StratificationAnalyzer {
     ) -> Result<StratifiedResults> {
         log::info!("Analyzing stratum: {} = {} (n = {})", variable, value, subjects.len());
 
-        // Perform population analysis for this stratum
-        let population_results = PopulationAnalyzer::analyze_population(subjects.to_vec(), config)?;
+        // Perform population analysis for this stratum without re-stratifying it
+        let stratum_config = AnalysisConfig {
+            stratification: None,
+            perform_covariate_analysis: false,
+            ..config.clone()
+        };
+        let population_results = PopulationAnalyzer::analyze_population(subjects.to_vec(), &stratum_config)?;
 
         Ok(StratifiedResults {
             stratum_name: variable.to_string(),
@@ -188,97 +192,303 @@ impl StratificationAnalyzer {
         strata
     }
 
-    /// Perform statistical comparison between strata
+    /// Combine the strata of one stratification variable into an overall n-weighted summary
+    ///
+    /// Strata are pooled from their summary statistics rather than re-analyzed: the pooled
+    /// mean is the n-weighted mean of stratum means, and the pooled variance combines the
+    /// within-stratum variances with the spread of stratum means around the pooled mean.
+    /// Geometric statistics are pooled the same way on the log scale (ln of the geometric
+    /// mean and the log-scale SD recovered from the geometric CV), never by averaging CVs.
+    /// Only strata of `stratum_name` are combined so that each subject is counted once.
+    pub fn pool_strata(
+        strata_results: &HashMap<String, StratifiedResults>,
+        stratum_name: &str,
+    ) -> PooledSummary {
+        let strata: Vec<&StratifiedResults> = strata_results
+            .values()
+            .filter(|s| s.stratum_name == stratum_name)
+            .collect();
+
+        let mut parameter_names: Vec<&String> = strata
+            .iter()
+            .flat_map(|s| s.summary_statistics.parameter_stats.keys())
+            .collect();
+        parameter_names.sort();
+        parameter_names.dedup();
+
+        let mut parameter_stats = HashMap::new();
+        for parameter in parameter_names {
+            let stats: Vec<&ParameterStats> = strata
+                .iter()
+                .filter_map(|s| s.summary_statistics.parameter_stats.get(parameter))
+                .filter(|p| p.n > 0)
+                .collect();
+
+            if let Some(pooled) = Self::pool_parameter_stats(&stats) {
+                parameter_stats.insert(parameter.clone(), pooled);
+            }
+        }
+
+        PooledSummary {
+            stratum_name: stratum_name.to_string(),
+            n_strata: strata.len(),
+            n_subjects: strata.iter().map(|s| s.n_subjects).sum(),
+            parameter_stats,
+        }
+    }
+
+    fn pool_parameter_stats(stats: &[&ParameterStats]) -> Option<PooledParameterStats> {
+        let n_total: usize = stats.iter().map(|s| s.n).sum();
+        if n_total == 0 {
+            return None;
+        }
+
+        let components: Vec<(usize, f64, f64)> = stats
+            .iter()
+            .map(|s| (s.n, s.arithmetic_mean, s.arithmetic_std.unwrap_or(0.0)))
+            .collect();
+        let (mean, std) = Self::pool_moments(&components);
+        let cv_percent = safe_div(std, mean).map(|cv| cv * 100.0);
+
+        // Geometric pooling on the log scale over the values behind each stratum's geometric
+        // stats, only when every stratum has them
+        let log_components: Option<Vec<(usize, f64, f64)>> = stats
+            .iter()
+            .map(|s| match (s.geometric_mean, s.geometric_cv_percent) {
+                (Some(gm), Some(gcv)) if gm > 0.0 => {
+                    let cv = gcv / 100.0;
+                    Some((s.geometric_n, gm.ln(), (1.0 + cv * cv).ln().sqrt()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let (geometric_mean, geometric_cv_percent) = match log_components {
+            Some(components) => {
+                let (ln_mean, ln_std) = Self::pool_moments(&components);
+                (Some(ln_mean.exp()), Some((ln_std.powi(2).exp() - 1.0).sqrt() * 100.0))
+            }
+            None => (None, None),
+        };
+
+        Some(PooledParameterStats {
+            n: n_total,
+            mean,
+            std,
+            cv_percent,
+            min: stats.iter().map(|s| s.min).fold(f64::INFINITY, f64::min),
+            max: stats.iter().map(|s| s.max).fold(f64::NEG_INFINITY, f64::max),
+            geometric_mean,
+            geometric_cv_percent,
+        })
+    }
+
+    /// Pool (n, mean, sample SD) triples into an overall mean and sample SD
+    fn pool_moments(components: &[(usize, f64, f64)]) -> (f64, f64) {
+        let n_total: f64 = components.iter().map(|(n, _, _)| *n as f64).sum();
+        let mean = components.iter().map(|(n, m, _)| *n as f64 * m).sum::<f64>() / n_total;
+
+        if n_total < 2.0 {
+            return (mean, 0.0);
+        }
+
+        let sum_squares: f64 = components
+            .iter()
+            .map(|(n, m, sd)| {
+                let n = *n as f64;
+                // A single-value stratum has an undefined SD but contributes no within-stratum spread
+                let within = if n > 1.0 { (n - 1.0) * sd * sd } else { 0.0 };
+                within + n * (m - mean).powi(2)
+            })
+            .sum();
+
+        (mean, (sum_squares / (n_total - 1.0)).sqrt())
+    }
+
+    /// Perform pairwise statistical comparisons between strata with the supplied test
+    ///
+    /// Built-in tests are [`WelchTTest`], [`PooledTTest`] and [`MannWhitneyTest`]; any other
+    /// [`StatisticalTest`] implementation can be passed in the same way.
     pub fn compare_strata(
         strata_results: &HashMap<String, StratifiedResults>,
         parameter: &str,
+        test: &dyn StatisticalTest,
     ) -> Result<StrataComparison> {
         let mut comparisons = Vec::new();
 
-        let strata_names: Vec<&String> = strata_results.keys().collect();
+        let mut strata_names: Vec<&String> = strata_results.keys().collect();
+        strata_names.sort();
         
         for i in 0..strata_names.len() {
             for j in (i + 1)..strata_names.len() {
                 let stratum1 = &strata_results[strata_names[i]];
                 let stratum2 = &strata_results[strata_names[j]];
                 
-                let comparison = Self::perform_statistical_test(stratum1, stratum2, parameter)?;
+                let comparison = Self::perform_statistical_test(stratum1, stratum2, parameter, test);
                 comparisons.push(comparison);
             }
         }
 
+        let mut variables: Vec<&str> = strata_results.values().map(|s| s.stratum_name.as_str()).collect();
+        variables.sort();
+        variables.dedup();
+
         Ok(StrataComparison {
+            stratification_variable: if variables.len() == 1 { variables[0].to_string() } else { "mixed".to_string() },
             parameter: parameter.to_string(),
             pairwise_comparisons: comparisons,
         })
     }
 
+    /// Compare strata within each stratification variable for every listed parameter
+    ///
+    /// Strata of different variables (e.g. SEX_M vs RACE_Asian) are never compared with each other;
+    /// variables with fewer than two strata and parameters without any values are skipped.
+    pub fn compare_strata_by_variable(
+        strata_results: &HashMap<String, StratifiedResults>,
+        parameters: &[&str],
+        test: &dyn StatisticalTest,
+    ) -> Result<Vec<StrataComparison>> {
+        let mut by_variable: HashMap<&str, HashMap<String, StratifiedResults>> = HashMap::new();
+        for (key, stratum) in strata_results {
+            by_variable
+                .entry(stratum.stratum_name.as_str())
+                .or_default()
+                .insert(key.clone(), stratum.clone());
+        }
+
+        let mut variables: Vec<&str> = by_variable.keys().copied().collect();
+        variables.sort();
+
+        let mut comparisons = Vec::new();
+        for variable in variables {
+            let strata = &by_variable[variable];
+            if strata.len() < 2 {
+                continue;
+            }
+
+            for parameter in parameters {
+                let comparison = Self::compare_strata(strata, parameter, test)?;
+                if comparison.pairwise_comparisons.iter().any(|c| c.n1 > 0 && c.n2 > 0) {
+                    comparisons.push(comparison);
+                }
+            }
+        }
+
+        Ok(comparisons)
+    }
+
     fn perform_statistical_test(
         stratum1: &StratifiedResults,
         stratum2: &StratifiedResults,
         parameter: &str,
-    ) -> Result<PairwiseComparison> {
+        test: &dyn StatisticalTest,
+    ) -> PairwiseComparison {
         let values1 = Self::extract_parameter_values(&stratum1.individual_results, parameter);
         let values2 = Self::extract_parameter_values(&stratum2.individual_results, parameter);
 
+        let mut comparison = if values1.is_empty() || values2.is_empty() {
+            PairwiseComparison::from_statistic(&values1, &values2, "insufficient_data", 0.0, 1.0)
+        } else {
+            test.compare(&values1, &values2)
+        };
+
+        comparison.stratum1_name = stratum1.stratum_value.clone();
+        comparison.stratum2_name = stratum2.stratum_value.clone();
+        comparison
+    }
+
+    /// Mann-Whitney U test using the normal approximation with tie and continuity corrections
+    ///
+    /// Returns the U statistic for the first sample and the two-sided p-value.
+    pub fn mann_whitney_u_test(values1: &[f64], values2: &[f64]) -> (f64, f64) {
         if values1.is_empty() || values2.is_empty() {
-            return Ok(PairwiseComparison {
-                stratum1_name: stratum1.stratum_value.clone(),
-                stratum2_name: stratum2.stratum_value.clone(),
-                n1: values1.len(),
-                n2: values2.len(),
-                mean1: 0.0,
-                mean2: 0.0,
-                p_value: 1.0,
-                test_statistic: 0.0,
-                test_type: "insufficient_data".to_string(),
-                significant: false,
-                effect_size: 0.0,
-            });
+            return (0.0, 1.0);
         }
 
-        let mean1 = values1.as_slice().mean();
-        let mean2 = values2.as_slice().mean();
-        
-        // Perform Welch's t-test (unequal variances)
-        let (t_stat, p_value) = Self::welch_t_test(&values1, &values2);
-        
-        // Calculate effect size (Cohen's d)
-        let pooled_std = Self::calculate_pooled_std(&values1, &values2);
-        let effect_size = if pooled_std > 0.0 { (mean1 - mean2).abs() / pooled_std } else { 0.0 };
+        let n1 = values1.len() as f64;
+        let n2 = values2.len() as f64;
 
-        Ok(PairwiseComparison {
-            stratum1_name: stratum1.stratum_value.clone(),
-            stratum2_name: stratum2.stratum_value.clone(),
-            n1: values1.len(),
-            n2: values2.len(),
-            mean1,
-            mean2,
-            p_value,
-            test_statistic: t_stat,
-            test_type: "welch_t_test".to_string(),
-            significant: p_value < 0.05,
-            effect_size,
-        })
+        let mut pooled: Vec<(f64, usize)> = values1.iter().map(|&v| (v, 0))
+            .chain(values2.iter().map(|&v| (v, 1)))
+            .collect();
+        pooled.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Assign mid-ranks to ties and accumulate the tie correction term
+        let mut rank_sum1 = 0.0;
+        let mut tie_term = 0.0;
+        let mut i = 0;
+        while i < pooled.len() {
+            let mut j = i;
+            while j + 1 < pooled.len() && pooled[j + 1].0 == pooled[i].0 {
+                j += 1;
+            }
+            let mid_rank = (i + j) as f64 / 2.0 + 1.0;
+            let tie_count = (j - i + 1) as f64;
+            tie_term += tie_count.powi(3) - tie_count;
+            rank_sum1 += pooled[i..=j].iter().filter(|(_, group)| *group == 0).count() as f64 * mid_rank;
+            i = j + 1;
+        }
+
+        let u1 = rank_sum1 - n1 * (n1 + 1.0) / 2.0;
+        let mean_u = n1 * n2 / 2.0;
+        let n = n1 + n2;
+        let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+
+        if variance <= 0.0 {
+            return (u1, 1.0);
+        }
+
+        let z = ((u1 - mean_u).abs() - 0.5).max(0.0) / variance.sqrt();
+        let p_value = 2.0 * (1.0 - Self::standard_normal_cdf(z));
+
+        (u1, p_value.min(1.0))
+    }
+
+    /// Hodges-Lehmann location shift (median of all pairwise differences x - y) with its
+    /// distribution-free 95% confidence interval
+    ///
+    /// The interval bounds are order statistics of the sorted pairwise differences, with the
+    /// rank taken from the normal approximation to the Mann-Whitney U distribution. No
+    /// interval is returned when the samples are too small for that rank to exist.
+    pub fn hodges_lehmann(values1: &[f64], values2: &[f64]) -> (f64, Option<(f64, f64)>) {
+        let mut differences: Vec<f64> = values1
+            .iter()
+            .flat_map(|x| values2.iter().map(move |y| x - y))
+            .collect();
+
+        if differences.is_empty() {
+            return (0.0, None);
+        }
+
+        differences.sort_by(|a, b| a.total_cmp(b));
+
+        let m = differences.len();
+        let estimate = if m.is_multiple_of(2) {
+            (differences[m / 2 - 1] + differences[m / 2]) / 2.0
+        } else {
+            differences[m / 2]
+        };
+
+        let n1 = values1.len() as f64;
+        let n2 = values2.len() as f64;
+        let sd_u = (n1 * n2 * (n1 + n2 + 1.0) / 12.0).sqrt();
+        let k = (n1 * n2 / 2.0 - 1.96 * sd_u).floor();
+
+        let ci = if k >= 1.0 {
+            let k = k as usize;
+            Some((differences[k - 1], differences[m - k]))
+        } else {
+            None
+        };
+
+        (estimate, ci)
     }
 
     fn extract_parameter_values(results: &[NcaResults], parameter: &str) -> Vec<f64> {
         results
             .iter()
-            .filter_map(|r| {
-                let params = &r.individual_parameters;
-                match parameter {
-                    "auc_last" => params.auc_last,
-                    "auc_inf" => params.auc_inf,
-                    "cmax" => params.cmax,
-                    "tmax" => params.tmax,
-                    "half_life" => params.half_life,
-                    "clearance" => params.clearance,
-                    "volume_terminal" => params.volume_terminal,
-                    "mrt" => params.mrt,
-                    _ => None,
-                }
-            })
+            .filter_map(|r| r.individual_parameters.get(parameter))
             .collect()
     }
 
@@ -312,6 +522,22 @@ impl StratificationAnalyzer {
         (t_stat, p_value)
     }
 
+    fn pooled_t_test(values1: &[f64], values2: &[f64]) -> (f64, f64) {
+        if values1.len() < 2 || values2.len() < 2 {
+            return (0.0, 1.0);
+        }
+
+        let n1 = values1.len() as f64;
+        let n2 = values2.len() as f64;
+        let pooled_std = Self::calculate_pooled_std(values1, values2);
+        let se = pooled_std * (1.0 / n1 + 1.0 / n2).sqrt();
+        let t_stat = safe_div(values1.mean() - values2.mean(), se).unwrap_or(0.0);
+
+        let p_value = Self::t_distribution_p_value(t_stat.abs(), n1 + n2 - 2.0);
+
+        (t_stat, p_value)
+    }
+
     fn calculate_pooled_std(values1: &[f64], values2: &[f64]) -> f64 {
         if values1.len() < 2 || values2.len() < 2 {
             return 0.0;
@@ -360,8 +586,56 @@ impl StratificationAnalyzer {
     }
 }
 
+/// A two-sample test used for pairwise strata comparisons
+///
+/// `compare` fills everything but the stratum names, which [`StratificationAnalyzer::compare_strata`]
+/// sets afterwards. [`PairwiseComparison::from_statistic`] covers the common fields.
+pub trait StatisticalTest: Sync {
+    fn compare(&self, values1: &[f64], values2: &[f64]) -> PairwiseComparison;
+}
+
+/// Welch's t-test (unequal variances)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WelchTTest;
+
+/// Student's t-test with pooled variance (equal variances)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PooledTTest;
+
+/// Mann-Whitney U test, reporting the Hodges-Lehmann shift estimate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MannWhitneyTest;
+
+impl StatisticalTest for WelchTTest {
+    fn compare(&self, values1: &[f64], values2: &[f64]) -> PairwiseComparison {
+        let (t_stat, p_value) = StratificationAnalyzer::welch_t_test(values1, values2);
+        PairwiseComparison::from_statistic(values1, values2, "welch_t_test", t_stat, p_value)
+    }
+}
+
+impl StatisticalTest for PooledTTest {
+    fn compare(&self, values1: &[f64], values2: &[f64]) -> PairwiseComparison {
+        let (t_stat, p_value) = StratificationAnalyzer::pooled_t_test(values1, values2);
+        PairwiseComparison::from_statistic(values1, values2, "pooled_t_test", t_stat, p_value)
+    }
+}
+
+impl StatisticalTest for MannWhitneyTest {
+    fn compare(&self, values1: &[f64], values2: &[f64]) -> PairwiseComparison {
+        let (u_stat, p_value) = StratificationAnalyzer::mann_whitney_u_test(values1, values2);
+        let (estimate, ci) = StratificationAnalyzer::hodges_lehmann(values1, values2);
+
+        let mut comparison = PairwiseComparison::from_statistic(values1, values2, "mann_whitney_u", u_stat, p_value);
+        comparison.hodges_lehmann_estimate = Some(estimate);
+        comparison.hodges_lehmann_ci = ci;
+        comparison
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrataComparison {
+    /// Stratification variable shared by the compared strata ("mixed" when they differ)
+    pub stratification_variable: String,
     pub parameter: String,
     pub pairwise_comparisons: Vec<PairwiseComparison>,
 }
@@ -379,4 +653,43 @@ pub struct PairwiseComparison {
     pub test_type: String,
     pub significant: bool,
     pub effect_size: f64,
-}
\ No newline at end of file
+    /// Median of pairwise differences (stratum 1 - stratum 2), for the Mann-Whitney test
+    pub hodges_lehmann_estimate: Option<f64>,
+    pub hodges_lehmann_ci: Option<(f64, f64)>,
+}
+
+impl PairwiseComparison {
+    /// Build a comparison from a test statistic and p-value, filling sample sizes, means,
+    /// Cohen's d and significance at the 5% level; stratum names are left empty
+    pub fn from_statistic(
+        values1: &[f64],
+        values2: &[f64],
+        test_type: &str,
+        test_statistic: f64,
+        p_value: f64,
+    ) -> Self {
+        let mean_of = |values: &[f64]| if values.is_empty() { 0.0 } else { values.mean() };
+        let mean1 = mean_of(values1);
+        let mean2 = mean_of(values2);
+
+        // Calculate effect size (Cohen's d)
+        let pooled_std = StratificationAnalyzer::calculate_pooled_std(values1, values2);
+        let effect_size = safe_div((mean1 - mean2).abs(), pooled_std).unwrap_or(0.0);
+
+        PairwiseComparison {
+            stratum1_name: String::new(),
+            stratum2_name: String::new(),
+            n1: values1.len(),
+            n2: values2.len(),
+            mean1,
+            mean2,
+            p_value,
+            test_statistic,
+            test_type: test_type.to_string(),
+            significant: p_value < 0.05,
+            effect_size,
+            hodges_lehmann_estimate: None,
+            hodges_lehmann_ci: None,
+        }
+    }
+}
@@ -6,7 +6,6 @@ use nca_analysis::{
     example_data::ExampleDataGenerator,
 };
 use tempfile::TempDir;
-use std::path::PathBuf;
 
 #[test]
 fn test_complete_nca_workflow() {
@@ -35,6 +34,7 @@ fn test_complete_nca_workflow() {
         lloq_handling: LloqHandling::HalfLloq,
         time_units: "h".to_string(),
         concentration_units: "ng/mL".to_string(),
+        ..AnalysisConfig::default()
     };
     
     // Perform analysis
@@ -51,6 +51,8 @@ fn test_complete_nca_workflow() {
     // Verify output files exist
     assert!(output_path.join("individual_results.csv").exists());
     assert!(output_path.join("summary_statistics.csv").exists());
+    assert!(output_path.join("distributions.csv").exists());
+    assert!(output_path.join("cumulative_auc.csv").exists());
     assert!(output_path.join("complete_results.json").exists());
 }
 
@@ -65,32 +67,44 @@ fn test_auc_calculation_methods() {
             concentration: 100.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 100.0,
+            cmt: None,
         },
         Observation {
             time: 1.0,
             concentration: 75.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 75.0,
+            cmt: None,
         },
         Observation {
             time: 2.0,
             concentration: 50.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 50.0,
+            cmt: None,
         },
         Observation {
             time: 4.0,
             concentration: 25.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 25.0,
+            cmt: None,
         },
     ];
     
@@ -102,6 +116,7 @@ fn test_auc_calculation_methods() {
         lloq_handling: LloqHandling::HalfLloq,
         time_units: "h".to_string(),
         concentration_units: "ng/mL".to_string(),
+        ..AnalysisConfig::default()
     };
     
     let auc_results = AucCalculator::calculate_all_methods(&observations, &config).unwrap();
@@ -122,24 +137,33 @@ fn test_parameter_calculation() {
             concentration: 0.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 0.0,
+            cmt: None,
         },
         Observation {
             time: 1.0,
             concentration: 100.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 100.0,
+            cmt: None,
         },
         Observation {
             time: 2.0,
             concentration: 75.0,
             lloq: Some(0.1),
             bloq: false,
+            lod: None,
+            blod: false,
             evid: 0,
             dv: 75.0,
+            cmt: None,
         },
     ];
     
@@ -151,4 +175,3708 @@ fn test_parameter_calculation() {
     let lambda_z = 0.1;
     let half_life = ParameterCalculator::calculate_half_life(lambda_z).unwrap();
     assert!((half_life - 6.93147).abs() < 0.001);
-}
\ No newline at end of file
+}
+#[test]
+fn test_pooled_strata_summary_matches_unstratified() {
+    use nca_analysis::stratification::StratificationAnalyzer;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("pool_dataset.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["SEX".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: false,
+            comparison_test: ComparisonTest::WelchTTest,
+        }),
+        ..AnalysisConfig::default()
+    };
+
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    let pooled = StratificationAnalyzer::pool_strata(&results.stratified_results, "SEX");
+
+    assert_eq!(pooled.n_subjects, 12);
+    let overall = &results.summary_statistics.parameter_stats["cmax"];
+    let pooled_cmax = &pooled.parameter_stats["cmax"];
+    assert_eq!(pooled_cmax.n, overall.n);
+    assert!((pooled_cmax.mean - overall.arithmetic_mean).abs() < 1e-9 * overall.arithmetic_mean);
+    let overall_std = overall.arithmetic_std.unwrap();
+    assert!((pooled_cmax.std - overall_std).abs() < 1e-9 * overall_std);
+    assert_eq!(pooled_cmax.min, overall.min);
+    assert_eq!(pooled_cmax.max, overall.max);
+
+    let geo_mean = overall.geometric_mean.unwrap();
+    assert!((pooled_cmax.geometric_mean.unwrap() - geo_mean).abs() < 1e-9 * geo_mean);
+    let geo_cv = overall.geometric_cv_percent.unwrap();
+    assert!((pooled_cmax.geometric_cv_percent.unwrap() - geo_cv).abs() < 1e-6 * geo_cv);
+}
+
+fn write_dataset(path: &std::path::Path, header: &str, rows: &[&str]) {
+    let mut content = format!("{}\n", header);
+    for row in rows {
+        content.push_str(row);
+        content.push('\n');
+    }
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_dose_and_observation_compartments() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("cmt_dataset.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CMT,RATE", &[
+        "1,0,0,100,1,1,0",
+        "1,0.5,4.0,0,0,2,0",
+        "1,1,8.0,0,0,2,0",
+        "1,2,6.0,0,0,2,0",
+        "1,4,3.0,0,0,2,0",
+        "1,8,1.5,0,0,2,0",
+        "1,12,0.75,0,0,2,0",
+        "1,4,250.0,0,0,3,0",
+        "1,12,400.0,0,0,3,0",
+    ]);
+
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    assert_eq!(subjects[0].dosing_events[0].cmt, Some(1));
+    assert_eq!(subjects[0].observations.len(), 8);
+
+    let config = AnalysisConfig {
+        analyte_compartment: Some(2),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subjects[0], &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.cmax, Some(8.0));
+    assert_eq!(params.tmax, Some(1.0));
+    assert_eq!(params.clast, Some(0.75));
+
+    let (unfiltered, _) = NcaAnalyzer::analyze_subject(&subjects[0], &AnalysisConfig::default()).unwrap();
+    assert_eq!(unfiltered.individual_parameters.cmax, Some(400.0));
+}
+
+fn observation(time: f64, concentration: f64) -> Observation {
+    Observation {
+        time,
+        concentration,
+        lloq: Some(0.1),
+        bloq: false,
+        lod: None,
+        blod: false,
+        evid: 0,
+        dv: concentration,
+        cmt: None,
+    }
+}
+
+fn single_dose_subject(id: &str, dose: f64, profile: &[(f64, f64)]) -> Subject {
+    Subject {
+        id: id.to_string(),
+        observations: profile.iter().map(|&(t, c)| observation(t, c)).collect(),
+        dosing_events: vec![DosingEvent {
+            time: 0.0,
+            dose,
+            route: DosingRoute::IntravenousBolus,
+            infusion_duration: None,
+            evid: 1,
+            cmt: None,
+            steady_state: false,
+            interval: None,
+        }],
+        demographics: Demographics::default(),
+        covariate_records: Vec::new(),
+        analyte: None,
+        matrix: None,
+    }
+}
+
+#[test]
+fn test_increasing_terminal_phase_is_flagged() {
+    use nca_analysis::nca::NcaAnalyzer;
+    use nca_analysis::parameters::ParameterCalculator;
+
+    let subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (6.0, 6.0), (8.0, 7.0), (12.0, 9.0),
+    ]);
+
+    assert_eq!(ParameterCalculator::is_terminal_phase_declining(&subject.observations, 3), Some(false));
+    // A window too short to judge gives no verdict rather than a wider window
+    assert_eq!(ParameterCalculator::is_terminal_phase_declining(&subject.observations, 1), None);
+    assert_eq!(ParameterCalculator::is_terminal_phase_declining(&subject.observations[..2], 3), None);
+    if let Ok((lambda_z, _, _)) = ParameterCalculator::calculate_lambda_z(
+        &subject.observations,
+        &LambdaZSelection::Auto,
+    ) {
+        assert!(lambda_z > 0.0);
+    }
+    assert!(ParameterCalculator::calculate_lambda_z(
+        &subject.observations,
+        &LambdaZSelection::Manual(vec![4, 5, 6]),
+    ).is_err());
+
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.lambda_z.is_none());
+    assert!(warnings.iter().any(|w| w.contains("Non-declining terminal phase")));
+
+    // A lambda_z fitted over an earlier, declining window is not reported as missing
+    let window = AnalysisConfig { lambda_z_selection: LambdaZSelection::Manual(vec![0, 1, 2]), ..AnalysisConfig::default() };
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &window).unwrap();
+    assert!(results.individual_parameters.lambda_z.is_some());
+    assert!(!warnings.iter().any(|w| w.contains("lambda_z not reported")));
+    assert!(warnings.iter().any(|w| w.contains("lambda_z reported from the declining window")));
+}
+
+#[test]
+fn test_high_extrapolation_flags_auc_inf() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // C = 100 * exp(-0.1 t) sampled to 10.5 h leaves ~35% of AUC_inf extrapolated
+    let times: [f64; 7] = [0.0, 1.0, 2.0, 4.0, 6.0, 8.0, 10.5];
+    let profile: Vec<(f64, f64)> = times.iter().map(|&t| (t, 100.0 * (-0.1 * t).exp())).collect();
+    let subject = single_dose_subject("1", 100.0, &profile);
+
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let extrap = params.auc_percent_extrap.unwrap();
+    assert!((extrap - 35.0).abs() < 2.0);
+    assert!(params.auc_inf.is_some());
+    assert_eq!(params.auc_inf_reliable, Some(false));
+    assert!(warnings.iter().any(|w| w.contains("flagged as unreliable")));
+
+    let lenient = AnalysisConfig {
+        validation_thresholds: ValidationThresholds { max_auc_extrap_percent: 40.0, ..ValidationThresholds::default() },
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &lenient).unwrap();
+    assert_eq!(results.individual_parameters.auc_inf_reliable, Some(true));
+}
+
+#[test]
+fn test_hodges_lehmann_estimate_for_mann_whitney() {
+    use nca_analysis::stratification::StratificationAnalyzer;
+
+    let x = [5.0, 6.0, 7.0, 8.0];
+    let y = [1.0, 2.0, 3.0, 4.0];
+
+    // Pairwise differences sorted: 1,2,2,3,3,3,4,4,4,4,5,5,5,6,6,7
+    let (estimate, ci) = StratificationAnalyzer::hodges_lehmann(&x, &y);
+    assert_eq!(estimate, 4.0);
+    assert_eq!(ci, Some((1.0, 7.0)));
+
+    let (u, p_value) = StratificationAnalyzer::mann_whitney_u_test(&x, &y);
+    assert_eq!(u, 16.0);
+    assert!((p_value - 0.0304).abs() < 1e-3);
+}
+
+#[test]
+fn test_auc_tau_pre_dose_anchoring_at_steady_state() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("ss_dataset.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CMT,RATE,SS,II", &[
+        "1,0,0,100,1,1,-1,1,12",
+        "1,0,2.0,0,0,1,0,0,0",
+        "1,1,10.0,0,0,1,0,0,0",
+        "1,2,8.0,0,0,1,0,0,0",
+        "1,4,5.0,0,0,1,0,0,0",
+        "1,8,3.0,0,0,1,0,0,0",
+        "1,12,2.2,0,0,1,0,0,0",
+    ]);
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let dose = &subjects[0].dosing_events[0];
+    assert!(dose.steady_state);
+    assert_eq!(dose.interval, Some(12.0));
+
+    // Linear trapezoidal over (0, 2.0), (1, 10), (2, 8), (4, 5), (8, 3), (12, 2.2)
+    let (observed, _) = NcaAnalyzer::analyze_subject(&subjects[0], &AnalysisConfig::default()).unwrap();
+    assert!((observed.individual_parameters.auc_tau.unwrap() - 54.4).abs() < 1e-9);
+
+    // Carrying the 12 h trough back to t = 0 adds (2.2 - 2.0) / 2 to the first segment
+    let trough_config = AnalysisConfig {
+        auc_tau_anchor: AucTauAnchor::NextIntervalTrough,
+        ..AnalysisConfig::default()
+    };
+    let (carried, _) = NcaAnalyzer::analyze_subject(&subjects[0], &trough_config).unwrap();
+    assert!((carried.individual_parameters.auc_tau.unwrap() - 54.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_intervals_from_the_dose_start_before_a_delayed_first_sample() {
+    use nca_analysis::auc::AucCalculator;
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2)];
+    let mut oral = single_dose_subject("ORAL", 100.0, &profile);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let config = AnalysisConfig {
+        dosing_interval: Some(12.0),
+        partial_auc_intervals: vec![(0.0, 4.0)],
+        ..AnalysisConfig::default()
+    };
+
+    // Zero at the oral dose adds 10 / 2 to the observed AUC from the first sample
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&oral, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert!((params.auc_tau.unwrap() - (params.auc_last.unwrap() + 5.0)).abs() < 1e-9);
+    assert!((params.partial_aucs[0].auc.unwrap() - (5.0 + 9.0 + 13.0)).abs() < 1e-9);
+    assert!(params.c0.is_none());
+    assert!(warnings.iter().any(|w| w.contains("starting before the first sample")));
+
+    // The pre-dose lookback stops one interval before the dose
+    let observations: Vec<Observation> = [(-30.0, 50.0), (0.0, 0.0), (6.0, 4.0), (12.0, 2.0)]
+        .iter()
+        .map(|&(t, c)| observation(t, c))
+        .collect();
+    let auc_tau = |dose_time: f64| AucCalculator::calculate_auc_tau(
+        &observations, dose_time, 12.0, &AucTauAnchor::ObservedPreDose, &AucMethod::LinearTrapezoidal, &InterpolationMethod::Linear,
+    );
+    assert!((auc_tau(0.0).unwrap() - (12.0 + 18.0)).abs() < 1e-9);
+    let stale = [observations[0].clone(), observations[2].clone(), observations[3].clone()];
+    let from_trough = AucCalculator::calculate_auc_tau(
+        &stale, 0.0, 12.0, &AucTauAnchor::ObservedPreDose, &AucMethod::LinearTrapezoidal, &InterpolationMethod::Linear,
+    );
+    assert!((from_trough.unwrap() - (3.0 * 6.0 + 18.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_partial_auc_percent_of_total() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let times: [f64; 9] = [0.0, 1.0, 2.0, 4.0, 8.0, 12.0, 24.0, 36.0, 48.0];
+    let profile: Vec<(f64, f64)> = times.iter().map(|&t| (t, 100.0 * (-0.1 * t).exp())).collect();
+    let subject = single_dose_subject("1", 100.0, &profile);
+
+    let config = AnalysisConfig {
+        partial_auc_intervals: vec![(0.0, 24.0), (0.0, 6.0)],
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    let auc_inf = params.auc_inf.unwrap();
+
+    let auc_0_24 = &params.partial_aucs[0];
+    assert_eq!(auc_0_24.reference.as_deref(), Some("AUC_inf"));
+    let expected = auc_0_24.auc.unwrap() / auc_inf * 100.0;
+    assert!((auc_0_24.percent_of_total.unwrap() - expected).abs() < 1e-9);
+    assert!(auc_0_24.percent_of_total.unwrap() > 85.0 && auc_0_24.percent_of_total.unwrap() < 95.0);
+
+    // 6 h lies between the 4 h and 8 h samples, so its concentration is interpolated
+    let c = |t: f64| 100.0 * (-0.1 * t).exp();
+    let c6 = (c(4.0) + c(8.0)) / 2.0;
+    let expected_0_6 = (c(0.0) + c(1.0)) / 2.0 + (c(1.0) + c(2.0)) / 2.0
+        + 2.0 * (c(2.0) + c(4.0)) / 2.0 + 2.0 * (c(4.0) + c6) / 2.0;
+    assert!((params.partial_aucs[1].auc.unwrap() - expected_0_6).abs() < 1e-9);
+
+    // A reversed or empty interval is a usage error, not NA for every subject
+    let temp_dir = TempDir::new().unwrap();
+    for interval in ["24-0", "12-12"] {
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+            .args(["--generate-example", "--subjects", "3", "--partial-auc", interval, "--output"])
+            .arg(temp_dir.path().join("out"))
+            .status()
+            .unwrap();
+        assert!(!status.success(), "--partial-auc {}", interval);
+    }
+}
+
+#[test]
+fn test_partial_auc_falls_back_to_auc_last() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // Rising then flat tail: no lambda_z, so AUC_inf is unavailable
+    let subject = single_dose_subject("1", 100.0, &[
+        (0.0, 1.0), (1.0, 5.0), (2.0, 8.0), (4.0, 8.0), (8.0, 8.0),
+    ]);
+    let config = AnalysisConfig {
+        partial_auc_intervals: vec![(0.0, 2.0)],
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert!(params.auc_inf.is_none());
+
+    let partial = &params.partial_aucs[0];
+    assert_eq!(partial.reference.as_deref(), Some("AUC_last"));
+    assert!((partial.percent_of_total.unwrap() - 9.5 / params.auc_last.unwrap() * 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_median_and_quartiles_share_type7_definition() {
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&[4.0, 1.0, 3.0, 2.0]);
+    assert_eq!(stats.median, 2.5);
+    assert_eq!(stats.q25, 1.75);
+    assert_eq!(stats.q75, 3.25);
+    assert_eq!(stats.min, 1.0);
+    assert_eq!(stats.max, 4.0);
+}
+
+#[test]
+fn test_stratification_by_custom_genotype_column() {
+    use nca_analysis::stratification::StratificationAnalyzer;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("genotype_dataset.csv");
+    let mut rows = Vec::new();
+    for (id, genotype, scale) in [(1, "EM", 1.0), (2, "EM", 1.2), (3, "PM", 2.0), (4, "PM", 2.4)] {
+        rows.push(format!("{},0,0,100,1,1,-1,{}", id, genotype));
+        for (time, conc) in [(1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2)] {
+            rows.push(format!("{},{},{},0,0,1,0,{}", id, time, conc * scale, genotype));
+        }
+    }
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CMT,RATE,GENOTYPE", &rows);
+
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    assert!(subjects.iter().all(|s| s.demographics.extra.contains_key("GENOTYPE")));
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["Genotype".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: false,
+            comparison_test: ComparisonTest::WelchTTest,
+        }),
+        ..AnalysisConfig::default()
+    };
+
+    let strata = StratificationAnalyzer::analyze_stratified(&subjects, &config).unwrap();
+    assert_eq!(strata.len(), 2);
+    assert_eq!(strata["Genotype_EM"].n_subjects, 2);
+    assert_eq!(strata["Genotype_PM"].n_subjects, 2);
+}
+
+#[test]
+fn test_zero_denominators_yield_missing_values() {
+    use nca_analysis::math::safe_div;
+    use nca_analysis::parameters::ParameterCalculator;
+
+    assert_eq!(safe_div(1.0, 4.0), Some(0.25));
+    assert_eq!(safe_div(1.0, 0.0), None);
+    assert_eq!(safe_div(f64::MAX, 1e-300), None);
+
+    // AUC = 0
+    assert!(ParameterCalculator::calculate_clearance_iv(100.0, 0.0).is_err());
+    assert!(ParameterCalculator::calculate_mrt(50.0, 0.0).is_err());
+    assert!(ParameterCalculator::calculate_auc_percent_extrap(0.0, 0.0).is_err());
+
+    // mean = 0
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&[-2.0, 2.0]);
+    assert_eq!(stats.arithmetic_mean, 0.0);
+    assert_eq!(stats.cv_percent, None);
+    assert_eq!(stats.arithmetic_cv_percent, None);
+}
+
+#[test]
+fn test_auc_per_dose_with_two_doses_in_sampling_window() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let mut subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (2.0, 10.0), (6.0, 6.0), (12.0, 3.0), (14.0, 12.0), (18.0, 7.0), (24.0, 4.0),
+    ]);
+    let mut second_dose = subject.dosing_events[0].clone();
+    second_dose.time = 12.0;
+    subject.dosing_events.push(second_dose);
+
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(warnings.iter().any(|w| w.contains("within the sampled profile") && w.contains("t = [0.0, 12.0]")));
+
+    let params = &results.individual_parameters;
+    assert_eq!(params.dose_interval_aucs.len(), 2);
+    let first = &params.dose_interval_aucs[0];
+    assert_eq!((first.dose_number, first.dose_time, first.end), (1, 0.0, 12.0));
+    assert!((first.auc.unwrap() - 69.0).abs() < 1e-9);
+    let second = &params.dose_interval_aucs[1];
+    assert_eq!((second.dose_number, second.dose_time, second.end), (2, 12.0, 24.0));
+    assert!((second.auc.unwrap() - 86.0).abs() < 1e-9);
+    assert!((params.auc_last.unwrap() - 155.0).abs() < 1e-9);
+
+    let single = single_dose_subject("2", 100.0, &[(0.0, 0.0), (2.0, 10.0), (6.0, 6.0), (12.0, 3.0)]);
+    let (single_results, single_warnings) = NcaAnalyzer::analyze_subject(&single, &AnalysisConfig::default()).unwrap();
+    assert!(single_results.individual_parameters.dose_interval_aucs.is_empty());
+    assert!(!single_warnings.iter().any(|w| w.contains("within the sampled profile")));
+}
+
+#[test]
+fn test_parameter_lookup_by_name() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2),
+    ]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+
+    // Every advertised name maps onto the field of the same name
+    let fields = serde_json::to_value(params).unwrap();
+    let names: Vec<&str> = IndividualParameters::parameter_names().collect();
+    for &name in &names {
+        assert!(fields.get(name).is_some(), "no field named {}", name);
+        assert_eq!(params.get(name), fields[name].as_f64(), "mismatch for {}", name);
+    }
+    // ...including the parameters added after the original list
+    for name in ["auc_extrap_area", "cavg_observed", "lambda_z_intercept", "time_to_steady_state", "c0"] {
+        assert!(names.contains(&name), "{} not listed", name);
+    }
+
+    assert_eq!(params.get("cmax"), Some(10.0));
+    assert_eq!(params.get("clearance"), params.clearance);
+    assert!(params.get("auc_inf").is_some());
+    assert_eq!(params.get("not_a_parameter"), None);
+}
+
+#[test]
+fn test_pluggable_statistical_tests_for_strata_comparison() {
+    use nca_analysis::stratification::*;
+
+    struct MeanDifferenceTest;
+
+    impl StatisticalTest for MeanDifferenceTest {
+        fn compare(&self, values1: &[f64], values2: &[f64]) -> PairwiseComparison {
+            let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+            let difference = mean(values1) - mean(values2);
+            PairwiseComparison::from_statistic(values1, values2, "mean_difference", difference, 0.5)
+        }
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("tests_dataset.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["SEX".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: false,
+            comparison_test: ComparisonTest::WelchTTest,
+        }),
+        ..AnalysisConfig::default()
+    };
+    let strata = StratificationAnalyzer::analyze_stratified(&subjects, &config).unwrap();
+    assert_eq!(strata.len(), 2);
+
+    let builtins: [(&dyn StatisticalTest, &str); 3] = [
+        (&WelchTTest, "welch_t_test"),
+        (&PooledTTest, "pooled_t_test"),
+        (&MannWhitneyTest, "mann_whitney_u"),
+    ];
+    for (test, test_type) in builtins {
+        let comparison = StratificationAnalyzer::compare_strata(&strata, "cmax", test).unwrap();
+        let pairwise = &comparison.pairwise_comparisons[0];
+        assert_eq!(pairwise.test_type, test_type);
+        assert!((0.0..=1.0).contains(&pairwise.p_value));
+        assert_eq!(pairwise.n1 + pairwise.n2, 12);
+    }
+
+    let custom = StratificationAnalyzer::compare_strata(&strata, "cmax", &MeanDifferenceTest).unwrap();
+    let pairwise = &custom.pairwise_comparisons[0];
+    assert_eq!(pairwise.test_type, "mean_difference");
+    assert!((pairwise.test_statistic - (pairwise.mean1 - pairwise.mean2)).abs() < 1e-9);
+    assert!(!pairwise.stratum1_name.is_empty());
+    assert!(pairwise.hodges_lehmann_estimate.is_none());
+}
+
+#[test]
+fn test_strata_comparisons_reach_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("comparison_dataset.csv");
+    let output_path = temp_dir.path().join("comparison_output");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["SEX".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: true,
+            comparison_test: ComparisonTest::WelchTTest,
+        }),
+        ..AnalysisConfig::default()
+    };
+
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    assert!(!results.strata_comparisons.is_empty());
+    let cmax = results.strata_comparisons.iter().find(|c| c.parameter == "cmax").unwrap();
+    assert_eq!(cmax.stratification_variable, "SEX");
+    assert_eq!(cmax.pairwise_comparisons.len(), 1);
+    assert_eq!(cmax.pairwise_comparisons[0].test_type, "welch_t_test");
+
+    OutputManager::save_results(&results, &config, &output_path).unwrap();
+    let comparisons_csv = std::fs::read_to_string(output_path.join("strata_comparisons.csv")).unwrap();
+    assert!(comparisons_csv.lines().any(|line| line.starts_with("SEX,cmax,")));
+
+    let json = std::fs::read_to_string(output_path.join("complete_results.json")).unwrap();
+    assert!(json.contains("\"strata_comparisons\""));
+    assert!(json.contains("welch_t_test"));
+}
+
+#[test]
+fn test_stricter_r_squared_threshold_flags_subject() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 4.0), (6.0, 3.5), (8.0, 1.5),
+    ]);
+    let config = AnalysisConfig {
+        lambda_z_selection: LambdaZSelection::Manual(vec![3, 4, 5]),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let r_squared = results.individual_parameters.lambda_z_r_squared.unwrap();
+    assert!(r_squared > 0.8 && r_squared < 0.9);
+
+    let default_warnings = NcaAnalyzer::validate_results(&results, &ValidationThresholds::default());
+    assert!(!default_warnings.iter().any(|w| w.contains("Poor terminal phase fit")));
+
+    let strict = ValidationThresholds {
+        min_lambda_z_r_squared: 0.9,
+        ..ValidationThresholds::default()
+    };
+    let strict_warnings = NcaAnalyzer::validate_results(&results, &strict);
+    assert!(strict_warnings.iter().any(|w| w.contains("Poor terminal phase fit")));
+}
+
+#[test]
+fn test_triplicate_samples_are_averaged_with_qc() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (1.0, 10.0), (2.0, 6.0), (2.0, 8.0), (2.0, 10.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2),
+    ]);
+
+    let (kept, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(kept.replicate_summaries.is_empty());
+
+    let config = AnalysisConfig {
+        replicate_handling: ReplicateHandling::ArithmeticMean,
+        ..AnalysisConfig::default()
+    };
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    assert_eq!(results.replicate_summaries.len(), 1);
+    let summary = &results.replicate_summaries[0];
+    assert_eq!(summary.time, 2.0);
+    assert_eq!(summary.n_replicates, 3);
+    assert!((summary.concentration - 8.0).abs() < 1e-12);
+    assert!((summary.sd - 2.0).abs() < 1e-12);
+    assert!((summary.cv_percent.unwrap() - 25.0).abs() < 1e-9);
+    assert!(summary.high_variability);
+    assert!(warnings.iter().any(|w| w.contains("High inter-replicate variability")));
+
+    // AUC over the averaged profile: 0-1 5, 1-2 9, 2-4 13, 4-8 15, 8-12 7.4
+    assert!((results.individual_parameters.auc_last.unwrap() - 49.4).abs() < 1e-9);
+
+    let geometric = AnalysisConfig {
+        replicate_handling: ReplicateHandling::GeometricMean,
+        replicate_cv_threshold: 30.0,
+        ..AnalysisConfig::default()
+    };
+    let (geo_results, _) = NcaAnalyzer::analyze_subject(&subject, &geometric).unwrap();
+    let geo_summary = &geo_results.replicate_summaries[0];
+    assert!((geo_summary.concentration - 480.0_f64.cbrt()).abs() < 1e-9);
+    assert!(!geo_summary.high_variability);
+}
+
+#[test]
+fn test_implausible_concentrations_are_flagged() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("qc_output");
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &[
+            (0.0, 0.0), (1.0, 10.0), (2.0, -0.5), (4.0, 5.0), (8.0, 100000.0), (12.0, 1.2),
+        ]),
+        single_dose_subject("2", 100.0, &[(0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5)]),
+    ];
+
+    let config = AnalysisConfig {
+        validation_thresholds: ValidationThresholds {
+            max_concentration: Some(1000.0),
+            ..ValidationThresholds::default()
+        },
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+
+    let flags = &results.concentration_flags;
+    assert_eq!(flags.len(), 2);
+    assert!(flags.iter().all(|f| f.subject_id == "1"));
+    assert!(flags.iter().any(|f| f.time == 2.0 && f.reason.contains("Negative")));
+    assert!(flags.iter().any(|f| f.time == 8.0 && f.reason.contains("maximum")));
+
+    // Flagged values are left untouched
+    let subject1 = results.individual_results.iter().find(|r| r.subject_id == "1").unwrap();
+    assert_eq!(subject1.individual_parameters.cmax, Some(100000.0));
+
+    OutputManager::save_results(&results, &config, &output_path).unwrap();
+    let qc = std::fs::read_to_string(output_path.join("concentration_qc.csv")).unwrap();
+    assert_eq!(qc.lines().count(), 3);
+
+    // Negative concentrations are flagged even without a ceiling
+    let default_results = PopulationAnalyzer::analyze_population(subjects, &AnalysisConfig::default()).unwrap();
+    assert_eq!(default_results.concentration_flags.len(), 1);
+}
+
+#[test]
+fn test_dose_normalized_summary_statistics() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("dn_output");
+    let profile = [(0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2)];
+    let doubled: Vec<(f64, f64)> = profile.iter().map(|&(t, c)| (t, 2.0 * c)).collect();
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &profile),
+        single_dose_subject("2", 200.0, &doubled),
+    ];
+
+    let plain = PopulationAnalyzer::analyze_population(subjects.clone(), &AnalysisConfig::default()).unwrap();
+    assert!(plain.dose_normalized_summary.is_none());
+
+    let config = AnalysisConfig {
+        dose_normalization: true,
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    let dn = results.dose_normalized_summary.as_ref().unwrap();
+    let cmax_dn = &dn.parameter_stats["cmax"];
+    assert_eq!(cmax_dn.n, 2);
+    assert!((cmax_dn.arithmetic_mean - 0.1).abs() < 1e-12);
+    assert!(cmax_dn.arithmetic_std.unwrap().abs() < 1e-12);
+    assert!(dn.parameter_stats.contains_key("auc_last"));
+    assert!(!dn.parameter_stats.contains_key("half_life"));
+
+    OutputManager::save_results(&results, &config, &output_path).unwrap();
+    let dn_csv = std::fs::read_to_string(output_path.join("summary_statistics_dn.csv")).unwrap();
+    assert!(dn_csv.lines().any(|line| line.starts_with("cmax,2,")));
+}
+
+#[test]
+fn test_dose_normalization_uses_interval_and_systemic_doses() {
+    let profile = [(0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (8.0, 3.0), (12.0, 1.5), (13.0, 11.0), (16.0, 7.0), (20.0, 3.5), (24.0, 1.8)];
+    let mut repeated = single_dose_subject("REPEAT", 100.0, &profile);
+    repeated.dosing_events.push(DosingEvent { time: 12.0, dose: 50.0, ..repeated.dosing_events[0].clone() });
+    let mut mixed = single_dose_subject("MIXED", 100.0, &profile);
+    mixed.dosing_events.push(DosingEvent { time: 12.0, dose: 50.0, route: DosingRoute::Oral, ..mixed.dosing_events[0].clone() });
+    let subjects = vec![repeated, mixed];
+
+    let config = AnalysisConfig { dose_normalization: true, dosing_interval: Some(12.0), ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    let find = |results: &PopulationResults, id: &str| {
+        results.dose_normalized_parameters.iter().find(|n| n.subject_id == id).cloned()
+    };
+    let raw = |results: &PopulationResults, id: &str, name: &str| {
+        results.individual_results.iter().find(|r| r.subject_id == id).unwrap().individual_parameters.get(name).unwrap()
+    };
+
+    // AUCtau over the second interval is divided by the 50 starting it, AUC_last by the total
+    let repeated = find(&results, "REPEAT").unwrap();
+    assert_eq!((repeated.dose, repeated.interval_dose), (150.0, Some(50.0)));
+    assert!((repeated.values["auc_tau"] - raw(&results, "REPEAT", "auc_tau") / 50.0).abs() < 1e-12);
+    assert!((repeated.values["auc_last"] - raw(&results, "REPEAT", "auc_last") / 150.0).abs() < 1e-12);
+
+    // Mixed routes need a bioavailability, and then use the systemic dose
+    assert!(find(&results, "MIXED").is_none());
+    let with_f = AnalysisConfig { extravascular_bioavailability: Some(0.5), ..config };
+    let results = PopulationAnalyzer::analyze_population(subjects, &with_f).unwrap();
+    let mixed = find(&results, "MIXED").unwrap();
+    assert_eq!((mixed.dose, mixed.interval_dose), (125.0, Some(25.0)));
+}
+
+#[test]
+fn test_dose_normalization_flag_end_to_end() {
+    let temp_dir = TempDir::new().unwrap();
+    let run = |output: &std::path::Path, extra: &[&str]| {
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+            .args(["--generate-example", "--subjects", "6", "--output"])
+            .arg(output)
+            .args(extra)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    let plain_output = temp_dir.path().join("plain");
+    run(&plain_output, &[]);
+    assert!(!plain_output.join("individual_results_dn.csv").exists());
+    assert!(!plain_output.join("summary_statistics_dn.csv").exists());
+
+    let dn_output = temp_dir.path().join("dn");
+    run(&dn_output, &["--dose-normalization"]);
+    let individual = std::fs::read_to_string(dn_output.join("individual_results_dn.csv")).unwrap();
+    assert!(individual.starts_with("SUBJECT_ID,DOSE,DOSE_TAU,AUC_LAST_DN,AUC_INF_DN,AUC_TAU_DN,CMAX_DN"));
+    assert_eq!(individual.lines().count(), 7);
+    assert!(dn_output.join("summary_statistics_dn.csv").exists());
+}
+
+#[test]
+fn test_regression_reports_n_and_suppresses_small_fits() {
+    use nca_analysis::covariate::CovariateAnalyzer;
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 5.0), (8.0, 2.5), (12.0, 1.2)];
+    let subjects: Vec<Subject> = [55.0, 70.0, 85.0, 100.0]
+        .iter()
+        .enumerate()
+        .map(|(i, &weight)| {
+            let scaled: Vec<(f64, f64)> = profile.iter().map(|&(t, c)| (t, c * 70.0 / weight)).collect();
+            let mut subject = single_dose_subject(&(i + 1).to_string(), 100.0, &scaled);
+            subject.demographics.weight = Some(weight);
+            subject
+        })
+        .collect();
+    let results: Vec<NcaResults> = subjects
+        .iter()
+        .map(|s| NcaAnalyzer::analyze_subject(s, &AnalysisConfig::default()).unwrap().0)
+        .collect();
+
+    let analysis = CovariateAnalyzer::analyze_covariates(&results, &subjects, &AnalysisConfig::default()).unwrap();
+    let regression = &analysis.regression_analysis["cmax_weight"];
+    assert_eq!(regression.n, 4);
+    assert!(regression.slope.unwrap() < 0.0);
+
+    let strict = AnalysisConfig {
+        min_regression_n: 5,
+        ..AnalysisConfig::default()
+    };
+    let analysis = CovariateAnalyzer::analyze_covariates(&results, &subjects, &strict).unwrap();
+    assert!(analysis.regression_analysis.is_empty());
+}
+
+#[test]
+fn test_regression_labels_set_at_construction() {
+    use nca_analysis::covariate::CovariateAnalyzer;
+
+    let regression = CovariateAnalyzer::simple_linear_regression(
+        &[50.0, 60.0, 70.0, 80.0], &[2.0, 2.4, 2.9, 3.1], "clearance", "weight",
+    );
+    assert_eq!(regression.parameter, "clearance");
+    assert_eq!(regression.covariate, "weight");
+    assert_eq!(regression.n, 4);
+
+    // Degenerate fits keep their labels too
+    let flat = CovariateAnalyzer::simple_linear_regression(&[70.0, 70.0, 70.0], &[1.0, 2.0, 3.0], "cmax", "age");
+    assert_eq!((flat.parameter.as_str(), flat.covariate.as_str()), ("cmax", "age"));
+    // ...and report an undefined slope as missing, not as a flat, non-significant one
+    assert_eq!((flat.n, flat.slope, flat.p_value, flat.confidence_interval), (3, None, None, None));
+
+    // A constant parameter has a zero slope but no coefficient of determination
+    let constant = CovariateAnalyzer::simple_linear_regression(&[50.0, 60.0, 70.0], &[2.0, 2.0, 2.0], "cmax", "weight");
+    assert_eq!((constant.slope, constant.r_squared, constant.p_value), (Some(0.0), None, None));
+
+    // The CLI refuses a regression minimum below 3 instead of raising it silently
+    let temp_dir = TempDir::new().unwrap();
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+        .args(["--generate-example", "--subjects", "3", "--min-regression-n", "2", "--output"])
+        .arg(temp_dir.path().join("out"))
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_distribution_summary_of_skewed_parameter() {
+    let values = [1.0, 1.0, 1.0, 2.0, 2.0, 3.0, 10.0];
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&values);
+    let distribution = &stats.distribution;
+
+    assert!((distribution.skewness.unwrap() - 2.366893).abs() < 1e-5);
+    assert!((distribution.excess_kurtosis.unwrap() - 5.824810).abs() < 1e-5);
+
+    // Sturges: ceil(log2(7)) + 1 = 4 bins over [1, 10]
+    assert_eq!(distribution.bin_edges, vec![1.0, 3.25, 5.5, 7.75, 10.0]);
+    assert_eq!(distribution.bin_counts, vec![6, 0, 0, 1]);
+
+    // Symmetric sample: no skew; constant sample: undefined shape, single bin
+    let symmetric = PopulationAnalyzer::calculate_parameter_stats(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert!(symmetric.distribution.skewness.unwrap().abs() < 1e-12);
+    let constant = PopulationAnalyzer::calculate_parameter_stats(&[4.0, 4.0, 4.0, 4.0]);
+    assert!(constant.distribution.skewness.is_none());
+    assert_eq!(constant.distribution.bin_counts, vec![4]);
+}
+
+#[test]
+fn test_data_completeness_per_subject() {
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2), (12.0, 0.4)];
+    let complete = single_dose_subject("1", 100.0, &profile);
+
+    // Missed the last sample, and two BLQ records in the tail
+    let mut sparse = single_dose_subject("2", 100.0, &profile[..5]);
+    sparse.observations[3].bloq = true;
+    sparse.observations[4].bloq = true;
+
+    let config = AnalysisConfig {
+        data_completeness: true,
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(vec![sparse, complete], &config).unwrap();
+    let completeness = &results.data_completeness;
+    assert_eq!(completeness.len(), 2);
+
+    let full = &completeness[0];
+    assert_eq!((full.subject_id.as_str(), full.planned_timepoints, full.observed_timepoints), ("1", 6, 6));
+    assert_eq!((full.quantifiable, full.blq), (6, 0));
+    assert_eq!((full.first_quantifiable_time, full.last_quantifiable_time), (Some(0.5), Some(12.0)));
+    assert!(full.terminal_phase_estimable);
+
+    let partial = &completeness[1];
+    assert_eq!((partial.planned_timepoints, partial.observed_timepoints), (6, 5));
+    assert_eq!((partial.quantifiable, partial.blq), (3, 2));
+    assert_eq!(partial.last_quantifiable_time, Some(2.0));
+
+    // Off by default
+    let results = PopulationAnalyzer::analyze_population(
+        vec![single_dose_subject("1", 100.0, &profile)], &AnalysisConfig::default(),
+    ).unwrap();
+    assert!(results.data_completeness.is_empty());
+}
+
+#[test]
+fn test_auc_tau_over_explicit_dosing_interval() {
+    use nca_analysis::{auc::AucCalculator, nca::NcaAnalyzer};
+
+    // Single (non-SS) dose; no sample at 12 h, so the interval end is interpolated
+    let profile = [(0.0, 0.0), (1.0, 12.0), (2.0, 10.0), (4.0, 7.0), (8.0, 3.5), (16.0, 0.9), (24.0, 0.3)];
+    let subject = single_dose_subject("1", 100.0, &profile);
+
+    let (default_results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(default_results.individual_parameters.auc_tau.is_none());
+
+    let config = AnalysisConfig {
+        dosing_interval: Some(12.0),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let auc_tau = results.individual_parameters.auc_tau.unwrap();
+
+    let expected = AucCalculator::calculate_partial_auc(
+        &subject.observations, 0.0, 12.0, &AucCalculator::primary_method(&config), &config.interpolation_method,
+    ).unwrap();
+    assert!((auc_tau - expected).abs() < 1e-9);
+
+    let to_8h = AucCalculator::calculate_partial_auc(
+        &subject.observations, 0.0, 8.0, &AucCalculator::primary_method(&config), &config.interpolation_method,
+    ).unwrap();
+    assert!(auc_tau > to_8h && auc_tau < results.individual_parameters.auc_last.unwrap());
+
+    // A non-positive interval is rejected rather than leaving AUCtau quietly missing
+    let zero = AnalysisConfig { dosing_interval: Some(0.0), ..AnalysisConfig::default() };
+    assert!(PopulationAnalyzer::analyze_population(vec![subject], &zero).is_err());
+    let temp_dir = TempDir::new().unwrap();
+    for tau in ["0", "-12"] {
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+            .args(["--generate-example", "--subjects", "3", "--output"])
+            .arg(temp_dir.path().join("out"))
+            .arg(format!("--dosing-interval={}", tau))
+            .status()
+            .unwrap();
+        assert!(!status.success(), "--dosing-interval {}", tau);
+    }
+}
+
+#[test]
+fn test_cmax_at_last_timepoint_is_flagged() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let thresholds = ValidationThresholds::default();
+    let rising = single_dose_subject("1", 100.0, &[(0.5, 1.0), (1.0, 2.5), (2.0, 4.0), (4.0, 6.5), (8.0, 9.0)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&rising, &AnalysisConfig::default()).unwrap();
+    assert_eq!(results.individual_parameters.tmax, results.individual_parameters.tlast);
+
+    let warnings = NcaAnalyzer::validate_results(&results, &thresholds);
+    assert!(warnings.iter().any(|w| w.contains("Tmax = Tlast")));
+
+    let declining = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&declining, &AnalysisConfig::default()).unwrap();
+    assert!(!NcaAnalyzer::validate_results(&results, &thresholds).iter().any(|w| w.contains("Tmax = Tlast")));
+}
+
+#[test]
+fn test_cumulative_auc_profile() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.0, 0.0), (0.5, 8.0), (1.0, 10.0), (2.0, 7.0), (4.0, 4.0), (8.0, 1.5), (12.0, 0.5)];
+    let subject = single_dose_subject("1", 100.0, &profile);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+
+    let times: Vec<f64> = params.cumulative_auc.iter().map(|p| p.time).collect();
+    assert_eq!(times, profile.iter().map(|&(t, _)| t).collect::<Vec<_>>());
+    assert_eq!(params.cumulative_auc[0].auc, 0.0);
+    assert!((params.cumulative_auc[1].auc - 2.0).abs() < 1e-12);
+    assert!(params.cumulative_auc.windows(2).all(|w| w[1].auc >= w[0].auc));
+
+    let last = params.cumulative_auc.last().unwrap().auc;
+    assert!((last - params.auc_last.unwrap()).abs() < 1e-9);
+}
+
+#[test]
+fn test_evid2_time_varying_covariates() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("evid2_dataset.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CRCL", &[
+        "1,0,0,100,1,",
+        "1,0,.,0,2,95",
+        "1,1,8.0,0,0,",
+        "1,2,6.0,0,0,",
+        "1,4,3.5,0,0,",
+        "1,6,.,0,2,80",
+        "1,8,1.2,0,0,",
+    ]);
+
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let subject = &subjects[0];
+    assert_eq!(subject.observations.len(), 4);
+    assert_eq!(subject.covariate_records.len(), 2);
+    assert_eq!(subject.covariate_records[1].values.get("CRCL"), Some(&80.0));
+    assert!(!subject.covariate_records[0].values.contains_key("DV"));
+
+    // Values are carried forward to the time of interest
+    assert_eq!(subject.covariate_at("crcl", 4.0), Some(95.0));
+    assert_eq!(subject.covariate_at("CRCL", 8.0), Some(80.0));
+    assert_eq!(subject.covariate_at("CRCL", -1.0), None);
+}
+
+#[test]
+fn test_summary_convention_per_parameter() {
+    use std::collections::HashMap;
+
+    let config = AnalysisConfig::default();
+    assert_eq!(config.summary_convention("auc_inf"), SummaryConvention::Geometric);
+    assert_eq!(config.summary_convention("cmax"), SummaryConvention::Geometric);
+    assert_eq!(config.summary_convention("tmax"), SummaryConvention::Arithmetic);
+    assert_eq!(config.summary_convention("half_life"), SummaryConvention::Harmonic);
+
+    let overridden = AnalysisConfig {
+        summary_conventions: HashMap::from([("cmax".to_string(), SummaryConvention::Arithmetic)]),
+        ..AnalysisConfig::default()
+    };
+    assert_eq!(overridden.summary_convention("cmax"), SummaryConvention::Arithmetic);
+    assert_eq!(overridden.summary_convention("auc_inf"), SummaryConvention::Geometric);
+
+    let temp_dir = TempDir::new().unwrap();
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]),
+        single_dose_subject("2", 100.0, &[(0.5, 12.0), (1.0, 9.0), (2.0, 6.5), (4.0, 3.0), (8.0, 1.0)]),
+    ];
+    let results = PopulationAnalyzer::analyze_population(subjects, &overridden).unwrap();
+    OutputManager::save_results(&results, &overridden, temp_dir.path()).unwrap();
+
+    let report = std::fs::read_to_string(temp_dir.path().join("analysis_report.txt")).unwrap();
+    let line = |param: &str| report.lines().find(|l| l.starts_with(&format!("- {}:", param))).unwrap().to_string();
+    assert!(line("auc_last").ends_with("(geometric)"));
+    assert!(line("tmax").ends_with("(arithmetic)"));
+    assert!(line("cmax").ends_with("(arithmetic)"));
+}
+
+#[test]
+fn test_csv_output_quotes_fields_with_commas() {
+    let temp_dir = TempDir::new().unwrap();
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let subjects: Vec<Subject> = ["Tablet, 50mg", "Capsule \"A\""]
+        .iter()
+        .enumerate()
+        .map(|(i, &treatment)| {
+            let mut subject = single_dose_subject(&(i + 1).to_string(), 100.0, &profile);
+            subject.demographics.treatment = Some(treatment.to_string());
+            subject
+        })
+        .collect();
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["TREATMENT".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: false,
+            comparison_test: ComparisonTest::WelchTTest,
+        }),
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("stratified_analysis.csv")).unwrap();
+    let width = reader.headers().unwrap().len();
+    let mut values = std::collections::HashSet::new();
+    for record in reader.records() {
+        let record = record.unwrap();
+        assert_eq!(record.len(), width);
+        values.insert(record[1].to_string());
+    }
+    assert!(values.contains("Tablet, 50mg"));
+    assert!(values.contains("Capsule \"A\""));
+}
+
+#[test]
+fn test_rate_flags_are_not_routes() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("rate_dataset.csv");
+    let mut rows = vec![
+        "1,0,0,100,1,,-2".to_string(), // modeled duration
+        "2,0,0,100,1,,-1".to_string(), // modeled rate
+        "3,0,0,100,1,,10".to_string(), // 10 h infusion
+        "4,0,0,100,1,ORAL,-2".to_string(),
+        "5,0,0,100,1,PO,".to_string(),
+    ];
+    for id in 1..=5 {
+        rows.push(format!("{},1,8.0,0,0,,", id));
+    }
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,ROUTE,RATE", &rows);
+
+    let mut subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    subjects.sort_by(|a, b| a.id.cmp(&b.id));
+    let dose = |i: usize| (subjects[i].dosing_events[0].route.clone(), subjects[i].dosing_events[0].infusion_duration);
+
+    assert_eq!(dose(0), (DosingRoute::IntravenousInfusion, None));
+    assert_eq!(dose(1), (DosingRoute::IntravenousBolus, None));
+    assert_eq!(dose(2), (DosingRoute::IntravenousInfusion, Some(10.0)));
+    assert_eq!(dose(3), (DosingRoute::Oral, None));
+    assert_eq!(dose(4), (DosingRoute::Oral, None));
+    assert!(!subjects[3].demographics.extra.contains_key("ROUTE"));
+}
+
+#[test]
+fn test_explicit_route_column_overrides_rate() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("route_dataset.csv");
+    let mut rows = vec![
+        "1,0,0,100,1,IV,10".to_string(),       // RATE alone would say infusion
+        "2,0,0,100,1,SC,-1".to_string(),
+        "3,0,0,100,1,IM,".to_string(),
+        "4,0,0,100,1,INFUSION,20".to_string(), // RATE still gives the duration
+        "5,0,0,100,1,,10".to_string(),         // no ROUTE: inferred from RATE
+    ];
+    for id in 1..=5 {
+        rows.push(format!("{},1,8.0,0,0,,", id));
+    }
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,ROUTE,RATE", &rows);
+
+    let mut subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    subjects.sort_by(|a, b| a.id.cmp(&b.id));
+    let dose = |i: usize| (subjects[i].dosing_events[0].route.clone(), subjects[i].dosing_events[0].infusion_duration);
+
+    assert_eq!(dose(0), (DosingRoute::IntravenousBolus, None));
+    assert_eq!(dose(1), (DosingRoute::Subcutaneous, None));
+    assert_eq!(dose(2), (DosingRoute::Intramuscular, None));
+    assert_eq!(dose(3), (DosingRoute::IntravenousInfusion, Some(5.0)));
+    assert_eq!(dose(4), (DosingRoute::IntravenousInfusion, Some(10.0)));
+    assert!(dose(1).0.is_extravascular() && !dose(0).0.is_extravascular());
+}
+
+#[test]
+fn test_subcutaneous_dose_is_extravascular() {
+    use nca_analysis::{example_data::ExampleDataGenerator, nca::NcaAnalyzer};
+
+    let profile = [(0.0, 0.0), (0.5, 0.0), (1.0, 4.0), (2.0, 9.0), (4.0, 7.0), (8.0, 3.5), (12.0, 1.6), (24.0, 0.2)];
+    let mut subject = single_dose_subject("1", 100.0, &profile);
+    subject.dosing_events[0].route = DosingRoute::Subcutaneous;
+
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    assert!(params.apparent_clearance);
+    assert!(params.clearance.is_some() && params.volume_terminal.is_some());
+    assert!(params.volume_steady_state.is_none());
+    assert_eq!(params.tlag, Some(0.5));
+    assert_eq!((params.cmax, params.tmax), (Some(9.0), Some(2.0)));
+
+    // The same profile after an IV bolus reports true clearance and no lag
+    subject.dosing_events[0].route = DosingRoute::IntravenousBolus;
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(!results.individual_parameters.apparent_clearance);
+    assert!(results.individual_parameters.tlag.is_none());
+
+    // The example generator can draw SC subjects
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("sc_dataset.csv");
+    ExampleDataGenerator::generate_dataset_with_routes(&dataset_path, 20, true).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    assert!(subjects.iter().any(|s| s.dosing_events[0].route == DosingRoute::Subcutaneous));
+}
+
+#[test]
+fn test_method_comparison_can_be_disabled() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (full, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert_eq!(full.method_comparisons.len(), 5);
+
+    let config = AnalysisConfig {
+        compute_method_comparison: false,
+        ..AnalysisConfig::default()
+    };
+    let (primary_only, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    assert!(primary_only.method_comparisons.is_empty());
+    assert_eq!(primary_only.individual_parameters.auc_last, full.individual_parameters.auc_last);
+}
+
+#[test]
+fn test_weighted_summary_statistics() {
+    let values = [1.0, 2.0, 3.0, 6.0];
+    let unweighted = PopulationAnalyzer::calculate_parameter_stats(&values);
+    let equal = PopulationAnalyzer::calculate_weighted_parameter_stats(&values, &[2.5; 4]);
+    assert!((equal.mean - unweighted.mean).abs() < 1e-12);
+    assert!((equal.std.unwrap() - unweighted.std.unwrap()).abs() < 1e-12);
+    assert!((equal.geometric_mean.unwrap() - unweighted.geometric_mean.unwrap()).abs() < 1e-12);
+    assert!((equal.geometric_cv_percent.unwrap() - unweighted.geometric_cv_percent.unwrap()).abs() < 1e-9);
+
+    // Weight 2 on the largest value: (1 + 2 + 3 + 12) / 5 = 3.6
+    let weighted = PopulationAnalyzer::calculate_weighted_parameter_stats(&values, &[1.0, 1.0, 1.0, 2.0]);
+    assert!((weighted.mean - 3.6).abs() < 1e-12);
+    assert!((weighted.arithmetic_mean - 3.6).abs() < 1e-12);
+    let expected_geo = ((2.0f64.ln() + 3.0f64.ln() + 2.0 * 6.0f64.ln()) / 5.0).exp();
+    assert!((weighted.geometric_mean.unwrap() - expected_geo).abs() < 1e-12);
+    assert_eq!(weighted.median, unweighted.median);
+
+    // Configured weights take precedence over the dataset column
+    let mut subject = Subject {
+        id: "7".to_string(),
+        observations: Vec::new(),
+        dosing_events: Vec::new(),
+        demographics: Demographics::default(),
+        covariate_records: Vec::new(),
+        analyte: None,
+        matrix: None,
+    };
+    subject.demographics.analysis_weight = Some(4.0);
+    let mut config = AnalysisConfig::default();
+    assert_eq!(PopulationAnalyzer::subject_weights(&[subject.clone()], &config)["7"], 4.0);
+    config.subject_weights.insert("7".to_string(), 0.5);
+    assert_eq!(PopulationAnalyzer::subject_weights(&[subject], &config)["7"], 0.5);
+}
+
+#[test]
+fn test_streaming_output_matches_batch() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("dataset.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let config = AnalysisConfig::default();
+
+    let batch_dir = temp_dir.path().join("batch");
+    let batch = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    OutputManager::save_results(&batch, &config, &batch_dir).unwrap();
+
+    let stream_dir = temp_dir.path().join("stream");
+    let streamed = PopulationAnalyzer::analyze_population_streaming(subjects, &config, &stream_dir).unwrap();
+    assert_eq!(streamed.subjects_analyzed, batch.individual_results.len());
+
+    let read = |dir: &std::path::Path| std::fs::read_to_string(dir.join("individual_results.csv")).unwrap();
+    assert_eq!(read(&stream_dir), read(&batch_dir));
+
+    for (param, expected) in &batch.summary_statistics.parameter_stats {
+        let actual = &streamed.summary_statistics.parameter_stats[param];
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b.abs().max(1.0);
+        assert_eq!(actual.n, expected.n);
+        assert!(close(actual.mean, expected.mean), "{} mean", param);
+        match (actual.std, expected.std) {
+            (Some(a), Some(b)) => assert!(close(a, b), "{} std", param),
+            (a, b) => assert_eq!(a, b, "{} std", param),
+        }
+        assert_eq!((actual.min, actual.max), (expected.min, expected.max));
+        match (actual.geometric_mean, expected.geometric_mean) {
+            (Some(a), Some(b)) => assert!(close(a, b), "{} geo mean", param),
+            (a, b) => assert_eq!(a, b, "{} geo mean", param),
+        }
+        match (actual.geometric_cv_percent, expected.geometric_cv_percent) {
+            (Some(a), Some(b)) => assert!(close(a, b), "{} geo CV", param),
+            (a, b) => assert_eq!(a.is_some(), b.is_some(), "{} geo CV", param),
+        }
+        assert_eq!((actual.median, actual.q25, actual.q75), (expected.median, expected.q25, expected.q75), "{} quantiles", param);
+    }
+
+    let streamed_summary = std::fs::read_to_string(stream_dir.join("summary_statistics.csv")).unwrap();
+    assert!(!streamed_summary.contains(",NA,NA,NA,"));
+}
+
+#[test]
+fn test_harmonic_mean_of_half_lives() {
+    // 3 / (1/2 + 1/4 + 1/8) = 24/7
+    let half_lives = [2.0, 4.0, 8.0];
+    let (harmonic_mean, pseudo_sd) = PopulationAnalyzer::calculate_harmonic_mean(&half_lives);
+    assert!((harmonic_mean.unwrap() - 24.0 / 7.0).abs() < 1e-12);
+
+    // Leave-one-out harmonic means 2/(3/8) = 16/3, 2/(5/8) = 16/5, 2/(3/4) = 8/3;
+    // pseudo-SD = sqrt(2 * sum of squared deviations from their mean 56/15)
+    let leave_one_out = [16.0 / 3.0, 16.0 / 5.0, 8.0 / 3.0];
+    let jackknife_mean = leave_one_out.iter().sum::<f64>() / 3.0;
+    let expected_sd = (2.0 * leave_one_out.iter().map(|h| (h - jackknife_mean).powi(2)).sum::<f64>()).sqrt();
+    assert!((jackknife_mean - 56.0 / 15.0).abs() < 1e-12);
+    assert!((pseudo_sd.unwrap() - expected_sd).abs() < 1e-12);
+
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&half_lives);
+    assert_eq!(stats.harmonic_mean, harmonic_mean);
+    assert!(PopulationAnalyzer::calculate_harmonic_mean(&[2.0, 0.0]).0.is_none());
+
+    // The report leads with the harmonic mean for half-life
+    let formatted = OutputManager::format_emphasized_statistic(&stats, &SummaryConvention::default_for("half_life"));
+    assert!(formatted.starts_with("3.429") && formatted.contains("harmonic"));
+}
+
+#[test]
+fn test_flip_flop_profile_is_flagged() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // Oral absorption (ka = 0.1/h) ten times slower than elimination (ke = 1/h): the terminal
+    // phase follows ka, so the oral half-life is ~6.9 h against ~0.69 h after IV
+    let times = [0.5, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0];
+    let oral_profile: Vec<(f64, f64)> = times
+        .iter()
+        .map(|&t: &f64| (t, 100.0 * ((-0.1 * t).exp() - (-t).exp())))
+        .collect();
+    let mut oral = single_dose_subject("oral", 100.0, &oral_profile);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let iv_profile: Vec<(f64, f64)> = [0.25, 0.5, 1.0, 2.0, 3.0, 4.0, 6.0]
+        .iter()
+        .map(|&t: &f64| (t, 10.0 * (-t).exp()))
+        .collect();
+    let iv = single_dose_subject("iv", 100.0, &iv_profile);
+
+    // Half-life / Tmax alone is ~2.7 here, below the default ratio
+    let config = AnalysisConfig::default();
+    let (oral_alone, _) = NcaAnalyzer::analyze_subject(&oral, &config).unwrap();
+    assert!(!oral_alone.individual_parameters.flip_flop_suspected);
+
+    // The IV arm gives the elimination reference
+    let results = PopulationAnalyzer::analyze_population(vec![oral.clone(), iv], &config).unwrap();
+    let flagged = |id: &str| {
+        results.individual_results.iter().find(|r| r.subject_id == id).unwrap().individual_parameters.flip_flop_suspected
+    };
+    assert!(flagged("oral"));
+    assert!(!flagged("iv"));
+
+    // A tighter half-life / Tmax ratio flags the profile on its own, with a validation warning
+    let strict = AnalysisConfig {
+        validation_thresholds: ValidationThresholds {
+            flip_flop_half_life_tmax_ratio: 2.0,
+            ..ValidationThresholds::default()
+        },
+        ..AnalysisConfig::default()
+    };
+    let (oral_strict, _) = NcaAnalyzer::analyze_subject(&oral, &strict).unwrap();
+    assert!(oral_strict.individual_parameters.flip_flop_suspected);
+    let warnings = NcaAnalyzer::validate_results(&oral_strict, &strict.validation_thresholds);
+    assert!(warnings.iter().any(|w| w.contains("flip-flop")));
+}
+
+#[test]
+fn test_lambda_z_point_count_and_time_range_are_reported() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let config = AnalysisConfig {
+        lambda_z_selection: LambdaZSelection::Manual(vec![2, 3, 4]),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.lambda_z_n_points, Some(3));
+    assert_eq!(params.lambda_z_time_range, Some((2.0, 8.0)));
+    let strict = ValidationThresholds {
+        min_lambda_z_r_squared: 1.0,
+        ..ValidationThresholds::default()
+    };
+    let warnings = NcaAnalyzer::validate_results(&results, &strict);
+    assert!(warnings.iter().any(|w| w.contains("Poor terminal phase fit") && w.contains("3 points, t = 2-8")));
+    assert!(!warnings.iter().any(|w| w.contains("fewer than")));
+
+    // A two-point fit is flagged against the default minimum of 3
+    let two_points = AnalysisConfig {
+        lambda_z_selection: LambdaZSelection::Manual(vec![3, 4]),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &two_points).unwrap();
+    let warnings = NcaAnalyzer::validate_results(&results, &ValidationThresholds::default());
+    assert!(warnings.iter().any(|w| w.contains("fewer than 3 points (2 points, t = 4-8)")));
+
+    let temp_dir = TempDir::new().unwrap();
+    let population = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+    OutputManager::save_results(&population, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("individual_results.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let row = reader.records().next().unwrap().unwrap();
+    let column = |name: &str| row[headers.iter().position(|h| h == name).unwrap()].to_string();
+    assert_eq!((column("LAMBDA_Z_N_POINTS"), column("LAMBDA_Z_TSTART"), column("LAMBDA_Z_TEND")), ("3".into(), "2".into(), "8".into()));
+}
+
+#[test]
+fn test_example_seed_and_variability_are_configurable() {
+    use nca_analysis::example_data::ExampleDataOptions;
+
+    let temp_dir = TempDir::new().unwrap();
+    let generate = |name: &str, options: &ExampleDataOptions| {
+        let path = temp_dir.path().join(name);
+        ExampleDataGenerator::generate_dataset_with_options(&path, 10, options).unwrap();
+        path
+    };
+
+    // The default options reproduce the historical seed-42 dataset
+    let default_path = generate("default.csv", &ExampleDataOptions::default());
+    let legacy_path = temp_dir.path().join("legacy.csv");
+    ExampleDataGenerator::generate_dataset(&legacy_path, 10).unwrap();
+    let read = |path: &std::path::Path| std::fs::read_to_string(path).unwrap();
+    assert_eq!(read(&default_path), read(&legacy_path));
+
+    let seed_7 = ExampleDataOptions { seed: 7, ..ExampleDataOptions::default() };
+    let first = generate("seed7a.csv", &seed_7);
+    let second = generate("seed7b.csv", &seed_7);
+    assert_eq!(read(&first), read(&second));
+    assert_ne!(read(&first), read(&default_path));
+
+    // Both seeds give complete, analyzable datasets of the same shape
+    for path in [&default_path, &first] {
+        let subjects = NonmemParser::parse_dataset(path).unwrap();
+        assert_eq!(subjects.len(), 10);
+        assert!(subjects.iter().all(|s| s.dosing_events.len() == 1 && s.observations.len() == 12));
+        let results = PopulationAnalyzer::analyze_population(subjects, &AnalysisConfig::default()).unwrap();
+        assert_eq!(results.individual_results.len() + results.failed_subjects.len(), 10);
+    }
+
+    // Variability settings change the concentrations but not the design
+    let noiseless = ExampleDataOptions { iiv_cv: Some(0.0), residual_cv: 0.0, ..ExampleDataOptions::default() };
+    let path = generate("noiseless.csv", &noiseless);
+    assert_ne!(read(&path), read(&default_path));
+    let subjects = NonmemParser::parse_dataset(&path).unwrap();
+    assert_eq!(subjects.len(), 10);
+    assert!(subjects.iter().all(|s| s.observations.iter().all(|o| o.concentration >= 0.0)));
+}
+
+#[test]
+fn test_example_data_with_missing_samples_and_dropouts() {
+    use nca_analysis::example_data::ExampleDataOptions;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("sparse.csv");
+    let options = ExampleDataOptions {
+        missing_fraction: 0.2,
+        dropout_fraction: 0.3,
+        ..ExampleDataOptions::default()
+    };
+    ExampleDataGenerator::generate_dataset_with_options(&path, 30, &options).unwrap();
+    let subjects = NonmemParser::parse_dataset(&path).unwrap();
+    assert_eq!(subjects.len(), 30);
+
+    // Complete profiles have 12 samples; at least some subjects now miss timepoints
+    let sample_counts: Vec<usize> = subjects.iter().map(|s| s.observations.len()).collect();
+    assert!(sample_counts.iter().any(|&n| n < 12));
+    assert!(sample_counts.iter().all(|&n| (1..=12).contains(&n)));
+    assert!(subjects.iter().all(|s| s.observations[0].time == 0.0));
+
+    let config = AnalysisConfig { data_completeness: true, ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    assert!(results.data_completeness.iter().any(|c| c.observed_timepoints < c.planned_timepoints));
+}
+
+#[test]
+fn test_method_comparison_percent_difference_from_primary() {
+    let temp_dir = TempDir::new().unwrap();
+    // Mono-exponential decline: linear trapezoids overestimate, so log-down methods come out lower
+    let profile: Vec<(f64, f64)> = [0.5, 1.0, 2.0, 3.0, 4.0]
+        .iter()
+        .map(|&t: &f64| (t, 10.0 * (-0.3 * t).exp()))
+        .collect();
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &profile),
+        single_dose_subject("2", 100.0, &profile),
+    ];
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    let comparison = &results.method_comparison;
+    assert_eq!(comparison.primary_method, "LinearTrapezoidal");
+
+    let diffs = &comparison.percent_difference_from_primary;
+    assert_eq!(diffs["LinearTrapezoidal"], 0.0);
+    assert!(diffs["LogTrapezoidal"] < 0.0);
+    assert!(diffs["LinearUpLogDown"] < 0.0);
+    let linear = comparison.auc_methods["LinearTrapezoidal"];
+    let log_down = comparison.auc_methods["LinearUpLogDown"];
+    assert!((diffs["LinearUpLogDown"] - (log_down - linear) / linear * 100.0).abs() < 1e-9);
+
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let csv = std::fs::read_to_string(temp_dir.path().join("method_comparison.csv")).unwrap();
+    assert!(csv.starts_with("METHOD,MEAN_AUC,PERCENT_DIFF_FROM_PRIMARY,PRIMARY"));
+    assert!(csv.lines().any(|line| line.starts_with("LinearTrapezoidal,") && line.ends_with(",0.00,true")));
+}
+
+#[test]
+fn test_units_row_is_read_as_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("units.csv");
+    std::fs::write(&path, "\
+ID,TIME,DV,AMT,EVID,CMT,WT
+,h,(ng/mL),mg,,,kg
+1,0,0,100,1,1,70
+1,1,10,0,0,1,70
+1,2,8,0,0,1,70
+1,4,5,0,0,1,70
+").unwrap();
+
+    let (subjects, units) = NonmemParser::parse_dataset_with_units(&path).unwrap();
+    let units = units.unwrap();
+    assert_eq!(units.time(), Some("h"));
+    assert_eq!(units.concentration(), Some("ng/mL"));
+    assert_eq!(units.columns.get("WT").map(String::as_str), Some("kg"));
+
+    // The units row is not a subject or an observation
+    assert_eq!(subjects.len(), 1);
+    assert_eq!(subjects[0].id, "1");
+    assert_eq!(subjects[0].observations.len(), 3);
+    assert_eq!(subjects[0].dosing_events.len(), 1);
+
+    // Datasets without a units row are unaffected
+    let plain = temp_dir.path().join("plain.csv");
+    ExampleDataGenerator::generate_dataset(&plain, 2).unwrap();
+    let (subjects, units) = NonmemParser::parse_dataset_with_units(&plain).unwrap();
+    assert!(units.is_none());
+    assert_eq!(subjects.len(), 2);
+}
+
+#[test]
+fn test_single_value_statistics() {
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&[42.0]);
+    assert_eq!(stats.n, 1);
+    assert_eq!((stats.mean, stats.median, stats.min, stats.max), (42.0, 42.0, 42.0, 42.0));
+    assert_eq!(stats.arithmetic_mean, 42.0);
+    assert_eq!(stats.std, None);
+    assert_eq!(stats.arithmetic_std, None);
+    assert_eq!(stats.cv_percent, None);
+    assert_eq!(stats.arithmetic_cv_percent, None);
+    assert!((stats.geometric_mean.unwrap() - 42.0).abs() < 1e-12);
+    assert_eq!(stats.geometric_cv_percent, None);
+
+    // Two values still get an SD and CVs
+    let pair = PopulationAnalyzer::calculate_parameter_stats(&[40.0, 44.0]);
+    assert!((pair.std.unwrap() - 8.0f64.sqrt()).abs() < 1e-12);
+    assert!(pair.cv_percent.is_some() && pair.geometric_cv_percent.is_some());
+
+    // A single-subject population writes NA rather than zero for its spread
+    let temp_dir = TempDir::new().unwrap();
+    let subjects = vec![single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)])];
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let summary = std::fs::read_to_string(temp_dir.path().join("summary_statistics.csv")).unwrap();
+    let cmax = summary.lines().find(|line| line.starts_with("cmax,")).unwrap();
+    let fields: Vec<&str> = cmax.split(',').collect();
+    assert_eq!((fields[3], fields[4], fields[11]), ("NA", "NA", "NA"));
+}
+
+#[test]
+fn test_auc_inf_extrapolation_slope_choice() {
+    use nca_analysis::nca::NcaAnalyzer;
+    use nca_analysis::parameters::ParameterCalculator;
+
+    // The last segment falls faster than the terminal regression over the last points
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.6), (12.0, 0.5)]);
+    let (regression, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let regression = regression.individual_parameters;
+    assert_eq!(regression.auc_inf_slope, regression.lambda_z);
+    assert_eq!(regression.auc_inf_slope_method, Some(AucExtrapolationSlope::TerminalRegression));
+
+    let config = AnalysisConfig {
+        auc_extrapolation_slope: AucExtrapolationSlope::LastTwoPoints,
+        ..AnalysisConfig::default()
+    };
+    let (last_two, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let last_two = last_two.individual_parameters;
+    let expected_slope = (1.6f64 / 0.5).ln() / 4.0;
+    assert!((last_two.auc_inf_slope.unwrap() - expected_slope).abs() < 1e-12);
+    assert_eq!(last_two.auc_inf_slope_method, Some(AucExtrapolationSlope::LastTwoPoints));
+    assert!((last_two.auc_inf.unwrap() - (last_two.auc_last.unwrap() + 0.5 / expected_slope)).abs() < 1e-9);
+    assert_ne!(last_two.auc_inf, regression.auc_inf);
+
+    // Half-life still comes from the terminal regression
+    assert_eq!(last_two.half_life, regression.half_life);
+
+    // AUMC_inf, MRT and Vz follow the chosen slope rather than mixing it with lambda_z
+    let expected_aumc = last_two.aumc_last.unwrap() + 12.0 * 0.5 / expected_slope + 0.5 / expected_slope.powi(2);
+    assert!((last_two.aumc_inf.unwrap() - expected_aumc).abs() < 1e-9);
+    assert!((last_two.mrt.unwrap() - expected_aumc / last_two.auc_inf.unwrap()).abs() < 1e-9);
+    assert!((last_two.volume_terminal.unwrap() - last_two.clearance.unwrap() / expected_slope).abs() < 1e-9);
+    assert!((regression.volume_terminal.unwrap() - regression.clearance.unwrap() / regression.lambda_z.unwrap()).abs() < 1e-9);
+
+    // A rising last segment has no usable two-point slope
+    let rising = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 7.0)]);
+    assert!(ParameterCalculator::calculate_last_two_points_slope(&rising.observations).is_none());
+}
+
+#[test]
+fn test_analyte_and_matrix_tags_round_trip_through_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("tagged.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,MDV,CMT,ANALYTE,MATRIX", &[
+        "1,0,0,100,1,1,1,,",
+        "1,0.5,10,0,0,0,2,Parent,Plasma",
+        "1,1,8,0,0,0,2,Parent,Plasma",
+        "1,2,6,0,0,0,2,Parent,Plasma",
+        "1,4,3.5,0,0,0,2,Parent,Plasma",
+        "1,8,1.2,0,0,0,2,Parent,Plasma",
+        "2,0,0,100,1,1,1,,",
+        "2,0.5,9,0,0,0,2,,",
+        "2,1,7,0,0,0,2,,",
+        "2,2,5,0,0,0,2,,",
+        "2,4,3,0,0,0,2,,",
+        "2,8,1,0,0,0,2,,",
+    ]);
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    let tagged = results.individual_results.iter().find(|r| r.subject_id == "1").unwrap();
+    assert_eq!((tagged.analyte.as_deref(), tagged.matrix.as_deref()), (Some("Parent"), Some("Plasma")));
+
+    let output_dir = temp_dir.path().join("output");
+    OutputManager::save_results(&results, &config, &output_dir).unwrap();
+    let mut reader = csv::Reader::from_path(output_dir.join("individual_results.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+    let row = |id: &str| rows.iter().find(|r| &r[0] == id).unwrap();
+    assert_eq!((&row("1")[column("ANALYTE")], &row("1")[column("MATRIX")]), ("Parent", "Plasma"));
+    assert_eq!((&row("2")[column("ANALYTE")], &row("2")[column("MATRIX")]), ("NA", "NA"));
+
+    let json = std::fs::read_to_string(output_dir.join("complete_results.json")).unwrap();
+    let parsed: PopulationResults = serde_json::from_str(&json).unwrap();
+    let tagged = parsed.individual_results.iter().find(|r| r.subject_id == "1").unwrap();
+    assert_eq!((tagged.analyte.as_deref(), tagged.matrix.as_deref()), (Some("Parent"), Some("Plasma")));
+
+    // Two analytes under one subject are rejected instead of merged under the last tag
+    let mixed_path = temp_dir.path().join("mixed.csv");
+    write_dataset(&mixed_path, "ID,TIME,DV,AMT,EVID,MDV,CMT,ANALYTE,MATRIX", &[
+        "1,0,0,100,1,1,1,,",
+        "1,1,8,0,0,0,2,Parent,Plasma",
+        "1,1,2,0,0,0,2,Metabolite,Plasma",
+    ]);
+    let error = NonmemParser::parse_dataset(&mixed_path).unwrap_err();
+    assert!(error.to_string().contains("more than one ANALYTE"), "{}", error);
+}
+
+#[test]
+fn test_unreliable_auc_inf_excluded_from_summary() {
+    // Subjects 1 and 2 are sampled well into the terminal phase; subject 3 stops at 10.5 h
+    // with ~35% of AUC_inf extrapolated
+    let profile = |k: f64, times: &[f64]| -> Vec<(f64, f64)> {
+        times.iter().map(|&t| (t, 100.0 * (-k * t).exp())).collect()
+    };
+    let long = [0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 24.0, 36.0];
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &profile(0.2, &long)),
+        single_dose_subject("2", 100.0, &profile(0.25, &long)),
+        single_dose_subject("3", 100.0, &profile(0.1, &[0.0, 1.0, 2.0, 4.0, 6.0, 8.0, 10.5])),
+    ];
+
+    let config = AnalysisConfig::default();
+    let included = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    let unreliable = included.individual_results.iter().find(|r| r.subject_id == "3").unwrap();
+    assert_eq!(unreliable.individual_parameters.auc_inf_reliable, Some(false));
+    assert_eq!(included.summary_statistics.parameter_stats["auc_inf"].n, 3);
+    assert_eq!(included.summary_statistics.auc_inf_excluded, 0);
+
+    let excluding = AnalysisConfig {
+        exclude_unreliable_auc_inf: true,
+        ..AnalysisConfig::default()
+    };
+    let excluded = PopulationAnalyzer::analyze_population(subjects, &excluding).unwrap();
+    let auc_inf = &excluded.summary_statistics.parameter_stats["auc_inf"];
+    assert_eq!(auc_inf.n, 2);
+    assert_eq!(excluded.summary_statistics.auc_inf_excluded, 1);
+    let reliable: Vec<f64> = excluded.individual_results.iter()
+        .filter(|r| r.subject_id != "3")
+        .map(|r| r.individual_parameters.auc_inf.unwrap())
+        .collect();
+    let expected = (reliable[0] * reliable[1]).sqrt();
+    assert!((auc_inf.geometric_mean.unwrap() - expected).abs() < 1e-9 * expected);
+    assert!((auc_inf.geometric_mean.unwrap() - included.summary_statistics.parameter_stats["auc_inf"].geometric_mean.unwrap()).abs() > 1.0);
+
+    // Still reported individually, and other parameters keep every subject
+    assert!(excluded.individual_results.iter().all(|r| r.individual_parameters.auc_inf.is_some()));
+    assert_eq!(excluded.summary_statistics.parameter_stats["cmax"].n, 3);
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&excluded, &excluding, temp_dir.path()).unwrap();
+    let summary = std::fs::read_to_string(temp_dir.path().join("population_summary.csv")).unwrap();
+    assert!(summary.contains("AUC_inf Included,2"));
+    assert!(summary.contains("AUC_inf Excluded (Unreliable),1"));
+}
+
+#[test]
+fn test_sparse_subject_gets_observed_only_parameters() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let sparse = single_dose_subject("sparse", 100.0, &[(1.0, 8.0), (4.0, 2.0)]);
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&sparse, &AnalysisConfig::default()).unwrap();
+    assert_eq!(results.result_type, NcaResultType::ObservedOnly);
+    let params = &results.individual_parameters;
+    assert_eq!((params.cmax, params.tmax), (Some(8.0), Some(1.0)));
+    assert_eq!((params.clast, params.tlast), (Some(2.0), Some(4.0)));
+    assert!((params.auc_last.unwrap() - 15.0).abs() < 1e-9);
+    assert!(params.lambda_z.is_none() && params.half_life.is_none());
+    assert!(params.auc_inf.is_none() && params.clearance.is_none() && params.mrt.is_none());
+    assert!(warnings.iter().any(|w| w.contains("observed-only parameters reported")));
+
+    // Even a slope that needs only two points is not extrapolated from
+    let last_two = AnalysisConfig {
+        auc_extrapolation_slope: AucExtrapolationSlope::LastTwoPoints,
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&sparse, &last_two).unwrap();
+    assert!(results.individual_parameters.auc_inf.is_none());
+
+    // Sparse subjects land in the individual results, not the failures
+    let full = single_dose_subject("full", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let population = PopulationAnalyzer::analyze_population(vec![sparse.clone(), full], &AnalysisConfig::default()).unwrap();
+    assert!(population.failed_subjects.is_empty());
+    assert_eq!(population.summary_statistics.parameter_stats["auc_last"].n, 2);
+    assert_eq!(population.summary_statistics.parameter_stats["half_life"].n, 1);
+
+    // Raising the observed minimum restores the old behaviour
+    let strict = AnalysisConfig {
+        min_observed_points: 3,
+        ..AnalysisConfig::default()
+    };
+    assert!(NcaAnalyzer::analyze_subject(&sparse, &strict).is_err());
+
+    // Minimums out of order are rejected once, before any subject is analyzed
+    let lax = AnalysisConfig { min_observed_points: 1, ..AnalysisConfig::default() };
+    let error = PopulationAnalyzer::analyze_population(vec![sparse.clone()], &lax).unwrap_err();
+    assert!(error.to_string().contains("at least 2"), "{}", error);
+    let inverted = AnalysisConfig { min_terminal_points: 1, ..AnalysisConfig::default() };
+    assert!(PopulationAnalyzer::analyze_population(vec![sparse], &inverted).is_err());
+    let temp_dir = TempDir::new().unwrap();
+    let cli = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+            .args(["--generate-example", "--subjects", "3", "--output"])
+            .arg(temp_dir.path().join("out"))
+            .args(extra)
+            .status()
+            .unwrap()
+    };
+    assert!(!cli(&["--min-observed-points", "1"]).success());
+    assert!(!cli(&["--min-terminal-points", "0"]).success());
+    assert!(!cli(&["--min-observed-points", "4", "--min-terminal-points", "3"]).success());
+}
+
+#[test]
+fn test_wagner_nelson_fraction_absorbed() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // One-compartment oral profile, ka = 1.5, k = 0.15
+    let (ka, k) = (1.5f64, 0.15f64);
+    let times = [0.0, 0.25, 0.5, 1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0, 36.0];
+    let mut subject = single_dose_subject(
+        "oral", 100.0,
+        &times.iter().map(|&t| (t, 50.0 * ((-k * t).exp() - (-ka * t).exp()))).collect::<Vec<_>>(),
+    );
+    subject.dosing_events[0].route = DosingRoute::Oral;
+    let config = AnalysisConfig {
+        auc_methods: vec![AucMethod::LinearUpLogDown],
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let absorbed = &results.individual_parameters.fraction_absorbed;
+    assert_eq!(absorbed.len(), times.len());
+    assert_eq!(absorbed[0].fraction, 0.0);
+    assert!((absorbed.last().unwrap().fraction - 1.0).abs() < 1e-12);
+    assert!(absorbed.windows(2).all(|w| w[1].fraction >= w[0].fraction - 1e-3));
+    // Close to the true 1 - exp(-ka t) once absorption is well under way
+    let at_two_hours = absorbed.iter().find(|p| p.time == 2.0).unwrap();
+    assert!((at_two_hours.fraction - (1.0 - (-ka * 2.0).exp())).abs() < 0.05);
+
+    // No absorption profile for IV doses
+    let iv = single_dose_subject("iv", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&iv, &config).unwrap();
+    assert!(results.individual_parameters.fraction_absorbed.is_empty());
+
+    let temp_dir = TempDir::new().unwrap();
+    let population = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+    OutputManager::save_results(&population, &config, temp_dir.path()).unwrap();
+    let table = std::fs::read_to_string(temp_dir.path().join("fraction_absorbed.csv")).unwrap();
+    assert_eq!(table.lines().count(), times.len() + 1);
+    assert!(table.lines().last().unwrap().ends_with(",36,1.000000"));
+}
+
+#[test]
+fn test_dilution_factor_scales_concentrations_when_enabled() {
+    use nca_analysis::parser::ParseOptions;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("diluted.csv");
+    write_dataset(&path, "ID,TIME,DV,AMT,EVID,CMT,DILUTION", &[
+        "1,0,0,100,1,1,",
+        "1,1,5,0,0,1,10",
+        "1,2,8,0,0,1,",
+        "1,4,4,0,0,1,1",
+    ]);
+    let concentrations = |subjects: Vec<Subject>| -> Vec<f64> {
+        subjects[0].observations.iter().map(|o| o.concentration).collect()
+    };
+
+    // Off by default, so pre-corrected datasets are not scaled twice
+    let (subjects, _) = NonmemParser::parse_dataset_with_units(&path).unwrap();
+    assert_eq!(concentrations(subjects), vec![5.0, 8.0, 4.0]);
+
+    let options = ParseOptions { apply_dilution_factor: true, ..ParseOptions::default() };
+    let (subjects, _) = NonmemParser::parse_dataset_with_options(&path, &options).unwrap();
+    assert_eq!(concentrations(subjects), vec![50.0, 8.0, 4.0]);
+
+    write_dataset(&path, "ID,TIME,DV,AMT,EVID,CMT,DF", &["1,0,0,100,1,1,", "1,1,5,0,0,1,0"]);
+    assert!(NonmemParser::parse_dataset_with_options(&path, &options).is_err());
+}
+
+#[test]
+fn test_dosing_summary_columns_match_input() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("dosing.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,RATE,EVID,CMT,ROUTE", &[
+        "1,0,0,100,50,1,1,",
+        "1,2,10,0,0,0,1,",
+        "1,3,8,0,0,0,1,",
+        "1,4,6,0,0,0,1,",
+        "1,6,3.5,0,0,0,1,",
+        "1,10,1.2,0,0,0,1,",
+        "2,0,0,50,0,1,1,ORAL",
+        "2,1,6,0,0,0,1,",
+        "2,2,5,0,0,0,1,",
+        "2,12,0,50,0,1,1,ORAL",
+        "2,14,7,0,0,0,1,",
+        "2,16,4,0,0,0,1,",
+        "2,24,1.5,0,0,0,1,",
+    ]);
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("individual_results.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+    let value = |id: &str, name: &str| -> String {
+        let row = rows.iter().find(|r| &r[0] == id).unwrap();
+        row[headers.iter().position(|h| h == name).unwrap()].to_string()
+    };
+
+    assert_eq!(
+        (value("1", "TOTAL_DOSE"), value("1", "DOSE_COUNT"), value("1", "ROUTE"), value("1", "INFUSION_DURATION")),
+        ("100".into(), "1".into(), "INFUSION".into(), "2".into()),
+    );
+    assert_eq!(
+        (value("2", "TOTAL_DOSE"), value("2", "DOSE_COUNT"), value("2", "ROUTE"), value("2", "INFUSION_DURATION")),
+        ("100".into(), "2".into(), "ORAL".into(), "NA".into()),
+    );
+
+    // CL can be recomputed from the CSV alone
+    let number = |id: &str, name: &str| value(id, name).parse::<f64>().unwrap();
+    let recomputed = number("1", "TOTAL_DOSE") / number("1", "AUC_INF");
+    assert!((number("1", "CLEARANCE") - recomputed).abs() < 1e-9 * recomputed);
+}
+
+#[test]
+fn test_lambda_z_below_r_squared_threshold_reported_with_flag() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // Noisy profile whose best terminal regression reaches R² ≈ 0.75
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 4.5), (4.0, 6.0), (6.0, 2.8), (8.0, 3.2)]);
+
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    assert!(params.lambda_z.is_none() && params.auc_inf.is_none());
+    assert!(params.lambda_z_reliable.is_none());
+
+    let config = AnalysisConfig {
+        lambda_z_report_below_threshold: true,
+        ..AnalysisConfig::default()
+    };
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    let r_squared = params.lambda_z_r_squared.unwrap();
+    assert!((r_squared - 0.75).abs() < 0.01);
+    assert!(params.lambda_z.is_some() && params.half_life.is_some() && params.auc_inf.is_some());
+    assert_eq!(params.lambda_z_reliable, Some(false));
+    assert!(warnings.iter().any(|w| w.contains("below the R² acceptance threshold")));
+
+    // A lower threshold accepts the same fit as reliable
+    let lenient = AnalysisConfig {
+        lambda_z_min_r_squared: 0.7,
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &lenient).unwrap();
+    assert_eq!(results.individual_parameters.lambda_z_r_squared, Some(r_squared));
+    assert_eq!(results.individual_parameters.lambda_z_reliable, Some(true));
+
+    // A manual window has no R² minimum, so it is neither reliable nor unreliable
+    let manual = AnalysisConfig {
+        lambda_z_selection: LambdaZSelection::Manual(vec![3, 4, 5]),
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &manual).unwrap();
+    assert!(results.individual_parameters.lambda_z.is_some());
+    assert_eq!(results.individual_parameters.lambda_z_reliable, None);
+}
+
+#[test]
+fn test_cavg_over_observed_interval() {
+    use nca_analysis::{nca::NcaAnalyzer, parameters::ParameterCalculator};
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let expected = params.auc_last.unwrap() / (8.0 - 0.5);
+    assert!((params.cavg_observed.unwrap() - expected).abs() < 1e-12);
+    assert_eq!(params.get("cavg_observed"), params.cavg_observed);
+
+    assert!(ParameterCalculator::calculate_cavg_observed(5.0, 2.0, 2.0).is_none());
+}
+
+#[test]
+fn test_placebo_arm_excluded_from_dose_normalized_analysis() {
+    use nca_analysis::covariate::CovariateAnalyzer;
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let arm = |treatment: &str, ids: &[&str], doses: &[f64]| -> Vec<Subject> {
+        ids.iter().zip(doses).map(|(id, &dose)| {
+            let scaled: Vec<(f64, f64)> = profile.iter().map(|&(t, c)| (t, c * dose.max(1.0) / 100.0)).collect();
+            let mut subject = single_dose_subject(id, dose, &scaled);
+            subject.demographics.treatment = Some(treatment.to_string());
+            subject
+        }).collect()
+    };
+    // The placebo arm records a nominal amount, and one active subject was not dosed
+    let mut subjects = arm("Drug", &["1", "2", "3", "4"], &[50.0, 100.0, 200.0, 0.0]);
+    subjects.extend(arm("Placebo", &["P1", "P2", "P3"], &[100.0, 100.0, 100.0]));
+    let results: Vec<NcaResults> = subjects
+        .iter()
+        .map(|s| NcaAnalyzer::analyze_subject(s, &AnalysisConfig::default()).unwrap().0)
+        .collect();
+
+    let unmapped = CovariateAnalyzer::analyze_covariates(&results, &subjects, &AnalysisConfig::default()).unwrap();
+    let unmapped = unmapped.dose_normalized_analysis.unwrap();
+    assert!(unmapped.dose_normalized_auc.contains_key("Placebo"));
+    assert_eq!(unmapped.exclusions.len(), 1);
+    assert_eq!((unmapped.exclusions[0].subject_id.as_str(), unmapped.exclusions[0].reason.as_str()), ("4", "Zero total dose"));
+
+    let config = AnalysisConfig {
+        treatment_roles: [("Placebo".to_string(), TreatmentRole::Placebo)].into_iter().collect(),
+        ..AnalysisConfig::default()
+    };
+    let analysis = CovariateAnalyzer::analyze_covariates(&results, &subjects, &config).unwrap();
+    let dose_analysis = analysis.dose_normalized_analysis.as_ref().unwrap();
+    assert!(!dose_analysis.dose_normalized_auc.contains_key("Placebo"));
+    assert!(!dose_analysis.dose_linearity_assessment.contains_key("Placebo"));
+    assert_eq!(dose_analysis.dose_normalized_auc["Drug"].n, 3);
+    let excluded: Vec<(&str, &str)> = dose_analysis.exclusions.iter()
+        .map(|e| (e.subject_id.as_str(), e.reason.as_str()))
+        .collect();
+    assert_eq!(excluded, vec![
+        ("4", "Zero total dose"),
+        ("P1", "Placebo treatment"),
+        ("P2", "Placebo treatment"),
+        ("P3", "Placebo treatment"),
+    ]);
+
+    // Dose normalization leaves out the same subjects, and the exclusions file lists each once
+    let population_config = AnalysisConfig { dose_normalization: true, perform_covariate_analysis: true, ..config };
+    let population = PopulationAnalyzer::analyze_population(subjects, &population_config).unwrap();
+    let normalized: Vec<&str> = population.dose_normalized_parameters.iter().map(|n| n.subject_id.as_str()).collect();
+    assert_eq!(normalized.len(), 3);
+    assert!(normalized.iter().all(|id| ["1", "2", "3"].contains(id)));
+    assert_eq!(population.dose_normalized_summary.as_ref().unwrap().parameter_stats["cmax"].n, 3);
+    assert_eq!(population.dose_normalization_exclusions.len(), 4);
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&population, &population_config, temp_dir.path()).unwrap();
+    let exclusions = std::fs::read_to_string(temp_dir.path().join("dose_normalization_exclusions.csv")).unwrap();
+    assert_eq!(exclusions.lines().count(), 5);
+    assert!(exclusions.contains("P1,Placebo,Placebo treatment"));
+}
+
+#[test]
+fn test_pp_export_codes_and_units() {
+    use nca_analysis::cdisc::CdiscExporter;
+
+    let iv = single_dose_subject("IV-1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let mut oral = single_dose_subject("PO-1", 100.0, &[(0.0, 0.0), (1.0, 9.0), (2.0, 7.0), (4.0, 4.0), (8.0, 1.5)]);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let config = AnalysisConfig {
+        time_units: "h".to_string(),
+        concentration_units: "ng/mL".to_string(),
+        dose_units: "mg".to_string(),
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(vec![iv, oral], &config).unwrap();
+    let records = CdiscExporter::pp_records(&results, &config);
+
+    let record = |subject: &str, code: &str| records.iter().find(|r| r.usubjid == subject && r.test_code == code);
+    let iv_result = results.individual_results.iter().find(|r| r.subject_id == "IV-1").unwrap();
+    let expected = [
+        ("AUCLST", iv_result.individual_parameters.auc_last, "ng/mL*h"),
+        ("AUCIFO", iv_result.individual_parameters.auc_inf, "ng/mL*h"),
+        ("CMAX", iv_result.individual_parameters.cmax, "ng/mL"),
+        ("TMAX", iv_result.individual_parameters.tmax, "h"),
+        ("LAMZHL", iv_result.individual_parameters.half_life, "h"),
+        ("LAMZ", iv_result.individual_parameters.lambda_z, "1/h"),
+        ("CLO", iv_result.individual_parameters.clearance, "mg/(ng/mL*h)"),
+        ("VZO", iv_result.individual_parameters.volume_terminal, "mg/(ng/mL)"),
+    ];
+    for (code, value, unit) in expected {
+        let pp = record("IV-1", code).unwrap_or_else(|| panic!("missing {}", code));
+        assert_eq!((Some(pp.value), pp.unit.as_str()), (value, unit), "{}", code);
+    }
+
+    // Extravascular doses report apparent clearance and volume, and no Vss
+    assert!(record("PO-1", "CLFO").is_some() && record("PO-1", "VZFO").is_some());
+    assert!(record("PO-1", "CLO").is_none() && record("PO-1", "VSSO").is_none());
+    assert_eq!(CdiscExporter::test_code("half_life", false), Some(("LAMZHL", "Half-Life Lambda z")));
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("pp.csv")).unwrap();
+    assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(),
+        vec!["USUBJID", "PPCAT", "PPSPEC", "PPTESTCD", "PPTEST", "PPSTRESN", "PPSTRESU"]);
+    assert_eq!(reader.records().count(), records.len());
+}
+
+#[test]
+fn test_secondary_peak_is_flagged() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // Trough of 4 at 4 h, then a rebound to 6 at 6 h
+    let mut subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (0.5, 5.0), (1.0, 10.0), (2.0, 7.0), (4.0, 4.0), (6.0, 6.0), (8.0, 3.0), (12.0, 1.5), (24.0, 0.4),
+    ]);
+    subject.dosing_events[0].route = DosingRoute::Oral;
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!((params.cmax, params.tmax), (Some(10.0), Some(1.0)));
+    assert_eq!((params.cmax2, params.tmax2), (Some(6.0), Some(6.0)));
+    assert!(warnings.iter().any(|w| w.contains("Secondary peak at t = 6")));
+
+    // A rebound smaller than the configured fraction of Cmax is noise
+    let strict = AnalysisConfig {
+        validation_thresholds: ValidationThresholds {
+            secondary_peak_min_rise: 0.25,
+            ..ValidationThresholds::default()
+        },
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &strict).unwrap();
+    assert!(results.individual_parameters.cmax2.is_none());
+
+    // A monoexponential decline has no secondary peak
+    let smooth = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&smooth, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.cmax2.is_none() && results.individual_parameters.tmax2.is_none());
+}
+
+#[test]
+fn test_rectangular_auc_baseline() {
+    use nca_analysis::{auc::AucCalculator, nca::NcaAnalyzer};
+
+    // Declining segments: rectangles overestimate, the log trapezoid is smallest
+    let declining: Vec<Observation> = [(0.0, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]
+        .iter()
+        .map(|&(t, c)| observation(t, c))
+        .collect();
+    let rectangular = AucCalculator::calculate_auc(&declining, &AucMethod::Rectangular).unwrap();
+    let linear = AucCalculator::calculate_auc(&declining, &AucMethod::LinearTrapezoidal).unwrap();
+    let log = AucCalculator::calculate_auc(&declining, &AucMethod::LogTrapezoidal).unwrap();
+    assert!((rectangular - (10.0 + 8.0 + 2.0 * 6.0 + 4.0 * 3.5)).abs() < 1e-12);
+    assert!(rectangular > linear && linear > log);
+    assert!((rectangular - linear) / linear < 0.3);
+
+    // Rising segments are underestimated
+    let rising: Vec<Observation> = [(0.0, 0.0), (1.0, 4.0), (2.0, 8.0)].iter().map(|&(t, c)| observation(t, c)).collect();
+    let rectangular = AucCalculator::calculate_auc(&rising, &AucMethod::Rectangular).unwrap();
+    assert!(rectangular < AucCalculator::calculate_auc(&rising, &AucMethod::LinearTrapezoidal).unwrap());
+
+    // Part of the default method comparison, though never the primary method
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(results.method_comparisons.contains_key("Rectangular"));
+    assert_eq!(AucCalculator::primary_method(&AnalysisConfig::default()), AucMethod::LinearTrapezoidal);
+}
+
+#[test]
+fn test_pre_dose_sample_excluded_from_auc() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.0, 0.0), (0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let mut with_pre_dose = vec![(-0.5, 2.0)];
+    with_pre_dose.extend_from_slice(&profile);
+
+    let config = AnalysisConfig::default();
+    let (reference, _) = NcaAnalyzer::analyze_subject(&single_dose_subject("1", 100.0, &profile), &config).unwrap();
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&single_dose_subject("1", 100.0, &with_pre_dose), &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.auc_last, reference.individual_parameters.auc_last);
+    assert_eq!(params.aumc_last, reference.individual_parameters.aumc_last);
+    assert!(warnings.iter().any(|w| w.contains("pre-dose")));
+
+    // Opting in integrates from the pre-dose sample
+    let config = AnalysisConfig { include_pre_dose_samples: true, ..AnalysisConfig::default() };
+    let (results, _) = NcaAnalyzer::analyze_subject(&single_dose_subject("1", 100.0, &with_pre_dose), &config).unwrap();
+    let extra = results.individual_parameters.auc_last.unwrap() - reference.individual_parameters.auc_last.unwrap();
+    assert!((extra - 0.5 * (2.0 + 0.0) * 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_custom_derivation_appears_in_output() {
+    use nca_analysis::derived::DerivationRegistry;
+
+    let mut derivations = DerivationRegistry::builtin();
+    derivations.register("auc_ratio_24_inf", |params, _| {
+        let auc_0_24 = params.partial_aucs.iter().find(|p| p.start == 0.0 && p.end == 24.0)?.auc?;
+        Some(auc_0_24 / params.auc_inf?)
+    });
+    let config = AnalysisConfig {
+        partial_auc_intervals: vec![(0.0, 24.0)],
+        derivations,
+        ..AnalysisConfig::default()
+    };
+    let subject = single_dose_subject("1", 100.0, &[(0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (12.0, 2.5), (24.0, 0.8), (48.0, 0.1)]);
+    let results = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+
+    let params = &results.individual_results[0].individual_parameters;
+    let ratio = params.get("auc_ratio_24_inf").unwrap();
+    let expected = params.partial_aucs[0].auc.unwrap() / params.auc_inf.unwrap();
+    assert!((ratio - expected).abs() < 1e-12 && ratio < 1.0);
+    assert!((params.get("cmax_per_dose").unwrap() - 0.1).abs() < 1e-12);
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("derived_parameters.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+    let names: Vec<&str> = rows.iter().map(|row| &row[column("PARAMETER")]).collect();
+    assert_eq!(names, ["auc_last_inf_ratio", "cmax_per_dose", "auc_ratio_24_inf"]);
+    assert_eq!(rows[2][column("VALUE")].parse::<f64>().unwrap(), ratio);
+}
+
+#[test]
+fn test_log_scale_dv_is_exponentiated() {
+    use nca_analysis::parser::ParseOptions;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("log_dv.csv");
+    let linear = [10.0_f64, 6.0, 3.5, 1.2];
+    let rows: Vec<String> = std::iter::once("1,0,0,100,1,1".to_string())
+        .chain([1.0, 2.0, 4.0, 8.0].iter().zip(linear).map(|(t, c)| format!("1,{},{},0,0,1", t, c.ln())))
+        .collect();
+    write_dataset(&path, "ID,TIME,DV,AMT,EVID,CMT", &rows.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let options = ParseOptions { dv_scale: DvScale::Log, ..ParseOptions::default() };
+    let (subjects, _) = NonmemParser::parse_dataset_with_options(&path, &options).unwrap();
+    for (obs, expected) in subjects[0].observations.iter().zip(linear) {
+        assert!((obs.concentration - expected).abs() < 1e-9);
+        assert_eq!(obs.dv, obs.concentration);
+    }
+
+    // Linear by default: the log values are taken as they are
+    let subjects = NonmemParser::parse_dataset(&path).unwrap();
+    assert!((subjects[0].observations[0].concentration - 10.0_f64.ln()).abs() < 1e-12);
+}
+
+#[test]
+fn test_nan_time_or_concentration_does_not_panic() {
+    let corrupt = single_dose_subject("1", 100.0, &[
+        (0.5, 10.0), (f64::NAN, 9.0), (1.0, 8.0), (2.0, f64::NAN), (2.5, 6.0), (4.0, 3.5), (8.0, 1.2),
+    ]);
+    let clean = single_dose_subject("2", 100.0, &[(0.5, 12.0), (1.0, 9.0), (2.5, 7.0), (4.0, 4.0), (8.0, 1.5)]);
+    let results = PopulationAnalyzer::analyze_population(vec![corrupt, clean], &AnalysisConfig::default()).unwrap();
+    assert_eq!(results.individual_results.len(), 2);
+
+    let corrupt = results.individual_results.iter().find(|r| r.subject_id == "1").unwrap();
+    let params = &corrupt.individual_parameters;
+    assert_eq!((params.cmax, params.tmax), (Some(10.0), Some(0.5)));
+    assert!(params.auc_last.unwrap().is_finite());
+
+    // NaN values are left out of descriptive statistics rather than breaking the sort
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&[3.0, f64::NAN, 1.0, 2.0]);
+    assert_eq!((stats.n, stats.min, stats.median, stats.max), (3, 1.0, 2.0, 3.0));
+}
+
+#[test]
+fn test_auc_extrap_area_completes_auc_inf() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let area = params.auc_extrap_area.unwrap();
+    assert!((area + params.auc_last.unwrap() - params.auc_inf.unwrap()).abs() < 1e-9);
+    assert!((area - 1.2 / params.lambda_z.unwrap()).abs() < 1e-9);
+    assert!((100.0 * area / params.auc_inf.unwrap() - params.auc_percent_extrap.unwrap()).abs() < 1e-9);
+
+    // No terminal phase, no extrapolation
+    let sparse = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&sparse, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.auc_extrap_area.is_none());
+}
+
+#[test]
+fn test_lambda_z_time_window_selection() {
+    use nca_analysis::{nca::NcaAnalyzer, parameters::ParameterCalculator};
+
+    let profile = [(0.5, 10.0), (1.0, 14.0), (2.0, 11.0), (6.0, 6.0), (12.0, 3.0), (24.0, 0.9), (36.0, 0.3), (48.0, 0.1)];
+    let subject = single_dose_subject("1", 100.0, &profile);
+    let config = AnalysisConfig {
+        lambda_z_selection: LambdaZSelection::TimeWindow { start_time: 12.0 },
+        ..AnalysisConfig::default()
+    };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.lambda_z_n_points, Some(4));
+    assert_eq!(params.lambda_z_time_range, Some((12.0, 48.0)));
+
+    // Same fit as selecting those points by index
+    let observations: Vec<Observation> = profile.iter().map(|&(t, c)| observation(t, c)).collect();
+    let (lambda_z, _, indices) = ParameterCalculator::calculate_lambda_z(&observations, &config.lambda_z_selection).unwrap();
+    let (manual, _, _) = ParameterCalculator::calculate_lambda_z(&observations, &LambdaZSelection::Manual(vec![4, 5, 6, 7])).unwrap();
+    assert_eq!(indices, vec![4, 5, 6, 7]);
+    assert!((lambda_z - manual).abs() < 1e-12);
+
+    // A window with fewer than two quantifiable points cannot be fitted
+    let late = LambdaZSelection::TimeWindow { start_time: 40.0 };
+    assert!(ParameterCalculator::calculate_lambda_z(&observations, &late).is_err());
+
+    // A protocol window is not held to the R² minimum, so a poor fit is not flagged unreliable
+    assert_eq!(params.lambda_z_reliable, None);
+    let noisy = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 4.5), (4.0, 6.0), (6.0, 2.8), (8.0, 3.2)]);
+    let window = AnalysisConfig { lambda_z_selection: LambdaZSelection::TimeWindow { start_time: 2.0 }, ..AnalysisConfig::default() };
+    let (results, _) = NcaAnalyzer::analyze_subject(&noisy, &window).unwrap();
+    let params = &results.individual_parameters;
+    assert!(params.lambda_z.is_some() && params.lambda_z_r_squared.unwrap() < AnalysisConfig::default().lambda_z_min_r_squared);
+    assert_eq!(params.lambda_z_reliable, None);
+}
+
+#[test]
+fn test_effective_half_life_from_accumulation_ratio() {
+    use nca_analysis::{nca::NcaAnalyzer, parameters::ParameterCalculator};
+
+    // Rac = 2 over tau = 12 means half the exposure carries over: t1/2,eff = 12
+    assert!((ParameterCalculator::calculate_effective_half_life(12.0, 2.0).unwrap() - 12.0).abs() < 1e-12);
+    // One-compartment steady state, Rac = 1 / (1 - exp(-k tau)), recovers the true half-life
+    let k = std::f64::consts::LN_2 / 6.0;
+    let rac = 1.0 / (1.0 - (-k * 12.0).exp());
+    assert!((ParameterCalculator::calculate_effective_half_life(12.0, rac).unwrap() - 6.0).abs() < 1e-9);
+    assert!(ParameterCalculator::calculate_effective_half_life(12.0, 1.0).is_none());
+
+    let mut subject = single_dose_subject("1", 100.0, &[
+        (0.0, 0.0), (1.0, 8.0), (2.0, 7.0), (4.0, 5.0), (8.0, 3.0), (12.0, 2.0),
+        (13.0, 10.0), (14.0, 9.0), (16.0, 7.0), (20.0, 4.5), (24.0, 3.0),
+    ]);
+    let second_dose = DosingEvent { time: 12.0, ..subject.dosing_events[0].clone() };
+    subject.dosing_events.push(second_dose);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let intervals = &params.dose_interval_aucs;
+    let expected_rac = intervals[1].auc.unwrap() / intervals[0].auc.unwrap();
+    let rac = params.accumulation_ratio.unwrap();
+    assert!((rac - expected_rac).abs() < 1e-12 && rac > 1.0);
+    let expected = -12.0 * std::f64::consts::LN_2 / (1.0 - 1.0 / rac).ln();
+    assert!((params.effective_half_life.unwrap() - expected).abs() < 1e-9);
+
+    // A last interval shorter than tau does not give an accumulation ratio
+    subject.observations.pop();
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.accumulation_ratio.is_none());
+}
+
+#[test]
+fn test_join_separate_covariates_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("pk.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CMT", &[
+        "1,0,0,100,1,1",
+        "1,1,10,0,0,1",
+        "1,4,5,0,0,1",
+        "2,0,0,100,1,1",
+        "2,1,12,0,0,1",
+        "2,4,6,0,0,1",
+    ]);
+    let covariates_path = temp_dir.path().join("covariates.csv");
+    write_dataset(&covariates_path, "ID,AGE,WT,SEX,TRT,GENOTYPE", &[
+        "1,34,72.5,F,A,EM",
+        "3,51,80,M,B,PM",
+    ]);
+
+    let mut subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let warnings = NonmemParser::join_covariates(&mut subjects, &covariates_path).unwrap();
+
+    let subject = subjects.iter().find(|s| s.id == "1").unwrap();
+    let demographics = &subject.demographics;
+    assert_eq!((demographics.age, demographics.weight), (Some(34.0), Some(72.5)));
+    assert_eq!((demographics.sex.as_deref(), demographics.treatment.as_deref()), (Some("F"), Some("A")));
+    assert_eq!(demographics.extra.get("GENOTYPE").map(String::as_str), Some("EM"));
+    assert!(!demographics.extra.contains_key("ID"));
+
+    let other = subjects.iter().find(|s| s.id == "2").unwrap();
+    assert!(other.demographics.age.is_none());
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().any(|w| w.contains("Subject 2")));
+    assert!(warnings.iter().any(|w| w.contains("ID 3")));
+}
+
+#[test]
+fn test_geometric_mean_excludes_non_positive_values() {
+    let values = [0.0, 2.0, 8.0, 4.0];
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&values);
+    assert_eq!((stats.n, stats.geometric_n), (4, 3));
+    assert!((stats.geometric_mean.unwrap() - 4.0).abs() < 1e-12);
+    assert!(stats.geometric_cv_percent.is_some());
+
+    let undefined = PopulationAnalyzer::calculate_parameter_stats_with_geometric(&values, &GeometricNonPositive::Undefined);
+    assert_eq!((undefined.geometric_mean, undefined.geometric_n), (None, 0));
+
+    // Geometric mean of value + 1, less 1: (1 * 3 * 9 * 5)^(1/4) - 1
+    let offset = PopulationAnalyzer::calculate_parameter_stats_with_geometric(&values, &GeometricNonPositive::Offset(1.0));
+    assert_eq!(offset.geometric_n, 4);
+    assert!((offset.geometric_mean.unwrap() - (135.0_f64.powf(0.25) - 1.0)).abs() < 1e-12);
+
+    // The streaming accumulator agrees
+    let mut online = nca_analysis::population::OnlineStats::default();
+    values.iter().for_each(|&v| online.push(v, 1.0));
+    let streamed = online.to_parameter_stats();
+    assert_eq!(streamed.geometric_n, 3);
+    assert!((streamed.geometric_mean.unwrap() - 4.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_quartiles_match_type_7_at_small_n() {
+    // Hand-computed R quantile(type = 7) / Excel QUARTILE.INC values
+    let cases = [
+        (vec![1.0, 2.0, 4.0], [1.5, 2.0, 3.0]),
+        (vec![1.0, 2.0, 4.0, 8.0], [1.75, 3.0, 5.0]),
+        (vec![1.0, 2.0, 4.0, 8.0, 16.0], [2.0, 4.0, 8.0]),
+    ];
+    for (sorted, [q25, median, q75]) in &cases {
+        let sorted = sorted.as_slice();
+        let (q25, median, q75) = (*q25, *median, *q75);
+        assert_eq!(PopulationAnalyzer::quantile(sorted, 0.25), q25, "q25 for n = {}", sorted.len());
+        assert_eq!(PopulationAnalyzer::quantile(sorted, 0.5), median, "median for n = {}", sorted.len());
+        assert_eq!(PopulationAnalyzer::quantile(sorted, 0.75), q75, "q75 for n = {}", sorted.len());
+    }
+
+    // Summary statistics sort first, and ties only occupy positions
+    let stats = PopulationAnalyzer::calculate_parameter_stats(&[8.0, 1.0, 4.0, 2.0]);
+    assert_eq!((stats.q25, stats.median, stats.q75), (1.75, 3.0, 5.0));
+    let tied = PopulationAnalyzer::calculate_parameter_stats(&[5.0, 2.0, 2.0, 2.0]);
+    assert_eq!((tied.q25, tied.median, tied.q75), (2.0, 2.0, 2.75));
+}
+
+#[test]
+fn test_end_of_infusion_cmax_reported_alongside_observed() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // 2 h infusion; the richest sample (1.5 h) comes before the end of infusion
+    let mut subject = single_dose_subject("1", 100.0, &[(0.0, 0.0), (0.5, 4.0), (1.5, 9.0), (4.0, 5.0), (8.0, 2.0), (12.0, 0.8)]);
+    subject.dosing_events[0].route = DosingRoute::IntravenousInfusion;
+    subject.dosing_events[0].infusion_duration = Some(2.0);
+
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.cmax_eoi.is_none());
+
+    let config = AnalysisConfig { report_end_of_infusion_cmax: true, ..AnalysisConfig::default() };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!((params.cmax, params.tmax), (Some(9.0), Some(1.5)));
+    assert_eq!(params.t_eoi, Some(2.0));
+    assert!((params.cmax_eoi.unwrap() - (9.0 + 0.2 * (5.0 - 9.0))).abs() < 1e-12);
+
+    let log_config = AnalysisConfig { interpolation_method: InterpolationMethod::LogLinear, ..config.clone() };
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &log_config).unwrap();
+    assert!((results.individual_parameters.cmax_eoi.unwrap() - 9.0 * (5.0_f64 / 9.0).powf(0.2)).abs() < 1e-12);
+
+    // Not an infusion: nothing to report
+    let bolus = single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&bolus, &config).unwrap();
+    assert!(results.individual_parameters.cmax_eoi.is_none());
+}
+
+#[test]
+fn test_chunked_population_analysis_matches_unchunked() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("chunks.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 37).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+
+    let run = |chunk: usize| {
+        let config = AnalysisConfig { parallel_chunk_size: chunk, ..AnalysisConfig::default() };
+        PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap()
+    };
+    let unchunked = run(1);
+    for chunk in [5, 16, 100] {
+        let chunked = run(chunk);
+        assert_eq!(chunked.individual_results.len(), unchunked.individual_results.len());
+        for (a, b) in chunked.individual_results.iter().zip(&unchunked.individual_results) {
+            assert_eq!(a.subject_id, b.subject_id);
+            assert_eq!(format!("{:?}", a.individual_parameters), format!("{:?}", b.individual_parameters));
+        }
+        let failed = |r: &PopulationResults| r.failed_subjects.iter().map(|f| f.subject_id.clone()).collect::<Vec<_>>();
+        assert_eq!(failed(&chunked), failed(&unchunked));
+        assert_eq!(
+            format!("{:?}", chunked.summary_statistics.parameter_stats["auc_last"]),
+            format!("{:?}", unchunked.summary_statistics.parameter_stats["auc_last"])
+        );
+    }
+
+    // An empty chunk is rejected once, up front, by the library and the CLI
+    let empty = AnalysisConfig { parallel_chunk_size: 0, ..AnalysisConfig::default() };
+    assert!(PopulationAnalyzer::analyze_population(subjects.clone(), &empty).is_err());
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+        .args(["--generate-example", "--subjects", "3", "--parallel-chunk-size", "0", "--output"])
+        .arg(temp_dir.path().join("out"))
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_panicking_subject_is_recorded_as_failure() {
+    use nca_analysis::derived::DerivationRegistry;
+
+    // Indexes the sixth sample unchecked, so subject "2" with five samples panics
+    let mut derivations = DerivationRegistry::new();
+    derivations.register("c_sixth_sample", |_, subject| Some(subject.observations[5].concentration));
+    let config = AnalysisConfig { derivations, ..AnalysisConfig::default() };
+    let full = [(0.0, 0.0), (1.0, 10.0), (2.0, 8.0), (4.0, 6.0), (8.0, 3.0), (12.0, 1.5)];
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &full),
+        single_dose_subject("2", 100.0, &full[..5]),
+        single_dose_subject("3", 100.0, &full),
+    ];
+
+    let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    let analyzed: Vec<&str> = results.individual_results.iter().map(|r| r.subject_id.as_str()).collect();
+    assert_eq!(analyzed, ["1", "3"]);
+    assert_eq!(results.failed_subjects.len(), 1);
+    let failed = &results.failed_subjects[0];
+    assert_eq!(failed.subject_id, "2");
+    assert!(failed.failure_reason.starts_with("Analysis panicked: index out of bounds"), "{}", failed.failure_reason);
+    assert_eq!(failed.total_observations, 5);
+
+    let temp_dir = TempDir::new().unwrap();
+    let streamed = PopulationAnalyzer::analyze_population_streaming(subjects, &config, temp_dir.path()).unwrap();
+    assert_eq!(streamed.subjects_analyzed, 2);
+    assert_eq!(streamed.failed_subjects.iter().map(|f| f.subject_id.as_str()).collect::<Vec<_>>(), ["2"]);
+}
+
+#[test]
+fn test_concentrations_export_holds_processed_observations() {
+    let mut subject = single_dose_subject("1", 100.0, &[(-0.5, 0.3), (0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (12.0, 2.5), (24.0, 0.0)]);
+    for obs in &mut subject.observations {
+        obs.lloq = Some(0.5);
+    }
+    subject.observations[5].bloq = true;
+    let config = AnalysisConfig {
+        lloq_handling: LloqHandling::HalfLloq,
+        export_concentrations: true,
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("concentrations.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+    let profile: Vec<(f64, f64, &str)> = rows
+        .iter()
+        .map(|row| (row[column("TIME")].parse().unwrap(), row[column("CONCENTRATION")].parse().unwrap(), &row[column("BLQ")]))
+        .collect();
+    // Pre-dose sample excluded, BLQ sample imputed at half the LLOQ
+    assert_eq!(profile, [
+        (0.0, 0.0, "false"), (1.0, 10.0, "false"), (4.0, 6.0, "false"), (12.0, 2.5, "false"), (24.0, 0.25, "true"),
+    ]);
+    assert!(rows.iter().all(|row| &row[column("SUBJECT_ID")] == "1" && &row[column("LLOQ")] == "0.5"));
+
+    let default_dir = TempDir::new().unwrap();
+    let default_results = PopulationAnalyzer::analyze_population(
+        vec![single_dose_subject("1", 100.0, &[(0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (12.0, 2.5)])],
+        &AnalysisConfig::default(),
+    ).unwrap();
+    OutputManager::save_results(&default_results, &AnalysisConfig::default(), default_dir.path()).unwrap();
+    assert!(!default_dir.path().join("concentrations.csv").exists());
+}
+
+#[test]
+fn test_oral_tmax_at_first_sample_is_flagged() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.0, 0.0), (0.5, 12.0), (1.0, 9.0), (2.0, 6.0), (4.0, 3.0), (8.0, 1.0)];
+    let mut oral = single_dose_subject("ORAL", 100.0, &profile);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let config = AnalysisConfig::default();
+
+    let (results, _) = NcaAnalyzer::analyze_subject(&oral, &config).unwrap();
+    assert_eq!(results.individual_parameters.tmax, Some(0.5));
+    assert!(results.individual_parameters.tmax_at_first_sample);
+    let warnings = NcaAnalyzer::validate_results(&results, &config.validation_thresholds);
+    assert!(warnings.iter().any(|w| w.starts_with("Tmax at the first post-dose sample (t = 0.5)")), "{:?}", warnings);
+
+    let quiet = ValidationThresholds { warn_tmax_at_first_sample: false, ..ValidationThresholds::default() };
+    assert!(!NcaAnalyzer::validate_results(&results, &quiet).iter().any(|w| w.contains("first post-dose sample")));
+
+    // A bolus peaks at the first sample by design, and a later oral peak is not flagged
+    let (bolus, _) = NcaAnalyzer::analyze_subject(&single_dose_subject("IV", 100.0, &profile), &config).unwrap();
+    assert!(!bolus.individual_parameters.tmax_at_first_sample);
+    let mut later_peak = single_dose_subject("ORAL2", 100.0, &[(0.0, 0.0), (0.5, 4.0), (1.0, 9.0), (2.0, 6.0), (4.0, 3.0), (8.0, 1.0)]);
+    later_peak.dosing_events[0].route = DosingRoute::Oral;
+    let (later_peak, _) = NcaAnalyzer::analyze_subject(&later_peak, &config).unwrap();
+    assert!(!later_peak.individual_parameters.tmax_at_first_sample);
+}
+
+#[test]
+fn test_time_above_threshold_with_multiple_crossings() {
+    use nca_analysis::parameters::ParameterCalculator;
+
+    // Up at t = 0.5, down at t = 3, up again at t = 5 and down at t = 7 (linear crossings)
+    let profile = [(0.0, 0.0), (1.0, 4.0), (2.0, 3.0), (4.0, 1.0), (6.0, 3.0), (8.0, 1.0)];
+    let observations: Vec<Observation> = profile.iter().map(|&(t, c)| observation(t, c)).collect();
+    let linear = ParameterCalculator::time_above_threshold(&observations, 2.0, &InterpolationMethod::Linear);
+    assert!((linear - (2.5 + 2.0)).abs() < 1e-12);
+
+    // Log-linear crossings between positive samples: 3 -> 1 over [2, 4] reaches 2 at 2 + 2 * ln(1.5) / ln(3)
+    let log_linear = ParameterCalculator::time_above_threshold(&observations, 2.0, &InterpolationMethod::LogLinear);
+    let down = 2.0 + 2.0 * 1.5_f64.ln() / 3.0_f64.ln();
+    let up = 4.0 + 2.0 * 2.0_f64.ln() / 3.0_f64.ln();
+    let down_again = 6.0 + 2.0 * 1.5_f64.ln() / 3.0_f64.ln();
+    assert!((log_linear - ((down - 0.5) + (down_again - up))).abs() < 1e-12);
+
+    assert_eq!(ParameterCalculator::time_above_threshold(&observations, 5.0, &InterpolationMethod::Linear), 0.0);
+    assert_eq!(ParameterCalculator::time_above_threshold(&observations, -1.0, &InterpolationMethod::Linear), 8.0);
+
+    let config = AnalysisConfig { concentration_threshold: Some(2.0), ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(vec![single_dose_subject("1", 100.0, &profile)], &config).unwrap();
+    let params = &results.individual_results[0].individual_parameters;
+    assert!((params.time_above_threshold.unwrap() - 4.5).abs() < 1e-12);
+
+    let unset = PopulationAnalyzer::analyze_population(vec![single_dose_subject("1", 100.0, &profile)], &AnalysisConfig::default()).unwrap();
+    assert!(unset.individual_results[0].individual_parameters.time_above_threshold.is_none());
+}
+
+#[test]
+fn test_threshold_crossings_match_linear_interpolation() {
+    use nca_analysis::parameters::ParameterCalculator;
+
+    let profile = [(0.0, 0.0), (1.0, 8.0), (2.0, 6.0), (6.0, 2.0), (8.0, 5.0), (12.0, 1.0)];
+    let config = AnalysisConfig { concentration_threshold: Some(4.0), ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(vec![single_dose_subject("1", 100.0, &profile)], &config).unwrap();
+    let params = &results.individual_results[0].individual_parameters;
+
+    // By hand: 0 -> 8 over [0, 1] reaches 4 at 0.5; 6 -> 2 over [2, 6] at 4; 2 -> 5 over [6, 8] at 6 + 2 * 2/3;
+    // 5 -> 1 over [8, 12] at 9
+    let expected = [(0.5, true), (4.0, false), (6.0 + 4.0 / 3.0, true), (9.0, false)];
+    let crossings: Vec<(f64, bool)> = params.threshold_crossings.iter().map(|c| (c.time, c.rising)).collect();
+    assert_eq!(crossings.len(), expected.len());
+    for ((time, rising), (expected_time, expected_rising)) in crossings.iter().zip(expected) {
+        assert!((time - expected_time).abs() < 1e-12 && *rising == expected_rising);
+    }
+    assert!((params.time_above_threshold.unwrap() - (3.5 + (9.0 - 6.0 - 4.0 / 3.0))).abs() < 1e-12);
+
+    // Linear trapezoids over [0.5, 4] and [22/3, 9], each starting and ending at C = 4
+    let first = 0.5 * (4.0 + 8.0) / 2.0 + (8.0 + 6.0) / 2.0 + 2.0 * (6.0 + 4.0) / 2.0;
+    let second = (8.0 - 22.0 / 3.0) * (4.0 + 5.0) / 2.0 + (5.0 + 4.0) / 2.0;
+    assert!((params.auc_above_threshold.unwrap() - (first + second)).abs() < 1e-9);
+
+    // Log-linear crossings between positive samples differ from the linear ones
+    let observations: Vec<Observation> = profile.iter().map(|&(t, c)| observation(t, c)).collect();
+    let log_crossings = ParameterCalculator::threshold_crossings(&observations, 4.0, &InterpolationMethod::LogLinear);
+    assert!((log_crossings[0].time - 0.5).abs() < 1e-12);
+    assert!((log_crossings[1].time - (2.0 + 4.0 * 1.5_f64.ln() / 3.0_f64.ln())).abs() < 1e-12);
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("threshold_crossings.csv")).unwrap();
+    let directions: Vec<String> = reader.records().map(|r| r.unwrap()[4].to_string()).collect();
+    assert_eq!(directions, ["UP", "DOWN", "UP", "DOWN"]);
+}
+
+#[test]
+fn test_inconsistent_dose_magnitudes_are_flagged() {
+    let profile = [(0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (12.0, 2.5), (24.0, 0.8)];
+    let mixed_units = {
+        let mut subject = single_dose_subject("MIXED", 50.0, &profile);
+        let mut second_dose = subject.dosing_events[0].clone();
+        second_dose.time = 12.0;
+        second_dose.dose = 50000.0;
+        subject.dosing_events.push(second_dose);
+        subject
+    };
+    let placebo = single_dose_subject("PLACEBO", 0.0, &profile);
+    let consistent = single_dose_subject("OK", 100.0, &profile);
+
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(vec![mixed_units.clone(), placebo, consistent], &config).unwrap();
+    let flagged: Vec<(&str, &str)> = results.dose_flags.iter().map(|f| (f.subject_id.as_str(), f.reason.as_str())).collect();
+    assert_eq!(flagged.len(), 2);
+    assert_eq!(flagged[0], ("MIXED", "Doses span a 1000-fold range (above 100); possible unit error"));
+    assert_eq!(flagged[1], ("PLACEBO", "Non-positive total dose (0)"));
+    assert_eq!((results.dose_flags[0].min_dose, results.dose_flags[0].max_dose, results.dose_flags[0].total_dose), (50.0, 50000.0, 50050.0));
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let rows: Vec<csv::StringRecord> = csv::Reader::from_path(temp_dir.path().join("dose_qc.csv"))
+        .unwrap()
+        .records()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+
+    let lenient = ValidationThresholds { max_dose_ratio: 5000.0, ..ValidationThresholds::default() };
+    assert!(nca_analysis::nca::NcaAnalyzer::check_dosing(&mixed_units, &lenient).is_empty());
+}
+
+#[test]
+fn test_result_cache_reuses_unchanged_subjects() {
+    use nca_analysis::cache::ResultCache;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("cache.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let config = AnalysisConfig::default();
+    let cache = ResultCache::new();
+
+    let first = PopulationAnalyzer::analyze_population_with_cache(subjects.clone(), &config, &cache).unwrap();
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 12, 12));
+
+    let reparsed = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let second = PopulationAnalyzer::analyze_population_with_cache(reparsed, &config, &cache).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (12, 12));
+    // Reparsing may reorder subjects, so results are matched by ID
+    assert_eq!(first.individual_results.len(), second.individual_results.len());
+    for a in &first.individual_results {
+        let b = second.individual_results.iter().find(|b| b.subject_id == a.subject_id).unwrap();
+        assert_eq!(format!("{:?}", a.individual_parameters), format!("{:?}", b.individual_parameters));
+    }
+
+    // Changing one subject or the configuration reanalyzes only what changed
+    let mut edited = subjects.clone();
+    edited[0].observations[2].concentration *= 2.0;
+    PopulationAnalyzer::analyze_population_with_cache(edited, &config, &cache).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (23, 13));
+
+    let tuned = AnalysisConfig { lloq_handling: LloqHandling::Zero, ..AnalysisConfig::default() };
+    PopulationAnalyzer::analyze_population_with_cache(subjects, &tuned, &cache).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (23, 25));
+}
+
+#[test]
+fn test_route_aware_dose_time_concentration_with_delayed_first_sample() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let route_aware = AnalysisConfig { dose_time_concentration: DoseTimeConcentration::RouteAware, ..AnalysisConfig::default() };
+    let profile = [(1.0, 8.0), (2.0, 4.0), (4.0, 2.0), (8.0, 0.5)];
+    let auc_from_first_sample = (8.0 + 4.0) / 2.0 + 2.0 * (4.0 + 2.0) / 2.0 + 4.0 * (2.0 + 0.5) / 2.0;
+
+    // IV bolus: C0 back-extrapolated from 8 -> 4 over one hour is 16
+    let bolus = single_dose_subject("IV", 100.0, &profile);
+    let (default_result, _) = NcaAnalyzer::analyze_subject(&bolus, &AnalysisConfig::default()).unwrap();
+    assert!((default_result.individual_parameters.auc_last.unwrap() - auc_from_first_sample).abs() < 1e-9);
+    assert_eq!(default_result.individual_parameters.c0, None);
+    let (iv, _) = NcaAnalyzer::analyze_subject(&bolus, &route_aware).unwrap();
+    let iv = &iv.individual_parameters;
+    assert!((iv.c0.unwrap() - 16.0).abs() < 1e-9);
+    assert!((iv.auc_last.unwrap() - (auc_from_first_sample + (16.0 + 8.0) / 2.0)).abs() < 1e-9);
+    assert_eq!((iv.cmax, iv.tmax), (Some(8.0), Some(1.0)));
+
+    // Oral: zero at the dose time, the first sample two hours later
+    let mut oral = single_dose_subject("ORAL", 100.0, &[(2.0, 6.0), (4.0, 4.0), (8.0, 1.0)]);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let (ev, _) = NcaAnalyzer::analyze_subject(&oral, &route_aware).unwrap();
+    let ev = &ev.individual_parameters;
+    assert_eq!(ev.c0, Some(0.0));
+    let expected = 2.0 * 6.0 / 2.0 + 2.0 * (6.0 + 4.0) / 2.0 + 4.0 * (4.0 + 1.0) / 2.0;
+    assert!((ev.auc_last.unwrap() - expected).abs() < 1e-9);
+
+    // A measured sample at the dose time is used as is
+    let mut measured = single_dose_subject("ORAL", 100.0, &[(0.0, 0.2), (2.0, 6.0), (4.0, 4.0), (8.0, 1.0)]);
+    measured.dosing_events[0].route = DosingRoute::Oral;
+    let (measured, _) = NcaAnalyzer::analyze_subject(&measured, &route_aware).unwrap();
+    assert_eq!(measured.individual_parameters.c0, None);
+    assert!((measured.individual_parameters.auc_last.unwrap() - (expected + 2.0 * 0.2 / 2.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_manifest_lists_exactly_the_files_written() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("manifest.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 10).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    let manifest_files = || {
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output_dir.join("manifest.json")).unwrap()).unwrap();
+        assert!(manifest["config"].as_str().unwrap().starts_with("AnalysisConfig {"));
+        let mut files: Vec<String> = manifest["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                assert!(!entry["description"].as_str().unwrap().is_empty());
+                entry["file"].as_str().unwrap().to_string()
+            })
+            .collect();
+        files.sort();
+        files
+    };
+
+    let config = AnalysisConfig { export_concentrations: true, ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    OutputManager::save_results(&results, &config, &output_dir).unwrap();
+    let mut on_disk: Vec<String> = std::fs::read_dir(&output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name != "manifest.json")
+        .collect();
+    on_disk.sort();
+    assert_eq!(manifest_files(), on_disk);
+    assert!(on_disk.contains(&"concentrations.csv".to_string()));
+
+    // A rerun without the export leaves the old file in place but does not list it
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    OutputManager::save_results(&results, &config, &output_dir).unwrap();
+    let listed = manifest_files();
+    assert!(!listed.contains(&"concentrations.csv".to_string()));
+    assert_eq!(listed.len(), on_disk.len() - 1);
+}
+
+#[test]
+fn test_dose_proportionality_power_model_ci_drives_conclusion() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("linearity_output");
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    // Exposure scales as dose^exponent, with a small subject-level jitter
+    let arm = |treatment: &str, exponent: f64| -> Vec<Subject> {
+        [(10.0, 1.02), (10.0, 0.98), (30.0, 0.99), (30.0, 1.01), (100.0, 1.03), (100.0, 0.97)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(dose, jitter))| {
+                let scale = (dose / 10.0_f64).powf(exponent) * jitter;
+                let scaled: Vec<(f64, f64)> = profile.iter().map(|&(t, c)| (t, c * scale)).collect();
+                let mut subject = single_dose_subject(&format!("{}{}", treatment, i), dose, &scaled);
+                subject.demographics.treatment = Some(treatment.to_string());
+                subject
+            })
+            .collect()
+    };
+    let mut subjects = arm("Proportional", 1.0);
+    subjects.extend(arm("Saturating", 1.6));
+    let config = AnalysisConfig {
+        perform_covariate_analysis: true,
+        ..AnalysisConfig::default()
+    };
+
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    let dose_analysis = results.covariate_analysis.dose_normalized_analysis.as_ref().unwrap();
+    // Dose range of 10: bounds 1 + ln(0.8) / ln(10) and 1 + ln(1.25) / ln(10)
+    let expected_bounds = (1.0 + 0.8_f64.ln() / 10.0_f64.ln(), 1.0 + 1.25_f64.ln() / 10.0_f64.ln());
+
+    let proportional = &dose_analysis.dose_linearity_assessment["Proportional"];
+    let (lower, upper) = proportional.beta_confidence_interval.unwrap();
+    let (bound_lower, bound_upper) = proportional.acceptance_bounds.unwrap();
+    assert!((bound_lower - expected_bounds.0).abs() < 1e-12 && (bound_upper - expected_bounds.1).abs() < 1e-12);
+    assert!((proportional.beta.unwrap() - 1.0).abs() < 0.02);
+    assert!(lower < proportional.beta.unwrap() && proportional.beta.unwrap() < upper);
+    assert!(lower >= bound_lower && upper <= bound_upper);
+    assert_eq!(proportional.linearity_conclusion, "Linear pharmacokinetics");
+
+    let saturating = &dose_analysis.dose_linearity_assessment["Saturating"];
+    let (lower, _) = saturating.beta_confidence_interval.unwrap();
+    assert!((saturating.beta.unwrap() - 1.6).abs() < 0.02);
+    assert!(lower > saturating.acceptance_bounds.unwrap().1);
+    assert_eq!(saturating.linearity_conclusion, "Non-linear pharmacokinetics");
+    assert!(saturating.conclusion_basis.contains("outside"));
+
+    OutputManager::save_results(&results, &config, &output_path).unwrap();
+    let mut reader = csv::Reader::from_path(output_path.join("dose_normalized_analysis.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+    let row = rows.iter()
+        .find(|r| &r[column("TREATMENT")] == "Proportional" && &r[column("PARAMETER")] == "AUC_DN")
+        .unwrap();
+    assert_eq!(&row[column("LINEARITY_ASSESSMENT")], "Linear pharmacokinetics");
+    let bound: f64 = row[column("BOUND_UPPER")].parse().unwrap();
+    assert!((bound - expected_bounds.1).abs() < 1e-4);
+    let ci_lower: f64 = row[column("BETA_CI90_LOWER")].parse().unwrap();
+    assert!(ci_lower >= row[column("BOUND_LOWER")].parse::<f64>().unwrap());
+    assert!(row[column("CONCLUSION_BASIS")].contains("within"));
+    let cmax_row = rows.iter()
+        .find(|r| &r[column("TREATMENT")] == "Proportional" && &r[column("PARAMETER")] == "CMAX_DN")
+        .unwrap();
+    assert_eq!(&cmax_row[column("BETA")], "NA");
+}
+
+#[test]
+fn test_subject_id_column_chosen_by_name() {
+    use nca_analysis::parser::ParseOptions;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("usubjid.csv");
+    write_dataset(&path, "USUBJID,TIME,DV,AMT,EVID,CMT,SUBJNO", &[
+        "STUDY-001,0,0,100,1,1,7",
+        "STUDY-001,1,10,0,0,1,7",
+        "STUDY-001,2,6,0,0,1,7",
+        "STUDY-002,0,0,100,1,1,8",
+        "STUDY-002,1,12,0,0,1,8",
+    ]);
+
+    let mut subjects = NonmemParser::parse_dataset(&path).unwrap();
+    subjects.sort_by(|a, b| a.id.cmp(&b.id));
+    let ids: Vec<&str> = subjects.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["STUDY-001", "STUDY-002"]);
+    assert_eq!(subjects[0].observations.len(), 2);
+    assert!(!subjects[0].demographics.extra.contains_key("USUBJID"));
+
+    let options = ParseOptions { id_column: Some("subjno".to_string()), ..ParseOptions::default() };
+    let (subjects, _) = NonmemParser::parse_dataset_with_options(&path, &options).unwrap();
+    let mut ids: Vec<&str> = subjects.iter().map(|s| s.id.as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["7", "8"]);
+
+    let options = ParseOptions { id_column: Some("PATID".to_string()), ..ParseOptions::default() };
+    assert!(NonmemParser::parse_dataset_with_options(&path, &options).is_err());
+    write_dataset(&path, "PATIENT,TIME,DV,AMT,EVID,CMT", &["1,0,0,100,1,1"]);
+    assert!(NonmemParser::parse_dataset(&path).is_err());
+}
+
+#[test]
+fn test_lambda_z_intercept_gives_predicted_clast() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let exact: Vec<(f64, f64)> = [2.0, 4.0, 8.0, 12.0, 24.0].iter().map(|&t| (t, 20.0 * (-0.15_f64 * t).exp())).collect();
+    let mut profile = vec![(0.5, 6.0), (1.0, 12.0)];
+    profile.extend(exact);
+    let subject = single_dose_subject("1", 100.0, &profile);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+
+    let intercept = params.lambda_z_intercept.unwrap();
+    let lambda_z = params.lambda_z.unwrap();
+    assert!((intercept - 20.0_f64.ln()).abs() < 1e-9);
+    assert!((lambda_z - 0.15).abs() < 1e-9);
+    let predicted_clast = (intercept - lambda_z * params.tlast.unwrap()).exp();
+    assert!((predicted_clast - params.clast.unwrap()).abs() < 1e-9);
+    assert_eq!(params.get("lambda_z_intercept"), Some(intercept));
+
+    // Off the fitted line the predicted Clast follows the regression, not the observation
+    let noisy = [(1.0, 10.0), (2.0, 8.5), (4.0, 5.0), (8.0, 2.2), (12.0, 1.1)];
+    let subject = single_dose_subject("2", 100.0, &noisy);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let (start, end) = params.lambda_z_time_range.unwrap();
+    let points: Vec<(f64, f64)> = noisy.iter().filter(|&&(t, _)| t >= start && t <= end).map(|&(t, c)| (t, c.ln())).collect();
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let slope = points.iter().map(|p| (p.0 - mean_t) * (p.1 - mean_y)).sum::<f64>()
+        / points.iter().map(|p| (p.0 - mean_t).powi(2)).sum::<f64>();
+    let predicted_clast = (params.lambda_z_intercept.unwrap() - params.lambda_z.unwrap() * 12.0).exp();
+    assert!((predicted_clast - (mean_y + slope * (12.0 - mean_t)).exp()).abs() < 1e-9);
+    assert!((predicted_clast - 1.1).abs() > 1e-6);
+}
+
+#[test]
+fn test_lambda_z_window_excludes_tmax_on_sparse_oral_profile() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let oral = |id: &str, profile: &[(f64, f64)]| {
+        let mut subject = single_dose_subject(id, 100.0, profile);
+        subject.dosing_events[0].route = DosingRoute::Oral;
+        subject
+    };
+    let exclude = AnalysisConfig { lambda_z_exclude_tmax: true, ..AnalysisConfig::default() };
+
+    // Four samples: only two follow Tmax, so the default fit is anchored on Cmax
+    let sparse = oral("1", &[(1.0, 4.0), (2.0, 10.0), (6.0, 5.0), (12.0, 1.0)]);
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&sparse, &AnalysisConfig::default()).unwrap();
+    assert_eq!(results.individual_parameters.lambda_z_time_range, Some((2.0, 12.0)));
+    assert!(!warnings.iter().any(|w| w.contains("after Tmax")));
+
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&sparse, &exclude).unwrap();
+    assert!(results.individual_parameters.lambda_z.is_none());
+    assert!(results.individual_parameters.auc_inf.is_none());
+    assert!(warnings.iter().any(|w| w.contains("Only 2 quantifiable point(s) after Tmax (t = 2)") && w.contains("3 needed")));
+
+    // With a point between Tmax and tlast to spare, the window starts after Tmax
+    let richer = oral("2", &[(1.0, 4.0), (2.0, 10.0), (4.0, 7.5), (6.0, 5.0), (12.0, 1.0)]);
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&richer, &exclude).unwrap();
+    let (start, end) = results.individual_parameters.lambda_z_time_range.unwrap();
+    assert!(start > 2.0 && end == 12.0);
+    assert!(results.individual_parameters.lambda_z_n_points.unwrap() >= 3);
+    assert!(!warnings.iter().any(|w| w.contains("after Tmax")));
+}
+
+#[test]
+fn test_covariate_p_values_adjusted_for_multiple_comparisons() {
+    use nca_analysis::covariate::CovariateAnalyzer;
+
+    // Matches R's p.adjust
+    let raw = [0.01, 0.04, 0.03, 0.005];
+    let bh = CovariateAnalyzer::adjust_p_values(&raw, &MultipleComparisonCorrection::BenjaminiHochberg);
+    for (adjusted, expected) in bh.iter().zip([0.02, 0.04, 0.04, 0.02]) {
+        assert!((adjusted - expected).abs() < 1e-12);
+    }
+    let bonferroni = CovariateAnalyzer::adjust_p_values(&raw, &MultipleComparisonCorrection::Bonferroni);
+    for (adjusted, expected) in bonferroni.iter().zip([0.04, 0.16, 0.12, 0.02]) {
+        assert!((adjusted - expected).abs() < 1e-12);
+    }
+    assert_eq!(CovariateAnalyzer::adjust_p_values(&[0.6, 0.9], &MultipleComparisonCorrection::Bonferroni), vec![1.0, 1.0]);
+
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("covariates.csv");
+    let output_path = temp_dir.path().join("covariate_output");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 20).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    let config = AnalysisConfig { perform_covariate_analysis: true, ..AnalysisConfig::default() };
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    OutputManager::save_results(&results, &config, &output_path).unwrap();
+
+    let mut reader = csv::Reader::from_path(output_path.join("covariate_correlations.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let rows: Vec<(f64, f64, String)> = reader
+        .records()
+        .map(|r| {
+            let r = r.unwrap();
+            (r[column("P_VALUE")].parse().unwrap(), r[column("P_VALUE_ADJUSTED")].parse().unwrap(), r[column("SIGNIFICANCE")].to_string())
+        })
+        .collect();
+    assert!(rows.len() > 1);
+    for (raw, adjusted, significance) in &rows {
+        assert!(adjusted >= raw && *adjusted <= 1.0);
+        assert_eq!(significance == "Yes", *adjusted < 0.05);
+    }
+    // Adjustment keeps the order of the raw p-values
+    for (a, b) in rows.iter().flat_map(|a| rows.iter().map(move |b| (a, b))) {
+        if a.0 < b.0 {
+            assert!(a.1 <= b.1 + 1e-4);
+        }
+    }
+}
+
+#[test]
+fn test_partial_correlation_removes_weight_driven_association() {
+    use nca_analysis::covariate::CovariateAnalyzer;
+
+    // Cmax rises with weight only; age tracks weight, so age looks correlated with Cmax
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let subjects: Vec<Subject> = (0..12)
+        .map(|i| {
+            let weight = 50.0 + 4.0 * i as f64;
+            let age = 20.0 + 0.5 * weight + if i % 2 == 0 { 3.0 } else { -3.0 };
+            let scale = weight / 70.0 * if i % 4 < 2 { 1.002 } else { 0.998 };
+            let scaled: Vec<(f64, f64)> = profile.iter().map(|&(t, c)| (t, c * scale)).collect();
+            let mut subject = single_dose_subject(&i.to_string(), 100.0, &scaled);
+            subject.demographics.weight = Some(weight);
+            subject.demographics.age = Some(age);
+            subject
+        })
+        .collect();
+    let config = AnalysisConfig {
+        perform_covariate_analysis: true,
+        partial_correlation_control: Some("weight".to_string()),
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    let age = &results.covariate_analysis.correlations["age"];
+    assert!(age.parameter_correlations["cmax"] > 0.9);
+    assert!(age.partial_correlations["cmax"].abs() < 0.3);
+    // The control covariate is not correlated with itself
+    assert!(results.covariate_analysis.correlations["weight"].partial_correlations.is_empty());
+
+    let direct = CovariateAnalyzer::partial_correlation(&results.individual_results, &subjects, "age", "cmax", "weight").unwrap();
+    assert_eq!(direct, age.partial_correlations["cmax"]);
+
+    let plain_config = AnalysisConfig { perform_covariate_analysis: true, ..AnalysisConfig::default() };
+    let plain = PopulationAnalyzer::analyze_population(subjects, &plain_config).unwrap();
+    assert!(plain.covariate_analysis.correlations["age"].partial_correlations.is_empty());
+}
+
+#[test]
+fn test_iv_loading_dose_with_oral_maintenance_reported_as_mixed_routes() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let mut subject = single_dose_subject("1", 100.0, &[
+        (0.5, 9.0), (2.0, 6.5), (6.0, 4.0), (12.0, 2.5), (13.0, 4.5), (18.0, 3.8), (24.0, 3.0),
+        (25.0, 5.0), (30.0, 4.0), (36.0, 3.0), (48.0, 1.6), (72.0, 0.5),
+    ]);
+    for time in [12.0, 24.0] {
+        subject.dosing_events.push(DosingEvent { time, dose: 50.0, route: DosingRoute::Oral, ..subject.dosing_events[0].clone() });
+    }
+    assert!(NcaAnalyzer::has_mixed_routes(&subject));
+    assert_eq!(NcaAnalyzer::effective_dose(&subject, 0.5), 150.0);
+    assert!(!NcaAnalyzer::has_mixed_routes(&single_dose_subject("2", 100.0, &[(1.0, 5.0)])));
+
+    // Without an assumed F the summed dose is not used for clearance
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    assert!(results.dosing.mixed_routes);
+    assert_eq!(results.dosing.total_dose, 200.0);
+    assert!(results.dosing.effective_dose.is_none());
+    let params = &results.individual_parameters;
+    assert!(params.auc_inf.is_some());
+    assert!(params.clearance.is_none() && params.volume_terminal.is_none() && params.volume_steady_state.is_none());
+    assert!(warnings.iter().any(|w| w.starts_with("Mixed dosing routes (IV, ORAL) for subject 1")));
+
+    let config = AnalysisConfig { extravascular_bioavailability: Some(0.5), ..AnalysisConfig::default() };
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&subject, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(results.dosing.effective_dose, Some(150.0));
+    assert!((params.clearance.unwrap() - 150.0 / params.auc_inf.unwrap()).abs() < 1e-12);
+    assert!(!params.apparent_clearance);
+    assert!(params.volume_terminal.is_some() && params.volume_steady_state.is_none());
+    assert!(warnings.iter().any(|w| w.contains("effective dose 150 assuming F = 0.5")));
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("mixed_output");
+    let population = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+    OutputManager::save_results(&population, &config, &output_path).unwrap();
+    let mut reader = csv::Reader::from_path(output_path.join("individual_results.csv")).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let row = reader.records().next().unwrap().unwrap();
+    let value = |name: &str| row[headers.iter().position(|h| h == name).unwrap()].to_string();
+    assert_eq!(value("MIXED_ROUTES"), "true");
+    assert_eq!(value("EFFECTIVE_DOSE"), "150");
+    assert_eq!(value("TOTAL_DOSE"), "200");
+}
+
+#[test]
+fn test_auc_inf_withheld_when_sampling_ends_soon_after_tmax() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    // Oral profile truncated 3 h after Tmax, with a 4.6 h half-life
+    let mut truncated = single_dose_subject("1", 100.0, &[(0.5, 4.0), (1.0, 8.0), (2.0, 10.0), (3.0, 8.6), (4.0, 7.4), (5.0, 6.4)]);
+    truncated.dosing_events[0].route = DosingRoute::Oral;
+    let (results, _) = NcaAnalyzer::analyze_subject(&truncated, &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.auc_inf.is_some());
+    assert!(results.individual_parameters.tmax_to_tlast_span_met.is_none());
+
+    for requirement in [TerminalSpanRequirement::Time(12.0), TerminalSpanRequirement::HalfLives(3.0)] {
+        let config = AnalysisConfig { min_tmax_to_tlast: Some(requirement), ..AnalysisConfig::default() };
+        let (results, warnings) = NcaAnalyzer::analyze_subject(&truncated, &config).unwrap();
+        let params = &results.individual_parameters;
+        assert_eq!(params.tmax_to_tlast_span_met, Some(false));
+        assert!(params.lambda_z.is_some() && params.auc_last.is_some());
+        assert!(params.auc_inf.is_none() && params.aumc_inf.is_none());
+        assert!(params.clearance.is_none() && params.mrt.is_none() && params.volume_terminal.is_none());
+        assert!(warnings.iter().any(|w| w.starts_with("Sampling ends 3 after Tmax (Tmax = 2, Tlast = 5) for subject 1")));
+    }
+
+    let config = AnalysisConfig { min_tmax_to_tlast: Some(TerminalSpanRequirement::Time(3.0)), ..AnalysisConfig::default() };
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&truncated, &config).unwrap();
+    assert_eq!(results.individual_parameters.tmax_to_tlast_span_met, Some(true));
+    assert!(results.individual_parameters.auc_inf.is_some());
+    assert!(!warnings.iter().any(|w| w.starts_with("Sampling ends")));
+}
+
+#[test]
+fn test_fluctuation_and_swing_require_a_fully_sampled_interval() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let steady_state = |id: &str, profile: &[(f64, f64)]| {
+        let mut subject = single_dose_subject(id, 100.0, profile);
+        subject.dosing_events[0].steady_state = true;
+        subject.dosing_events[0].interval = Some(12.0);
+        subject
+    };
+    let full = steady_state("1", &[(0.0, 2.0), (1.0, 10.0), (2.0, 8.0), (4.0, 6.0), (8.0, 3.5), (12.0, 2.1)]);
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&full, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    let cavg = params.auc_tau.unwrap() / 12.0;
+    assert!((params.swing_percent.unwrap() - 400.0).abs() < 1e-9);
+    assert!((params.fluctuation_percent.unwrap() - 100.0 * 8.0 / cavg).abs() < 1e-9);
+    assert!(!warnings.iter().any(|w| w.contains("not fully sampled")));
+
+    // Sampling stops 4 h short of the next dose
+    let truncated = steady_state("2", &[(0.0, 2.0), (1.0, 10.0), (2.0, 8.0), (4.0, 6.0), (8.0, 3.5)]);
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&truncated, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    assert!(params.fluctuation_percent.is_none() && params.swing_percent.is_none());
+    assert!(warnings.iter().any(|w| w.starts_with("Dosing interval t = 0-12 not fully sampled for subject 2 (samples t = 0-8)")));
+
+    // Without a dosing interval there is nothing to check
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&single_dose_subject("3", 100.0, &[(1.0, 10.0), (2.0, 8.0), (4.0, 6.0)]), &AnalysisConfig::default()).unwrap();
+    assert!(results.individual_parameters.swing_percent.is_none());
+    assert!(!warnings.iter().any(|w| w.contains("not fully sampled")));
+}
+
+#[test]
+fn test_analysis_report_breaks_down_failures_and_missing_parameters() {
+    let temp_dir = TempDir::new().unwrap();
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]),
+        // Too short a tail for lambda_z: analyzed, but without the terminal parameters
+        single_dose_subject("2", 100.0, &[(0.5, 10.0), (1.0, 12.0), (2.0, 9.0)]),
+        // No quantifiable concentrations: fails
+        single_dose_subject("3", 100.0, &[(0.5, 0.0), (1.0, 0.0)]),
+        single_dose_subject("4", 100.0, &[(0.5, 0.0), (1.0, 0.0), (2.0, 0.0)]),
+    ];
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+    assert_eq!(results.failed_subjects.len(), 2);
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+
+    let report = std::fs::read_to_string(temp_dir.path().join("analysis_report.txt")).unwrap();
+    let yield_section = &report[report.find("Data Yield:").unwrap()..];
+    assert!(yield_section.contains("- Subjects: 2 analyzed, 2 failed"));
+    let categories = OutputManager::failure_categories(&results.failed_subjects);
+    assert_eq!(categories.iter().map(|(_, count)| count).sum::<usize>(), 2);
+    let (category, count) = &categories[0];
+    assert!(yield_section.contains(&format!("  - {}: {}", category, count)));
+    assert!(yield_section.contains("  - auc_inf missing for 1 of 2"));
+    assert!(yield_section.contains("  - lambda_z missing for 1 of 2"));
+    assert!(yield_section.contains("  - cmax missing for 0 of 2"));
+}
+
+#[test]
+fn test_population_summary_statistics_selection() {
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &profile),
+        single_dose_subject("2", 120.0, &profile.map(|(t, c)| (t, 1.3 * c))),
+    ];
+    let parameter_table = |statistics: PopulationSummaryStatistics| -> Vec<String> {
+        let temp_dir = TempDir::new().unwrap();
+        let config = AnalysisConfig { population_summary_statistics: statistics, ..AnalysisConfig::default() };
+        let results = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+        OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+        let summary = std::fs::read_to_string(temp_dir.path().join("population_summary.csv")).unwrap();
+        summary.lines().skip_while(|line| !line.starts_with("PARAMETER")).map(str::to_string).collect()
+    };
+
+    let both = parameter_table(PopulationSummaryStatistics::Both);
+    assert_eq!(both[0], "PARAMETER,N,MEAN,MEDIAN,CV%,GEO_MEAN,GEO_CV%");
+
+    let geometric = parameter_table(PopulationSummaryStatistics::Geometric);
+    assert_eq!(geometric[0], "PARAMETER,N,MEDIAN,GEO_MEAN,GEO_CV%");
+    assert!(geometric.iter().all(|line| line.split(',').count() == 5));
+    let cmax = geometric.iter().find(|line| line.starts_with("cmax,")).unwrap();
+    let both_cmax: Vec<&str> = both.iter().find(|line| line.starts_with("cmax,")).unwrap().split(',').collect();
+    assert_eq!(cmax, &[both_cmax[0], both_cmax[1], both_cmax[3], both_cmax[5], both_cmax[6]].join(","));
+
+    let arithmetic = parameter_table(PopulationSummaryStatistics::Arithmetic);
+    assert_eq!(arithmetic[0], "PARAMETER,N,MEAN,MEDIAN,CV%");
+}
+
+#[test]
+fn test_time_to_steady_state_estimates() {
+    use nca_analysis::nca::NcaAnalyzer;
+    use nca_analysis::parameters::ParameterCalculator;
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.time_to_steady_state, Some(5.0 * params.half_life.unwrap()));
+    assert_eq!(params.get("time_to_steady_state"), params.time_to_steady_state);
+    assert!(params.time_to_steady_state_observed.is_none());
+
+    // Troughs approaching a plateau at k = 0.1 give 5 * ln(2) / 0.1: the same 5 half-lives
+    let plateau = |t: f64| 10.0 * (1.0 - (-0.1_f64 * t).exp());
+    let troughs: Vec<(f64, f64)> = [0.0, 12.0, 24.0, 36.0].iter().map(|&t| (t, plateau(t))).collect();
+    let observed = ParameterCalculator::time_to_steady_state_from_troughs(&troughs).unwrap();
+    assert!((observed - 5.0 * std::f64::consts::LN_2 / 0.1).abs() < 1e-9);
+    assert!(ParameterCalculator::time_to_steady_state_from_troughs(&troughs[..2]).is_none());
+    assert!(ParameterCalculator::time_to_steady_state_from_troughs(&[(0.0, 5.0), (12.0, 5.0), (24.0, 5.0)]).is_none());
+
+    // The troughs are the last samples at or before each dose
+    let mut multiple = single_dose_subject("2", 100.0, &[
+        (0.0, 0.0), (1.0, 9.0), (12.0, plateau(12.0)), (13.0, 12.0), (24.0, plateau(24.0)), (25.0, 14.0),
+        (36.0, plateau(36.0)), (37.0, 15.0), (48.0, 7.0),
+    ]);
+    for time in [12.0, 24.0, 36.0] {
+        multiple.dosing_events.push(DosingEvent { time, ..multiple.dosing_events[0].clone() });
+    }
+    let dose_times: Vec<f64> = multiple.dosing_events.iter().map(|dose| dose.time).collect();
+    assert_eq!(ParameterCalculator::pre_dose_troughs(&multiple.observations, &dose_times), troughs);
+    let (results, _) = NcaAnalyzer::analyze_subject(&multiple, &AnalysisConfig::default()).unwrap();
+    assert!((results.individual_parameters.time_to_steady_state_observed.unwrap() - observed).abs() < 1e-9);
+}
+
+#[test]
+fn test_subject_without_dose_reports_exposure_only() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let mut undosed = single_dose_subject("NODOSE", 100.0, &profile);
+    undosed.dosing_events.clear();
+
+    let config = AnalysisConfig::default();
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&undosed, &config).unwrap();
+    let params = &results.individual_parameters;
+    assert_eq!(params.cmax, Some(10.0));
+    assert!(params.auc_last.is_some() && params.half_life.is_some());
+    assert!(params.clearance.is_none() && params.volume_terminal.is_none() && params.volume_steady_state.is_none());
+    assert!(warnings.iter().any(|w| w.starts_with("No dose for subject NODOSE (no dosing record)")));
+
+    // A zero dose is treated the same way, instead of giving CL = 0
+    let (results, warnings) = NcaAnalyzer::analyze_subject(&single_dose_subject("ZERO", 0.0, &profile), &config).unwrap();
+    assert!(results.individual_parameters.clearance.is_none());
+    assert!(warnings.iter().any(|w| w.starts_with("No dose for subject ZERO (total dose 0)")));
+
+    let dosed = single_dose_subject("DOSED", 100.0, &profile);
+    let population = PopulationAnalyzer::analyze_population(vec![undosed, dosed], &config).unwrap();
+    assert_eq!(population.individual_results.len(), 2);
+    let flagged: Vec<(&str, &str)> = population.dose_flags.iter().map(|f| (f.subject_id.as_str(), f.reason.as_str())).collect();
+    assert_eq!(flagged, [("NODOSE", "No dosing record")]);
+}
+
+#[test]
+fn test_blod_and_bloq_imputed_separately() {
+    use nca_analysis::auc::AucCalculator;
+
+    let mut subject = single_dose_subject("1", 100.0, &[(0.0, 0.0), (1.0, 10.0), (4.0, 6.0), (12.0, 0.0), (24.0, 0.0)]);
+    for obs in &mut subject.observations {
+        obs.lloq = Some(0.5);
+        obs.lod = Some(0.2);
+    }
+    subject.observations[3].bloq = true;
+    subject.observations[4].bloq = true;
+    subject.observations[4].blod = true;
+
+    let imputed = |config: &AnalysisConfig| -> Vec<(f64, f64)> {
+        AucCalculator::filter_observations(&subject.observations, config).iter().map(|obs| (obs.time, obs.concentration)).collect()
+    };
+
+    // Without a BLOD rule every BLQ sample follows the LLOQ handling
+    let config = AnalysisConfig { lloq_handling: LloqHandling::HalfLloq, ..AnalysisConfig::default() };
+    assert_eq!(&imputed(&config)[3..], &[(12.0, 0.25), (24.0, 0.25)]);
+
+    let separate = AnalysisConfig { blod_handling: Some(LloqHandling::Zero), ..config.clone() };
+    assert_eq!(&imputed(&separate)[3..], &[(12.0, 0.25), (24.0, 0.0)]);
+
+    let half_lod = AnalysisConfig { blod_handling: Some(LloqHandling::HalfLloq), ..config.clone() };
+    assert_eq!(&imputed(&half_lod)[3..], &[(12.0, 0.25), (24.0, 0.1)]);
+
+    let dropped = AnalysisConfig { lloq_handling: LloqHandling::Drop, blod_handling: Some(LloqHandling::Zero), ..config };
+    assert_eq!(&imputed(&dropped)[3..], &[(24.0, 0.0)]);
+
+    // The parser reads the flag and limit; a BLOD sample is also BLQ
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("blod.csv");
+    write_dataset(&path, "ID,TIME,DV,AMT,EVID,BLQ,LLOQ,BLOD,LOD", &[
+        "1,0,0,100,1,0,0.5,0,0.2",
+        "1,1,10,0,0,0,0.5,0,0.2",
+        "1,24,0,0,0,0,0.5,1,0.2",
+        "1,30,0,0,2,0,0.5,0,0.2",
+    ]);
+    let parsed = NonmemParser::parse_dataset(&path).unwrap();
+    let last = parsed[0].observations.last().unwrap();
+    assert!(last.blod && last.bloq);
+    assert_eq!(last.lod, Some(0.2));
+    // ...and keeps them out of the covariates, fixed or time-varying
+    assert!(!parsed[0].demographics.extra.contains_key("LOD") && !parsed[0].demographics.extra.contains_key("BLOD"));
+    assert!(parsed[0].covariate_records.iter().all(|record| !record.values.contains_key("LOD")));
+}
+
+#[test]
+fn test_data_quality_summary_leads_report() {
+    let good = single_dose_subject("GOOD", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2), (24.0, 0.02)]);
+    let mut blq_tail = single_dose_subject("BLQ", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 0.0), (8.0, 0.0)]);
+    for obs in &mut blq_tail.observations[3..] {
+        obs.bloq = true;
+    }
+    let failed = single_dose_subject("FAILED", 100.0, &[(1.0, 0.0)]);
+
+    let config = AnalysisConfig::default();
+    let results = PopulationAnalyzer::analyze_population(vec![good, blq_tail, failed], &config).unwrap();
+    let quality = &results.data_quality;
+    assert_eq!(quality.subjects_total, 3);
+    assert_eq!(results.failed_subjects.len(), 1);
+    assert!((quality.percent_analyzed - 200.0 / 3.0).abs() < 1e-9);
+    assert!((quality.percent_blq - 100.0 * 2.0 / 12.0).abs() < 1e-9);
+    let r_squared: Vec<f64> = results.individual_results.iter().filter_map(|r| r.individual_parameters.lambda_z_r_squared).collect();
+    assert!(!r_squared.is_empty());
+    let median = quality.median_lambda_z_r_squared.unwrap();
+    assert!(median > 0.9 && median <= 1.0);
+    let reliable = results.individual_results.iter().filter(|r| r.individual_parameters.auc_inf_reliable == Some(true)).count();
+    assert!((quality.percent_reliable_auc_inf - 50.0 * reliable as f64).abs() < 1e-9);
+    let expected = (quality.percent_analyzed + quality.percent_reliable_auc_inf + 100.0 * median + 100.0 - quality.percent_blq) / 4.0;
+    assert!((quality.score - expected).abs() < 1e-9);
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+    let report = std::fs::read_to_string(temp_dir.path().join("analysis_report.txt")).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[3], format!("Data Quality: {:.0}/100", quality.score));
+    assert_eq!(lines[4], "- Subjects analyzed: 66.7% (2 of 3)");
+    assert_eq!(lines[7], "- BLQ observations: 16.7%");
+}
+
+#[test]
+fn test_individual_results_headers_follow_naming_scheme() {
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let header = |config: &AnalysisConfig| -> Vec<String> {
+        let results = PopulationAnalyzer::analyze_population(vec![subject.clone()], config).unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        OutputManager::save_results(&results, config, temp_dir.path()).unwrap();
+        let mut reader = csv::Reader::from_path(temp_dir.path().join("individual_results.csv")).unwrap();
+        reader.headers().unwrap().iter().map(String::from).collect()
+    };
+
+    let default = header(&AnalysisConfig::default());
+    assert_eq!(&default[3..5], ["AUC_LAST", "AUC_INF"]);
+
+    let winnonlin = header(&AnalysisConfig { parameter_naming: ParameterNaming::WinNonlin, ..AnalysisConfig::default() });
+    assert_eq!(winnonlin.len(), default.len());
+    assert_eq!(&winnonlin[3..5], ["AUClast", "AUCINF_obs"]);
+    assert!(winnonlin.contains(&"Lambda_z".to_string()) && winnonlin.contains(&"HL_Lambda_z".to_string()));
+    // Columns without a WinNonlin name keep the default one
+    assert_eq!(winnonlin[0], "SUBJECT_ID");
+
+    let cdisc = header(&AnalysisConfig { parameter_naming: ParameterNaming::Cdisc, ..AnalysisConfig::default() });
+    assert_eq!(&cdisc[..5], ["USUBJID", "ANALYTE", "MATRIX", "AUCLST", "AUCIFO"]);
+    let lambda_z = default.iter().position(|c| c == "LAMBDA_Z").unwrap();
+    assert_eq!(cdisc[lambda_z], "LAMZ");
+
+    // Custom aliases override the scheme
+    let aliased = header(&AnalysisConfig {
+        parameter_naming: ParameterNaming::Cdisc,
+        column_aliases: std::collections::HashMap::from([("AUC_INF".to_string(), "AUC0-inf".to_string())]),
+        ..AnalysisConfig::default()
+    });
+    assert_eq!(&aliased[3..5], ["AUCLST", "AUC0-inf"]);
+}
+
+#[test]
+fn test_naming_scheme_follows_route_and_reaches_summary_files() {
+    let profile = [(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)];
+    let iv = single_dose_subject("IV", 100.0, &profile);
+    let mut oral = single_dose_subject("ORAL", 100.0, &profile);
+    oral.dosing_events[0].route = DosingRoute::Oral;
+    let save = |subjects: Vec<Subject>, config: &AnalysisConfig, file: &str| -> String {
+        let results = PopulationAnalyzer::analyze_population(subjects, config).unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        OutputManager::save_results(&results, config, temp_dir.path()).unwrap();
+        std::fs::read_to_string(temp_dir.path().join(file)).unwrap()
+    };
+    let header = |subjects: Vec<Subject>, config: &AnalysisConfig| -> Vec<String> {
+        save(subjects, config, "individual_results.csv").lines().next().unwrap().split(',').map(String::from).collect()
+    };
+    let cdisc = AnalysisConfig { parameter_naming: ParameterNaming::Cdisc, ..AnalysisConfig::default() };
+    let winnonlin = AnalysisConfig { parameter_naming: ParameterNaming::WinNonlin, ..AnalysisConfig::default() };
+
+    let iv_header = header(vec![iv.clone()], &cdisc);
+    assert!(iv_header.contains(&"CLO".to_string()) && iv_header.contains(&"VZO".to_string()));
+    let oral_header = header(vec![oral.clone()], &cdisc);
+    assert!(oral_header.contains(&"CLFO".to_string()) && oral_header.contains(&"VZFO".to_string()));
+    assert!(header(vec![oral.clone()], &winnonlin).contains(&"Cl_F_obs".to_string()));
+    // Mixed populations keep the default names, CL_TYPE telling the routes apart
+    let mixed_header = header(vec![iv.clone(), oral], &cdisc);
+    assert!(mixed_header.contains(&"CLEARANCE".to_string()) && !mixed_header.contains(&"CLO".to_string()));
+
+    // Summary files use the same names, and the default names when no scheme or alias applies
+    let aliased = AnalysisConfig {
+        column_aliases: std::collections::HashMap::from([("AUC_INF".to_string(), "AUC0-inf".to_string())]),
+        ..cdisc.clone()
+    };
+    let summary = save(vec![iv.clone()], &aliased, "summary_statistics.csv");
+    assert!(summary.lines().any(|line| line.starts_with("AUC0-inf,")));
+    assert!(summary.lines().any(|line| line.starts_with("CLO,")));
+    assert!(save(vec![iv.clone()], &aliased, "population_summary.csv").lines().any(|line| line.starts_with("LAMZHL,")));
+    assert!(save(vec![iv], &AnalysisConfig::default(), "summary_statistics.csv").lines().any(|line| line.starts_with("auc_inf,")));
+
+    // An alias for a column that does not exist is rejected
+    let temp_dir = TempDir::new().unwrap();
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+        .args(["--generate-example", "--subjects", "3", "--column-alias", "AUC_INFINITY=AUC0-inf", "--output"])
+        .arg(temp_dir.path().join("out"))
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_best_fit_lambda_z_on_dense_profile() {
+    use nca_analysis::parameters::{ParameterCalculator, MAX_BEST_FIT_WINDOWS};
+
+    // Biexponential decline with deterministic +-3% noise
+    let profile = |n: usize| -> Vec<Observation> {
+        (0..n)
+            .map(|i| {
+                let t = 0.25 * (i + 1) as f64;
+                let noise = 1.0 + 0.03 * ((i * 7919 % 13) as f64 / 6.0 - 1.0);
+                observation(t, (80.0 * (-1.2 * t).exp() + 20.0 * (-0.15 * t).exp()) * noise)
+            })
+            .collect()
+    };
+    let selection = LambdaZSelection::BestFit { min_points: 3, r_squared_threshold: 0.8 };
+
+    let dense = profile(60);
+    let started = std::time::Instant::now();
+    let (lambda_z, r_squared, indices) = ParameterCalculator::calculate_lambda_z(&dense, &selection).unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+    // Exhaustive search over every window, fitted one by one
+    let mut expected: Option<(f64, f64, Vec<usize>)> = None;
+    for start in 0..=dense.len() - 3 {
+        for end in start + 2..dense.len() {
+            let window: Vec<usize> = (start..=end).collect();
+            if let Ok(fit) = ParameterCalculator::calculate_lambda_z(&dense, &LambdaZSelection::Manual(window)) {
+                if expected.as_ref().is_none_or(|best| fit.1 > best.1) {
+                    expected = Some(fit);
+                }
+            }
+        }
+    }
+    let (expected_lambda_z, expected_r_squared, expected_indices) = expected.unwrap();
+    assert_eq!(indices, expected_indices);
+    assert_eq!((lambda_z, r_squared), (expected_lambda_z, expected_r_squared));
+
+    // Past the window cap the search thins out but still reaches the last point
+    let very_dense = profile(400);
+    assert!((very_dense.len() - 2) * (very_dense.len() - 1) / 2 > MAX_BEST_FIT_WINDOWS);
+    let started = std::time::Instant::now();
+    let (lambda_z, _, indices) = ParameterCalculator::calculate_lambda_z(&very_dense, &selection).unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    assert!((lambda_z - 0.15).abs() < 0.05);
+    assert!(indices.len() >= 3);
+}
+
+#[test]
+fn test_lambda_z_and_half_life_units() {
+    let config = AnalysisConfig { time_units: "h".to_string(), units_in_headers: true, ..AnalysisConfig::default() };
+    assert_eq!(config.lambda_z_unit(), "1/h");
+    assert_eq!(config.terminal_parameter_unit("half_life").as_deref(), Some("h"));
+    assert!(config.terminal_parameter_unit("cmax").is_none());
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let results = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("individual_results.csv")).unwrap();
+    let header: Vec<String> = reader.headers().unwrap().iter().map(String::from).collect();
+    assert!(header.contains(&"LAMBDA_Z (1/h)".to_string()));
+    assert!(header.contains(&"HALF_LIFE (h)".to_string()));
+    assert!(header.contains(&"LAMBDA_Z_R2".to_string()));
+
+    let report = std::fs::read_to_string(temp_dir.path().join("analysis_report.txt")).unwrap();
+    assert!(report.lines().any(|line| line == "- Lambda_z units: 1/h"));
+    assert!(report.lines().any(|line| line.starts_with("- half_life (h): ")));
+
+    // Units in headers are opt-in; the plain column names stay the default
+    let plain = OutputManager::individual_results_header(&AnalysisConfig::default(), None);
+    assert!(plain.contains(&"LAMBDA_Z".to_string()));
+}
+
+#[test]
+fn test_pooled_lambda_z_salvages_auc_inf_within_dose_group() {
+    use nca_analysis::parameters::ParameterCalculator;
+
+    let decline = |c0: f64| -> Vec<(f64, f64)> { [1.0, 2.0, 4.0, 8.0, 12.0].iter().map(|&t| (t, c0 * (-0.2_f64 * t).exp())).collect() };
+    let fitted_a = single_dose_subject("A", 100.0, &decline(10.0));
+    let fitted_b = single_dose_subject("B", 100.0, &decline(25.0));
+    let sparse = single_dose_subject("SPARSE", 100.0, &[(1.0, 12.0), (6.0, 4.0)]);
+    let other_group = single_dose_subject("OTHER", 200.0, &[(1.0, 12.0), (6.0, 4.0)]);
+    let subjects = vec![fitted_a, fitted_b, sparse, other_group];
+
+    assert!((ParameterCalculator::pooled_lambda_z(&[decline(10.0), decline(25.0)]).unwrap() - 0.2).abs() < 1e-9);
+    assert!(ParameterCalculator::pooled_lambda_z(&[decline(10.0)]).is_none());
+
+    let individual = PopulationAnalyzer::analyze_population(subjects.clone(), &AnalysisConfig::default()).unwrap();
+    let find = |results: &PopulationResults, id: &str| results.individual_results.iter().find(|r| r.subject_id == id).unwrap().individual_parameters.clone();
+    assert!(find(&individual, "SPARSE").auc_inf.is_none());
+
+    let config = AnalysisConfig { pooled_lambda_z: true, ..AnalysisConfig::default() };
+    let pooled = PopulationAnalyzer::analyze_population(subjects.clone(), &config).unwrap();
+    let sparse = find(&pooled, "SPARSE");
+    assert!(sparse.lambda_z.is_none() && sparse.half_life.is_none());
+    assert_eq!(sparse.auc_inf_slope_method, Some(AucExtrapolationSlope::PooledGroup));
+    assert!((sparse.auc_inf_slope.unwrap() - 0.2).abs() < 1e-9);
+    let expected = sparse.auc_last.unwrap() + 4.0 / sparse.auc_inf_slope.unwrap();
+    assert!((sparse.auc_inf.unwrap() - expected).abs() < 1e-9);
+    assert!(sparse.auc_percent_extrap.is_some());
+
+    // The parameters derived from AUC_inf are recomputed with the pooled slope
+    assert!((sparse.clearance.unwrap() - 100.0 / sparse.auc_inf.unwrap()).abs() < 1e-9);
+    let expected_aumc = sparse.aumc_last.unwrap() + 6.0 * 4.0 / 0.2 + 4.0 / (0.2 * 0.2);
+    assert!((sparse.aumc_inf.unwrap() - expected_aumc).abs() < 1e-6);
+    assert!((sparse.mrt.unwrap() - sparse.aumc_inf.unwrap() / sparse.auc_inf.unwrap()).abs() < 1e-9);
+    assert!((sparse.volume_terminal.unwrap() - sparse.clearance.unwrap() / 0.2).abs() < 1e-6);
+
+    // Fitted subjects keep their own slope; a dose group without fits gets none
+    assert_eq!(find(&pooled, "A").auc_inf_slope_method, Some(AucExtrapolationSlope::TerminalRegression));
+    assert!(find(&pooled, "OTHER").auc_inf.is_none());
+
+    // The PooledGroup extrapolation slope enables pooling on its own
+    let slope_config = AnalysisConfig { auc_extrapolation_slope: AucExtrapolationSlope::PooledGroup, ..AnalysisConfig::default() };
+    let by_slope = PopulationAnalyzer::analyze_population(subjects.clone(), &slope_config).unwrap();
+    assert_eq!(find(&by_slope, "SPARSE").auc_inf, sparse.auc_inf);
+    assert_eq!(find(&by_slope, "A").auc_inf_slope_method, Some(AucExtrapolationSlope::TerminalRegression));
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&pooled, &config, temp_dir.path()).unwrap();
+    let mut reader = csv::Reader::from_path(temp_dir.path().join("individual_results.csv")).unwrap();
+    let method = reader.headers().unwrap().iter().position(|h| h == "AUC_INF_SLOPE_METHOD").unwrap();
+    let labels: Vec<(String, String)> = reader
+        .records()
+        .map(|record| record.unwrap())
+        .map(|record| (record[0].to_string(), record[method].to_string()))
+        .collect();
+    assert!(labels.contains(&("SPARSE".to_string(), "PooledGroup".to_string())));
+}
+
+#[test]
+fn test_clearance_cross_check_flags_inconsistent_profile() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let profile: Vec<(f64, f64)> = [0.5, 1.0, 2.0, 3.0, 4.0].iter().map(|&t| (t, 20.0 * (-0.2_f64 * t).exp())).collect();
+    let consistent = single_dose_subject("CONSISTENT", 100.0, &profile);
+    // A last sample at half its expected value, left out of the lambda_z window
+    let mut manipulated = single_dose_subject("MANIPULATED", 100.0, &profile);
+    manipulated.observations.last_mut().unwrap().concentration *= 0.5;
+    let oral = DosingEvent { route: DosingRoute::Oral, ..consistent.dosing_events[0].clone() };
+    let extravascular = Subject { id: "ORAL".to_string(), dosing_events: vec![oral], ..consistent.clone() };
+
+    let config = AnalysisConfig { lambda_z_selection: LambdaZSelection::Manual(vec![1, 2, 3]), ..AnalysisConfig::default() };
+    let (results, _) = NcaAnalyzer::analyze_subject(&consistent, &config).unwrap();
+    let check = NcaAnalyzer::cross_check_clearance(&results, &config.validation_thresholds).unwrap();
+    assert!(check.percent_difference.abs() < 1e-6);
+    assert!(!check.flagged);
+    assert_eq!(check.clearance, results.individual_parameters.clearance.unwrap());
+
+    let population = PopulationAnalyzer::analyze_population(vec![consistent, manipulated, extravascular], &config).unwrap();
+    let checks: Vec<(&str, bool)> = population.clearance_checks.iter().map(|c| (c.subject_id.as_str(), c.flagged)).collect();
+    assert_eq!(checks, [("CONSISTENT", false), ("MANIPULATED", true)]);
+    let manipulated = &population.clearance_checks[1];
+    assert!(manipulated.percent_difference.abs() > config.validation_thresholds.max_clearance_discrepancy_percent);
+    let reported = population.individual_results.iter().find(|r| r.subject_id == "MANIPULATED").unwrap();
+    assert_eq!(manipulated.expected_clearance, 100.0 / reported.individual_parameters.auc_inf_pred.unwrap());
+
+    let temp_dir = TempDir::new().unwrap();
+    OutputManager::save_results(&population, &config, temp_dir.path()).unwrap();
+    let qc = std::fs::read_to_string(temp_dir.path().join("clearance_qc.csv")).unwrap();
+    let lines: Vec<&str> = qc.lines().collect();
+    assert_eq!(lines[0], "SUBJECT_ID,CLEARANCE,EXPECTED_CLEARANCE,PERCENT_DIFFERENCE,FLAGGED");
+    assert!(lines[2].starts_with("MANIPULATED,") && lines[2].ends_with(",true"));
+}
+
+#[test]
+fn test_json_output_rounded_to_significant_digits() {
+    use nca_analysis::math::round_significant;
+
+    assert_eq!(round_significant(12.340000000001, 6), 12.34);
+    assert_eq!(round_significant(0.000123456, 3), 0.000123);
+    assert_eq!(round_significant(98765.4, 2), 99000.0);
+    assert_eq!(round_significant(0.0, 3), 0.0);
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.3), (1.0, 8.1), (2.0, 6.2), (4.0, 3.5), (8.0, 1.2)]);
+    let json = |digits: Option<u32>| -> serde_json::Value {
+        let config = AnalysisConfig { json_significant_digits: digits, ..AnalysisConfig::default() };
+        let results = PopulationAnalyzer::analyze_population(vec![subject.clone()], &config).unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        OutputManager::save_results(&results, &config, temp_dir.path()).unwrap();
+        serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join("complete_results.json")).unwrap()).unwrap()
+    };
+
+    let full = json(None);
+    let rounded = json(Some(4));
+    let params = |value: &serde_json::Value| value["individual_results"][0]["individual_parameters"].clone();
+    let full_half_life = params(&full)["half_life"].as_f64().unwrap();
+    assert_eq!(params(&rounded)["half_life"].as_f64().unwrap(), round_significant(full_half_life, 4));
+    assert_ne!(full_half_life, round_significant(full_half_life, 4));
+
+    // Every decimal carries at most 4 significant digits; integers are untouched
+    fn check(value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Number(number) if number.is_f64() => {
+                let x = number.as_f64().unwrap();
+                assert_eq!(x, round_significant(x, 4), "{} not rounded", x);
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(check),
+            serde_json::Value::Object(fields) => fields.values().for_each(check),
+            _ => {}
+        }
+    }
+    check(&rounded);
+    assert_eq!(rounded["individual_results"][0]["dosing"]["dose_count"], full["individual_results"][0]["dosing"]["dose_count"]);
+
+    // Zero digits is rejected up front rather than rounded as one
+    let zero = AnalysisConfig { json_significant_digits: Some(0), ..AnalysisConfig::default() };
+    assert!(PopulationAnalyzer::analyze_population(vec![subject], &zero).is_err());
+}
+
+#[test]
+fn test_primary_auc_method_added_when_not_configured() {
+    use nca_analysis::auc::AucCalculator;
+
+    let mut config = AnalysisConfig {
+        auc_methods: vec![AucMethod::LinearTrapezoidal, AucMethod::LogTrapezoidal],
+        primary_auc_method: Some(AucMethod::LinearUpLogDown),
+        ..AnalysisConfig::default()
+    };
+    let warning = config.ensure_primary_auc_method().unwrap();
+    assert!(warning.contains("linear_up_log_down"));
+    assert_eq!(config.auc_methods, [AucMethod::LinearUpLogDown, AucMethod::LinearTrapezoidal, AucMethod::LogTrapezoidal]);
+    assert!(config.ensure_primary_auc_method().is_none());
+
+    let subject = single_dose_subject("1", 100.0, &[(0.5, 10.0), (1.0, 8.0), (2.0, 6.0), (4.0, 3.5), (8.0, 1.2)]);
+    let results = PopulationAnalyzer::analyze_population(vec![subject], &config).unwrap();
+    let result = &results.individual_results[0];
+    let method_auc = |method: &str| result.method_comparisons[method].auc_last.unwrap();
+    assert_eq!(result.individual_parameters.auc_last.unwrap(), method_auc("LinearUpLogDown"));
+    assert_ne!(method_auc("LinearTrapezoidal"), method_auc("LinearUpLogDown"));
+    assert_eq!(results.method_comparison.primary_method, "LinearUpLogDown");
+    assert_eq!(results.method_comparison.percent_difference_from_primary["LinearUpLogDown"], 0.0);
+
+    // Without any configured method the linear trapezoidal default is added
+    let mut empty = AnalysisConfig { auc_methods: Vec::new(), ..AnalysisConfig::default() };
+    assert!(empty.ensure_primary_auc_method().unwrap().contains("linear_trapezoidal"));
+    assert_eq!(empty.auc_methods, [AucMethod::LinearTrapezoidal]);
+    assert_eq!(AucCalculator::primary_method(&AnalysisConfig::default()), AucMethod::LinearTrapezoidal);
+}
+
+#[test]
+fn test_route_inferred_from_dose_compartment_without_route_column() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("cmt_route_dataset.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,CMT,RATE", &[
+        "1,0,0,100,1,1,-2", // depot dose with modeled duration, sampled in CMT=2
+        "1,1,8.0,0,0,2,",
+        "2,0,0,100,1,1,",   // dosed and sampled in the same compartment
+        "2,1,8.0,0,0,1,",
+        "3,0,0,100,1,1,10", // positive RATE stays an infusion
+        "3,1,8.0,0,0,2,",
+    ]);
+
+    let mut subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    subjects.sort_by(|a, b| a.id.cmp(&b.id));
+    let dose = |i: usize| (subjects[i].dosing_events[0].route.clone(), subjects[i].dosing_events[0].infusion_duration);
+
+    assert_eq!(dose(0), (DosingRoute::Oral, None));
+    assert_eq!(dose(1), (DosingRoute::IntravenousBolus, None));
+    assert_eq!(dose(2), (DosingRoute::IntravenousInfusion, Some(10.0)));
+}
+
+#[test]
+fn test_unknown_route_value_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("ev_route_dataset.csv");
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,ROUTE", &["1,0,0,100,1,EV", "1,1,8.0,0,0,"]);
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+    assert_eq!(subjects[0].dosing_events[0].route, DosingRoute::Oral);
+
+    write_dataset(&dataset_path, "ID,TIME,DV,AMT,EVID,ROUTE", &["1,0,0,100,1,INTRANASAL", "1,1,8.0,0,0,"]);
+    let error = NonmemParser::parse_dataset(&dataset_path).unwrap_err();
+    assert!(error.to_string().contains("INTRANASAL"));
+}
+
+#[test]
+fn test_auc_inf_pred_uses_predicted_clast() {
+    use nca_analysis::nca::NcaAnalyzer;
+
+    let noisy = [(1.0, 10.0), (2.0, 8.5), (4.0, 5.0), (8.0, 2.2), (12.0, 1.1)];
+    let subject = single_dose_subject("1", 100.0, &noisy);
+    let (results, _) = NcaAnalyzer::analyze_subject(&subject, &AnalysisConfig::default()).unwrap();
+    let params = &results.individual_parameters;
+
+    let lambda_z = params.lambda_z.unwrap();
+    let clast_pred = (params.lambda_z_intercept.unwrap() - lambda_z * 12.0).exp();
+    let expected = params.auc_last.unwrap() + clast_pred / lambda_z;
+    assert!((params.auc_inf_pred.unwrap() - expected).abs() < 1e-9);
+    assert!((params.auc_inf_pred.unwrap() - params.auc_inf.unwrap()).abs() > 1e-6);
+}
+
+#[test]
+fn test_strata_comparison_test_is_configurable() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_path = temp_dir.path().join("comparison_dataset.csv");
+    ExampleDataGenerator::generate_dataset(&dataset_path, 12).unwrap();
+    let subjects = NonmemParser::parse_dataset(&dataset_path).unwrap();
+
+    let config = AnalysisConfig {
+        stratification: Some(StratificationConfig {
+            stratify_columns: vec!["SEX".to_string()],
+            include_interactions: false,
+            minimum_n_per_stratum: 1,
+            perform_statistical_tests: true,
+            comparison_test: ComparisonTest::MannWhitney,
+        }),
+        ..AnalysisConfig::default()
+    };
+    let results = PopulationAnalyzer::analyze_population(subjects, &config).unwrap();
+
+    let pairwise = &results.strata_comparisons[0].pairwise_comparisons[0];
+    assert_eq!(pairwise.test_type, "mann_whitney_u");
+    assert!(pairwise.hodges_lehmann_estimate.is_some());
+}
+
+#[test]
+fn test_streaming_writes_qc_files_and_rejects_population_options() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut no_dose = single_dose_subject("2", 100.0, &[(1.0, 10.0), (2.0, 8.0), (4.0, 6.0), (8.0, 3.0)]);
+    no_dose.dosing_events.clear();
+    let subjects = vec![
+        single_dose_subject("1", 100.0, &[(1.0, 10.0), (2.0, 8.0), (4.0, 6.0), (8.0, 3.0)]),
+        no_dose,
+    ];
+    let config = AnalysisConfig { export_concentrations: true, ..AnalysisConfig::default() };
+
+    PopulationAnalyzer::analyze_population_streaming(subjects.clone(), &config, temp_dir.path()).unwrap();
+    let dose_qc = std::fs::read_to_string(temp_dir.path().join("dose_qc.csv")).unwrap();
+    assert!(dose_qc.lines().nth(1).unwrap().starts_with("2,"));
+    assert!(temp_dir.path().join("clearance_qc.csv").exists());
+    let concentrations = std::fs::read_to_string(temp_dir.path().join("concentrations.csv")).unwrap();
+    assert_eq!(concentrations.lines().count(), 1 + 8);
+    let manifest = std::fs::read_to_string(temp_dir.path().join("manifest.json")).unwrap();
+    assert!(manifest.contains("\"dose_qc.csv\"") && manifest.contains("\"individual_results.csv\""));
+
+    let pooled = AnalysisConfig { pooled_lambda_z: true, dose_normalization: true, ..AnalysisConfig::default() };
+    let error = PopulationAnalyzer::analyze_population_streaming(subjects, &pooled, temp_dir.path()).unwrap_err();
+    assert!(error.to_string().contains("pooled lambda_z, dose normalization"), "{}", error);
+}
+
+#[test]
+fn test_interval_extremes_ignore_samples_outside_the_interval() {
+    use nca_analysis::parameters::ParameterCalculator;
+
+    let observations: Vec<Observation> = [(0.0, 1.0), (12.0, 2.0), (13.0, 10.0), (16.0, 6.0), (23.5, 2.5), (48.0, 0.5)]
+        .iter()
+        .map(|&(t, c)| observation(t, c))
+        .collect();
+
+    // The trough of the dose at 12 h is the 12 h sample, not the 0 h one; 48 h is past the interval
+    assert_eq!(ParameterCalculator::interval_extremes(&observations, 12.0, 24.0), Some((10.0, 2.0)));
+    // A trough drawn half an hour early still counts as the end of the interval
+    assert!(ParameterCalculator::interval_fully_observed(&observations, 12.0, 24.0));
+    // Nothing sampled near 36 h: the 48 h sample does not stand in for it
+    assert!(!ParameterCalculator::interval_fully_observed(&observations, 24.0, 36.0));
+    assert_eq!(ParameterCalculator::interval_extremes(&observations, 24.0, 36.0), None);
+}
+
+#[test]
+fn test_unknown_option_values_are_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let run = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_nca-analysis"))
+            .args(["--generate-example", "--subjects", "3", "--output"])
+            .arg(temp_dir.path().join("out"))
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    for option in [
+        "--auc-extrap-slope", "--dv-scale", "--lloq-handling", "--auc-tau-anchor", "--population-summary",
+        "--dose-time-concentration", "--parameter-names", "--covariate-p-adjust", "--geometric-non-positive",
+    ] {
+        let output = run(&[option, "bogus"]);
+        assert!(!output.status.success(), "{} bogus was accepted", option);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("'bogus'"), "{}", option);
+    }
+    // The documented defaults are spelled out and still accepted
+    assert!(run(&["--covariate-p-adjust", "bh", "--geometric-non-positive", "exclude", "--population-summary", "both"]).status.success());
+}